@@ -10,6 +10,25 @@ mod m20251006_163609_books;
 mod m20251006_172232_chapters;
 mod m20251006_172318_medias;
 mod m20251008_120000_add_chapter_tree_fields;
+mod m20251020_120000_create_media_plays;
+mod m20251021_120000_add_feed_fields_to_books;
+mod m20251022_120000_create_media_subtitles;
+mod m20251023_120000_create_personal_access_tokens;
+mod m20251024_120000_create_user_sessions;
+mod m20251025_120000_create_user_totp;
+mod m20251026_120000_create_user_identities;
+mod m20251027_120000_create_audit_logs;
+mod m20251028_120000_add_soft_delete;
+mod m20251029_120000_create_media_versions;
+mod m20251030_120000_add_publish_schedule;
+mod m20251031_120000_create_webhooks;
+mod m20251101_120000_create_media_placements;
+mod m20251102_120000_add_media_sort_order;
+mod m20251103_120000_create_storage_blobs;
+mod m20251104_120000_add_media_file_missing;
+mod m20251105_120000_add_audio_normalization;
+mod m20251106_120000_create_media_clips;
+mod m20251107_120000_webhook_delivery_latency;
 pub struct Migrator;
 
 #[async_trait::async_trait]
@@ -24,6 +43,25 @@ impl MigratorTrait for Migrator {
             Box::new(m20250111_120000_add_admin_fields_to_users::Migration),
             Box::new(m20250111_120001_create_user_groups::Migration),
             Box::new(m20250111_120002_create_site_settings::Migration),
+            Box::new(m20251020_120000_create_media_plays::Migration),
+            Box::new(m20251021_120000_add_feed_fields_to_books::Migration),
+            Box::new(m20251022_120000_create_media_subtitles::Migration),
+            Box::new(m20251023_120000_create_personal_access_tokens::Migration),
+            Box::new(m20251024_120000_create_user_sessions::Migration),
+            Box::new(m20251025_120000_create_user_totp::Migration),
+            Box::new(m20251026_120000_create_user_identities::Migration),
+            Box::new(m20251027_120000_create_audit_logs::Migration),
+            Box::new(m20251028_120000_add_soft_delete::Migration),
+            Box::new(m20251029_120000_create_media_versions::Migration),
+            Box::new(m20251030_120000_add_publish_schedule::Migration),
+            Box::new(m20251031_120000_create_webhooks::Migration),
+            Box::new(m20251101_120000_create_media_placements::Migration),
+            Box::new(m20251102_120000_add_media_sort_order::Migration),
+            Box::new(m20251103_120000_create_storage_blobs::Migration),
+            Box::new(m20251104_120000_add_media_file_missing::Migration),
+            Box::new(m20251105_120000_add_audio_normalization::Migration),
+            Box::new(m20251106_120000_create_media_clips::Migration),
+            Box::new(m20251107_120000_webhook_delivery_latency::Migration),
             // inject-above (do not remove this comment)
         ]
     }
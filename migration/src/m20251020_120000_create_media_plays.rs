@@ -0,0 +1,58 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 播放事件表：每次播放/完播各记录一行，用于按时间范围统计
+        create_table(
+            m,
+            "media_plays",
+            &[
+                ("id", ColType::PkAuto),
+                ("media_id", ColType::Integer),
+                ("book_id", ColType::Integer),
+                ("user_id", ColType::Integer),
+                ("kind", ColType::String),
+            ],
+            &[],
+        )
+        .await?;
+
+        m.create_index(
+            Index::create()
+                .name("idx_media_plays_media_id_created_at")
+                .table(MediaPlays::Table)
+                .col(MediaPlays::MediaId)
+                .col(MediaPlays::CreatedAt)
+                .to_owned(),
+        )
+        .await?;
+
+        m.create_index(
+            Index::create()
+                .name("idx_media_plays_user_id")
+                .table(MediaPlays::Table)
+                .col(MediaPlays::UserId)
+                .to_owned(),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        drop_table(m, "media_plays").await
+    }
+}
+
+#[derive(DeriveIden)]
+enum MediaPlays {
+    Table,
+    MediaId,
+    UserId,
+    CreatedAt,
+}
@@ -0,0 +1,80 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 播客 RSS 订阅源的书籍级设置（SQLite 每次只能添加一列）
+        let columns = [
+            ColumnDef::new(Books::FeedAuthor).string().null().to_owned(),
+            ColumnDef::new(Books::FeedOwnerEmail)
+                .string()
+                .null()
+                .to_owned(),
+            ColumnDef::new(Books::FeedLanguage)
+                .string()
+                .null()
+                .to_owned(),
+            ColumnDef::new(Books::FeedCategory)
+                .string()
+                .null()
+                .to_owned(),
+            ColumnDef::new(Books::FeedExplicit)
+                .boolean()
+                .null()
+                .to_owned(),
+            ColumnDef::new(Books::FeedCopyright)
+                .string()
+                .null()
+                .to_owned(),
+        ];
+
+        for mut column in columns {
+            m.alter_table(
+                Table::alter()
+                    .table(Books::Table)
+                    .add_column(&mut column)
+                    .to_owned(),
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [
+            Books::FeedCopyright,
+            Books::FeedExplicit,
+            Books::FeedCategory,
+            Books::FeedLanguage,
+            Books::FeedOwnerEmail,
+            Books::FeedAuthor,
+        ];
+
+        for column in columns {
+            m.alter_table(
+                Table::alter()
+                    .table(Books::Table)
+                    .drop_column(column)
+                    .to_owned(),
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Books {
+    Table,
+    FeedAuthor,
+    FeedOwnerEmail,
+    FeedLanguage,
+    FeedCategory,
+    FeedExplicit,
+    FeedCopyright,
+}
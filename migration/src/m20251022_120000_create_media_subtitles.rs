@@ -0,0 +1,55 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 字幕/文稿表：统一以 WebVTT 格式保存内容
+        create_table(
+            m,
+            "media_subtitles",
+            &[
+                ("id", ColType::PkAuto),
+                ("media_id", ColType::Integer),
+                ("language", ColType::String),
+                ("label", ColType::StringNull),
+                ("kind", ColType::String),
+                ("source_format", ColType::String),
+                ("content", ColType::Text),
+                ("cue_count", ColType::Integer),
+            ],
+            &[],
+        )
+        .await?;
+
+        // 每个媒体同一语言、同一类型只保留一份
+        m.create_index(
+            Index::create()
+                .name("idx_media_subtitles_media_language_kind")
+                .table(MediaSubtitles::Table)
+                .col(MediaSubtitles::MediaId)
+                .col(MediaSubtitles::Language)
+                .col(MediaSubtitles::Kind)
+                .unique()
+                .to_owned(),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        drop_table(m, "media_subtitles").await
+    }
+}
+
+#[derive(DeriveIden)]
+enum MediaSubtitles {
+    Table,
+    MediaId,
+    Language,
+    Kind,
+}
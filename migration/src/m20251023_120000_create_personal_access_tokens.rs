@@ -0,0 +1,51 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 个人访问令牌：只保存 SHA-256 摘要，明文仅在创建时返回一次
+        create_table(
+            m,
+            "personal_access_tokens",
+            &[
+                ("id", ColType::PkAuto),
+                ("pid", ColType::Uuid),
+                ("user_id", ColType::Integer),
+                ("name", ColType::String),
+                ("token_prefix", ColType::String),
+                ("token_hash", ColType::StringUniq),
+                ("scopes", ColType::String),
+                ("last_used_at", ColType::TimestampWithTimeZoneNull),
+                ("expires_at", ColType::TimestampWithTimeZoneNull),
+                ("revoked_at", ColType::TimestampWithTimeZoneNull),
+            ],
+            &[],
+        )
+        .await?;
+
+        m.create_index(
+            Index::create()
+                .name("idx_personal_access_tokens_user_id")
+                .table(PersonalAccessTokens::Table)
+                .col(PersonalAccessTokens::UserId)
+                .to_owned(),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        drop_table(m, "personal_access_tokens").await
+    }
+}
+
+#[derive(DeriveIden)]
+enum PersonalAccessTokens {
+    Table,
+    UserId,
+}
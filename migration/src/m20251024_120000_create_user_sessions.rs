@@ -0,0 +1,62 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 登录会话：每次登录对应一台设备，刷新令牌只保存 SHA-256 摘要
+        create_table(
+            m,
+            "user_sessions",
+            &[
+                ("id", ColType::PkAuto),
+                ("pid", ColType::Uuid),
+                ("user_id", ColType::Integer),
+                ("refresh_token_hash", ColType::StringUniq),
+                ("previous_token_hash", ColType::StringNull),
+                ("user_agent", ColType::StringNull),
+                ("ip_address", ColType::StringNull),
+                ("last_used_at", ColType::TimestampWithTimeZoneNull),
+                ("expires_at", ColType::TimestampWithTimeZone),
+                ("revoked_at", ColType::TimestampWithTimeZoneNull),
+            ],
+            &[],
+        )
+        .await?;
+
+        m.create_index(
+            Index::create()
+                .name("idx_user_sessions_pid")
+                .table(UserSessions::Table)
+                .col(UserSessions::Pid)
+                .unique()
+                .to_owned(),
+        )
+        .await?;
+
+        m.create_index(
+            Index::create()
+                .name("idx_user_sessions_user_id")
+                .table(UserSessions::Table)
+                .col(UserSessions::UserId)
+                .to_owned(),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        drop_table(m, "user_sessions").await
+    }
+}
+
+#[derive(DeriveIden)]
+enum UserSessions {
+    Table,
+    Pid,
+    UserId,
+}
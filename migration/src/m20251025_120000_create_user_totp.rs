@@ -0,0 +1,30 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 两步验证：enabled_at 为空表示尚未完成绑定；恢复码只保存摘要（JSON 数组）
+        create_table(
+            m,
+            "user_totps",
+            &[
+                ("id", ColType::PkAuto),
+                ("user_id", ColType::IntegerUniq),
+                ("secret", ColType::String),
+                ("enabled_at", ColType::TimestampWithTimeZoneNull),
+                ("recovery_codes", ColType::Text),
+                ("last_used_step", ColType::BigIntegerNull),
+            ],
+            &[],
+        )
+        .await
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        drop_table(m, "user_totps").await
+    }
+}
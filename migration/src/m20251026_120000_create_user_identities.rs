@@ -0,0 +1,60 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 单点登录身份：同一身份提供方（issuer）下的 subject 唯一对应一个本地用户
+        create_table(
+            m,
+            "user_identities",
+            &[
+                ("id", ColType::PkAuto),
+                ("user_id", ColType::Integer),
+                ("issuer", ColType::String),
+                ("subject", ColType::String),
+                ("email", ColType::StringNull),
+                ("last_login_at", ColType::TimestampWithTimeZoneNull),
+            ],
+            &[],
+        )
+        .await?;
+
+        m.create_index(
+            Index::create()
+                .name("idx_user_identities_issuer_subject")
+                .table(UserIdentities::Table)
+                .col(UserIdentities::Issuer)
+                .col(UserIdentities::Subject)
+                .unique()
+                .to_owned(),
+        )
+        .await?;
+
+        m.create_index(
+            Index::create()
+                .name("idx_user_identities_user_id")
+                .table(UserIdentities::Table)
+                .col(UserIdentities::UserId)
+                .to_owned(),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        drop_table(m, "user_identities").await
+    }
+}
+
+#[derive(DeriveIden)]
+enum UserIdentities {
+    Table,
+    Issuer,
+    Subject,
+    UserId,
+}
@@ -0,0 +1,73 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 审计日志：只追加不修改；操作者信息冗余保存，删除用户后仍可追溯
+        create_table(
+            m,
+            "audit_logs",
+            &[
+                ("id", ColType::PkAuto),
+                ("actor_id", ColType::IntegerNull),
+                ("actor_email", ColType::StringNull),
+                ("action", ColType::String),
+                ("target_type", ColType::StringNull),
+                ("target_id", ColType::StringNull),
+                ("before_summary", ColType::TextNull),
+                ("after_summary", ColType::TextNull),
+                ("ip_address", ColType::StringNull),
+                ("source", ColType::String),
+            ],
+            &[],
+        )
+        .await?;
+
+        m.create_index(
+            Index::create()
+                .name("idx_audit_logs_created_at")
+                .table(AuditLogs::Table)
+                .col(AuditLogs::CreatedAt)
+                .to_owned(),
+        )
+        .await?;
+
+        m.create_index(
+            Index::create()
+                .name("idx_audit_logs_actor_id")
+                .table(AuditLogs::Table)
+                .col(AuditLogs::ActorId)
+                .to_owned(),
+        )
+        .await?;
+
+        m.create_index(
+            Index::create()
+                .name("idx_audit_logs_target")
+                .table(AuditLogs::Table)
+                .col(AuditLogs::TargetType)
+                .col(AuditLogs::TargetId)
+                .to_owned(),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        drop_table(m, "audit_logs").await
+    }
+}
+
+#[derive(DeriveIden)]
+enum AuditLogs {
+    Table,
+    CreatedAt,
+    ActorId,
+    TargetType,
+    TargetId,
+}
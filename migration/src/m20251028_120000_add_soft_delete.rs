@@ -0,0 +1,129 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 软删除：删除时间不为空表示已移入回收站
+        m.alter_table(
+            Table::alter()
+                .table(Books::Table)
+                .add_column(
+                    ColumnDef::new(Books::DeletedAt)
+                        .timestamp_with_time_zone()
+                        .null(),
+                )
+                .to_owned(),
+        )
+        .await?;
+
+        m.alter_table(
+            Table::alter()
+                .table(Chapters::Table)
+                .add_column(
+                    ColumnDef::new(Chapters::DeletedAt)
+                        .timestamp_with_time_zone()
+                        .null(),
+                )
+                .to_owned(),
+        )
+        .await?;
+
+        m.alter_table(
+            Table::alter()
+                .table(Medias::Table)
+                .add_column(
+                    ColumnDef::new(Medias::DeletedAt)
+                        .timestamp_with_time_zone()
+                        .null(),
+                )
+                .to_owned(),
+        )
+        .await?;
+
+        // 定时清理按删除时间查找过期条目
+        m.create_index(
+            Index::create()
+                .name("idx_books_deleted_at")
+                .table(Books::Table)
+                .col(Books::DeletedAt)
+                .to_owned(),
+        )
+        .await?;
+
+        m.create_index(
+            Index::create()
+                .name("idx_chapters_deleted_at")
+                .table(Chapters::Table)
+                .col(Chapters::DeletedAt)
+                .to_owned(),
+        )
+        .await?;
+
+        m.create_index(
+            Index::create()
+                .name("idx_medias_deleted_at")
+                .table(Medias::Table)
+                .col(Medias::DeletedAt)
+                .to_owned(),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        m.drop_index(Index::drop().name("idx_medias_deleted_at").to_owned())
+            .await?;
+        m.drop_index(Index::drop().name("idx_chapters_deleted_at").to_owned())
+            .await?;
+        m.drop_index(Index::drop().name("idx_books_deleted_at").to_owned())
+            .await?;
+
+        m.alter_table(
+            Table::alter()
+                .table(Medias::Table)
+                .drop_column(Medias::DeletedAt)
+                .to_owned(),
+        )
+        .await?;
+
+        m.alter_table(
+            Table::alter()
+                .table(Chapters::Table)
+                .drop_column(Chapters::DeletedAt)
+                .to_owned(),
+        )
+        .await?;
+
+        m.alter_table(
+            Table::alter()
+                .table(Books::Table)
+                .drop_column(Books::DeletedAt)
+                .to_owned(),
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Books {
+    Table,
+    DeletedAt,
+}
+
+#[derive(DeriveIden)]
+enum Chapters {
+    Table,
+    DeletedAt,
+}
+
+#[derive(DeriveIden)]
+enum Medias {
+    Table,
+    DeletedAt,
+}
@@ -0,0 +1,54 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 媒体文件版本：每次上传或替换文件记录一条，当前文件也在其中
+        create_table(
+            m,
+            "media_versions",
+            &[
+                ("id", ColType::PkAuto),
+                ("media_id", ColType::Integer),
+                ("version", ColType::Integer),
+                ("file_type", ColType::String),
+                ("file_path", ColType::String),
+                ("file_size", ColType::BigIntegerNull),
+                ("duration", ColType::IntegerNull),
+                ("mime_type", ColType::StringNull),
+                ("original_filename", ColType::StringNull),
+                ("uploaded_by", ColType::IntegerNull),
+            ],
+            &[],
+        )
+        .await?;
+
+        m.create_index(
+            Index::create()
+                .name("idx_media_versions_media_version")
+                .table(MediaVersions::Table)
+                .col(MediaVersions::MediaId)
+                .col(MediaVersions::Version)
+                .unique()
+                .to_owned(),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        drop_table(m, "media_versions").await
+    }
+}
+
+#[derive(DeriveIden)]
+enum MediaVersions {
+    Table,
+    MediaId,
+    Version,
+}
@@ -0,0 +1,97 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 定时发布与下架时间（SQLite 每次只能添加一列）
+        for column in [Books::PublishAt, Books::UnpublishAt] {
+            m.alter_table(
+                Table::alter()
+                    .table(Books::Table)
+                    .add_column(ColumnDef::new(column).timestamp_with_time_zone().null())
+                    .to_owned(),
+            )
+            .await?;
+        }
+        for column in [Medias::PublishAt, Medias::UnpublishAt] {
+            m.alter_table(
+                Table::alter()
+                    .table(Medias::Table)
+                    .add_column(ColumnDef::new(column).timestamp_with_time_zone().null())
+                    .to_owned(),
+            )
+            .await?;
+        }
+
+        // 后台任务按时间查找到期的条目
+        for (name, table, col) in [
+            ("idx_books_publish_at", Books::Table, Books::PublishAt),
+            ("idx_books_unpublish_at", Books::Table, Books::UnpublishAt),
+        ] {
+            m.create_index(Index::create().name(name).table(table).col(col).to_owned())
+                .await?;
+        }
+        for (name, table, col) in [
+            ("idx_medias_publish_at", Medias::Table, Medias::PublishAt),
+            (
+                "idx_medias_unpublish_at",
+                Medias::Table,
+                Medias::UnpublishAt,
+            ),
+        ] {
+            m.create_index(Index::create().name(name).table(table).col(col).to_owned())
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        for name in [
+            "idx_medias_unpublish_at",
+            "idx_medias_publish_at",
+            "idx_books_unpublish_at",
+            "idx_books_publish_at",
+        ] {
+            m.drop_index(Index::drop().name(name).to_owned()).await?;
+        }
+
+        for column in [Medias::UnpublishAt, Medias::PublishAt] {
+            m.alter_table(
+                Table::alter()
+                    .table(Medias::Table)
+                    .drop_column(column)
+                    .to_owned(),
+            )
+            .await?;
+        }
+        for column in [Books::UnpublishAt, Books::PublishAt] {
+            m.alter_table(
+                Table::alter()
+                    .table(Books::Table)
+                    .drop_column(column)
+                    .to_owned(),
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Books {
+    Table,
+    PublishAt,
+    UnpublishAt,
+}
+
+#[derive(DeriveIden)]
+enum Medias {
+    Table,
+    PublishAt,
+    UnpublishAt,
+}
@@ -0,0 +1,98 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // Webhook 地址：events 为逗号分隔的订阅事件列表
+        create_table(
+            m,
+            "webhook_endpoints",
+            &[
+                ("id", ColType::PkAuto),
+                ("user_id", ColType::Integer),
+                ("url", ColType::String),
+                ("secret", ColType::String),
+                ("events", ColType::Text),
+                ("description", ColType::StringNull),
+                ("is_active", ColType::Boolean),
+            ],
+            &[],
+        )
+        .await?;
+
+        m.create_index(
+            Index::create()
+                .name("idx_webhook_endpoints_user_id")
+                .table(WebhookEndpoints::Table)
+                .col(WebhookEndpoints::UserId)
+                .to_owned(),
+        )
+        .await?;
+
+        // 投递记录：每个事件对每个地址一条，重试时更新同一条记录
+        create_table(
+            m,
+            "webhook_deliveries",
+            &[
+                ("id", ColType::PkAuto),
+                ("endpoint_id", ColType::Integer),
+                ("event", ColType::String),
+                ("payload", ColType::Text),
+                ("status", ColType::String),
+                ("attempts", ColType::Integer),
+                ("response_status", ColType::IntegerNull),
+                ("response_body", ColType::TextNull),
+                ("error", ColType::TextNull),
+                ("next_attempt_at", ColType::TimestampWithTimeZoneNull),
+                ("delivered_at", ColType::TimestampWithTimeZoneNull),
+            ],
+            &[],
+        )
+        .await?;
+
+        m.create_index(
+            Index::create()
+                .name("idx_webhook_deliveries_endpoint_id")
+                .table(WebhookDeliveries::Table)
+                .col(WebhookDeliveries::EndpointId)
+                .to_owned(),
+        )
+        .await?;
+
+        // 重试任务按状态和下次尝试时间查找
+        m.create_index(
+            Index::create()
+                .name("idx_webhook_deliveries_retry")
+                .table(WebhookDeliveries::Table)
+                .col(WebhookDeliveries::Status)
+                .col(WebhookDeliveries::NextAttemptAt)
+                .to_owned(),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        drop_table(m, "webhook_deliveries").await?;
+        drop_table(m, "webhook_endpoints").await
+    }
+}
+
+#[derive(DeriveIden)]
+enum WebhookEndpoints {
+    Table,
+    UserId,
+}
+
+#[derive(DeriveIden)]
+enum WebhookDeliveries {
+    Table,
+    EndpointId,
+    Status,
+    NextAttemptAt,
+}
@@ -0,0 +1,60 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 媒体的附加位置：同一个媒体（同一文件和访问令牌）可以出现在多个章节或书籍中，
+        // 媒体自身的 book_id / chapter_id 仍是它的原始位置
+        create_table(
+            m,
+            "media_placements",
+            &[
+                ("id", ColType::PkAuto),
+                ("media_id", ColType::Integer),
+                ("book_id", ColType::Integer),
+                ("chapter_id", ColType::IntegerNull),
+                ("sort_order", ColType::IntegerNull),
+                ("title", ColType::StringNull),
+            ],
+            &[],
+        )
+        .await?;
+
+        m.create_index(
+            Index::create()
+                .name("idx_media_placements_media")
+                .table(MediaPlacements::Table)
+                .col(MediaPlacements::MediaId)
+                .to_owned(),
+        )
+        .await?;
+
+        m.create_index(
+            Index::create()
+                .name("idx_media_placements_book_chapter")
+                .table(MediaPlacements::Table)
+                .col(MediaPlacements::BookId)
+                .col(MediaPlacements::ChapterId)
+                .to_owned(),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        drop_table(m, "media_placements").await
+    }
+}
+
+#[derive(DeriveIden)]
+enum MediaPlacements {
+    Table,
+    MediaId,
+    BookId,
+    ChapterId,
+}
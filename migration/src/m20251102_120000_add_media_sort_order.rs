@@ -0,0 +1,66 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 媒体在所属章节（或书籍顶层）中的排序号
+        m.alter_table(
+            Table::alter()
+                .table(Medias::Table)
+                .add_column(ColumnDef::new(Medias::SortOrder).integer().null())
+                .to_owned(),
+        )
+        .await?;
+
+        // 已有媒体按创建顺序排列
+        m.exec_stmt(
+            Query::update()
+                .table(Medias::Table)
+                .value(Medias::SortOrder, Expr::col(Medias::Id))
+                .to_owned(),
+        )
+        .await?;
+
+        m.create_index(
+            Index::create()
+                .name("idx_medias_chapter_sort_order")
+                .table(Medias::Table)
+                .col(Medias::ChapterId)
+                .col(Medias::SortOrder)
+                .to_owned(),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        m.drop_index(
+            Index::drop()
+                .name("idx_medias_chapter_sort_order")
+                .to_owned(),
+        )
+        .await?;
+
+        m.alter_table(
+            Table::alter()
+                .table(Medias::Table)
+                .drop_column(Medias::SortOrder)
+                .to_owned(),
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Medias {
+    Table,
+    Id,
+    ChapterId,
+    SortOrder,
+}
@@ -0,0 +1,77 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 按内容（SHA-256）存储的文件，内容相同的上传共用一个文件；
+        // ref_count 是引用该文件的媒体版本数，降到 0 时删除文件
+        create_table(
+            m,
+            "storage_blobs",
+            &[
+                ("id", ColType::PkAuto),
+                ("content_hash", ColType::StringUniq),
+                ("file_path", ColType::String),
+                ("file_size", ColType::BigInteger),
+                ("ref_count", ColType::Integer),
+            ],
+            &[],
+        )
+        .await?;
+
+        // 启用内容寻址存储之前上传的文件没有摘要，仍按路径判断是否共用
+        m.alter_table(
+            Table::alter()
+                .table(Medias::Table)
+                .add_column(ColumnDef::new(Medias::ContentHash).string().null())
+                .to_owned(),
+        )
+        .await?;
+
+        m.alter_table(
+            Table::alter()
+                .table(MediaVersions::Table)
+                .add_column(ColumnDef::new(MediaVersions::ContentHash).string().null())
+                .to_owned(),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        m.alter_table(
+            Table::alter()
+                .table(MediaVersions::Table)
+                .drop_column(MediaVersions::ContentHash)
+                .to_owned(),
+        )
+        .await?;
+
+        m.alter_table(
+            Table::alter()
+                .table(Medias::Table)
+                .drop_column(Medias::ContentHash)
+                .to_owned(),
+        )
+        .await?;
+
+        drop_table(m, "storage_blobs").await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Medias {
+    Table,
+    ContentHash,
+}
+
+#[derive(DeriveIden)]
+enum MediaVersions {
+    Table,
+    ContentHash,
+}
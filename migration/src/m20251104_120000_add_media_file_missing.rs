@@ -0,0 +1,40 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 存储检查发现文件丢失的时间，文件恢复或替换后清空
+        m.alter_table(
+            Table::alter()
+                .table(Medias::Table)
+                .add_column(
+                    ColumnDef::new(Medias::FileMissingAt)
+                        .timestamp_with_time_zone()
+                        .null(),
+                )
+                .to_owned(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        m.alter_table(
+            Table::alter()
+                .table(Medias::Table)
+                .drop_column(Medias::FileMissingAt)
+                .to_owned(),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Medias {
+    Table,
+    FileMissingAt,
+}
@@ -0,0 +1,91 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 按书籍开启：播放时使用响度统一、去掉首尾静音后的版本
+        m.alter_table(
+            Table::alter()
+                .table(Books::Table)
+                .add_column(
+                    ColumnDef::new(Books::NormalizeAudio)
+                        .boolean()
+                        .not_null()
+                        .default(false),
+                )
+                .to_owned(),
+        )
+        .await?;
+
+        // 响度测量结果（JSON 文本）和处理后的文件（存储键），替换文件后清空并重新处理
+        // （SQLite 每次只能添加一列）
+        let columns = [
+            ColumnDef::new(Medias::AudioAnalysis)
+                .text()
+                .null()
+                .to_owned(),
+            ColumnDef::new(Medias::NormalizedFilePath)
+                .string()
+                .null()
+                .to_owned(),
+            ColumnDef::new(Medias::NormalizedFileSize)
+                .big_integer()
+                .null()
+                .to_owned(),
+        ];
+        for mut column in columns {
+            m.alter_table(
+                Table::alter()
+                    .table(Medias::Table)
+                    .add_column(&mut column)
+                    .to_owned(),
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        for column in [
+            Medias::NormalizedFileSize,
+            Medias::NormalizedFilePath,
+            Medias::AudioAnalysis,
+        ] {
+            m.alter_table(
+                Table::alter()
+                    .table(Medias::Table)
+                    .drop_column(column)
+                    .to_owned(),
+            )
+            .await?;
+        }
+
+        m.alter_table(
+            Table::alter()
+                .table(Books::Table)
+                .drop_column(Books::NormalizeAudio)
+                .to_owned(),
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum Books {
+    Table,
+    NormalizeAudio,
+}
+
+#[derive(DeriveIden)]
+enum Medias {
+    Table,
+    AudioAnalysis,
+    NormalizedFilePath,
+    NormalizedFileSize,
+}
@@ -0,0 +1,53 @@
+use loco_rs::schema::*;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 剪辑任务：从已有媒体的时间范围生成新媒体，完成后记录新媒体 ID
+        create_table(
+            m,
+            "media_clips",
+            &[
+                ("id", ColType::PkAuto),
+                ("user_id", ColType::Integer),
+                ("source_media_id", ColType::Integer),
+                ("start_ms", ColType::BigInteger),
+                ("end_ms", ColType::BigInteger),
+                ("title", ColType::String),
+                ("book_id", ColType::Integer),
+                ("chapter_id", ColType::IntegerNull),
+                ("status", ColType::String),
+                ("method", ColType::StringNull),
+                ("media_id", ColType::IntegerNull),
+                ("error", ColType::TextNull),
+            ],
+            &[],
+        )
+        .await?;
+
+        m.create_index(
+            Index::create()
+                .name("idx_media_clips_source_media_id")
+                .table(MediaClips::Table)
+                .col(MediaClips::SourceMediaId)
+                .to_owned(),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        drop_table(m, "media_clips").await
+    }
+}
+
+#[derive(DeriveIden)]
+enum MediaClips {
+    Table,
+    SourceMediaId,
+}
@@ -0,0 +1,59 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // 投递记录只保存状态码和耗时，不再保存对方返回的内容
+        m.alter_table(
+            Table::alter()
+                .table(WebhookDeliveries::Table)
+                .add_column(
+                    ColumnDef::new(WebhookDeliveries::LatencyMs)
+                        .integer()
+                        .null(),
+                )
+                .to_owned(),
+        )
+        .await?;
+        m.alter_table(
+            Table::alter()
+                .table(WebhookDeliveries::Table)
+                .drop_column(WebhookDeliveries::ResponseBody)
+                .to_owned(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        m.alter_table(
+            Table::alter()
+                .table(WebhookDeliveries::Table)
+                .add_column(
+                    ColumnDef::new(WebhookDeliveries::ResponseBody)
+                        .text()
+                        .null(),
+                )
+                .to_owned(),
+        )
+        .await?;
+        m.alter_table(
+            Table::alter()
+                .table(WebhookDeliveries::Table)
+                .drop_column(WebhookDeliveries::LatencyMs)
+                .to_owned(),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[derive(DeriveIden)]
+enum WebhookDeliveries {
+    Table,
+    ResponseBody,
+    LatencyMs,
+}
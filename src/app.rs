@@ -1,7 +1,8 @@
 use async_trait::async_trait;
+use axum::Router as AxumRouter;
 use loco_rs::{
     app::{AppContext, Hooks, Initializer},
-    bgworker::Queue,
+    bgworker::{BackgroundWorker, Queue},
     boot::{create_app, BootResult, StartMode},
     config::Config,
     controller::AppRoutes,
@@ -42,7 +43,9 @@ impl Hooks for App {
     }
 
     async fn initializers(_ctx: &AppContext) -> Result<Vec<Box<dyn Initializer>>> {
-        Ok(vec![])
+        Ok(vec![Box::new(
+            initializers::view_engine::ViewEngineInitializer,
+        )])
     }
 
     fn routes(_ctx: &AppContext) -> AppRoutes {
@@ -50,16 +53,41 @@ impl Hooks for App {
             .add_route(controllers::books::routes())
             .add_route(controllers::chapters::routes())
             .add_route(controllers::medias::routes())
+            .add_route(controllers::media_placements::routes())
+            .add_route(controllers::subtitles::routes())
             .add_route(controllers::public::routes())
+            .add_route(controllers::feeds::routes())
+            .add_route(controllers::embed::routes())
+            .add_route(controllers::landing::routes())
             .add_route(controllers::auth::routes())
+            .add_route(controllers::two_factor::routes())
+            .add_route(controllers::oidc::routes())
+            .add_route(controllers::tokens::routes())
+            .add_route(controllers::trash::routes())
+            .add_route(controllers::webhooks::routes())
             .add_route(controllers::dashboard::routes())
             // 后台管理路由
             .add_route(controllers::admin::users::routes())
             .add_route(controllers::admin::groups::routes())
+            .add_route(controllers::admin::audit_logs::routes())
+            .add_route(controllers::admin::storage::routes())
             .add_route(controllers::site_settings::routes())
     }
-    async fn connect_workers(_ctx: &AppContext, _queue: &Queue) -> Result<()> {
-        // 暂无 worker
+    async fn after_routes(router: AxumRouter, ctx: &AppContext) -> Result<AxumRouter> {
+        // 已吊销会话的 JWT 在所有接口上立即失效
+        Ok(router.layer(axum::middleware::from_fn_with_state(
+            ctx.clone(),
+            controllers::api_auth::require_active_session,
+        )))
+    }
+
+    async fn connect_workers(ctx: &AppContext, queue: &Queue) -> Result<()> {
+        queue
+            .register(workers::webhook_delivery::WebhookDeliveryWorker::build(ctx))
+            .await?;
+        queue
+            .register(workers::media_processing::MediaProcessingWorker::build(ctx))
+            .await?;
         Ok(())
     }
 
@@ -70,6 +98,14 @@ impl Hooks for App {
         tasks.register(tasks::set_admin_status::SetAdminStatus);
         tasks.register(tasks::list_admins::ListAdmins);
         tasks.register(tasks::regenerate_media_urls::RegenerateMediaUrls);
+        tasks.register(tasks::export_analytics::ExportAnalytics);
+        tasks.register(tasks::unlock_account::UnlockAccount);
+        tasks.register(tasks::purge_trash::PurgeTrash);
+        tasks.register(tasks::prune_media_versions::PruneMediaVersions);
+        tasks.register(tasks::run_publish_schedule::RunPublishSchedule);
+        tasks.register(tasks::retry_webhooks::RetryWebhooks);
+        tasks.register(tasks::check_storage::CheckStorage);
+        tasks.register(tasks::migrate_storage::MigrateStorage);
         // tasks-inject (do not remove)
     }
     async fn truncate(ctx: &AppContext) -> Result<()> {
@@ -0,0 +1,54 @@
+use crate::controllers::api_auth::ApiAuth;
+use crate::controllers::two_factor;
+use crate::models::audit_logs::{self, AuditQuery};
+use crate::models::personal_access_tokens::Scope;
+use crate::views::audit_logs::AuditLogResponse;
+use axum::extract::Query;
+use loco_rs::prelude::*;
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct AuditLogListResponse {
+    pub logs: Vec<AuditLogResponse>,
+    pub pagination: PaginationInfo,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PaginationInfo {
+    pub page: u64,
+    pub per_page: u64,
+    pub total_pages: u64,
+    pub total: u64,
+}
+
+/// 查询审计日志（需要管理员权限），支持按操作者、操作、对象、来源和时间范围筛选
+pub async fn list(
+    auth: ApiAuth,
+    State(ctx): State<AppContext>,
+    Query(params): Query<AuditQuery>,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::Admin)?;
+
+    if !user.is_admin() {
+        return unauthorized("需要管理员权限");
+    }
+    two_factor::require_for_admin(&ctx, &user).await?;
+
+    let (logs, total_pages, total) = audit_logs::Model::search(&ctx.db, &params).await?;
+
+    format::json(AuditLogListResponse {
+        logs: logs.into_iter().map(AuditLogResponse::from).collect(),
+        pagination: PaginationInfo {
+            page: params.page.unwrap_or(1).max(1),
+            per_page: params.per_page.unwrap_or(50).clamp(1, 200),
+            total_pages,
+            total,
+        },
+    })
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("/api/admin/audit-logs")
+        .add("/", get(list))
+}
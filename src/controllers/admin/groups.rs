@@ -1,5 +1,10 @@
+use crate::controllers::api_auth::ApiAuth;
+use crate::controllers::auth::ClientIp;
+use crate::controllers::two_factor;
 use crate::models::_entities::{user_groups as user_groups_entity, users as users_entity};
-use crate::models::{user_groups, users};
+use crate::models::audit_logs::{self, AuditEntry};
+use crate::models::personal_access_tokens::Scope;
+use crate::models::user_groups;
 use axum::routing::method_routing::delete as axum_delete;
 use loco_rs::prelude::*;
 use serde::{Deserialize, Serialize};
@@ -30,28 +35,41 @@ pub struct GroupWithMembers {
 
 /// 创建用户组（需要管理员权限）
 pub async fn create(
-    auth: auth::JWT,
+    auth: ApiAuth,
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
     Json(params): Json<CreateGroupParams>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::Admin)?;
 
     if !user.is_admin() {
         return unauthorized("需要管理员权限");
     }
+    two_factor::require_for_admin(&ctx, &user).await?;
 
     let group = user_groups::Model::create_group(&ctx.db, params.name, params.description).await?;
 
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("group.create")
+            .actor(&user)
+            .target("group", group.id)
+            .after(serde_json::json!({ "name": group.name }))
+            .ip(ip),
+    )
+    .await;
+
     format::json(group)
 }
 
 /// 列出所有用户组
-pub async fn list(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+pub async fn list(auth: ApiAuth, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = auth.authorize(Scope::Admin)?;
 
     if !user.is_admin() {
         return unauthorized("需要管理员权限");
     }
+    two_factor::require_for_admin(&ctx, &user).await?;
 
     let groups = user_groups::Model::list_all(&ctx.db).await?;
 
@@ -70,15 +88,16 @@ pub async fn list(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Resp
 
 /// 获取用户组详情
 pub async fn show(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path(group_id): Path<i32>,
     State(ctx): State<AppContext>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::Admin)?;
 
     if !user.is_admin() {
         return unauthorized("需要管理员权限");
     }
+    two_factor::require_for_admin(&ctx, &user).await?;
 
     let group = user_groups::Model::find_by_id(&ctx.db, group_id).await?;
     let members = user_groups::Model::get_members(&ctx.db, group_id).await?;
@@ -93,16 +112,18 @@ pub async fn show(
 
 /// 添加用户到组
 pub async fn add_member(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path(group_id): Path<i32>,
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
     Json(params): Json<AddMemberParams>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::Admin)?;
 
     if !user.is_admin() {
         return unauthorized("需要管理员权限");
     }
+    two_factor::require_for_admin(&ctx, &user).await?;
 
     // 检查用户是否存在
     users_entity::Entity::find_by_id(params.user_id)
@@ -120,42 +141,77 @@ pub async fn add_member(
 
     user_groups::Model::add_user(&ctx.db, group_id, params.user_id).await?;
 
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("group.add_member")
+            .actor(&user)
+            .target("group", group_id)
+            .after(serde_json::json!({ "user_id": params.user_id }))
+            .ip(ip),
+    )
+    .await;
+
     format::empty()
 }
 
 /// 从组中移除用户
 pub async fn remove_member(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path((group_id, user_id)): Path<(i32, i32)>,
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
 ) -> Result<Response> {
-    let admin = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let admin = auth.authorize(Scope::Admin)?;
 
     if !admin.is_admin() {
         return unauthorized("需要管理员权限");
     }
+    two_factor::require_for_admin(&ctx, &admin).await?;
 
     user_groups::Model::remove_user(&ctx.db, group_id, user_id).await?;
 
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("group.remove_member")
+            .actor(&admin)
+            .target("group", group_id)
+            .before(serde_json::json!({ "user_id": user_id }))
+            .ip(ip),
+    )
+    .await;
+
     format::empty()
 }
 
 /// 删除用户组
 pub async fn delete(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path(group_id): Path<i32>,
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::Admin)?;
 
     if !user.is_super_admin() {
         return unauthorized("需要超级管理员权限");
     }
+    two_factor::require_for_admin(&ctx, &user).await?;
 
+    let group = user_groups::Model::find_by_id(&ctx.db, group_id).await?;
     user_groups_entity::Entity::delete_by_id(group_id)
         .exec(&ctx.db)
         .await?;
 
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("group.delete")
+            .actor(&user)
+            .target("group", group_id)
+            .before(serde_json::json!({ "name": group.name }))
+            .ip(ip),
+    )
+    .await;
+
     format::empty()
 }
 
@@ -1,2 +1,4 @@
+pub mod audit_logs;
 pub mod groups;
+pub mod storage;
 pub mod users;
@@ -0,0 +1,56 @@
+use crate::controllers::api_auth::ApiAuth;
+use crate::controllers::auth::ClientIp;
+use crate::controllers::two_factor;
+use crate::models::audit_logs::{self, AuditEntry};
+use crate::models::personal_access_tokens::Scope;
+use crate::services::storage_integrity::{self, CheckOptions};
+use loco_rs::prelude::*;
+
+/// 检查存储文件与数据库是否一致（需要管理员权限）；
+/// `apply` 时执行修复，需要超级管理员权限
+pub async fn check(
+    auth: ApiAuth,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    Json(options): Json<CheckOptions>,
+) -> Result<Response> {
+    let admin = auth.authorize(Scope::Admin)?;
+
+    if !admin.is_admin() {
+        return unauthorized("需要管理员权限");
+    }
+    if options.apply && !admin.is_super_admin() {
+        return unauthorized("需要超级管理员权限");
+    }
+    two_factor::require_for_admin(&ctx, &admin).await?;
+
+    let report = storage_integrity::run(&ctx.db, options).await?;
+
+    if options.apply {
+        let repairs = &report.repairs;
+        audit_logs::record(
+            &ctx.db,
+            AuditEntry::new("storage.repair")
+                .actor(&admin)
+                .after(serde_json::json!({
+                    "missing_files": report.missing_files.len(),
+                    "checksum_mismatches": report.checksum_mismatches.len(),
+                    "deleted_files": repairs.deleted_files,
+                    "freed_bytes": repairs.freed_bytes,
+                    "regenerated_qrcodes": repairs.regenerated_qrcodes,
+                    "marked_missing": repairs.marked_missing,
+                    "cleared_missing": repairs.cleared_missing,
+                }))
+                .ip(ip),
+        )
+        .await;
+    }
+
+    format::json(report)
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("/api/admin/storage")
+        .add("/check", post(check))
+}
@@ -1,5 +1,11 @@
+use crate::controllers::api_auth::ApiAuth;
+use crate::controllers::auth::ClientIp;
+use crate::controllers::two_factor;
 use crate::models::_entities::users as users_entity;
-use crate::models::users;
+use crate::models::audit_logs::{self, AuditEntry};
+use crate::models::personal_access_tokens::Scope;
+use crate::models::{user_identities, user_sessions, user_totp, users};
+use crate::services::rate_limit::RATE_LIMITER;
 use axum::extract::Query;
 use axum::routing::method_routing::delete as axum_delete;
 use loco_rs::prelude::*;
@@ -33,15 +39,16 @@ pub struct PaginationInfo {
 
 /// 列出所有用户（需要管理员权限）
 pub async fn list(
-    auth: auth::JWT,
+    auth: ApiAuth,
     State(ctx): State<AppContext>,
     Query(params): Query<ListParams>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::Admin)?;
 
     if !user.is_admin() {
         return unauthorized("需要管理员权限");
     }
+    two_factor::require_for_admin(&ctx, &user).await?;
 
     let page = params.page.unwrap_or(1);
     let per_page = params.per_page.unwrap_or(20);
@@ -64,55 +71,132 @@ pub async fn list(
 
 /// 更新用户角色（需要超级管理员权限）
 pub async fn update_role(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path(user_id): Path<i32>,
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
     Json(params): Json<UpdateRoleParams>,
 ) -> Result<Response> {
-    let admin = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let admin = auth.authorize(Scope::Admin)?;
 
     if !admin.is_super_admin() {
         return unauthorized("需要超级管理员权限");
     }
+    two_factor::require_for_admin(&ctx, &admin).await?;
 
+    let before = users_entity::Entity::find_by_id(user_id)
+        .one(&ctx.db)
+        .await?
+        .ok_or_else(|| Error::NotFound)?;
     let updated_user =
         users::Model::update_admin_status(&ctx.db, user_id, params.is_staff, params.is_superuser)
             .await?;
 
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("user.update_role")
+            .actor(&admin)
+            .target("user", user_id)
+            .before(serde_json::json!({
+                "is_staff": before.is_staff,
+                "is_superuser": before.is_superuser,
+            }))
+            .after(serde_json::json!({
+                "is_staff": updated_user.is_staff,
+                "is_superuser": updated_user.is_superuser,
+            }))
+            .ip(ip),
+    )
+    .await;
+
     format::json(updated_user)
 }
 
 /// 删除用户（需要超级管理员权限）
 pub async fn delete(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path(user_id): Path<i32>,
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
 ) -> Result<Response> {
-    let admin = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let admin = auth.authorize(Scope::Admin)?;
 
     if !admin.is_super_admin() {
         return unauthorized("需要超级管理员权限");
     }
+    two_factor::require_for_admin(&ctx, &admin).await?;
 
     // 不允许删除自己
     if admin.id == user_id {
         return bad_request("不能删除自己");
     }
 
+    let user = users_entity::Entity::find_by_id(user_id)
+        .one(&ctx.db)
+        .await?
+        .ok_or_else(|| Error::NotFound)?;
     users_entity::Entity::delete_by_id(user_id)
         .exec(&ctx.db)
         .await?;
+    user_sessions::Model::delete_by_user(&ctx.db, user_id).await?;
+    user_identities::Model::delete_by_user(&ctx.db, user_id).await?;
+    if let Some(two_factor) = user_totp::Model::find_by_user(&ctx.db, user_id).await? {
+        two_factor.delete(&ctx.db).await?;
+    }
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("user.delete")
+            .actor(&admin)
+            .target("user", user_id)
+            .before(serde_json::json!({ "email": user.email, "name": user.name }))
+            .ip(ip),
+    )
+    .await;
+
+    format::empty()
+}
+
+/// 解除用户的登录锁定（需要管理员权限）
+pub async fn unlock(
+    auth: ApiAuth,
+    Path(user_id): Path<i32>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+) -> Result<Response> {
+    let admin = auth.authorize(Scope::Admin)?;
+
+    if !admin.is_admin() {
+        return unauthorized("需要管理员权限");
+    }
+    two_factor::require_for_admin(&ctx, &admin).await?;
+
+    let user = users_entity::Entity::find_by_id(user_id)
+        .one(&ctx.db)
+        .await?
+        .ok_or_else(|| Error::NotFound)?;
+    RATE_LIMITER.unlock(&user.pid.to_string()).await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("user.unlock")
+            .actor(&admin)
+            .target("user", user.id)
+            .ip(ip),
+    )
+    .await;
 
     format::empty()
 }
 
 /// 获取统计信息
-pub async fn stats(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+pub async fn stats(auth: ApiAuth, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = auth.authorize(Scope::Admin)?;
 
     if !user.is_admin() {
         return unauthorized("需要管理员权限");
     }
+    two_factor::require_for_admin(&ctx, &user).await?;
 
     let total_users = users::Model::count_all(&ctx.db).await?;
     let total_admins = users::Model::count_admins(&ctx.db).await?;
@@ -129,5 +213,6 @@ pub fn routes() -> Routes {
         .add("/", get(list))
         .add("/stats", get(stats))
         .add("/{id}/role", put(update_role))
+        .add("/{id}/unlock", post(unlock))
         .add("/{id}", axum_delete(delete))
 }
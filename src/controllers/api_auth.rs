@@ -0,0 +1,147 @@
+use axum::extract::{FromRequestParts, Request};
+use axum::http::{header, request::Parts, HeaderMap};
+use axum::middleware::Next;
+use loco_rs::auth::jwt;
+use loco_rs::prelude::*;
+
+use crate::models::personal_access_tokens::{self, Scope, TOKEN_PREFIX};
+use crate::models::user_sessions::{self, SESSION_CLAIM};
+use crate::models::users;
+
+/// 个人访问令牌的自定义请求头（与 `Authorization: Bearer` 二选一）
+const API_KEY_HEADER: &str = "x-api-key";
+
+/// 认证提取器：支持登录获得的 JWT 和个人访问令牌
+///
+/// JWT 拥有用户的全部权限；个人访问令牌只拥有创建时授予的权限范围，
+/// 处理函数通过 [`ApiAuth::authorize`] 声明所需权限。
+#[derive(Debug)]
+pub struct ApiAuth {
+    pub user: users::Model,
+    /// 使用 JWT 认证时为 `None`
+    pub token: Option<personal_access_tokens::Model>,
+}
+
+impl ApiAuth {
+    /// 校验权限并返回当前用户
+    ///
+    /// # Errors
+    ///
+    /// Will return unauthorized if the access token lacks the given scope
+    pub fn authorize(self, scope: Scope) -> Result<users::Model> {
+        match &self.token {
+            Some(token) if !token.has_scope(scope) => Err(Error::Unauthorized(format!(
+                "访问令牌缺少权限: {}",
+                scope.as_str()
+            ))),
+            _ => Ok(self.user),
+        }
+    }
+}
+
+impl FromRequestParts<AppContext> for ApiAuth {
+    type Rejection = Error;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        ctx: &AppContext,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        let Some(token) = extract_access_token(&parts.headers) else {
+            let jwt = auth::JWT::from_request_parts(parts, ctx).await?;
+            let user = users::Model::find_by_pid(&ctx.db, &jwt.claims.pid).await?;
+            return Ok(Self { user, token: None });
+        };
+
+        let record = personal_access_tokens::Model::find_active_by_token(&ctx.db, &token)
+            .await?
+            .ok_or_else(|| Error::Unauthorized("访问令牌无效、已过期或已吊销".to_string()))?;
+        let user = users::Entity::find_by_id(record.user_id)
+            .one(&ctx.db)
+            .await?
+            .ok_or_else(|| Error::Unauthorized("访问令牌无效、已过期或已吊销".to_string()))?;
+
+        record.touch(&ctx.db).await?;
+
+        Ok(Self {
+            user,
+            token: Some(record),
+        })
+    }
+}
+
+/// 从请求头中取出个人访问令牌；JWT 不以 `qcp_` 开头，交给 JWT 提取器处理
+fn extract_access_token(headers: &HeaderMap) -> Option<String> {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    let api_key = headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok());
+
+    bearer
+        .into_iter()
+        .chain(api_key)
+        .map(str::trim)
+        .find(|token| token.starts_with(TOKEN_PREFIX))
+        .map(str::to_string)
+}
+
+/// 从 JWT 声明中取出登录会话 ID
+#[must_use]
+pub fn session_id(claims: &jwt::UserClaims) -> Option<&str> {
+    claims
+        .claims
+        .get(SESSION_CLAIM)
+        .and_then(serde_json::Value::as_str)
+}
+
+/// 全局中间件：校验 JWT 对应的登录会话仍然有效
+///
+/// JWT 本身无法撤销，签发时绑定会话 ID，退出登录、修改密码或权限变更后
+/// 吊销会话即可让已签发的令牌立即失效。会话失效时移除认证头，需要登录的
+/// 接口由认证提取器返回 401，登录、刷新等公开接口不受影响。
+/// 签名无效或过期的令牌同样交给认证提取器处理，个人访问令牌不经过此检查。
+pub async fn require_active_session(
+    State(ctx): State<AppContext>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| !token.starts_with(TOKEN_PREFIX))
+        .map(str::to_string);
+
+    if let Some(token) = bearer {
+        match is_session_valid(&ctx, &token).await {
+            Ok(true) => {}
+            Ok(false) => {
+                request.headers_mut().remove(header::AUTHORIZATION);
+            }
+            Err(err) => return err.into_response(),
+        }
+    }
+
+    next.run(request).await
+}
+
+/// 令牌是否可以继续使用：签名无效或已过期的令牌原样交给认证提取器拒绝
+async fn is_session_valid(ctx: &AppContext, token: &str) -> Result<bool> {
+    let jwt_config = ctx.config.get_jwt_config()?;
+    let Ok(token_data) = jwt::JWT::new(&jwt_config.secret).validate(token) else {
+        return Ok(true);
+    };
+
+    // 未绑定会话的旧令牌无法撤销，不再接受
+    let Some(sid) = session_id(&token_data.claims) else {
+        return Ok(false);
+    };
+    let Some(session) = user_sessions::Model::find_active_by_pid(&ctx.db, sid).await? else {
+        return Ok(false);
+    };
+
+    session.touch(&ctx.db).await?;
+    Ok(true)
+}
@@ -1,19 +1,38 @@
+use crate::services::client_ip;
+use crate::services::rate_limit::{Action, RATE_LIMITER};
 use crate::{
+    controllers::{api_auth::session_id, two_factor},
     mailers::auth::AuthMailer,
     models::{
         _entities::users,
+        audit_logs::{self, AuditEntry},
+        user_sessions::{self, DeviceInfo},
         users::{LoginParams, RegisterParams},
     },
-    views::auth::{CurrentResponse, LoginResponse},
+    views::auth::{CurrentResponse, LoginResponse, SessionResponse},
 };
 use axum::debug_handler;
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::{header, request::Parts, HeaderMap, StatusCode};
+use axum::routing::method_routing::delete as axum_delete;
+use data_encoding::BASE64;
+use hmac::{Hmac, Mac};
+use loco_rs::auth::jwt;
+use loco_rs::hash;
 use loco_rs::prelude::*;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::sync::OnceLock;
+use sha2::Sha256;
+use std::net::SocketAddr;
+use std::sync::{LazyLock, OnceLock};
+use std::time::Duration;
 
 pub static EMAIL_DOMAIN_RE: OnceLock<Regex> = OnceLock::new();
 
+/// 登录未注册的邮箱时用于校验的密码哈希
+static DUMMY_PASSWORD_HASH: LazyLock<String> =
+    LazyLock::new(|| hash::hash_password(&uuid::Uuid::new_v4().to_string()).unwrap_or_default());
+
 fn get_allow_email_domain_re() -> &'static Regex {
     EMAIL_DOMAIN_RE.get_or_init(|| {
         Regex::new(r"@example\.com$|@gmail\.com$").expect("Failed to compile regex")
@@ -41,6 +60,142 @@ pub struct ResendVerificationParams {
     pub email: String,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MfaParams {
+    pub mfa_token: String,
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RefreshParams {
+    pub refresh_token: String,
+}
+
+fn header_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+}
+
+/// 记录登录设备
+pub(crate) fn device_info(headers: &HeaderMap, ip: Option<String>) -> DeviceInfo {
+    DeviceInfo {
+        user_agent: header_value(headers, header::USER_AGENT.as_str())
+            .map(|ua| ua.chars().take(255).collect()),
+        ip_address: ip,
+    }
+}
+
+/// 客户端 IP：连接来自可信反向代理时使用代理传递的地址，否则使用连接地址，
+/// 都没有时为 `None`（见 [`client_ip`]）
+pub struct ClientIp(pub Option<String>);
+
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let ip = client_ip::resolve(peer, &parts.headers, &client_ip::trusted_proxies());
+        Ok(Self(ip.map(|ip| ip.to_string())))
+    }
+}
+
+/// 请求过于频繁时返回 429，并通过 `Retry-After` 告知需要等待的秒数
+fn too_many_requests(retry_after: Duration) -> Response {
+    let seconds = retry_after.as_secs().max(1);
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, seconds.to_string())],
+        Json(serde_json::json!({
+            "error": "too_many_requests",
+            "description": format!("尝试次数过多，请 {seconds} 秒后再试"),
+        })),
+    )
+        .into_response()
+}
+
+/// 用途专属的令牌签名器：密钥由 JWT 密钥和用途标记经 HMAC-SHA256 派生，
+/// 签出的令牌不能被当作访问令牌或其他用途的令牌使用
+///
+/// 签名库要求密钥是 base64 编码，因此派生结果编码后再使用。
+pub(crate) fn purpose_signer(ctx: &AppContext, purpose: &str) -> Result<jwt::JWT> {
+    let jwt_config = ctx.config.get_jwt_config()?;
+    let mut mac = Hmac::<Sha256>::new_from_slice(jwt_config.secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(purpose.as_bytes());
+    Ok(jwt::JWT::new(&BASE64.encode(&mac.finalize().into_bytes())))
+}
+
+/// 为用户创建登录会话，签发绑定会话的短期访问令牌和刷新令牌
+pub(crate) async fn issue_session(
+    ctx: &AppContext,
+    user: &users::Model,
+    device: DeviceInfo,
+) -> Result<LoginResponse> {
+    let (session, refresh_token) =
+        user_sessions::Model::create_for_user(&ctx.db, user.id, device).await?;
+    login_response(ctx, user, &session, &refresh_token)
+}
+
+fn login_response(
+    ctx: &AppContext,
+    user: &users::Model,
+    session: &user_sessions::Model,
+    refresh_token: &str,
+) -> Result<LoginResponse> {
+    let jwt_secret = ctx.config.get_jwt_config()?;
+
+    let token = user
+        .generate_session_jwt(&jwt_secret.secret, jwt_secret.expiration, &session.pid)
+        .or_else(|_| unauthorized("unauthorized!"))?;
+
+    Ok(LoginResponse::new(
+        user,
+        &token,
+        refresh_token,
+        jwt_secret.expiration,
+    ))
+}
+
+/// 当前请求所属的登录会话
+async fn current_session(
+    ctx: &AppContext,
+    auth: &auth::JWT,
+) -> Result<(users::Model, user_sessions::Model)> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let session = match session_id(&auth.claims) {
+        Some(sid) => user_sessions::Model::find_active_by_pid(&ctx.db, sid).await?,
+        None => None,
+    };
+    match session {
+        Some(session) if session.user_id == user.id => Ok((user, session)),
+        _ => unauthorized("登录已失效，请重新登录"),
+    }
+}
+
+/// 账号的邮件发送是否超出频率限制（超出时记录审计日志）
+async fn email_rate_limited(action: Action, user: &users::Model) -> bool {
+    let pid = user.pid.to_string();
+    if RATE_LIMITER.check_email(action, &pid).await.is_none() {
+        return false;
+    }
+    tracing::warn!(
+        target: "audit",
+        pid,
+        action = action.as_str(),
+        "email rate limit exceeded"
+    );
+    true
+}
+
 /// Register function creates a new user with the given parameters and sends a
 /// welcome email to the user
 #[debug_handler]
@@ -108,14 +263,27 @@ async fn verify(State(ctx): State<AppContext>, Path(token): Path<String>) -> Res
 #[debug_handler]
 async fn forgot(
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
     Json(params): Json<ForgotParams>,
 ) -> Result<Response> {
+    if let Some(retry_after) = RATE_LIMITER
+        .check_ip(Action::ForgotPassword, ip.as_deref())
+        .await
+    {
+        return Ok(too_many_requests(retry_after));
+    }
+
     let Ok(user) = users::Model::find_by_email(&ctx.db, &params.email).await else {
         // we don't want to expose our users email. if the email is invalid we still
         // returning success to the caller
         return format::json(());
     };
 
+    // 超出发送频率时同样返回成功，避免暴露账号是否存在
+    if email_rate_limited(Action::ForgotPassword, &user).await {
+        return format::json(());
+    }
+
     let user = user
         .into_active_model()
         .set_forgot_password_sent(&ctx.db)
@@ -145,28 +313,103 @@ async fn reset(State(ctx): State<AppContext>, Json(params): Json<ResetParams>) -
 
 /// Creates a user login and returns a token
 #[debug_handler]
-async fn login(State(ctx): State<AppContext>, Json(params): Json<LoginParams>) -> Result<Response> {
-    let Ok(user) = users::Model::find_by_email(&ctx.db, &params.email).await else {
+async fn login(
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+    Json(params): Json<LoginParams>,
+) -> Result<Response> {
+    // 按来源 IP 只统计失败的登录，同一出口下的正常用户不受影响
+    if let Some(retry_after) = RATE_LIMITER.ip_limited(Action::Login, ip.as_deref()).await {
+        tracing::warn!(
+            target: "audit",
+            ip = ip.as_deref(),
+            "login rate limit exceeded"
+        );
+        return Ok(too_many_requests(retry_after));
+    }
+
+    // 未注册的邮箱同样计数和锁定，并校验一次密码哈希，使响应内容和耗时都与已注册账号一致，
+    // 无法据此判断邮箱是否注册
+    let user = users::Model::find_by_email(&ctx.db, &params.email)
+        .await
+        .ok();
+    let account = user.as_ref().map_or_else(
+        || format!("email:{}", params.email.trim().to_lowercase()),
+        |user| user.pid.to_string(),
+    );
+
+    // 账号锁定期间即使密码正确也不允许登录
+    if let Some(retry_after) = RATE_LIMITER.lockout_remaining(&account).await {
+        return Ok(too_many_requests(retry_after));
+    }
+
+    let Some(user) = user else {
         tracing::debug!(
             email = params.email,
             "login attempt with non-existent email"
         );
-        return unauthorized("Invalid credentials!");
+        let _ = hash::verify_password(&params.password, &DUMMY_PASSWORD_HASH);
+        RATE_LIMITER.check_ip(Action::Login, ip.as_deref()).await;
+        RATE_LIMITER.record_failure(&account).await;
+        return unauthorized("unauthorized!");
     };
 
-    let valid = user.verify_password(&params.password);
-
-    if !valid {
+    if !user.verify_password(&params.password) {
+        RATE_LIMITER.check_ip(Action::Login, ip.as_deref()).await;
+        record_login_failure(&ctx, &user, ip).await;
         return unauthorized("unauthorized!");
     }
 
-    let jwt_secret = ctx.config.get_jwt_config()?;
+    // 已启用两步验证时，需要再提交验证码才签发令牌；失败计数在第二步通过后才清除，
+    // 避免知道密码的人通过反复重新登录绕过验证码的锁定
+    if let Some(challenge) = two_factor::login_challenge(&ctx, &user).await? {
+        return format::json(challenge);
+    }
+    RATE_LIMITER.record_success(&account).await;
 
-    let token = user
-        .generate_jwt(&jwt_secret.secret, jwt_secret.expiration)
-        .or_else(|_| unauthorized("unauthorized!"))?;
+    format::json(issue_session(&ctx, &user, device_info(&headers, ip)).await?)
+}
+
+/// 记录一次登录失败（密码或验证码错误），达到上限锁定账号时写入审计日志
+async fn record_login_failure(ctx: &AppContext, user: &users::Model, ip: Option<String>) {
+    if let Some(lockout) = RATE_LIMITER.record_failure(&user.pid.to_string()).await {
+        audit_logs::record(
+            &ctx.db,
+            AuditEntry::new("user.lock")
+                .target("user", user.id)
+                .after(serde_json::json!({ "lockout_secs": lockout.as_secs() }))
+                .ip(ip)
+                .source(audit_logs::SOURCE_SYSTEM),
+        )
+        .await;
+    }
+}
+
+/// 登录第二步：提交验证器中的验证码或恢复码
+///
+/// 验证码错误与密码错误计入同一个账号锁定计数
+#[debug_handler]
+async fn login_mfa(
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+    Json(params): Json<MfaParams>,
+) -> Result<Response> {
+    let user = two_factor::challenge_user(&ctx, &params.mfa_token).await?;
+
+    let account = user.pid.to_string();
+    if let Some(retry_after) = RATE_LIMITER.lockout_remaining(&account).await {
+        return Ok(too_many_requests(retry_after));
+    }
+
+    if !two_factor::verify_login_code(&ctx, &user, &params.code).await? {
+        record_login_failure(&ctx, &user, ip).await;
+        return unauthorized("验证码错误");
+    }
+    RATE_LIMITER.record_success(&account).await;
 
-    format::json(LoginResponse::new(&user, &token))
+    format::json(issue_session(&ctx, &user, device_info(&headers, ip)).await?)
 }
 
 #[debug_handler]
@@ -191,8 +434,16 @@ async fn current(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Respo
 /// This flow enhances security by avoiding traditional passwords and providing a seamless login experience.
 async fn magic_link(
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
     Json(params): Json<MagicLinkParams>,
 ) -> Result<Response> {
+    if let Some(retry_after) = RATE_LIMITER
+        .check_ip(Action::MagicLink, ip.as_deref())
+        .await
+    {
+        return Ok(too_many_requests(retry_after));
+    }
+
     let email_regex = get_allow_email_domain_re();
     if !email_regex.is_match(&params.email) {
         tracing::debug!(
@@ -209,6 +460,10 @@ async fn magic_link(
         return format::empty_json();
     };
 
+    if email_rate_limited(Action::MagicLink, &user).await {
+        return format::empty_json();
+    }
+
     let user = user.into_active_model().create_magic_link(&ctx.db).await?;
     AuthMailer::send_magic_link(&ctx, &user).await?;
 
@@ -219,6 +474,8 @@ async fn magic_link(
 async fn magic_link_verify(
     Path(token): Path<String>,
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
 ) -> Result<Response> {
     let Ok(user) = users::Model::find_by_magic_token(&ctx.db, &token).await else {
         // we don't want to expose our users email. if the email is invalid we still
@@ -228,20 +485,26 @@ async fn magic_link_verify(
 
     let user = user.into_active_model().clear_magic_link(&ctx.db).await?;
 
-    let jwt_secret = ctx.config.get_jwt_config()?;
-
-    let token = user
-        .generate_jwt(&jwt_secret.secret, jwt_secret.expiration)
-        .or_else(|_| unauthorized("unauthorized!"))?;
+    if let Some(challenge) = two_factor::login_challenge(&ctx, &user).await? {
+        return format::json(challenge);
+    }
 
-    format::json(LoginResponse::new(&user, &token))
+    format::json(issue_session(&ctx, &user, device_info(&headers, ip)).await?)
 }
 
 #[debug_handler]
 async fn resend_verification_email(
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
     Json(params): Json<ResendVerificationParams>,
 ) -> Result<Response> {
+    if let Some(retry_after) = RATE_LIMITER
+        .check_ip(Action::ResendVerification, ip.as_deref())
+        .await
+    {
+        return Ok(too_many_requests(retry_after));
+    }
+
     let Ok(user) = users::Model::find_by_email(&ctx.db, &params.email).await else {
         tracing::info!(
             email = params.email,
@@ -258,6 +521,10 @@ async fn resend_verification_email(
         return format::json(());
     }
 
+    if email_rate_limited(Action::ResendVerification, &user).await {
+        return format::json(());
+    }
+
     let user = user
         .into_active_model()
         .set_email_verification_sent(&ctx.db)
@@ -269,16 +536,96 @@ async fn resend_verification_email(
     format::json(())
 }
 
+/// 使用刷新令牌换取新的访问令牌，刷新令牌同时轮换（旧令牌失效）
+#[debug_handler]
+async fn refresh(
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+    Json(params): Json<RefreshParams>,
+) -> Result<Response> {
+    let Some((session, refresh_token)) = user_sessions::Model::rotate(
+        &ctx.db,
+        params.refresh_token.trim(),
+        device_info(&headers, ip),
+    )
+    .await?
+    else {
+        return unauthorized("刷新令牌无效或已过期，请重新登录");
+    };
+
+    let Some(user) = users::Entity::find_by_id(session.user_id)
+        .one(&ctx.db)
+        .await?
+    else {
+        return unauthorized("刷新令牌无效或已过期，请重新登录");
+    };
+
+    format::json(login_response(&ctx, &user, &session, &refresh_token)?)
+}
+
+/// 退出当前设备
+#[debug_handler]
+async fn logout(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let (_, session) = current_session(&ctx, &auth).await?;
+    session.revoke(&ctx.db).await?;
+    format::json(())
+}
+
+/// 退出所有设备
+#[debug_handler]
+async fn logout_all(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let (user, _) = current_session(&ctx, &auth).await?;
+    let revoked = user_sessions::Model::revoke_all_for_user(&ctx.db, user.id).await?;
+    format::json(serde_json::json!({ "revoked": revoked }))
+}
+
+/// 列出当前用户已登录的设备
+#[debug_handler]
+async fn sessions(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let (user, current) = current_session(&ctx, &auth).await?;
+    let current_sid = current.pid.to_string();
+
+    let sessions = user_sessions::Model::find_active_by_user(&ctx.db, user.id).await?;
+    let responses: Vec<SessionResponse> = sessions
+        .into_iter()
+        .map(|session| SessionResponse::new(session, Some(&current_sid)))
+        .collect();
+    format::json(responses)
+}
+
+/// 退出指定设备
+#[debug_handler]
+async fn revoke_session(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let (user, _) = current_session(&ctx, &auth).await?;
+    let session = user_sessions::Model::find_for_user(&ctx.db, user.id, id)
+        .await?
+        .ok_or_else(|| Error::NotFound)?;
+
+    session.revoke(&ctx.db).await?;
+    format::empty()
+}
+
 pub fn routes() -> Routes {
     Routes::new()
         .prefix("/api/auth")
         .add("/register", post(register))
         .add("/verify/{token}", get(verify))
         .add("/login", post(login))
+        .add("/login/mfa", post(login_mfa))
         .add("/forgot", post(forgot))
         .add("/reset", post(reset))
         .add("/current", get(current))
         .add("/magic-link", post(magic_link))
         .add("/magic-link/{token}", get(magic_link_verify))
         .add("/resend-verification-mail", post(resend_verification_email))
+        .add("/refresh", post(refresh))
+        .add("/logout", post(logout))
+        .add("/logout-all", post(logout_all))
+        .add("/sessions", get(sessions))
+        .add("/sessions/{id}", axum_delete(revoke_session))
 }
@@ -7,10 +7,19 @@ use axum::routing::method_routing::delete as axum_delete;
 use loco_rs::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use crate::controllers::api_auth::ApiAuth;
+use crate::controllers::auth::ClientIp;
 use crate::models::_entities::books::{ActiveModel, Column, Entity, Model};
 use crate::models::_entities::{chapters, medias};
-use crate::models::users;
-use crate::views::books::{BookResponse, BookTreeResponse};
+use crate::models::audit_logs::{self, AuditEntry};
+use crate::models::personal_access_tokens::Scope;
+use crate::models::site_settings;
+use crate::services::publishing::{self, ScheduleParams};
+use crate::services::trash::{self, ChildBooks};
+use crate::services::{audio_processing, content_transfer, podcast_feed};
+use crate::views::books::{
+    AudioProcessingSettingsResponse, BookResponse, BookTreeResponse, FeedSettingsResponse,
+};
 use sea_orm::PaginatorTrait;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -38,21 +47,110 @@ pub struct ReorderParams {
     pub sort_order: i32,
 }
 
+/// 移动书籍，`new_parent_id` 为空表示移到顶级
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MoveBookParams {
+    pub new_parent_id: Option<i32>,
+    pub new_sort_order: Option<i32>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DeleteParams {
+    /// 子书籍的处理方式：`reparent`（默认）或 `cascade`
+    #[serde(default)]
+    pub children: ChildBooks,
+}
+
+/// 播客订阅源设置，空字符串表示清除该项
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FeedSettingsParams {
+    pub author: Option<String>,
+    pub owner_email: Option<String>,
+    pub language: Option<String>,
+    pub category: Option<String>,
+    pub explicit: Option<bool>,
+    pub copyright: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AudioProcessingParams {
+    /// 开启后音频统一响度并去掉首尾静音，播放时使用处理后的版本
+    pub normalize_audio: bool,
+}
+
+/// 将空字符串转换为 None
+fn non_empty(value: String) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CopyBookParams {
+    /// 为 true 时新媒体与原媒体共用文件，不复制文件内容（按内容存储的文件总是共用）
+    #[serde(default)]
+    pub share_files: bool,
+}
+
+async fn feed_url(ctx: &AppContext, book_id: i32) -> Result<String> {
+    let site_url = site_settings::Model::site_url(&ctx.db).await?;
+    Ok(podcast_feed::feed_url(&site_url, book_id))
+}
+
 async fn load_item(ctx: &AppContext, id: i32, user_id: i32) -> Result<Model> {
     let item = Entity::find_by_id(id)
         .filter(Column::UserId.eq(user_id))
+        .filter(Column::DeletedAt.is_null())
         .one(&ctx.db)
         .await?;
     item.ok_or_else(|| Error::NotFound)
 }
 
+/// 校验上级书籍：必须是当前用户未删除的书籍，且移动后不能形成循环
+async fn validate_parent(
+    ctx: &AppContext,
+    user_id: i32,
+    book_id: Option<i32>,
+    parent_id: i32,
+) -> Result<()> {
+    Entity::find_by_id(parent_id)
+        .filter(Column::UserId.eq(user_id))
+        .filter(Column::DeletedAt.is_null())
+        .one(&ctx.db)
+        .await?
+        .ok_or_else(|| Error::BadRequest("上级书籍不存在".to_string()))?;
+    if let Some(book_id) = book_id {
+        if Model::would_create_cycle(&ctx.db, book_id, parent_id).await? {
+            return Err(Error::BadRequest(
+                "不能把书籍移动到自身或其子书籍下".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// 审计日志中记录的书籍摘要
+fn audit_summary(item: &Model) -> serde_json::Value {
+    serde_json::json!({
+        "title": item.title,
+        "parent_id": item.parent_id,
+        "is_public": item.is_public,
+        "publish_at": item.publish_at,
+        "unpublish_at": item.unpublish_at,
+    })
+}
+
 /// 获取当前用户的所有书籍
 #[debug_handler]
-pub async fn list(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+pub async fn list(auth: ApiAuth, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksRead)?;
 
     let books = Entity::find()
         .filter(Column::UserId.eq(user.id))
+        .filter(Column::DeletedAt.is_null())
         .all(&ctx.db)
         .await?;
 
@@ -61,12 +159,14 @@ pub async fn list(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Resp
         // 统计该书籍的媒体数量
         let media_count = medias::Entity::find()
             .filter(medias::Column::BookId.eq(book.id))
+            .filter(medias::Column::DeletedAt.is_null())
             .count(&ctx.db)
             .await?;
 
         // 统计该书籍的章节数量
         let chapter_count = chapters::Entity::find()
             .filter(chapters::Column::BookId.eq(book.id))
+            .filter(chapters::Column::DeletedAt.is_null())
             .count(&ctx.db)
             .await?;
 
@@ -82,11 +182,15 @@ pub async fn list(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Resp
 /// 创建书籍
 #[debug_handler]
 pub async fn create(
-    auth: auth::JWT,
+    auth: ApiAuth,
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
     Json(params): Json<CreateParams>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksWrite)?;
+    if let Some(parent_id) = params.parent_id {
+        validate_parent(&ctx, user.id, None, parent_id).await?;
+    }
 
     let item = ActiveModel {
         user_id: Set(user.id),
@@ -101,6 +205,16 @@ pub async fn create(
 
     let item = item.insert(&ctx.db).await?;
 
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("book.create")
+            .actor(&user)
+            .target("book", item.id)
+            .after(audit_summary(&item))
+            .ip(ip),
+    )
+    .await;
+
     let mut response = BookResponse::from(item);
     response.media_count = Some(0);
     response.chapter_count = Some(0);
@@ -111,22 +225,24 @@ pub async fn create(
 /// 获取书籍详情
 #[debug_handler]
 pub async fn show(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path(id): Path<i32>,
     State(ctx): State<AppContext>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksRead)?;
     let item = load_item(&ctx, id, user.id).await?;
 
     // 统计该书籍的媒体数量
     let media_count = medias::Entity::find()
         .filter(medias::Column::BookId.eq(item.id))
+        .filter(medias::Column::DeletedAt.is_null())
         .count(&ctx.db)
         .await?;
 
     // 统计该书籍的章节数量
     let chapter_count = chapters::Entity::find()
         .filter(chapters::Column::BookId.eq(item.id))
+        .filter(chapters::Column::DeletedAt.is_null())
         .count(&ctx.db)
         .await?;
 
@@ -140,13 +256,18 @@ pub async fn show(
 /// 更新书籍
 #[debug_handler]
 pub async fn update(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path(id): Path<i32>,
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
     Json(params): Json<UpdateParams>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksWrite)?;
     let item = load_item(&ctx, id, user.id).await?;
+    if let Some(parent_id) = params.parent_id {
+        validate_parent(&ctx, user.id, Some(id), parent_id).await?;
+    }
+    let before = audit_summary(&item);
 
     let mut item = item.into_active_model();
 
@@ -171,15 +292,28 @@ pub async fn update(
 
     let item = item.update(&ctx.db).await?;
 
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("book.update")
+            .actor(&user)
+            .target("book", item.id)
+            .before(before)
+            .after(audit_summary(&item))
+            .ip(ip),
+    )
+    .await;
+
     // 统计该书籍的媒体数量
     let media_count = medias::Entity::find()
         .filter(medias::Column::BookId.eq(item.id))
+        .filter(medias::Column::DeletedAt.is_null())
         .count(&ctx.db)
         .await?;
 
     // 统计该书籍的章节数量
     let chapter_count = chapters::Entity::find()
         .filter(chapters::Column::BookId.eq(item.id))
+        .filter(chapters::Column::DeletedAt.is_null())
         .count(&ctx.db)
         .await?;
 
@@ -190,28 +324,45 @@ pub async fn update(
     format::json(response)
 }
 
-/// 删除书籍
+/// 删除书籍（连同其中的章节和媒体移入回收站）
+///
+/// 子书籍默认移到被删除书籍的上级下，`?children=cascade` 时一起移入回收站
 #[debug_handler]
 pub async fn delete(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path(id): Path<i32>,
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    Query(params): Query<DeleteParams>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksWrite)?;
     let item = load_item(&ctx, id, user.id).await?;
+    let mut before = audit_summary(&item);
+    before["children"] = serde_json::json!(params.children);
+
+    trash::trash_book(&ctx, &item, params.children).await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("book.delete")
+            .actor(&user)
+            .target("book", id)
+            .before(before)
+            .ip(ip),
+    )
+    .await;
 
-    item.delete(&ctx.db).await?;
     format::empty()
 }
 
 /// 搜索书籍
 #[allow(clippy::implicit_hasher)]
 pub async fn search(
-    auth: auth::JWT,
+    auth: ApiAuth,
     State(ctx): State<AppContext>,
     Query(params): Query<std::collections::HashMap<String, String>>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksRead)?;
 
     let query = params.get("q").map_or("", |q| q.as_str());
 
@@ -227,12 +378,14 @@ pub async fn search(
         // 统计该书籍的媒体数量
         let media_count = medias::Entity::find()
             .filter(medias::Column::BookId.eq(book.id))
+            .filter(medias::Column::DeletedAt.is_null())
             .count(&ctx.db)
             .await?;
 
         // 统计该书籍的章节数量
         let chapter_count = chapters::Entity::find()
             .filter(chapters::Column::BookId.eq(book.id))
+            .filter(chapters::Column::DeletedAt.is_null())
             .count(&ctx.db)
             .await?;
 
@@ -248,11 +401,11 @@ pub async fn search(
 /// 获取书籍的完整树形结构
 #[debug_handler]
 pub async fn tree(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path(id): Path<i32>,
     State(ctx): State<AppContext>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksRead)?;
 
     // 验证用户是否有权限访问该书籍
     let _ = load_item(&ctx, id, user.id).await?;
@@ -262,15 +415,64 @@ pub async fn tree(
     format::json(tree_response)
 }
 
+/// 获取当前用户所有书籍的树形结构（含媒体数量和时长的汇总）
+#[debug_handler]
+pub async fn user_tree(auth: ApiAuth, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksRead)?;
+    let tree = Model::get_user_tree(&ctx.db, user.id).await?;
+    format::json(
+        tree.into_iter()
+            .map(BookTreeResponse::from)
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// 移动书籍到另一本书籍下或顶级
+#[debug_handler]
+pub async fn move_book(
+    auth: ApiAuth,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    Json(params): Json<MoveBookParams>,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksWrite)?;
+    let item = load_item(&ctx, id, user.id).await?;
+    if let Some(parent_id) = params.new_parent_id {
+        validate_parent(&ctx, user.id, Some(id), parent_id).await?;
+    }
+    let before = audit_summary(&item);
+
+    let mut item = item.into_active_model();
+    item.parent_id = Set(params.new_parent_id);
+    if let Some(sort_order) = params.new_sort_order {
+        item.sort_order = Set(Some(sort_order));
+    }
+    let item = item.update(&ctx.db).await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("book.move")
+            .actor(&user)
+            .target("book", id)
+            .before(before)
+            .after(audit_summary(&item))
+            .ip(ip),
+    )
+    .await;
+
+    format::json(BookTreeResponse::from(Model::get_tree(&ctx.db, id).await?))
+}
+
 /// 调整书籍顺序
 #[debug_handler]
 pub async fn reorder(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path(id): Path<i32>,
     State(ctx): State<AppContext>,
     Json(params): Json<ReorderParams>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksWrite)?;
     let item = load_item(&ctx, id, user.id).await?;
 
     let mut item = item.into_active_model();
@@ -280,12 +482,14 @@ pub async fn reorder(
     // 统计该书籍的媒体数量
     let media_count = medias::Entity::find()
         .filter(medias::Column::BookId.eq(item.id))
+        .filter(medias::Column::DeletedAt.is_null())
         .count(&ctx.db)
         .await?;
 
     // 统计该书籍的章节数量
     let chapter_count = chapters::Entity::find()
         .filter(chapters::Column::BookId.eq(item.id))
+        .filter(chapters::Column::DeletedAt.is_null())
         .count(&ctx.db)
         .await?;
 
@@ -296,16 +500,234 @@ pub async fn reorder(
     format::json(response)
 }
 
+/// 获取书籍的播客订阅源设置
+#[debug_handler]
+pub async fn get_feed_settings(
+    auth: ApiAuth,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksRead)?;
+    let item = load_item(&ctx, id, user.id).await?;
+
+    let feed_url = feed_url(&ctx, item.id).await?;
+    format::json(FeedSettingsResponse::new(&item, feed_url))
+}
+
+/// 更新书籍的播客订阅源设置
+#[debug_handler]
+pub async fn update_feed_settings(
+    auth: ApiAuth,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    Json(params): Json<FeedSettingsParams>,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksWrite)?;
+    let item = load_item(&ctx, id, user.id).await?;
+
+    if let Some(email) = params.owner_email.as_deref().map(str::trim) {
+        if !email.is_empty() && !email.contains('@') {
+            return bad_request("无效的邮箱地址");
+        }
+    }
+
+    let mut item = item.into_active_model();
+
+    if let Some(author) = params.author {
+        item.feed_author = Set(non_empty(author));
+    }
+    if let Some(owner_email) = params.owner_email {
+        item.feed_owner_email = Set(non_empty(owner_email));
+    }
+    if let Some(language) = params.language {
+        item.feed_language = Set(non_empty(language).map(|l| l.to_lowercase()));
+    }
+    if let Some(category) = params.category {
+        item.feed_category = Set(non_empty(category));
+    }
+    if let Some(explicit) = params.explicit {
+        item.feed_explicit = Set(Some(explicit));
+    }
+    if let Some(copyright) = params.copyright {
+        item.feed_copyright = Set(non_empty(copyright));
+    }
+
+    let item = item.update(&ctx.db).await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("book.update_feed")
+            .actor(&user)
+            .target("book", item.id)
+            .after(serde_json::json!({
+                "author": item.feed_author,
+                "owner_email": item.feed_owner_email,
+                "language": item.feed_language,
+                "category": item.feed_category,
+                "explicit": item.feed_explicit,
+                "copyright": item.feed_copyright,
+            }))
+            .ip(ip),
+    )
+    .await;
+
+    let feed_url = feed_url(&ctx, item.id).await?;
+    format::json(FeedSettingsResponse::new(&item, feed_url))
+}
+
+/// 获取书籍的音频处理设置
+#[debug_handler]
+pub async fn get_audio_processing(
+    auth: ApiAuth,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksRead)?;
+    let item = load_item(&ctx, id, user.id).await?;
+
+    format::json(AudioProcessingSettingsResponse::new(
+        &item,
+        audio_processing::target_lufs(),
+    ))
+}
+
+/// 更新书籍的音频处理设置，开启时为还没有播放版本的音频提交处理任务
+#[debug_handler]
+pub async fn update_audio_processing(
+    auth: ApiAuth,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    Json(params): Json<AudioProcessingParams>,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksWrite)?;
+    let item = load_item(&ctx, id, user.id).await?;
+    let before = item.normalize_audio;
+
+    let mut item = item.into_active_model();
+    item.normalize_audio = Set(params.normalize_audio);
+    let item = item.update(&ctx.db).await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("book.update_audio_processing")
+            .actor(&user)
+            .target("book", item.id)
+            .before(serde_json::json!({ "normalize_audio": before }))
+            .after(serde_json::json!({ "normalize_audio": item.normalize_audio }))
+            .ip(ip),
+    )
+    .await;
+
+    audio_processing::enqueue_book(&ctx, &item).await?;
+
+    format::json(AudioProcessingSettingsResponse::new(
+        &item,
+        audio_processing::target_lufs(),
+    ))
+}
+
+/// 设置书籍的定时发布和下架时间
+///
+/// 发布时间在未来时书籍先取消公开，到时间后由后台任务自动发布
+#[debug_handler]
+pub async fn schedule(
+    auth: ApiAuth,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    Json(params): Json<ScheduleParams>,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksWrite)?;
+    params.validate()?;
+    let item = load_item(&ctx, id, user.id).await?;
+    let before = audit_summary(&item);
+
+    let mut item = item.into_active_model();
+    if params
+        .publish_at
+        .is_some_and(|at| !publishing::is_due(Some(at)))
+    {
+        item.is_public = Set(Some(false));
+    }
+    item.publish_at = Set(params.publish_at);
+    item.unpublish_at = Set(params.unpublish_at);
+    let item = item.update(&ctx.db).await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("book.schedule")
+            .actor(&user)
+            .target("book", item.id)
+            .before(before)
+            .after(audit_summary(&item))
+            .ip(ip),
+    )
+    .await;
+
+    format::json(BookResponse::from(item))
+}
+
+/// 复制整本书（章节结构、媒体和字幕），新书默认不公开
+#[debug_handler]
+pub async fn copy(
+    auth: ApiAuth,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    Json(params): Json<CopyBookParams>,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksWrite)?;
+    let item = load_item(&ctx, id, user.id).await?;
+    let site_url = site_settings::Model::site_url(&ctx.db).await?;
+
+    let (copy, summary) =
+        content_transfer::copy_book(&ctx.db, &item, &site_url, params.share_files).await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("book.copy")
+            .actor(&user)
+            .target("book", copy.id)
+            .after(serde_json::json!({
+                "source_id": item.id,
+                "title": copy.title,
+                "chapters": summary.chapters,
+                "medias": summary.medias,
+                "share_files": params.share_files,
+            }))
+            .ip(ip),
+    )
+    .await;
+
+    // 复制出的媒体没有播放版本，开启了音频处理时重新生成
+    audio_processing::enqueue_book(&ctx, &copy).await?;
+
+    let mut response = BookResponse::from(copy);
+    response.media_count = Some(i64::try_from(summary.medias).unwrap_or(i64::MAX));
+    response.chapter_count = Some(i64::try_from(summary.chapters).unwrap_or(i64::MAX));
+    format::json(response)
+}
+
 pub fn routes() -> Routes {
     Routes::new()
         .prefix("/api/books")
         .add("/", get(list))
         .add("/", post(create))
         .add("/search", get(search))
+        .add("/tree", get(user_tree))
         .add("/{id}", get(show))
         .add("/{id}", put(update))
         .add("/{id}", patch(update))
         .add("/{id}", axum_delete(delete))
         .add("/{id}/tree", get(tree))
+        .add("/{id}/move", post(move_book))
         .add("/{id}/reorder", post(reorder))
+        .add("/{id}/feed", get(get_feed_settings))
+        .add("/{id}/feed", put(update_feed_settings))
+        .add("/{id}/audio-processing", get(get_audio_processing))
+        .add("/{id}/audio-processing", put(update_audio_processing))
+        .add("/{id}/schedule", put(schedule))
+        .add("/{id}/copy", post(copy))
 }
@@ -7,9 +7,14 @@ use axum::routing::method_routing::delete as axum_delete;
 use loco_rs::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::models::_entities::chapters::{ActiveModel, Entity, Model};
+use crate::controllers::api_auth::ApiAuth;
+use crate::controllers::auth::ClientIp;
+use crate::models::_entities::chapters::{ActiveModel, Column, Entity, Model};
+use crate::models::audit_logs::{self, AuditEntry};
 use crate::models::books;
-use crate::models::users;
+use crate::models::personal_access_tokens::Scope;
+use crate::models::site_settings;
+use crate::services::{content_transfer, locations, trash};
 use crate::views::chapters::ChapterResponse;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -50,13 +55,40 @@ pub struct MoveChapterParams {
     pub new_sort_order: Option<i32>,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MoveToBookParams {
+    pub target_book_id: i32,
+    /// 目标书籍中的父章节，不填则作为顶级章节
+    pub new_parent_id: Option<i32>,
+    pub new_sort_order: Option<i32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CopyChapterParams {
+    /// 目标书籍，不填则复制到当前书籍
+    pub target_book_id: Option<i32>,
+    /// 目标父章节，不填则作为顶级章节
+    pub parent_id: Option<i32>,
+    /// 为 true 时新媒体与原媒体共用文件，不复制文件内容（按内容存储的文件总是共用）
+    #[serde(default)]
+    pub share_files: bool,
+}
+
+async fn get_site_url(ctx: &AppContext) -> Result<String> {
+    Ok(site_settings::Model::site_url(&ctx.db).await?)
+}
+
 async fn load_item(ctx: &AppContext, id: i32, user_id: i32) -> Result<Model> {
-    let item = Entity::find_by_id(id).one(&ctx.db).await?;
+    let item = Entity::find_by_id(id)
+        .filter(Column::DeletedAt.is_null())
+        .one(&ctx.db)
+        .await?;
 
     if let Some(ref chapter) = item {
         // 验证书籍是否属于当前用户
         let book = books::Entity::find_by_id(chapter.book_id)
             .filter(books::Column::UserId.eq(user_id))
+            .filter(books::Column::DeletedAt.is_null())
             .one(&ctx.db)
             .await?;
 
@@ -68,18 +100,29 @@ async fn load_item(ctx: &AppContext, id: i32, user_id: i32) -> Result<Model> {
     item.ok_or_else(|| Error::NotFound)
 }
 
+/// 审计日志中记录的章节摘要
+fn audit_summary(item: &Model) -> serde_json::Value {
+    serde_json::json!({
+        "book_id": item.book_id,
+        "title": item.title,
+        "parent_id": item.parent_id,
+        "sort_order": item.sort_order,
+    })
+}
+
 /// 获取书籍的章节列表
 #[debug_handler]
 pub async fn list(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path(book_id): Path<i32>,
     State(ctx): State<AppContext>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksRead)?;
 
     // 验证用户是否有权限访问该书籍
     let _book = books::Entity::find_by_id(book_id)
         .filter(books::Column::UserId.eq(user.id))
+        .filter(books::Column::DeletedAt.is_null())
         .one(&ctx.db)
         .await?
         .ok_or_else(|| Error::NotFound)?;
@@ -97,16 +140,17 @@ pub async fn list(
 #[debug_handler]
 #[allow(clippy::implicit_hasher)]
 pub async fn search(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path(book_id): Path<i32>,
     State(ctx): State<AppContext>,
     Query(params): Query<std::collections::HashMap<String, String>>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksRead)?;
 
     // 验证用户是否有权限访问该书籍
     let _book = books::Entity::find_by_id(book_id)
         .filter(books::Column::UserId.eq(user.id))
+        .filter(books::Column::DeletedAt.is_null())
         .one(&ctx.db)
         .await?
         .ok_or_else(|| Error::NotFound)?;
@@ -127,16 +171,18 @@ pub async fn search(
 /// 创建章节
 #[debug_handler]
 pub async fn create(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path(book_id): Path<i32>,
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
     Json(params): Json<CreateParams>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksWrite)?;
 
     // 验证用户是否有权限访问该书籍
     let _book = books::Entity::find_by_id(book_id)
         .filter(books::Column::UserId.eq(user.id))
+        .filter(books::Column::DeletedAt.is_null())
         .one(&ctx.db)
         .await?
         .ok_or_else(|| Error::NotFound)?;
@@ -196,17 +242,27 @@ pub async fn create(
         .await?
         .ok_or(Error::NotFound)?;
 
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("chapter.create")
+            .actor(&user)
+            .target("chapter", updated_item.id)
+            .after(audit_summary(&updated_item))
+            .ip(ip),
+    )
+    .await;
+
     format::json(ChapterResponse::from(updated_item))
 }
 
 /// 获取章节详情
 #[debug_handler]
 pub async fn show(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path((_book_id, id)): Path<(i32, i32)>,
     State(ctx): State<AppContext>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksRead)?;
     let item = load_item(&ctx, id, user.id).await?;
 
     format::json(ChapterResponse::from(item))
@@ -215,13 +271,15 @@ pub async fn show(
 /// 更新章节
 #[debug_handler]
 pub async fn update(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path((_book_id, id)): Path<(i32, i32)>,
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
     Json(params): Json<UpdateParams>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksWrite)?;
     let item = load_item(&ctx, id, user.id).await?;
+    let before = audit_summary(&item);
 
     let mut item = item.into_active_model();
 
@@ -237,32 +295,56 @@ pub async fn update(
 
     let item = item.update(&ctx.db).await?;
 
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("chapter.update")
+            .actor(&user)
+            .target("chapter", item.id)
+            .before(before)
+            .after(audit_summary(&item))
+            .ip(ip),
+    )
+    .await;
+
     format::json(ChapterResponse::from(item))
 }
 
-/// 删除章节
+/// 删除章节（连同子章节和其中的媒体移入回收站）
 #[debug_handler]
 pub async fn delete(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path((_book_id, id)): Path<(i32, i32)>,
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksWrite)?;
     let item = load_item(&ctx, id, user.id).await?;
+    let before = audit_summary(&item);
+
+    trash::trash_chapter(&ctx, &item).await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("chapter.delete")
+            .actor(&user)
+            .target("chapter", id)
+            .before(before)
+            .ip(ip),
+    )
+    .await;
 
-    item.delete(&ctx.db).await?;
     format::empty()
 }
 
 /// 调整章节顺序
 #[debug_handler]
 pub async fn reorder(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path((_book_id, id)): Path<(i32, i32)>,
     State(ctx): State<AppContext>,
     Json(params): Json<ReorderParams>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksWrite)?;
     let item = load_item(&ctx, id, user.id).await?;
 
     let mut item = item.into_active_model();
@@ -275,16 +357,17 @@ pub async fn reorder(
 /// 批量重排序章节
 #[debug_handler]
 pub async fn batch_reorder(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path(book_id): Path<i32>,
     State(ctx): State<AppContext>,
     Json(params): Json<BatchReorderParams>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksWrite)?;
 
     // 验证用户是否有权限访问该书籍
     let _book = books::Entity::find_by_id(book_id)
         .filter(books::Column::UserId.eq(user.id))
+        .filter(books::Column::DeletedAt.is_null())
         .one(&ctx.db)
         .await?
         .ok_or_else(|| Error::NotFound)?;
@@ -304,11 +387,11 @@ pub async fn batch_reorder(
 /// Panics if the chapter cannot be found after moving (should never happen in normal operation)
 #[debug_handler]
 pub async fn move_up(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path((_book_id, id)): Path<(i32, i32)>,
     State(ctx): State<AppContext>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksWrite)?;
     let _item = load_item(&ctx, id, user.id).await?;
 
     let moved = Model::move_up(&ctx.db, id).await?;
@@ -328,11 +411,11 @@ pub async fn move_up(
 /// Panics if the chapter cannot be found after moving (should never happen in normal operation)
 #[debug_handler]
 pub async fn move_down(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path((_book_id, id)): Path<(i32, i32)>,
     State(ctx): State<AppContext>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksWrite)?;
     let _item = load_item(&ctx, id, user.id).await?;
 
     let moved = Model::move_down(&ctx.db, id).await?;
@@ -350,15 +433,16 @@ pub async fn move_down(
 /// 获取书籍的章节树状结构
 #[debug_handler]
 pub async fn tree(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path(book_id): Path<i32>,
     State(ctx): State<AppContext>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksRead)?;
 
     // 验证用户是否有权限访问该书籍
     let _book = books::Entity::find_by_id(book_id)
         .filter(books::Column::UserId.eq(user.id))
+        .filter(books::Column::DeletedAt.is_null())
         .one(&ctx.db)
         .await?
         .ok_or_else(|| Error::NotFound)?;
@@ -370,15 +454,16 @@ pub async fn tree(
 /// 获取章节的扁平列表（包含层级信息）
 #[debug_handler]
 pub async fn flat_list(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path(book_id): Path<i32>,
     State(ctx): State<AppContext>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksRead)?;
 
     // 验证用户是否有权限访问该书籍
     let _book = books::Entity::find_by_id(book_id)
         .filter(books::Column::UserId.eq(user.id))
+        .filter(books::Column::DeletedAt.is_null())
         .one(&ctx.db)
         .await?
         .ok_or_else(|| Error::NotFound)?;
@@ -390,11 +475,11 @@ pub async fn flat_list(
 /// 获取章节的子章节
 #[debug_handler]
 pub async fn children(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path((_book_id, id)): Path<(i32, i32)>,
     State(ctx): State<AppContext>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksRead)?;
     let _chapter = load_item(&ctx, id, user.id).await?;
 
     let children = Model::find_children(&ctx.db, id).await?;
@@ -406,12 +491,13 @@ pub async fn children(
 /// 创建子章节
 #[debug_handler]
 pub async fn create_child(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path((_book_id, id)): Path<(i32, i32)>,
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
     Json(params): Json<CreateChildParams>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksWrite)?;
     let parent_chapter = load_item(&ctx, id, user.id).await?;
 
     let item = if let Some(sort_order) = params.sort_order {
@@ -448,6 +534,16 @@ pub async fn create_child(
         .await?
         .ok_or(Error::NotFound)?;
 
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("chapter.create")
+            .actor(&user)
+            .target("chapter", updated_item.id)
+            .after(audit_summary(&updated_item))
+            .ip(ip),
+    )
+    .await;
+
     format::json(ChapterResponse::from(updated_item))
 }
 
@@ -458,17 +554,21 @@ pub async fn create_child(
 /// Panics if the chapter cannot be found after moving (should never happen in normal operation)
 #[debug_handler]
 pub async fn move_chapter(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path((_book_id, id)): Path<(i32, i32)>,
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
     Json(params): Json<MoveChapterParams>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
-    let _chapter = load_item(&ctx, id, user.id).await?;
+    let user = auth.authorize(Scope::BooksWrite)?;
+    let chapter = load_item(&ctx, id, user.id).await?;
 
     // 如果指定了新的父级，验证父级章节是否存在且属于同一本书
     if let Some(new_parent_id) = params.new_parent_id {
-        let parent_chapter = Entity::find_by_id(new_parent_id).one(&ctx.db).await?;
+        let parent_chapter = Entity::find_by_id(new_parent_id)
+            .filter(Column::DeletedAt.is_null())
+            .one(&ctx.db)
+            .await?;
         if let Some(parent_chapter) = parent_chapter {
             // 验证书籍是否属于当前用户
             let _book = books::Entity::find_by_id(parent_chapter.book_id)
@@ -476,6 +576,11 @@ pub async fn move_chapter(
                 .one(&ctx.db)
                 .await?
                 .ok_or_else(|| Error::NotFound)?;
+            if parent_chapter.book_id != chapter.book_id {
+                return Err(Error::BadRequest(
+                    "父章节属于其他书籍，请使用移动到其他书籍的接口".to_string(),
+                ));
+            }
         } else {
             return Err(Error::NotFound);
         }
@@ -496,9 +601,113 @@ pub async fn move_chapter(
     }
 
     let updated_item = Entity::find_by_id(id).one(&ctx.db).await?.unwrap();
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("chapter.move")
+            .actor(&user)
+            .target("chapter", id)
+            .before(audit_summary(&chapter))
+            .after(audit_summary(&updated_item))
+            .ip(ip),
+    )
+    .await;
+
     format::json(ChapterResponse::from(updated_item))
 }
 
+/// 把章节（连同子章节和媒体）移动到另一本书
+#[debug_handler]
+pub async fn move_to_book(
+    auth: ApiAuth,
+    Path((_book_id, id)): Path<(i32, i32)>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    Json(params): Json<MoveToBookParams>,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksWrite)?;
+    let chapter = load_item(&ctx, id, user.id).await?;
+    let target = locations::validate(
+        &ctx.db,
+        user.id,
+        params.target_book_id,
+        params.new_parent_id,
+    )
+    .await?;
+
+    let moved = content_transfer::move_chapter_to_book(
+        &ctx.db,
+        &chapter,
+        target.id,
+        params.new_parent_id,
+        params.new_sort_order,
+    )
+    .await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("chapter.move_to_book")
+            .actor(&user)
+            .target("chapter", id)
+            .before(audit_summary(&chapter))
+            .after(audit_summary(&moved))
+            .ip(ip),
+    )
+    .await;
+
+    format::json(ChapterResponse::from(moved))
+}
+
+/// 复制章节子树（包括媒体和字幕），返回新章节的树形结构
+#[debug_handler]
+pub async fn copy(
+    auth: ApiAuth,
+    Path((_book_id, id)): Path<(i32, i32)>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    Json(params): Json<CopyChapterParams>,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksWrite)?;
+    let chapter = load_item(&ctx, id, user.id).await?;
+    let target = locations::validate(
+        &ctx.db,
+        user.id,
+        params.target_book_id.unwrap_or(chapter.book_id),
+        params.parent_id,
+    )
+    .await?;
+    let site_url = get_site_url(&ctx).await?;
+
+    let (copy, summary) = content_transfer::copy_chapter(
+        &ctx.db,
+        &chapter,
+        &target,
+        params.parent_id,
+        &site_url,
+        params.share_files,
+    )
+    .await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("chapter.copy")
+            .actor(&user)
+            .target("chapter", copy.id)
+            .after(serde_json::json!({
+                "source_id": chapter.id,
+                "book_id": copy.book_id,
+                "parent_id": copy.parent_id,
+                "chapters": summary.chapters,
+                "medias": summary.medias,
+                "share_files": params.share_files,
+            }))
+            .ip(ip),
+    )
+    .await;
+
+    format::json(crate::models::chapters::Model::get_tree(&ctx.db, copy.id).await?)
+}
+
 pub fn routes() -> Routes {
     Routes::new()
         .prefix("/api/books")
@@ -514,6 +723,8 @@ pub fn routes() -> Routes {
         .add("/{book_id}/chapters/{id}/children", get(children))
         .add("/{book_id}/chapters/{id}/children", post(create_child))
         .add("/{book_id}/chapters/{id}/move", post(move_chapter))
+        .add("/{book_id}/chapters/{id}/move-to-book", post(move_to_book))
+        .add("/{book_id}/chapters/{id}/copy", post(copy))
         .add("/{book_id}/chapters/{id}", get(show))
         .add("/{book_id}/chapters/{id}", put(update))
         .add("/{book_id}/chapters/{id}", patch(update))
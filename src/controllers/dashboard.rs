@@ -1,13 +1,17 @@
 #![allow(clippy::missing_errors_doc)]
 #![allow(clippy::unnecessary_struct_initialization)]
 #![allow(clippy::unused_async)]
+use axum::body::Body;
 use axum::debug_handler;
+use axum::extract::Query;
+use axum::http::header;
 use loco_rs::prelude::*;
 use sea_orm::{PaginatorTrait, QueryOrder, QuerySelect};
 use serde::{Deserialize, Serialize};
 
-use crate::models::_entities::{books, chapters, medias};
+use crate::models::_entities::{books, chapters, media_placements, medias};
 use crate::models::users;
+use crate::services::analytics::{AnalyticsExporter, ExportFilter, ExportFormat};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DashboardStats {
@@ -15,6 +19,8 @@ pub struct DashboardStats {
     pub total_medias: i64,
     pub total_chapters: i64,
     pub total_plays: i64,
+    /// 媒体被附加到其他章节或书籍的位置数
+    pub total_placements: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -37,6 +43,16 @@ pub struct RecentMedia {
     pub book_id: i32,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ExportParams {
+    pub format: Option<String>,
+    pub book_id: Option<i32>,
+    /// 起始日期（YYYY-MM-DD，包含）
+    pub from: Option<String>,
+    /// 结束日期（YYYY-MM-DD，包含）
+    pub to: Option<String>,
+}
+
 /// 获取仪表盘统计数据
 #[debug_handler]
 pub async fn stats(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
@@ -45,12 +61,14 @@ pub async fn stats(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Res
     // 统计书籍数量
     let total_books = books::Entity::find()
         .filter(books::Column::UserId.eq(user.id))
+        .filter(books::Column::DeletedAt.is_null())
         .count(&ctx.db)
         .await?;
 
     // 统计媒体数量
     let total_medias = medias::Entity::find()
         .filter(medias::Column::UserId.eq(user.id))
+        .filter(medias::Column::DeletedAt.is_null())
         .count(&ctx.db)
         .await?;
 
@@ -58,22 +76,31 @@ pub async fn stats(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Res
     let total_chapters = chapters::Entity::find()
         .inner_join(books::Entity)
         .filter(books::Column::UserId.eq(user.id))
+        .filter(chapters::Column::DeletedAt.is_null())
         .count(&ctx.db)
         .await?;
 
     // 统计总播放次数
     let medias_list = medias::Entity::find()
         .filter(medias::Column::UserId.eq(user.id))
+        .filter(medias::Column::DeletedAt.is_null())
         .all(&ctx.db)
         .await?;
 
     let total_plays: i64 = medias_list.iter().map(|m| i64::from(m.play_count)).sum();
 
+    // 统计附加位置数量
+    let total_placements = media_placements::Entity::find()
+        .filter(media_placements::Column::MediaId.is_in(medias_list.iter().map(|m| m.id)))
+        .count(&ctx.db)
+        .await?;
+
     let stats = DashboardStats {
         total_books: i64::try_from(total_books).unwrap_or(i64::MAX),
         total_medias: i64::try_from(total_medias).unwrap_or(i64::MAX),
         total_chapters: i64::try_from(total_chapters).unwrap_or(i64::MAX),
         total_plays,
+        total_placements: i64::try_from(total_placements).unwrap_or(i64::MAX),
     };
 
     format::json(stats)
@@ -87,6 +114,7 @@ pub async fn top_medias(auth: auth::JWT, State(ctx): State<AppContext>) -> Resul
     // 获取用户的所有媒体，按播放次数排序
     let medias_list = medias::Entity::find()
         .filter(medias::Column::UserId.eq(user.id))
+        .filter(medias::Column::DeletedAt.is_null())
         .order_by_desc(medias::Column::PlayCount)
         .limit(10)
         .all(&ctx.db)
@@ -131,6 +159,7 @@ pub async fn recent_medias(auth: auth::JWT, State(ctx): State<AppContext>) -> Re
     // 获取用户最近上传的媒体
     let medias_list = medias::Entity::find()
         .filter(medias::Column::UserId.eq(user.id))
+        .filter(medias::Column::DeletedAt.is_null())
         .order_by_desc(medias::Column::CreatedAt)
         .limit(5)
         .all(&ctx.db)
@@ -167,10 +196,56 @@ pub async fn recent_medias(auth: auth::JWT, State(ctx): State<AppContext>) -> Re
     format::json(recent_list)
 }
 
+/// 导出媒体播放统计（CSV/JSON，流式输出）
+#[debug_handler]
+pub async fn export(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Query(params): Query<ExportParams>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+
+    let format = ExportFormat::parse(params.format.as_deref().unwrap_or("csv"))?;
+
+    // 指定书籍时验证书籍属于当前用户
+    if let Some(book_id) = params.book_id {
+        books::Entity::find_by_id(book_id)
+            .filter(books::Column::UserId.eq(user.id))
+            .one(&ctx.db)
+            .await?
+            .ok_or_else(|| Error::NotFound)?;
+    }
+
+    let filter = ExportFilter {
+        user_id: Some(user.id),
+        book_id: params.book_id,
+        ..Default::default()
+    }
+    .with_date_range(params.from.as_deref(), params.to.as_deref())?;
+
+    let filename = format!(
+        "qcast-analytics-{}.{}",
+        chrono::Utc::now().format("%Y%m%d"),
+        format.extension()
+    );
+
+    let stream = AnalyticsExporter::new(ctx.db.clone(), filter).into_stream(format);
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, format.content_type())
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"{filename}\""),
+        )
+        .body(Body::from_stream(stream))
+        .map_err(|_| Error::InternalServerError)
+}
+
 pub fn routes() -> Routes {
     Routes::new()
         .prefix("/api/dashboard")
         .add("/stats", get(stats))
         .add("/top-medias", get(top_medias))
         .add("/recent-medias", get(recent_medias))
+        .add("/export", get(export))
 }
@@ -0,0 +1,224 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unnecessary_struct_initialization)]
+#![allow(clippy::unused_async)]
+use axum::debug_handler;
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use loco_rs::controller::views::{engines::TeraView, ViewEngine};
+use loco_rs::prelude::*;
+use sea_orm::QueryOrder;
+use serde::{Deserialize, Serialize};
+
+use crate::controllers::public;
+use crate::models::_entities::{chapters, users};
+use crate::models::site_settings;
+use crate::services::audio_processing;
+use crate::services::embed::{EmbedService, EmbedTarget, EMBED_SERVICE};
+use crate::services::podcast_feed::{absolute_url, enclosure_url, load_live_medias};
+
+#[derive(Debug, Deserialize)]
+pub struct OEmbedParams {
+    pub url: String,
+    pub format: Option<String>,
+    pub maxwidth: Option<u32>,
+    pub maxheight: Option<u32>,
+}
+
+/// oEmbed 1.0 `rich` 类型响应
+#[derive(Debug, Serialize)]
+pub struct OEmbedResponse {
+    pub version: &'static str,
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub provider_name: &'static str,
+    pub provider_url: String,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail_url: Option<String>,
+    pub html: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+async fn get_site_url(ctx: &AppContext) -> Result<String> {
+    Ok(site_settings::Model::site_url(&ctx.db).await?)
+}
+
+/// 为嵌入页添加 `frame-ancestors` 限制
+fn with_embed_headers(response: Response) -> Result<Response> {
+    let mut response = response;
+    let csp = HeaderValue::from_str(&EMBED_SERVICE.content_security_policy())
+        .map_err(|e| Error::Message(format!("无效的 CSP 配置: {}", e)))?;
+    response
+        .headers_mut()
+        .insert(header::CONTENT_SECURITY_POLICY, csp);
+    Ok(response)
+}
+
+/// 单个媒体的嵌入播放器页面
+#[debug_handler]
+pub async fn media_player(
+    ViewEngine(v): ViewEngine<TeraView>,
+    Path(access_token): Path<String>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let (media, book) = public::load_public_media(&ctx, &access_token).await?;
+    let site_url = get_site_url(&ctx).await?;
+
+    let response = format::view(
+        &v,
+        "embed/media.html",
+        serde_json::json!({
+            "media": {
+                "title": media.title,
+                "file_type": media.file_type,
+                "access_token": media.access_token,
+                "access_url": media.access_url.clone().unwrap_or_else(|| site_url.clone()),
+                "src": enclosure_url(&site_url, &media.access_token),
+                "src_mime_type": audio_processing::playback_mime_type(&book, &media),
+            },
+        }),
+    )?;
+
+    with_embed_headers(response)
+}
+
+/// 书籍播放列表嵌入页面（按章节树和章节内的排序列出书籍内的公开音频）
+#[debug_handler]
+pub async fn book_player(
+    ViewEngine(v): ViewEngine<TeraView>,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let book = public::load_public_book(&ctx, id).await?;
+    let site_url = get_site_url(&ctx).await?;
+
+    let chapters = chapters::Entity::find()
+        .filter(chapters::Column::BookId.eq(book.id))
+        .filter(chapters::Column::DeletedAt.is_null())
+        .order_by_asc(chapters::Column::SortOrder)
+        .order_by_asc(chapters::Column::CreatedAt)
+        .order_by_asc(chapters::Column::Id)
+        .all(&ctx.db)
+        .await?;
+    let medias = load_live_medias(&ctx.db, book.id, &chapters).await?;
+
+    let episodes: Vec<serde_json::Value> = medias
+        .into_iter()
+        .filter(|media| media.file_type == "audio")
+        .map(|media| {
+            serde_json::json!({
+                "title": media.title,
+                "access_token": media.access_token,
+                "src": enclosure_url(&site_url, &media.access_token),
+                "duration": media.duration.map(format_duration),
+            })
+        })
+        .collect();
+
+    let response = format::view(
+        &v,
+        "embed/book.html",
+        serde_json::json!({
+            "book": { "title": book.title },
+            "episodes": episodes,
+            "site_url": site_url,
+        }),
+    )?;
+
+    with_embed_headers(response)
+}
+
+/// oEmbed 接口：根据媒体访问地址或嵌入地址返回 iframe 代码
+#[debug_handler]
+pub async fn oembed(
+    Query(params): Query<OEmbedParams>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    // 按 oEmbed 规范，不支持的格式返回 501
+    if let Some(format) = params.format.as_deref() {
+        if !format.eq_ignore_ascii_case("json") {
+            return Ok((StatusCode::NOT_IMPLEMENTED, "仅支持 JSON 格式").into_response());
+        }
+    }
+
+    let site_url = get_site_url(&ctx).await?;
+    let target = EmbedService::resolve_url(&site_url, &params.url).ok_or(Error::NotFound)?;
+
+    let (title, user_id, file_type, src, thumbnail_url) = match &target {
+        EmbedTarget::Media(access_token) => {
+            let (media, _) = public::load_public_media(&ctx, access_token).await?;
+            (
+                media.title,
+                media.user_id,
+                Some(media.file_type),
+                EmbedService::media_embed_url(&site_url, &media.access_token),
+                None,
+            )
+        }
+        EmbedTarget::Book(id) => {
+            let book = public::load_public_book(&ctx, *id).await?;
+            let thumbnail_url = book
+                .cover_image
+                .filter(|c| !c.is_empty())
+                .map(|c| absolute_url(&site_url, &c));
+            (
+                book.title,
+                book.user_id,
+                None,
+                EmbedService::book_embed_url(&site_url, book.id),
+                thumbnail_url,
+            )
+        }
+    };
+
+    let author_name = users::Entity::find_by_id(user_id)
+        .one(&ctx.db)
+        .await?
+        .map(|user| user.name);
+
+    let (width, height) = EmbedService::embed_size(
+        &target,
+        file_type.as_deref(),
+        params.maxwidth,
+        params.maxheight,
+    );
+
+    format::json(OEmbedResponse {
+        version: "1.0",
+        kind: "rich",
+        provider_name: "QCast",
+        html: EmbedService::iframe_html(&src, &title, width, height),
+        provider_url: site_url,
+        title,
+        author_name,
+        thumbnail_url,
+        width,
+        height,
+    })
+}
+
+/// 将秒数格式化为 `MM:SS` 或 `H:MM:SS`
+fn format_duration(seconds: i32) -> String {
+    let seconds = seconds.max(0);
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            (seconds % 3600) / 60,
+            seconds % 60
+        )
+    } else {
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .add("/embed/media/{access_token}", get(media_player))
+        .add("/embed/books/{id}", get(book_player))
+        .add("/oembed", get(oembed))
+}
@@ -0,0 +1,81 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unnecessary_struct_initialization)]
+#![allow(clippy::unused_async)]
+use axum::debug_handler;
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::Response;
+use chrono::{DateTime, Utc};
+use loco_rs::prelude::*;
+
+use crate::controllers::public;
+use crate::models::site_settings;
+use crate::services::podcast_feed::{etag, PodcastFeed};
+
+/// 获取公开书籍的播客 RSS 订阅源
+#[debug_handler]
+pub async fn podcast_feed(
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    let book = public::load_public_book(&ctx, id).await?;
+
+    let settings = site_settings::Model::current(&ctx.db).await?;
+    let feed = PodcastFeed::load(
+        &ctx.db,
+        book,
+        &settings.site_url,
+        settings.updated_at.into(),
+    )
+    .await?;
+
+    let xml = feed.to_xml();
+    let etag = etag(&xml);
+    let last_modified = http_date(feed.last_modified);
+
+    let builder = Response::builder()
+        .header(header::ETAG, &etag)
+        .header(header::LAST_MODIFIED, &last_modified)
+        .header(header::CACHE_CONTROL, "public, max-age=300");
+
+    if is_not_modified(&headers, &etag) {
+        return builder
+            .status(StatusCode::NOT_MODIFIED)
+            .body(axum::body::Body::empty())
+            .map_err(|e| Error::Message(format!("构建响应失败: {}", e)));
+    }
+
+    builder
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/rss+xml; charset=utf-8")
+        .body(axum::body::Body::from(xml))
+        .map_err(|e| Error::Message(format!("构建响应失败: {}", e)))
+}
+
+/// 判断条件请求是否命中缓存
+///
+/// 只按 `If-None-Match` 判断：媒体删除、下架或移出书籍后不会留下更新时间，
+/// `Last-Modified` 无法反映这些变化，因此忽略 `If-Modified-Since`。
+fn is_not_modified(headers: &HeaderMap, etag: &str) -> bool {
+    headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|if_none_match| {
+            if_none_match
+                .split(',')
+                .map(|tag| tag.trim().trim_start_matches("W/"))
+                .any(|tag| tag == "*" || tag == etag)
+        })
+}
+
+/// 格式化为 HTTP 日期（RFC 7231 IMF-fixdate）
+fn http_date(dt: DateTime<Utc>) -> String {
+    dt.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("/api/public/books")
+        .add("/{id}/feed.xml", get(podcast_feed))
+}
@@ -0,0 +1,144 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unnecessary_struct_initialization)]
+#![allow(clippy::unused_async)]
+use axum::debug_handler;
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap};
+use axum::response::Response;
+use loco_rs::controller::views::{engines::TeraView, ViewEngine};
+use loco_rs::prelude::*;
+
+use crate::controllers::public;
+use crate::models::{chapters, site_settings};
+use crate::services::audio_processing;
+use crate::services::embed::{EmbedService, EmbedTarget};
+use crate::services::podcast_feed::{absolute_url, enclosure_url};
+
+/// 分享预览中描述的最大长度（字符）
+const MAX_DESCRIPTION_CHARS: usize = 200;
+
+/// 扫码落地页：浏览器访问返回 HTML 页面，其他客户端（播放器、下载工具）直接返回媒体文件
+#[debug_handler]
+pub async fn media_page(
+    ViewEngine(v): ViewEngine<TeraView>,
+    Path(access_token): Path<String>,
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    if !accepts_html(&headers) {
+        let mut response = public::get_media(Path(access_token), State(ctx), headers).await?;
+        vary_on_accept(&mut response);
+        return Ok(response);
+    }
+
+    let (media, book) = public::load_public_media(&ctx, &access_token).await?;
+
+    let chapter_titles: Vec<String> = match media.chapter_id {
+        Some(chapter_id) => chapters::Model::breadcrumb(&ctx.db, chapter_id)
+            .await?
+            .into_iter()
+            .map(|chapter| chapter.title)
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let site_url = site_settings::Model::site_url(&ctx.db).await?;
+
+    let description = media
+        .description
+        .as_deref()
+        .filter(|d| !d.trim().is_empty())
+        .or(book.description.as_deref())
+        .map_or_else(|| book.title.clone(), truncate_description);
+    let image_url = book
+        .cover_image
+        .as_deref()
+        .filter(|c| !c.is_empty())
+        .map(|c| absolute_url(&site_url, c));
+    let (embed_width, embed_height) = EmbedService::embed_size(
+        &EmbedTarget::Media(media.access_token.clone()),
+        Some(&media.file_type),
+        None,
+        None,
+    );
+    let page_url = format!("{}/public/{}", site_url, media.access_token);
+    let oembed_url = url::Url::parse_with_params(
+        &format!("{site_url}/oembed"),
+        &[("url", page_url.as_str()), ("format", "json")],
+    )
+    .map_err(|e| Error::Message(format!("生成 oEmbed 地址失败: {}", e)))?;
+
+    let mut response = format::view(
+        &v,
+        "public/media.html",
+        serde_json::json!({
+            "media": {
+                "title": media.title,
+                "description": media.description,
+                "file_type": media.file_type,
+                "access_token": media.access_token,
+            },
+            "book": { "title": book.title },
+            "chapters": chapter_titles,
+            "description": description,
+            "image_url": image_url,
+            "page_url": page_url,
+            "stream_url": enclosure_url(&site_url, &media.access_token),
+            "stream_mime_type": audio_processing::playback_mime_type(&book, &media),
+            "embed_url": EmbedService::media_embed_url(&site_url, &media.access_token),
+            "embed_width": embed_width,
+            "embed_height": embed_height,
+            "oembed_url": oembed_url.as_str(),
+        }),
+    )?;
+
+    vary_on_accept(&mut response);
+    Ok(response)
+}
+
+/// 同一地址按 Accept 返回页面或文件，告知缓存区分
+fn vary_on_accept(response: &mut Response) {
+    response
+        .headers_mut()
+        .append(header::VARY, header::ACCEPT.into());
+}
+
+/// 判断客户端是否希望获取 HTML（浏览器导航和链接预览爬虫）
+///
+/// HTML 的权重（`q`）必须大于 0，且不低于其他任何类型的权重。
+fn accepts_html(headers: &HeaderMap) -> bool {
+    let Some(accept) = headers.get(header::ACCEPT).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+
+    let mut html_quality = 0.0_f32;
+    let mut other_quality = 0.0_f32;
+    for entry in accept.split(',') {
+        let mut parts = entry.split(';');
+        let media_type = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+        let quality = parts
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        if media_type == "text/html" || media_type == "application/xhtml+xml" {
+            html_quality = html_quality.max(quality);
+        } else if !media_type.is_empty() {
+            other_quality = other_quality.max(quality);
+        }
+    }
+    html_quality > 0.0 && html_quality >= other_quality
+}
+
+fn truncate_description(description: &str) -> String {
+    let description = description.trim();
+    if description.chars().count() <= MAX_DESCRIPTION_CHARS {
+        return description.to_string();
+    }
+    let mut truncated: String = description.chars().take(MAX_DESCRIPTION_CHARS).collect();
+    truncated.push('…');
+    truncated
+}
+
+pub fn routes() -> Routes {
+    Routes::new().add("/public/{access_token}", get(media_page))
+}
@@ -0,0 +1,224 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+use axum::debug_handler;
+use axum::routing::method_routing::delete as axum_delete;
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::controllers::api_auth::ApiAuth;
+use crate::controllers::auth::ClientIp;
+use crate::models::_entities::medias;
+use crate::models::audit_logs::{self, AuditEntry};
+use crate::models::media_placements::{self, ActiveModel};
+use crate::models::personal_access_tokens::Scope;
+use crate::services::locations;
+use crate::views::media_placements::MediaPlacementResponse;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CreatePlacementParams {
+    pub book_id: i32,
+    pub chapter_id: Option<i32>,
+    /// 为空时排在该位置的最后
+    pub sort_order: Option<i32>,
+    /// 在该位置显示的标题，为空时使用媒体标题
+    pub title: Option<String>,
+}
+
+/// 更新附加位置，`title` 为空字符串表示改回使用媒体标题
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UpdatePlacementParams {
+    pub sort_order: Option<i32>,
+    pub title: Option<String>,
+}
+
+async fn load_media(ctx: &AppContext, id: i32, user_id: i32) -> Result<medias::Model> {
+    let item = medias::Entity::find_by_id(id)
+        .filter(medias::Column::UserId.eq(user_id))
+        .filter(medias::Column::DeletedAt.is_null())
+        .one(&ctx.db)
+        .await?;
+    item.ok_or_else(|| Error::NotFound)
+}
+
+async fn load_placement(
+    ctx: &AppContext,
+    media_id: i32,
+    placement_id: i32,
+) -> Result<media_placements::Model> {
+    media_placements::Model::find_for_media(&ctx.db, media_id, placement_id)
+        .await?
+        .ok_or_else(|| Error::NotFound)
+}
+
+/// 将空字符串转换为 None
+fn non_empty(value: String) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// 校验目标位置：书籍和章节属于当前用户且未删除，媒体尚未出现在该位置
+async fn validate_location(
+    ctx: &AppContext,
+    user_id: i32,
+    media: &medias::Model,
+    book_id: i32,
+    chapter_id: Option<i32>,
+) -> Result<()> {
+    locations::validate(&ctx.db, user_id, book_id, chapter_id).await?;
+
+    let at_home = media.book_id == book_id && media.chapter_id == chapter_id;
+    if at_home || media_placements::Model::exists_at(&ctx.db, media.id, book_id, chapter_id).await?
+    {
+        return Err(Error::BadRequest("媒体已在该位置".to_string()));
+    }
+    Ok(())
+}
+
+/// 审计日志中记录的位置摘要
+fn audit_summary(placement: &media_placements::Model) -> serde_json::Value {
+    serde_json::json!({
+        "placement_id": placement.id,
+        "book_id": placement.book_id,
+        "chapter_id": placement.chapter_id,
+        "sort_order": placement.sort_order,
+        "title": placement.title,
+    })
+}
+
+/// 获取媒体的所有附加位置
+#[debug_handler]
+pub async fn list(
+    auth: ApiAuth,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksRead)?;
+    let media = load_media(&ctx, id, user.id).await?;
+
+    let placements = media_placements::Model::find_by_media(&ctx.db, media.id).await?;
+    let responses: Vec<MediaPlacementResponse> = placements
+        .into_iter()
+        .map(MediaPlacementResponse::from)
+        .collect();
+
+    format::json(responses)
+}
+
+/// 把媒体附加到另一个章节或书籍
+#[debug_handler]
+pub async fn create(
+    auth: ApiAuth,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    Json(params): Json<CreatePlacementParams>,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksWrite)?;
+    let media = load_media(&ctx, id, user.id).await?;
+    validate_location(&ctx, user.id, &media, params.book_id, params.chapter_id).await?;
+
+    let sort_order = match params.sort_order {
+        Some(sort_order) => sort_order,
+        None => medias::Model::next_sort_order(&ctx.db, params.book_id, params.chapter_id).await?,
+    };
+    let placement = ActiveModel {
+        media_id: Set(media.id),
+        book_id: Set(params.book_id),
+        chapter_id: Set(params.chapter_id),
+        sort_order: Set(Some(sort_order)),
+        title: Set(params.title.and_then(non_empty)),
+        ..Default::default()
+    }
+    .insert(&ctx.db)
+    .await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("media.place")
+            .actor(&user)
+            .target("media", media.id)
+            .after(audit_summary(&placement))
+            .ip(ip),
+    )
+    .await;
+
+    format::json(MediaPlacementResponse::from(placement))
+}
+
+/// 更新附加位置的排序和标题
+#[debug_handler]
+pub async fn update(
+    auth: ApiAuth,
+    Path((id, placement_id)): Path<(i32, i32)>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    Json(params): Json<UpdatePlacementParams>,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksWrite)?;
+    let media = load_media(&ctx, id, user.id).await?;
+    let placement = load_placement(&ctx, media.id, placement_id).await?;
+    let before = audit_summary(&placement);
+
+    let mut item = placement.into_active_model();
+    if let Some(sort_order) = params.sort_order {
+        item.sort_order = Set(Some(sort_order));
+    }
+    if let Some(title) = params.title {
+        item.title = Set(non_empty(title));
+    }
+    let placement = item.update(&ctx.db).await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("media.update_placement")
+            .actor(&user)
+            .target("media", media.id)
+            .before(before)
+            .after(audit_summary(&placement))
+            .ip(ip),
+    )
+    .await;
+
+    format::json(MediaPlacementResponse::from(placement))
+}
+
+/// 移除附加位置（媒体本身和原始位置不受影响）
+#[debug_handler]
+pub async fn delete(
+    auth: ApiAuth,
+    Path((id, placement_id)): Path<(i32, i32)>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksWrite)?;
+    let media = load_media(&ctx, id, user.id).await?;
+    let placement = load_placement(&ctx, media.id, placement_id).await?;
+    let before = audit_summary(&placement);
+
+    placement.delete(&ctx.db).await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("media.unplace")
+            .actor(&user)
+            .target("media", media.id)
+            .before(before)
+            .ip(ip),
+    )
+    .await;
+
+    format::empty()
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("/api/media")
+        .add("/{id}/placements", get(list))
+        .add("/{id}/placements", post(create))
+        .add("/{id}/placements/{placement_id}", put(update))
+        .add("/{id}/placements/{placement_id}", axum_delete(delete))
+}
@@ -6,46 +6,159 @@ use axum::extract::{DefaultBodyLimit, Path as AxumPath, Query};
 use axum::routing::method_routing::delete as axum_delete;
 use axum_extra::extract::Multipart;
 use loco_rs::prelude::*;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use sea_orm::QueryOrder;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncReadExt;
 use tokio_util::io::ReaderStream;
 use uuid::Uuid;
 
+use crate::controllers::api_auth::ApiAuth;
+use crate::controllers::auth::ClientIp;
+use crate::controllers::public;
 use crate::models::_entities::books;
 use crate::models::_entities::chapters;
 use crate::models::_entities::medias::{ActiveModel, Column, Entity, Model};
-use crate::models::{site_settings, users};
+use crate::models::audit_logs::{self, AuditEntry};
+use crate::models::media_clips::{self, ClipRequest};
+use crate::models::personal_access_tokens::Scope;
+use crate::models::webhook_endpoints::{
+    EVENT_MEDIA_PROCESSED, EVENT_MEDIA_PUBLISHED, EVENT_MEDIA_REPLACED, EVENT_MEDIA_UPLOADED,
+};
+use crate::models::{media_placements, media_plays, media_versions, site_settings};
+use crate::services::audio_clipping;
 use crate::services::audio_metadata::AUDIO_METADATA_SERVICE;
+use crate::services::audio_processing;
+use crate::services::content_store;
+use crate::services::locations;
+use crate::services::media_versions as versions;
+use crate::services::publishing::{self, ScheduleParams};
 use crate::services::qrcode::QRCODE_SERVICE;
-use crate::services::storage::StorageService;
+use crate::services::storage::{TempUpload, STORAGE_SERVICE};
+use crate::services::trash;
 use crate::services::video_metadata::VIDEO_METADATA_SERVICE;
+use crate::services::webhooks;
+use crate::views::media_clips::MediaClipResponse;
+use crate::views::media_versions::MediaVersionResponse;
 use crate::views::medias::{MediaResponse, UpdateMediaParams};
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BatchReorderParams {
+    pub book_id: i32,
+    /// 为空时重排书籍顶层的媒体
+    pub chapter_id: Option<i32>,
+    pub media_ids: Vec<i32>,
+}
+
+/// 移动媒体到另一个章节，`chapter_id` 为空表示移到书籍顶层
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MoveMediaParams {
+    /// 不填则在当前书籍内移动
+    pub book_id: Option<i32>,
+    pub chapter_id: Option<i32>,
+    /// 不填则排在目标位置的最后
+    pub sort_order: Option<i32>,
+}
+
+/// 从媒体的时间范围剪辑出新媒体
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ClipParams {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    /// 不填则使用原标题加时间范围
+    pub title: Option<String>,
+    /// 不填则放在原媒体所在的章节
+    pub chapter_id: Option<i32>,
+}
+
 async fn load_item(ctx: &AppContext, id: i32, user_id: i32) -> Result<Model> {
     let item = Entity::find_by_id(id)
         .filter(Column::UserId.eq(user_id))
+        .filter(Column::DeletedAt.is_null())
         .one(&ctx.db)
         .await?;
     item.ok_or_else(|| Error::NotFound)
 }
 
+/// 审计日志中记录的媒体摘要
+fn audit_summary(item: &Model) -> serde_json::Value {
+    serde_json::json!({
+        "title": item.title,
+        "book_id": item.book_id,
+        "chapter_id": item.chapter_id,
+        "sort_order": item.sort_order,
+        "is_public": item.is_public,
+        "file_version": item.file_version,
+        "original_filename": item.original_filename,
+    })
+}
+
+/// 审计日志中记录的发布时间窗口
+fn schedule_summary(
+    is_public: bool,
+    publish_at: Option<sea_orm::prelude::DateTimeWithTimeZone>,
+    unpublish_at: Option<sea_orm::prelude::DateTimeWithTimeZone>,
+) -> serde_json::Value {
+    serde_json::json!({
+        "is_public": is_public,
+        "publish_at": publish_at,
+        "unpublish_at": unpublish_at,
+    })
+}
+
+/// 位置中的媒体（包括附加到该位置的媒体），按排序号排列
+async fn location_medias(
+    ctx: &AppContext,
+    user_id: i32,
+    book_id: i32,
+    chapter_id: Option<i32>,
+) -> Result<Vec<MediaResponse>> {
+    let chapter_filter = match chapter_id {
+        Some(chapter_id) => Column::ChapterId.eq(chapter_id),
+        None => Column::ChapterId.is_null(),
+    };
+    let medias = Entity::find()
+        .filter(Column::BookId.eq(book_id))
+        .filter(chapter_filter)
+        .filter(Column::UserId.eq(user_id))
+        .filter(Column::DeletedAt.is_null())
+        .order_by_asc(Column::SortOrder)
+        .order_by_asc(Column::CreatedAt)
+        .all(&ctx.db)
+        .await?;
+    let placed = media_placements::Model::find_at(&ctx.db, book_id, chapter_id).await?;
+
+    let mut responses: Vec<MediaResponse> = medias
+        .into_iter()
+        .map(MediaResponse::from)
+        .chain(
+            placed
+                .into_iter()
+                .map(|(placement, media)| MediaResponse::placed(media, placement)),
+        )
+        .collect();
+    // 没有排序号的旧数据排在最后
+    responses.sort_by_key(|media| (media.sort_order.is_none(), media.sort_order));
+    Ok(responses)
+}
+
 /// 获取站点URL（从数据库设置）
 async fn get_site_url(ctx: &AppContext) -> Result<String> {
-    const DEFAULT_SITE_URL: &str = "http://localhost:5150";
-    let settings = site_settings::Model::get_or_create(&ctx.db, DEFAULT_SITE_URL).await?;
-    Ok(settings.site_url)
+    Ok(site_settings::Model::site_url(&ctx.db).await?)
 }
 
 /// 获取当前用户的所有媒体，支持按 `book_id` 过滤
 #[debug_handler]
 #[allow(clippy::implicit_hasher)]
 pub async fn list(
-    auth: auth::JWT,
+    auth: ApiAuth,
     State(ctx): State<AppContext>,
     Query(params): Query<std::collections::HashMap<String, String>>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksRead)?;
 
-    let mut query = Entity::find().filter(Column::UserId.eq(user.id));
+    let mut query = Entity::find()
+        .filter(Column::UserId.eq(user.id))
+        .filter(Column::DeletedAt.is_null());
 
     // 如果提供了 book_id 参数，添加过滤条件
     if let Some(book_id_str) = params.get("book_id") {
@@ -63,11 +176,11 @@ pub async fn list(
 /// 获取媒体详情
 #[debug_handler]
 pub async fn show(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path(id): Path<i32>,
     State(ctx): State<AppContext>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksRead)?;
     let item = load_item(&ctx, id, user.id).await?;
 
     format::json(MediaResponse::from(item))
@@ -76,13 +189,16 @@ pub async fn show(
 /// 更新媒体
 #[debug_handler]
 pub async fn update(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path(id): Path<i32>,
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
     Json(params): Json<UpdateMediaParams>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::MediaUpload)?;
     let item = load_item(&ctx, id, user.id).await?;
+    let before = audit_summary(&item);
+    let previous_chapter_id = item.chapter_id;
 
     let mut item = item.into_active_model();
 
@@ -99,6 +215,7 @@ pub async fn update(
         // 如果提供了 chapter_id，验证章节是否存在且属于同一个书籍
         if chapter_id > 0 {
             let chapter = chapters::Entity::find_by_id(chapter_id)
+                .filter(chapters::Column::DeletedAt.is_null())
                 .one(&ctx.db)
                 .await?
                 .ok_or_else(|| Error::Message("章节不存在".to_string()))?;
@@ -115,60 +232,155 @@ pub async fn update(
             item.chapter_id = Set(None);
         }
     }
+    // 换了章节的媒体排在新章节的最后
+    if *item.chapter_id.as_ref() != previous_chapter_id {
+        let sort_order =
+            Model::next_sort_order(&ctx.db, *item.book_id.as_ref(), *item.chapter_id.as_ref())
+                .await?;
+        item.sort_order = Set(Some(sort_order));
+    }
 
     let item = item.update(&ctx.db).await?;
 
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("media.update")
+            .actor(&user)
+            .target("media", item.id)
+            .before(before)
+            .after(audit_summary(&item))
+            .ip(ip),
+    )
+    .await;
+
     format::json(MediaResponse::from(item))
 }
 
-/// 删除媒体
+/// 删除媒体（移入回收站，文件保留到永久删除时）
 #[debug_handler]
-#[allow(clippy::cognitive_complexity)]
 pub async fn delete(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path(id): Path<i32>,
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::MediaUpload)?;
     let item = load_item(&ctx, id, user.id).await?;
 
-    // 删除物理文件
-    let file_path = std::path::Path::new(&item.file_path);
-    if tokio::fs::metadata(file_path).await.is_ok() {
-        if let Err(e) = tokio::fs::remove_file(file_path).await {
-            tracing::warn!("删除媒体文件失败: {:?}, 错误: {}", file_path, e);
-        } else {
-            tracing::info!("已删除媒体文件: {:?}", file_path);
-        }
+    let before = audit_summary(&item);
+    trash::trash_media(&ctx, &item).await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("media.delete")
+            .actor(&user)
+            .target("media", id)
+            .before(before)
+            .ip(ip),
+    )
+    .await;
+
+    format::empty()
+}
+
+/// 按给定顺序批量重排一个章节（或书籍顶层）中的媒体
+#[debug_handler]
+pub async fn batch_reorder(
+    auth: ApiAuth,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    Json(params): Json<BatchReorderParams>,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksWrite)?;
+    locations::validate(&ctx.db, user.id, params.book_id, params.chapter_id).await?;
+
+    if let Err(e) = Model::reorder_in_location(
+        &ctx.db,
+        params.book_id,
+        params.chapter_id,
+        &params.media_ids,
+    )
+    .await
+    {
+        return match e {
+            sea_orm::DbErr::Custom(_) => Err(Error::BadRequest("媒体不在该章节中".to_string())),
+            _ => Err(e.into()),
+        };
     }
 
-    // 删除二维码文件
-    if let Some(ref qr_path) = item.qr_code_path {
-        let qr_full_path =
-            std::path::Path::new("assets/static").join(qr_path.trim_start_matches('/'));
-        if tokio::fs::metadata(&qr_full_path).await.is_ok() {
-            if let Err(e) = tokio::fs::remove_file(&qr_full_path).await {
-                tracing::warn!("删除二维码文件失败: {:?}, 错误: {}", qr_full_path, e);
-            } else {
-                tracing::info!("已删除二维码文件: {:?}", qr_full_path);
-            }
-        }
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("media.reorder")
+            .actor(&user)
+            .target("book", params.book_id)
+            .after(serde_json::json!({
+                "chapter_id": params.chapter_id,
+                "media_ids": params.media_ids,
+            }))
+            .ip(ip),
+    )
+    .await;
+
+    let responses = location_medias(&ctx, user.id, params.book_id, params.chapter_id).await?;
+    format::json(responses)
+}
+
+/// 把媒体移动到另一个章节（可以是其他书籍中的章节）
+#[debug_handler]
+pub async fn move_media(
+    auth: ApiAuth,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    Json(params): Json<MoveMediaParams>,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksWrite)?;
+    let item = load_item(&ctx, id, user.id).await?;
+    let book_id = params.book_id.unwrap_or(item.book_id);
+    locations::validate(&ctx.db, user.id, book_id, params.chapter_id).await?;
+
+    // 已经附加到目标位置的媒体不能再移过去，否则会在同一位置出现两次
+    if media_placements::Model::exists_at(&ctx.db, item.id, book_id, params.chapter_id).await? {
+        return Err(Error::BadRequest("媒体已附加到该位置".to_string()));
     }
 
-    // 删除数据库记录
-    item.delete(&ctx.db).await?;
-    format::empty()
+    let same_location = item.book_id == book_id && item.chapter_id == params.chapter_id;
+    let sort_order = match params.sort_order {
+        Some(sort_order) => Some(sort_order),
+        None if same_location => item.sort_order,
+        None => Some(Model::next_sort_order(&ctx.db, book_id, params.chapter_id).await?),
+    };
+    let before = audit_summary(&item);
+
+    let mut active = item.into_active_model();
+    active.book_id = Set(book_id);
+    active.chapter_id = Set(params.chapter_id);
+    active.sort_order = Set(sort_order);
+    let item = active.update(&ctx.db).await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("media.move")
+            .actor(&user)
+            .target("media", item.id)
+            .before(before)
+            .after(audit_summary(&item))
+            .ip(ip),
+    )
+    .await;
+
+    format::json(MediaResponse::from(item))
 }
 
 /// 搜索媒体
 #[debug_handler]
 #[allow(clippy::implicit_hasher)]
 pub async fn search(
-    auth: auth::JWT,
+    auth: ApiAuth,
     State(ctx): State<AppContext>,
     Query(params): Query<std::collections::HashMap<String, String>>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksRead)?;
 
     let query = params.get("q").map_or("", |q| q.as_str());
 
@@ -182,6 +394,7 @@ pub async fn search(
             // 验证用户是否有权限访问该书籍
             let _book = books::Entity::find_by_id(book_id)
                 .filter(books::Column::UserId.eq(user.id))
+                .filter(books::Column::DeletedAt.is_null())
                 .one(&ctx.db)
                 .await?
                 .ok_or_else(|| Error::NotFound)?;
@@ -205,11 +418,11 @@ pub async fn search(
 #[debug_handler]
 #[allow(clippy::implicit_hasher)]
 pub async fn list_by_chapter(
-    auth: auth::JWT,
+    auth: ApiAuth,
     State(ctx): State<AppContext>,
     Query(params): Query<std::collections::HashMap<String, String>>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksRead)?;
 
     let chapter_id = params
         .get("chapter_id")
@@ -218,29 +431,24 @@ pub async fn list_by_chapter(
 
     // 验证章节存在且属于用户
     let chapter = chapters::Entity::find_by_id(chapter_id)
+        .filter(chapters::Column::DeletedAt.is_null())
         .one(&ctx.db)
         .await?;
-    if let Some(chapter) = chapter {
-        // 验证书籍是否属于当前用户
-        let book = crate::models::_entities::books::Entity::find_by_id(chapter.book_id)
-            .filter(crate::models::_entities::books::Column::UserId.eq(user.id))
-            .one(&ctx.db)
-            .await?;
-
-        if book.is_none() {
-            return Err(Error::NotFound);
-        }
-    } else {
+    let Some(chapter) = chapter else {
         return Err(Error::NotFound);
-    }
-
-    let medias = Entity::find()
-        .filter(Column::ChapterId.eq(chapter_id))
-        .filter(Column::UserId.eq(user.id))
-        .all(&ctx.db)
+    };
+    // 验证书籍是否属于当前用户
+    let book = crate::models::_entities::books::Entity::find_by_id(chapter.book_id)
+        .filter(crate::models::_entities::books::Column::UserId.eq(user.id))
+        .filter(crate::models::_entities::books::Column::DeletedAt.is_null())
+        .one(&ctx.db)
         .await?;
+    if book.is_none() {
+        return Err(Error::NotFound);
+    }
 
-    let responses: Vec<MediaResponse> = medias.into_iter().map(MediaResponse::from).collect();
+    // 原始位置在该章节的媒体和附加到该章节的媒体共用一个顺序
+    let responses = location_medias(&ctx, user.id, chapter.book_id, Some(chapter_id)).await?;
     format::json(responses)
 }
 
@@ -252,11 +460,11 @@ pub async fn list_by_chapter(
 #[debug_handler]
 #[allow(clippy::implicit_hasher)]
 pub async fn list_by_chapter_recursive(
-    auth: auth::JWT,
+    auth: ApiAuth,
     State(ctx): State<AppContext>,
     Query(params): Query<std::collections::HashMap<String, String>>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksRead)?;
 
     let chapter_id = params
         .get("chapter_id")
@@ -265,12 +473,14 @@ pub async fn list_by_chapter_recursive(
 
     // 验证章节存在且属于用户
     let chapter = chapters::Entity::find_by_id(chapter_id)
+        .filter(chapters::Column::DeletedAt.is_null())
         .one(&ctx.db)
         .await?;
     if let Some(ref chapter) = chapter {
         // 验证书籍是否属于当前用户
         let book = crate::models::_entities::books::Entity::find_by_id(chapter.book_id)
             .filter(crate::models::_entities::books::Column::UserId.eq(user.id))
+            .filter(crate::models::_entities::books::Column::DeletedAt.is_null())
             .one(&ctx.db)
             .await?;
 
@@ -296,34 +506,47 @@ pub async fn list_by_chapter_recursive(
                 .like(format!("{chapter_path}%"))
                 .or(chapters::Column::Id.eq(chapter_id)),
         )
+        .filter(chapters::Column::DeletedAt.is_null())
         .all(&ctx.db)
         .await?;
 
     // 收集所有相关章节的ID
     let chapter_ids: Vec<i32> = related_chapters.iter().map(|c| c.id).collect();
 
-    // 获取所有这些章节的媒体
+    // 获取所有这些章节的媒体（包括附加到这些章节的媒体）
     let medias = Entity::find()
-        .filter(Column::ChapterId.is_in(chapter_ids))
+        .filter(Column::ChapterId.is_in(chapter_ids.clone()))
         .filter(Column::UserId.eq(user.id))
+        .filter(Column::DeletedAt.is_null())
+        .order_by_asc(Column::SortOrder)
         .all(&ctx.db)
         .await?;
-
-    let responses: Vec<MediaResponse> = medias.into_iter().map(MediaResponse::from).collect();
+    let placed = media_placements::Model::find_in_chapters(&ctx.db, &chapter_ids).await?;
+
+    let responses: Vec<MediaResponse> = medias
+        .into_iter()
+        .map(MediaResponse::from)
+        .chain(
+            placed
+                .into_iter()
+                .map(|(placement, media)| MediaResponse::placed(media, placement)),
+        )
+        .collect();
     format::json(responses)
 }
 
 /// 获取章节的直接子章节列表
 #[debug_handler]
 pub async fn list_child_chapters(
-    auth: auth::JWT,
+    auth: ApiAuth,
     State(ctx): State<AppContext>,
     Path(chapter_id): Path<i32>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::BooksRead)?;
 
     // 验证父章节存在且属于用户
     let parent_chapter = chapters::Entity::find_by_id(chapter_id)
+        .filter(chapters::Column::DeletedAt.is_null())
         .one(&ctx.db)
         .await?;
 
@@ -332,6 +555,7 @@ pub async fn list_child_chapters(
             // 验证书籍是否属于当前用户
             let book = crate::models::_entities::books::Entity::find_by_id(parent_chapter.book_id)
                 .filter(crate::models::_entities::books::Column::UserId.eq(user.id))
+                .filter(crate::models::_entities::books::Column::DeletedAt.is_null())
                 .one(&ctx.db)
                 .await?;
 
@@ -346,6 +570,7 @@ pub async fn list_child_chapters(
     let child_chapters: Vec<chapters::Model> = chapters::Entity::find()
         .filter(chapters::Column::ParentId.eq(chapter_id))
         .filter(chapters::Column::BookId.eq(book_id))
+        .filter(chapters::Column::DeletedAt.is_null())
         .all(&ctx.db)
         .await?;
 
@@ -361,17 +586,17 @@ pub async fn list_child_chapters(
 #[allow(clippy::cognitive_complexity)]
 #[allow(clippy::too_many_lines)]
 pub async fn upload(
-    auth: auth::JWT,
+    auth: ApiAuth,
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
     mut multipart: Multipart,
 ) -> Result<Response> {
     // 设置最大文件大小为 2GB
     const MAX_FILE_SIZE: u64 = 2_147_483_648;
 
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::MediaUpload)?;
 
-    let mut temp_file_path: Option<std::path::PathBuf> = None;
-    let mut file_size: Option<u64> = None;
+    let mut temp_upload: Option<TempUpload> = None;
     let mut filename: Option<String> = None;
     let mut content_type: Option<String> = None;
     let mut title: Option<String> = None;
@@ -380,7 +605,7 @@ pub async fn upload(
     let mut chapter_id: Option<i32> = None;
 
     // 解析 multipart 数据
-    while let Some(mut field) = multipart
+    while let Some(field) = multipart
         .next_field()
         .await
         .map_err(|e| Error::Message(format!("解析上传数据失败: {e}")))?
@@ -389,63 +614,22 @@ pub async fn upload(
 
         match name.as_str() {
             "file" => {
-                let field_filename = field.file_name().map(str::to_string);
-                let field_content_type = field.content_type().map(str::to_string);
-
                 // 验证必需信息
-                let fname = field_filename
-                    .clone()
+                let fname = field
+                    .file_name()
+                    .map(str::to_string)
                     .ok_or_else(|| Error::Message("缺少文件名".to_string()))?;
-                let ctype = field_content_type
-                    .clone()
+                let ctype = field
+                    .content_type()
+                    .map(str::to_string)
                     .ok_or_else(|| Error::Message("缺少文件类型".to_string()))?;
 
-                // 先验证文件类型
-                let storage = StorageService::new("uploads");
-                storage.validate_file_type(&fname, &ctype)?;
-
-                // 创建临时文件并流式写入
-                let temp_dir = std::env::temp_dir();
-                let temp_filename = format!("upload_{}.tmp", uuid::Uuid::new_v4());
-                let temp_path = temp_dir.join(&temp_filename);
-
-                let mut file = tokio::fs::File::create(&temp_path)
-                    .await
-                    .map_err(|e| Error::Message(format!("创建临时文件失败: {e}")))?;
-
-                let mut total_size: u64 = 0;
-
-                // 逐块读取并写入
-                while let Some(chunk) = field
-                    .chunk()
-                    .await
-                    .map_err(|e| Error::Message(format!("读取数据块失败: {e}")))?
-                {
-                    total_size += chunk.len() as u64;
-
-                    // 检查大小限制
-                    if total_size > MAX_FILE_SIZE {
-                        // 清理临时文件
-                        let _ = tokio::fs::remove_file(&temp_path).await;
-                        return Err(Error::Message(format!(
-                            "文件大小超过限制 ({}GB)",
-                            MAX_FILE_SIZE / 1_073_741_824
-                        )));
-                    }
-
-                    // 写入块
-                    file.write_all(&chunk)
-                        .await
-                        .map_err(|e| Error::Message(format!("写入数据失败: {e}")))?;
-                }
+                // 流式写入临时文件（验证文件类型、检查大小限制），同时计算内容摘要
+                let upload = STORAGE_SERVICE
+                    .save_to_temp(field, &fname, &ctype, MAX_FILE_SIZE)
+                    .await?;
 
-                // 确保所有数据都写入磁盘
-                file.flush()
-                    .await
-                    .map_err(|e| Error::Message(format!("刷新缓冲区失败: {e}")))?;
-
-                temp_file_path = Some(temp_path);
-                file_size = Some(total_size);
+                temp_upload = Some(upload);
                 filename = Some(fname);
                 content_type = Some(ctype);
             }
@@ -494,16 +678,16 @@ pub async fn upload(
     }
 
     // 验证必需字段
-    let temp_path = temp_file_path.ok_or_else(|| Error::Message("缺少文件数据".to_string()))?;
-    let file_size = file_size.ok_or_else(|| Error::Message("未获取文件大小".to_string()))?;
+    let temp_upload = temp_upload.ok_or_else(|| Error::Message("缺少文件数据".to_string()))?;
     let filename = filename.ok_or_else(|| Error::Message("缺少文件名".to_string()))?;
     let content_type = content_type.ok_or_else(|| Error::Message("缺少文件类型".to_string()))?;
     let title = title.ok_or_else(|| Error::Message("缺少标题".to_string()))?;
     let book_id = book_id.ok_or_else(|| Error::Message("缺少书籍ID".to_string()))?;
 
     // 验证用户是否拥有该书籍
-    let _book = books::Entity::find_by_id(book_id)
+    let book = books::Entity::find_by_id(book_id)
         .filter(books::Column::UserId.eq(user.id))
+        .filter(books::Column::DeletedAt.is_null())
         .one(&ctx.db)
         .await?
         .ok_or_else(|| Error::Message("书籍不存在或无权限".to_string()))?;
@@ -512,23 +696,16 @@ pub async fn upload(
     if let Some(chapter_id) = chapter_id {
         let _chapter = chapters::Entity::find_by_id(chapter_id)
             .filter(chapters::Column::BookId.eq(book_id))
+            .filter(chapters::Column::DeletedAt.is_null())
             .one(&ctx.db)
             .await?
             .ok_or_else(|| Error::Message("章节不存在或不属于指定书籍".to_string()))?;
     }
 
-    // 使用存储服务移动临时文件到永久位置
-    let storage = StorageService::new("uploads");
-    let uploaded_file = storage
-        .move_temp_file(
-            user.id,
-            book_id,
-            &temp_path,
-            &filename,
-            &content_type,
-            file_size,
-        )
-        .await?;
+    // 按内容存储文件，与已有文件内容相同时共用
+    let storage = &*STORAGE_SERVICE;
+    let file_path = content_store::store_upload(&ctx.db, storage, &temp_upload, &filename).await?;
+    let local_path = storage.resolve(&file_path).to_string_lossy().to_string();
 
     // 确定文件类型
     let file_type = storage.determine_file_type(&content_type)?;
@@ -536,13 +713,11 @@ pub async fn upload(
     // 提取元数据（包含时长）- 使用原始文件
     let duration = if content_type.starts_with("video/") {
         // 视频文件使用 FFmpeg 提取
-        let metadata = VIDEO_METADATA_SERVICE
-            .extract_with_fallback(&uploaded_file.path.to_string_lossy(), &content_type);
+        let metadata = VIDEO_METADATA_SERVICE.extract_with_fallback(&local_path, &content_type);
         metadata.duration
     } else {
         // 音频文件使用 Symphonia 提取
-        let metadata = AUDIO_METADATA_SERVICE
-            .extract_with_fallback(&uploaded_file.path.to_string_lossy(), &content_type);
+        let metadata = AUDIO_METADATA_SERVICE.extract_with_fallback(&local_path, &content_type);
         metadata.duration
     };
 
@@ -575,8 +750,8 @@ pub async fn upload(
         title: Set(title),
         description: Set(description),
         file_type: Set(file_type),
-        file_path: Set(uploaded_file.path.to_string_lossy().to_string()),
-        file_size: Set(Some(i64::try_from(uploaded_file.size).unwrap_or(i64::MAX))),
+        file_path: Set(file_path),
+        file_size: Set(Some(i64::try_from(temp_upload.size).unwrap_or(i64::MAX))),
         duration: Set(duration),
         mime_type: Set(Some(content_type.clone())),
         access_token: Set(access_token.clone()),
@@ -590,10 +765,33 @@ pub async fn upload(
         original_filename: Set(Some(filename.clone())),
         play_count: Set(0),
         is_public: Set(false),
+        content_hash: Set(Some(temp_upload.content_hash)),
         ..Default::default()
     };
 
     let media = media.insert(&ctx.db).await?;
+    media_versions::Model::record(&ctx.db, &media, Some(user.id), None).await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("media.upload")
+            .actor(&user)
+            .target("media", media.id)
+            .after(audit_summary(&media))
+            .ip(ip),
+    )
+    .await;
+
+    webhooks::dispatch(
+        &ctx,
+        media.user_id,
+        EVENT_MEDIA_UPLOADED,
+        webhooks::media_data(&media),
+    )
+    .await;
+
+    // 书籍开启了音频处理时生成播放版本
+    audio_processing::enqueue(&ctx, &book, &media).await;
 
     // 异步生成二维码（不阻塞响应）
     if let Some(ref access_url) = media.access_url {
@@ -612,7 +810,20 @@ pub async fn upload(
     format::json(MediaResponse::from(media))
 }
 
-/// 替换媒体文件
+/// 更新媒体记录并记录新版本，两者在同一事务中写入
+async fn save_replacement(
+    db: &DatabaseConnection,
+    active_model: ActiveModel,
+    user_id: i32,
+) -> Result<Model> {
+    let txn = db.begin().await?;
+    let media = active_model.update(&txn).await?;
+    media_versions::Model::record(&txn, &media, Some(user_id), None).await?;
+    txn.commit().await?;
+    Ok(media)
+}
+
+/// 替换媒体文件（旧文件保留为历史版本）
 ///
 /// # Panics
 ///
@@ -621,29 +832,26 @@ pub async fn upload(
 #[allow(clippy::cognitive_complexity)]
 #[allow(clippy::too_many_lines)]
 pub async fn replace_file(
-    auth: auth::JWT,
+    auth: ApiAuth,
     AxumPath(id): AxumPath<i32>,
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
     mut multipart: Multipart,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::MediaUpload)?;
 
     // 验证媒体是否存在且属于当前用户
     let media = load_item(&ctx, id, user.id).await?;
-    let book_id = media.book_id;
-    let old_file_path = media.file_path.clone();
+    let before = audit_summary(&media);
     // 设置最大文件大小为 2GB
     const MAX_FILE_SIZE: u64 = 2_147_483_648;
 
-    let old_file_version = media.file_version;
-
-    let mut temp_file_path: Option<std::path::PathBuf> = None;
-    let mut file_size: Option<u64> = None;
+    let mut temp_upload: Option<TempUpload> = None;
     let mut filename: Option<String> = None;
     let mut content_type: Option<String> = None;
 
     // 解析 multipart 数据
-    while let Some(mut field) = multipart
+    while let Some(field) = multipart
         .next_field()
         .await
         .map_err(|e| Error::Message(format!("解析上传数据失败: {e}")))?
@@ -651,63 +859,22 @@ pub async fn replace_file(
         let name = field.name().unwrap_or("").to_string();
 
         if name == "file" {
-            let field_filename = field.file_name().map(str::to_string);
-            let field_content_type = field.content_type().map(str::to_string);
-
             // 验证必需信息
-            let fname = field_filename
-                .clone()
+            let fname = field
+                .file_name()
+                .map(str::to_string)
                 .ok_or_else(|| Error::Message("缺少文件名".to_string()))?;
-            let ctype = field_content_type
-                .clone()
+            let ctype = field
+                .content_type()
+                .map(str::to_string)
                 .ok_or_else(|| Error::Message("缺少文件类型".to_string()))?;
 
-            // 先验证文件类型
-            let storage = StorageService::new("uploads");
-            storage.validate_file_type(&fname, &ctype)?;
-
-            // 创建临时文件并流式写入
-            let temp_dir = std::env::temp_dir();
-            let temp_filename = format!("upload_{}.tmp", uuid::Uuid::new_v4());
-            let temp_path = temp_dir.join(&temp_filename);
-
-            let mut file = tokio::fs::File::create(&temp_path)
-                .await
-                .map_err(|e| Error::Message(format!("创建临时文件失败: {e}")))?;
-
-            let mut total_size: u64 = 0;
-
-            // 逐块读取并写入
-            while let Some(chunk) = field
-                .chunk()
-                .await
-                .map_err(|e| Error::Message(format!("读取数据块失败: {e}")))?
-            {
-                total_size += chunk.len() as u64;
-
-                // 检查大小限制
-                if total_size > MAX_FILE_SIZE {
-                    // 清理临时文件
-                    let _ = tokio::fs::remove_file(&temp_path).await;
-                    return Err(Error::Message(format!(
-                        "文件大小超过限制 ({}GB)",
-                        MAX_FILE_SIZE / 1_073_741_824
-                    )));
-                }
-
-                // 写入块
-                file.write_all(&chunk)
-                    .await
-                    .map_err(|e| Error::Message(format!("写入数据失败: {e}")))?;
-            }
-
-            // 确保所有数据都写入磁盘
-            file.flush()
-                .await
-                .map_err(|e| Error::Message(format!("刷新缓冲区失败: {e}")))?;
+            // 流式写入临时文件（验证文件类型、检查大小限制），同时计算内容摘要
+            let upload = STORAGE_SERVICE
+                .save_to_temp(field, &fname, &ctype, MAX_FILE_SIZE)
+                .await?;
 
-            temp_file_path = Some(temp_path);
-            file_size = Some(total_size);
+            temp_upload = Some(upload);
             filename = Some(fname);
             content_type = Some(ctype);
             break;
@@ -715,40 +882,28 @@ pub async fn replace_file(
     }
 
     // 验证必需字段
-    let temp_path = temp_file_path.ok_or_else(|| Error::Message("缺少文件数据".to_string()))?;
-    let file_size = file_size.ok_or_else(|| Error::Message("未获取文件大小".to_string()))?;
+    let temp_upload = temp_upload.ok_or_else(|| Error::Message("缺少文件数据".to_string()))?;
     let filename = filename.ok_or_else(|| Error::Message("缺少文件名".to_string()))?;
     let content_type = content_type.ok_or_else(|| Error::Message("缺少文件类型".to_string()))?;
 
-    // 使用存储服务移动临时文件到永久位置（替换旧文件）
-    let storage = StorageService::new("uploads");
-    let uploaded_file = storage
-        .move_temp_file(
-            user.id,
-            book_id,
-            &temp_path,
-            &filename,
-            &content_type,
-            file_size,
-        )
-        .await?;
-
-    // 删除旧文件
-    let _ = storage
-        .delete_file(std::path::Path::new(&old_file_path))
-        .await;
+    // 旧文件作为历史版本保留，不再删除
+    versions::ensure_current_recorded(&ctx.db, &media).await?;
+    let new_version = versions::next_version(&ctx.db, &media).await?;
 
     // 确定文件类型
+    let storage = &*STORAGE_SERVICE;
     let file_type = storage.determine_file_type(&content_type)?;
 
+    // 按内容存储文件，与已有文件内容相同时共用
+    let file_path = content_store::store_upload(&ctx.db, storage, &temp_upload, &filename).await?;
+    let local_path = storage.resolve(&file_path).to_string_lossy().to_string();
+
     // 提取元数据（包含时长）- 使用原始文件
     let duration = if content_type.starts_with("video/") {
-        let metadata = VIDEO_METADATA_SERVICE
-            .extract_with_fallback(&uploaded_file.path.to_string_lossy(), &content_type);
+        let metadata = VIDEO_METADATA_SERVICE.extract_with_fallback(&local_path, &content_type);
         metadata.duration
     } else {
-        let metadata = AUDIO_METADATA_SERVICE
-            .extract_with_fallback(&uploaded_file.path.to_string_lossy(), &content_type);
+        let metadata = AUDIO_METADATA_SERVICE.extract_with_fallback(&local_path, &content_type);
         metadata.duration
     };
 
@@ -768,21 +923,210 @@ pub async fn replace_file(
     }
 
     // 更新媒体记录（保持 access_token 不变）
+    let previous = media.clone();
+    let content_hash = temp_upload.content_hash.clone();
     let mut active_model: ActiveModel = media.into();
     active_model.file_type = Set(file_type);
-    active_model.file_path = Set(uploaded_file.path.to_string_lossy().to_string());
-    active_model.file_size = Set(Some(uploaded_file.size as i64));
+    active_model.file_path = Set(file_path);
+    active_model.file_size = Set(Some(i64::try_from(temp_upload.size).unwrap_or(i64::MAX)));
+    active_model.content_hash = Set(Some(temp_upload.content_hash));
+    active_model.file_missing_at = Set(None);
+    audio_processing::clear_rendition(&mut active_model);
     active_model.duration = Set(duration);
     active_model.mime_type = Set(Some(content_type.clone()));
-    active_model.file_version = Set(old_file_version + 1);
+    active_model.file_version = Set(new_version);
     active_model.original_filename = Set(Some(filename));
     active_model.updated_at = Set(chrono::Utc::now().into());
 
-    let updated_media = active_model.update(&ctx.db).await?;
+    // 保存上传时增加的引用由新版本持有，写入失败时释放
+    let updated_media = match save_replacement(&ctx.db, active_model, user.id).await {
+        Ok(updated_media) => updated_media,
+        Err(e) => {
+            if let Err(release_err) = content_store::release(&ctx.db, &content_hash).await {
+                tracing::warn!(
+                    "释放内容文件引用失败: {}, 错误: {}",
+                    content_hash,
+                    release_err
+                );
+            }
+            return Err(e);
+        }
+    };
+    versions::prune(&ctx.db, &updated_media, versions::retention_count()).await?;
+    audio_processing::remove_rendition(&previous).await;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("media.replace_file")
+            .actor(&user)
+            .target("media", updated_media.id)
+            .before(before)
+            .after(audit_summary(&updated_media))
+            .ip(ip),
+    )
+    .await;
+
+    webhooks::dispatch(
+        &ctx,
+        updated_media.user_id,
+        EVENT_MEDIA_REPLACED,
+        webhooks::media_data(&updated_media),
+    )
+    .await;
+
+    if let Some(book) = books::Entity::find_by_id(updated_media.book_id)
+        .one(&ctx.db)
+        .await?
+    {
+        audio_processing::enqueue(&ctx, &book, &updated_media).await;
+    }
 
     format::json(MediaResponse::from(updated_media))
 }
 
+/// 获取媒体的文件版本历史
+#[debug_handler]
+pub async fn list_versions(
+    auth: ApiAuth,
+    AxumPath(id): AxumPath<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksRead)?;
+    let media = load_item(&ctx, id, user.id).await?;
+    versions::ensure_current_recorded(&ctx.db, &media).await?;
+
+    let responses: Vec<MediaVersionResponse> =
+        media_versions::Model::find_by_media(&ctx.db, media.id)
+            .await?
+            .into_iter()
+            .map(|version| MediaVersionResponse::new(version, media.file_version))
+            .collect();
+    format::json(responses)
+}
+
+/// 回滚到指定的文件版本（保持 access_token 不变）
+#[debug_handler]
+pub async fn rollback_version(
+    auth: ApiAuth,
+    AxumPath((id, version)): AxumPath<(i32, i32)>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::MediaUpload)?;
+    let media = load_item(&ctx, id, user.id).await?;
+    let before = audit_summary(&media);
+
+    let updated_media = versions::rollback(&ctx.db, media, version).await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("media.rollback")
+            .actor(&user)
+            .target("media", updated_media.id)
+            .before(before)
+            .after(audit_summary(&updated_media))
+            .ip(ip),
+    )
+    .await;
+
+    webhooks::dispatch(
+        &ctx,
+        updated_media.user_id,
+        EVENT_MEDIA_REPLACED,
+        webhooks::media_data(&updated_media),
+    )
+    .await;
+
+    if let Some(book) = books::Entity::find_by_id(updated_media.book_id)
+        .one(&ctx.db)
+        .await?
+    {
+        audio_processing::enqueue(&ctx, &book, &updated_media).await;
+    }
+
+    format::json(MediaResponse::from(updated_media))
+}
+
+/// 从媒体的时间范围剪辑出新媒体（由处理任务生成，新媒体有自己的访问令牌和二维码）
+#[debug_handler]
+pub async fn clip(
+    auth: ApiAuth,
+    AxumPath(id): AxumPath<i32>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    Json(params): Json<ClipParams>,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::MediaUpload)?;
+    let media = load_item(&ctx, id, user.id).await?;
+    if media.file_type != "audio" {
+        return Err(Error::BadRequest("只能剪辑音频".to_string()));
+    }
+    audio_clipping::validate_range(params.start_ms, params.end_ms, media.duration)?;
+
+    let chapter_id = params.chapter_id.or(media.chapter_id);
+    locations::validate(&ctx.db, user.id, media.book_id, chapter_id).await?;
+    let title = params
+        .title
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| {
+            audio_clipping::default_title(&media.title, params.start_ms, params.end_ms)
+        });
+
+    let clip = media_clips::Model::create(
+        &ctx.db,
+        user.id,
+        ClipRequest {
+            source_media_id: media.id,
+            start_ms: params.start_ms,
+            end_ms: params.end_ms,
+            title,
+            book_id: media.book_id,
+            chapter_id,
+        },
+    )
+    .await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("media.clip")
+            .actor(&user)
+            .target("media", media.id)
+            .after(serde_json::json!({
+                "clip_id": clip.id,
+                "start_ms": clip.start_ms,
+                "end_ms": clip.end_ms,
+                "title": clip.title,
+                "chapter_id": clip.chapter_id,
+            }))
+            .ip(ip),
+    )
+    .await;
+
+    audio_clipping::enqueue(&ctx, &clip).await;
+
+    // 处理任务同步执行时返回的已经是处理结果
+    let clip = media_clips::Entity::find_by_id(clip.id)
+        .one(&ctx.db)
+        .await?
+        .unwrap_or(clip);
+    format::json(MediaClipResponse::from(clip))
+}
+
+/// 获取剪辑任务的状态
+#[debug_handler]
+pub async fn show_clip(
+    auth: ApiAuth,
+    AxumPath(clip_id): AxumPath<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksRead)?;
+    let clip = media_clips::Model::find_for_user(&ctx.db, clip_id, user.id)
+        .await?
+        .ok_or_else(|| Error::NotFound)?;
+    format::json(MediaClipResponse::from(clip))
+}
+
 /// 异步生成二维码的辅助函数
 async fn generate_qrcode_for_media(
     ctx: &AppContext,
@@ -811,27 +1155,112 @@ async fn generate_qrcode_for_media(
     let mut active_model: crate::models::_entities::medias::ActiveModel = media.into();
     active_model.qr_code_path = Set(Some(qrcode_path.clone()));
 
-    active_model.update(&ctx.db).await.map_err(|e| {
+    let media = active_model.update(&ctx.db).await.map_err(|e| {
         tracing::error!("更新媒体 {} 的二维码路径失败: {}", media_id, e);
         Error::Message(format!("更新二维码路径失败: {e}"))
     })?;
 
     tracing::info!("媒体 {} 的二维码生成完成: {}", media_id, qrcode_path);
 
+    webhooks::dispatch(
+        ctx,
+        media.user_id,
+        EVENT_MEDIA_PROCESSED,
+        webhooks::media_data(&media),
+    )
+    .await;
+
     Ok(())
 }
 
 /// 发布/取消发布媒体
 #[debug_handler]
 pub async fn publish(
-    auth: auth::JWT,
+    auth: ApiAuth,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::MediaUpload)?;
+    let item = load_item(&ctx, id, user.id).await?;
+    let publish_now = !item.is_public;
+
+    // 手动发布会取消尚未到期的定时发布；已过下架时间的内容重新发布时清除下架时间
+    let mut media = item.clone().into_active_model();
+    media.is_public = Set(publish_now);
+    if publish_now {
+        media.publish_at = Set(None);
+        if publishing::is_due(item.unpublish_at) {
+            media.unpublish_at = Set(None);
+        }
+    }
+    let media = media.update(&ctx.db).await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("media.publish")
+            .actor(&user)
+            .target("media", id)
+            .before(serde_json::json!({ "is_public": item.is_public }))
+            .after(serde_json::json!({ "is_public": media.is_public }))
+            .ip(ip),
+    )
+    .await;
+
+    if media.is_public {
+        webhooks::dispatch(
+            &ctx,
+            media.user_id,
+            EVENT_MEDIA_PUBLISHED,
+            webhooks::media_data(&media),
+        )
+        .await;
+    }
+
+    format::json(MediaResponse::from(media))
+}
+
+/// 设置定时发布和下架时间
+///
+/// 发布时间在未来时媒体先取消公开，到时间后由后台任务自动发布
+#[debug_handler]
+pub async fn schedule(
+    auth: ApiAuth,
     Path(id): Path<i32>,
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    Json(params): Json<ScheduleParams>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
-    let _ = load_item(&ctx, id, user.id).await?;
+    let user = auth.authorize(Scope::MediaUpload)?;
+    params.validate()?;
+    let item = load_item(&ctx, id, user.id).await?;
+    let before = schedule_summary(item.is_public, item.publish_at, item.unpublish_at);
 
-    let media = ActiveModel::toggle_public(&ctx.db, id, user.id).await?;
+    let mut media = item.into_active_model();
+    if params
+        .publish_at
+        .is_some_and(|at| !publishing::is_due(Some(at)))
+    {
+        media.is_public = Set(false);
+    }
+    media.publish_at = Set(params.publish_at);
+    media.unpublish_at = Set(params.unpublish_at);
+    let media = media.update(&ctx.db).await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("media.schedule")
+            .actor(&user)
+            .target("media", id)
+            .before(before)
+            .after(schedule_summary(
+                media.is_public,
+                media.publish_at,
+                media.unpublish_at,
+            ))
+            .ip(ip),
+    )
+    .await;
 
     format::json(MediaResponse::from(media))
 }
@@ -839,11 +1268,11 @@ pub async fn publish(
 /// 获取媒体二维码
 #[debug_handler]
 pub async fn get_qrcode(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path(id): Path<i32>,
     State(ctx): State<AppContext>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::QrcodeManage)?;
     let media = load_item(&ctx, id, user.id).await?;
 
     // 获取访问链接
@@ -883,11 +1312,11 @@ pub async fn get_qrcode(
 /// 重新生成媒体二维码
 #[debug_handler]
 pub async fn regenerate_qrcode(
-    auth: auth::JWT,
+    auth: ApiAuth,
     Path(id): Path<i32>,
     State(ctx): State<AppContext>,
 ) -> Result<Response> {
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::QrcodeManage)?;
     let media = load_item(&ctx, id, user.id).await?;
 
     // 重新生成二维码
@@ -935,16 +1364,20 @@ pub async fn stream_media(
         .one(&ctx.db)
         .await?
         .ok_or_else(|| Error::NotFound)?;
+    if media.deleted_at.is_some() {
+        return Err(public::gone());
+    }
 
-    // 检查文件是否存在
-    let file_path = &media.file_path;
-    if tokio::fs::metadata(file_path).await.is_err() {
+    // 检查文件是否存在（书籍开启了音频处理时播放处理后的版本）
+    let playback = audio_processing::playback_file(&ctx.db, &media).await?;
+    let file_path = playback.path;
+    if tokio::fs::metadata(&file_path).await.is_err() {
         return Err(Error::NotFound);
     }
 
     // 获取文件大小和内容类型
-    let file_size = tokio::fs::metadata(file_path).await?.len();
-    let content_type = media
+    let file_size = tokio::fs::metadata(&file_path).await?.len();
+    let content_type = playback
         .mime_type
         .as_deref()
         .unwrap_or("application/octet-stream");
@@ -1010,7 +1443,7 @@ pub async fn stream_media(
     };
 
     // 打开文件
-    let mut file = File::open(file_path)
+    let mut file = File::open(&file_path)
         .await
         .map_err(|_| Error::InternalServerError)?;
 
@@ -1081,10 +1514,17 @@ async fn increment_play_count_safe(ctx: &AppContext, media_id: i32) -> Result<()
         let mut active_model: crate::models::_entities::medias::ActiveModel = media.into();
         active_model.play_count = Set(play_count + 1);
 
-        active_model.update(&ctx.db).await.map_err(|e| {
+        let media = active_model.update(&ctx.db).await.map_err(|e| {
             tracing::warn!("更新媒体 {} 播放次数失败: {}", media_id, e);
             e
         })?;
+
+        media_plays::Model::record(&ctx.db, &media, media_plays::KIND_PLAY)
+            .await
+            .map_err(|e| {
+                tracing::warn!("记录媒体 {} 播放事件失败: {}", media_id, e);
+                e
+            })?;
     }
 
     Ok(())
@@ -1137,11 +1577,18 @@ pub fn routes() -> Routes {
         .add("/by-chapter", get(list_by_chapter))
         .add("/by-chapter-recursive", get(list_by_chapter_recursive))
         .add("/chapters/{id}/children", get(list_child_chapters))
+        .add("/batch-reorder", post(batch_reorder))
+        .add("/clips/{clip_id}", get(show_clip))
         .add("/{id}", get(show))
         .add("/{id}", put(update))
         .add("/{id}", patch(update))
         .add("/{id}", axum_delete(delete))
+        .add("/{id}/move", post(move_media))
         .add("/{id}/publish", post(publish))
+        .add("/{id}/schedule", put(schedule))
+        .add("/{id}/versions", get(list_versions))
+        .add("/{id}/versions/{version}/rollback", post(rollback_version))
+        .add("/{id}/clip", post(clip))
         .add("/{id}/qrcode", get(get_qrcode))
         .add("/{id}/regenerate-qr", post(regenerate_qrcode))
         .add("/{id}/stream", get(stream_media))
@@ -1,8 +1,19 @@
 pub mod admin;
+pub mod api_auth;
 pub mod auth;
 pub mod books;
 pub mod chapters;
 pub mod dashboard;
+pub mod embed;
+pub mod feeds;
+pub mod landing;
+pub mod media_placements;
 pub mod medias;
+pub mod oidc;
 pub mod public;
 pub mod site_settings;
+pub mod subtitles;
+pub mod tokens;
+pub mod trash;
+pub mod two_factor;
+pub mod webhooks;
@@ -0,0 +1,207 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+use axum::debug_handler;
+use axum::http::{HeaderMap, StatusCode};
+use loco_rs::auth::jwt;
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::controllers::auth::{device_info, issue_session, purpose_signer, ClientIp};
+use crate::controllers::two_factor;
+use crate::models::user_groups;
+use crate::models::user_identities;
+use crate::models::users::{self, RegisterParams};
+use crate::services::oidc::{self, IdTokenClaims, OidcConfig};
+use crate::views::auth::OidcAuthorizeResponse;
+
+/// 从发起登录到身份提供方回调的最长时间（秒）
+const STATE_TOKEN_EXPIRATION: u64 = 600;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CallbackParams {
+    pub code: String,
+    pub state: String,
+    pub state_token: String,
+}
+
+/// 登录状态令牌使用独立的签名密钥，不能被当作访问令牌使用
+fn state_signer(ctx: &AppContext) -> Result<jwt::JWT> {
+    purpose_signer(ctx, "oidc")
+}
+
+/// 未配置身份提供方时单点登录接口不存在
+fn load_config() -> Result<OidcConfig> {
+    OidcConfig::from_env().ok_or(Error::NotFound)
+}
+
+fn forbidden(error: &str, description: &str) -> Error {
+    Error::CustomError(
+        StatusCode::FORBIDDEN,
+        loco_rs::controller::ErrorDetail::new(error.to_string(), description.to_string()),
+    )
+}
+
+/// 找到外部身份对应的本地用户：优先使用已关联的身份，其次按邮箱关联已有账号，
+/// 都没有时根据配置自动创建账号
+async fn resolve_user(
+    ctx: &AppContext,
+    config: &OidcConfig,
+    claims: &IdTokenClaims,
+) -> Result<users::Model> {
+    let email = claims.verified_email();
+
+    if let Some(identity) =
+        user_identities::Model::find_by_subject(&ctx.db, &config.issuer, &claims.sub).await?
+    {
+        let user = users::Entity::find_by_id(identity.user_id)
+            .one(&ctx.db)
+            .await?
+            .ok_or_else(|| Error::Unauthorized("账号不存在".to_string()))?;
+        identity.record_login(&ctx.db, email).await?;
+        return Ok(user);
+    }
+
+    let Some(email) = email else {
+        return Err(forbidden(
+            "oidc_email_required",
+            "身份提供方未返回已验证的邮箱，无法关联账号",
+        ));
+    };
+
+    let user = match users::Model::find_by_email(&ctx.db, &email).await {
+        Ok(user) => user,
+        Err(ModelError::EntityNotFound) if config.auto_create_users => {
+            let params = RegisterParams {
+                email: email.clone(),
+                // 随机密码，需要密码登录时可通过找回密码重新设置
+                password: format!("{}{}", oidc::random_token(), oidc::random_token()),
+                name: claims.display_name(),
+            };
+            let user = users::Model::create_with_password(&ctx.db, &params).await?;
+            tracing::info!(pid = user.pid.to_string(), "user created via oidc");
+            user.into_active_model().verified(&ctx.db).await?
+        }
+        Err(ModelError::EntityNotFound) => {
+            return Err(forbidden(
+                "oidc_account_not_found",
+                "该邮箱尚未开通账号，请联系管理员",
+            ));
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    user_identities::Model::link(&ctx.db, user.id, &config.issuer, &claims.sub, Some(email))
+        .await?;
+    tracing::info!(
+        pid = user.pid.to_string(),
+        issuer = %config.issuer,
+        "oidc identity linked"
+    );
+    Ok(user)
+}
+
+/// 按身份提供方的用户组同步本地用户组成员关系
+async fn sync_groups(
+    ctx: &AppContext,
+    config: &OidcConfig,
+    user: &users::Model,
+    external_groups: &[String],
+) -> Result<()> {
+    let local_groups: Vec<(i32, String)> = user_groups::Model::list_all(&ctx.db)
+        .await?
+        .into_iter()
+        .map(|group| (group.id, group.name))
+        .collect();
+
+    let (managed, desired) =
+        oidc::resolve_groups(&config.group_mapping, external_groups, &local_groups);
+    user_groups::Model::sync_memberships(&ctx.db, user.id, &managed, &desired).await?;
+    Ok(())
+}
+
+/// 发起单点登录：生成 state、nonce 和 PKCE 校验码，返回身份提供方的授权地址
+#[debug_handler]
+async fn authorize(State(ctx): State<AppContext>) -> Result<Response> {
+    let config = load_config()?;
+    let metadata = oidc::discover(&config).await?;
+
+    let state = oidc::random_token();
+    let nonce = oidc::random_token();
+    let (code_verifier, code_challenge) = oidc::generate_pkce();
+
+    let mut claims = Map::new();
+    claims.insert("nonce".to_string(), Value::String(nonce.clone()));
+    claims.insert("code_verifier".to_string(), Value::String(code_verifier));
+    let state_token = state_signer(&ctx)?
+        .generate_token(STATE_TOKEN_EXPIRATION, state.clone(), claims)
+        .or_else(|_| unauthorized("unauthorized!"))?;
+
+    format::json(OidcAuthorizeResponse {
+        authorization_url: oidc::authorization_url(
+            &metadata,
+            &config,
+            &state,
+            &nonce,
+            &code_challenge,
+        )?,
+        state_token,
+        expires_in: STATE_TOKEN_EXPIRATION,
+    })
+}
+
+/// 身份提供方回调：用授权码换取并校验 ID Token，关联本地账号后签发登录令牌
+#[debug_handler]
+async fn callback(
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+    Json(params): Json<CallbackParams>,
+) -> Result<Response> {
+    let config = load_config()?;
+
+    let Ok(token_data) = state_signer(&ctx)?.validate(&params.state_token) else {
+        return unauthorized("登录已过期，请重新发起单点登录");
+    };
+    if token_data.claims.pid != params.state {
+        return unauthorized("登录状态不匹配，请重新发起单点登录");
+    }
+    let state_claim = |name: &str| {
+        token_data
+            .claims
+            .claims
+            .get(name)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+    };
+
+    let metadata = oidc::discover(&config).await?;
+    let id_token = oidc::exchange_code(
+        &metadata,
+        &config,
+        &params.code,
+        state_claim("code_verifier"),
+    )
+    .await?;
+    let claims =
+        oidc::validate_id_token(&metadata, &config, &id_token, state_claim("nonce")).await?;
+
+    let user = resolve_user(&ctx, &config, &claims).await?;
+    if let Some(groups_claim) = &config.groups_claim {
+        sync_groups(&ctx, &config, &user, &claims.groups(groups_claim)).await?;
+    }
+
+    // 本地启用了两步验证的账号仍需提交验证码
+    if let Some(challenge) = two_factor::login_challenge(&ctx, &user).await? {
+        return format::json(challenge);
+    }
+
+    format::json(issue_session(&ctx, &user, device_info(&headers, ip)).await?)
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("/api/auth/oidc")
+        .add("/authorize", get(authorize))
+        .add("/callback", post(callback))
+}
@@ -3,7 +3,7 @@
 #![allow(clippy::unused_async)]
 use axum::body::Body;
 use axum::debug_handler;
-use axum::extract::{Path, State};
+use axum::extract::{Path, Query, State};
 use axum::http::{header, StatusCode};
 use axum::response::Response;
 use loco_rs::prelude::*;
@@ -11,9 +11,80 @@ use tokio::fs::File;
 use tokio::io::{AsyncReadExt, AsyncSeekExt, SeekFrom};
 use tokio_util::io::ReaderStream;
 
-use crate::models::_entities::medias::{Column, Entity};
+use crate::controllers::auth::ClientIp;
+use crate::models::_entities::books;
+use crate::models::_entities::medias::{self, Column, Entity};
+use crate::models::{media_placements, media_plays, media_subtitles};
+use crate::services::audio_processing;
+use crate::services::publishing;
+use crate::services::rate_limit::COMPLETION_DEDUP;
+use crate::services::subtitles::{self, SubtitleFormat};
 use crate::views::medias::PublicMediaResponse;
 
+/// 已移入回收站的内容返回 410，与从未存在的内容区分开
+#[must_use]
+pub fn gone() -> Error {
+    Error::CustomError(
+        StatusCode::GONE,
+        loco_rs::controller::ErrorDetail::new("gone".to_string(), "内容已被删除".to_string()),
+    )
+}
+
+/// 加载对外公开的书籍：已删除返回 410，不在公开时间窗口内返回 403/410，
+/// 书籍或任一上级书籍未公开返回 404
+pub async fn load_public_book(ctx: &AppContext, id: i32) -> Result<books::Model> {
+    let book = books::Entity::find_by_id(id)
+        .one(&ctx.db)
+        .await?
+        .ok_or_else(|| Error::NotFound)?;
+    if book.deleted_at.is_some() {
+        return Err(gone());
+    }
+    publishing::check_window(book.publish_at, book.unpublish_at)?;
+    if !publishing::book_is_live(&book) {
+        return Err(Error::NotFound);
+    }
+    publishing::check_book_ancestors(&ctx.db, &book).await?;
+    Ok(book)
+}
+
+/// 通过 access_token 加载对外公开的媒体及其所属书籍，所有公开访问入口共用
+///
+/// 媒体自身和所属书籍（含上级书籍）都必须处于公开状态；
+/// 所属书籍未公开时，附加到了公开书籍中的媒体仍可访问，返回该公开书籍。
+pub async fn load_public_media(
+    ctx: &AppContext,
+    access_token: &str,
+) -> Result<(medias::Model, books::Model)> {
+    let media = Entity::find()
+        .filter(Column::AccessToken.eq(access_token))
+        .one(&ctx.db)
+        .await?
+        .ok_or_else(|| Error::NotFound)?;
+    if media.deleted_at.is_some() {
+        return Err(gone());
+    }
+    publishing::check_window(media.publish_at, media.unpublish_at)?;
+    if !publishing::is_live(media.is_public, media.publish_at) {
+        return Err(Error::NotFound);
+    }
+    let book = match load_public_book(ctx, media.book_id).await {
+        Ok(book) => book,
+        Err(err) => placed_public_book(ctx, media.id).await?.ok_or(err)?,
+    };
+    Ok((media, book))
+}
+
+/// 媒体附加到的第一本公开书籍
+async fn placed_public_book(ctx: &AppContext, media_id: i32) -> Result<Option<books::Model>> {
+    for placement in media_placements::Model::find_by_media(&ctx.db, media_id).await? {
+        if let Ok(book) = load_public_book(ctx, placement.book_id).await {
+            return Ok(Some(book));
+        }
+    }
+    Ok(None)
+}
+
 /// 通过 access_token 公开访问媒体文件
 #[debug_handler]
 pub async fn get_media(
@@ -21,26 +92,17 @@ pub async fn get_media(
     State(ctx): State<AppContext>,
     headers: header::HeaderMap,
 ) -> Result<Response> {
-    // 查找媒体记录
-    let media = Entity::find()
-        .filter(Column::AccessToken.eq(&access_token))
-        .one(&ctx.db)
-        .await?
-        .ok_or_else(|| Error::Message("媒体不存在或访问令牌无效".to_string()))?;
+    let (media, _) = load_public_media(&ctx, &access_token).await?;
 
-    // 检查媒体是否为公开状态
-    if !media.is_public {
-        return Err(Error::Unauthorized("媒体未公开".to_string()));
-    }
-
-    // 检查文件是否存在
-    let file_path = &media.file_path;
-    if tokio::fs::metadata(file_path).await.is_err() {
+    // 检查文件是否存在（书籍开启了音频处理时播放处理后的版本）
+    let playback = audio_processing::playback_file(&ctx.db, &media).await?;
+    let file_path = playback.path.to_string_lossy().to_string();
+    if tokio::fs::metadata(&file_path).await.is_err() {
         return Err(Error::NotFound);
     }
 
     // 获取文件大小
-    let file_size = tokio::fs::metadata(file_path).await?.len();
+    let file_size = tokio::fs::metadata(&file_path).await?.len();
 
     // 处理 Range 请求（支持断点续传和拖动播放）
     if let Some(range_value) = headers.get(header::RANGE) {
@@ -50,7 +112,7 @@ pub async fn get_media(
             .map_err(|_| Error::BadRequest("无效的 Range 头".to_string()))?;
 
         if let Some((start, end)) = parse_range_header(range_str, file_size) {
-            return serve_range_file(file_path, start, end, file_size, &media.mime_type).await;
+            return serve_range_file(&file_path, start, end, file_size, &playback.mime_type).await;
         }
     }
 
@@ -58,7 +120,7 @@ pub async fn get_media(
     increment_play_count(&ctx, media.id).await?;
 
     // 完整文件服务
-    serve_full_file(file_path, &media.mime_type).await
+    serve_full_file(&file_path, &playback.mime_type).await
 }
 
 /// 获取媒体公开信息
@@ -67,22 +129,83 @@ pub async fn get_media_info(
     Path(access_token): Path<String>,
     State(ctx): State<AppContext>,
 ) -> Result<Response> {
-    // 查找媒体记录
-    let media = Entity::find()
-        .filter(Column::AccessToken.eq(&access_token))
-        .one(&ctx.db)
-        .await?
-        .ok_or_else(|| Error::Message("媒体不存在或访问令牌无效".to_string()))?;
-
-    // 检查媒体是否为公开状态
-    if !media.is_public {
-        return Err(Error::Unauthorized("媒体未公开".to_string()));
-    }
+    let (media, _) = load_public_media(&ctx, &access_token).await?;
 
     // 增加播放次数
     increment_play_count(&ctx, media.id).await?;
 
-    format::json(PublicMediaResponse::from(media))
+    let subtitles = media_subtitles::Model::find_by_media(&ctx.db, media.id).await?;
+
+    format::json(PublicMediaResponse::from(media).with_tracks(&access_token, subtitles))
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct SubtitleParams {
+    /// `txt` 返回纯文本文稿，默认返回 WebVTT
+    pub format: Option<String>,
+}
+
+/// 公开获取媒体字幕（WebVTT 或纯文本文稿）
+#[debug_handler]
+pub async fn get_subtitle(
+    Path((access_token, subtitle_id)): Path<(String, i32)>,
+    Query(params): Query<SubtitleParams>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let (media, _) = load_public_media(&ctx, &access_token).await?;
+
+    let subtitle = media_subtitles::Model::find_for_media(&ctx.db, media.id, subtitle_id)
+        .await?
+        .ok_or_else(|| Error::NotFound)?;
+
+    subtitle_response(&subtitle, params.format.as_deref())
+}
+
+/// 构建字幕响应，公开和作者接口共用
+pub fn subtitle_response(
+    subtitle: &media_subtitles::Model,
+    format: Option<&str>,
+) -> Result<Response> {
+    let (body, content_type) = match format {
+        Some("txt") => {
+            let cues = subtitles::parse(&subtitle.content, SubtitleFormat::Vtt)?;
+            (subtitles::to_plain_text(&cues), "text/plain; charset=utf-8")
+        }
+        Some("vtt") | None => (subtitle.content.clone(), "text/vtt; charset=utf-8"),
+        Some(other) => {
+            return Err(Error::BadRequest(format!(
+                "不支持的字幕格式: {other}（可选 vtt, txt）"
+            )))
+        }
+    };
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, content_type)
+        // 允许跨域加载（<track> 元素需要 CORS）
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .body(Body::from(body))
+        .map_err(|e| Error::Message(format!("构建响应失败: {}", e)))
+}
+
+/// 上报完整播放（由播放器在播放结束时调用，用于统计完播率）
+#[debug_handler]
+pub async fn report_completion(
+    Path(access_token): Path<String>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+) -> Result<Response> {
+    let (media, _) = load_public_media(&ctx, &access_token).await?;
+
+    // 接口无需登录，同一来源的重复上报直接忽略，避免刷高完播率
+    if COMPLETION_DEDUP
+        .first_completion(&media.access_token, ip.as_deref())
+        .await
+    {
+        media_plays::Model::record(&ctx.db, &media, media_plays::KIND_COMPLETE).await?;
+    }
+
+    format::empty()
 }
 
 /// 增加播放次数
@@ -98,7 +221,12 @@ async fn increment_play_count(ctx: &AppContext, media_id: i32) -> Result<()> {
     let mut active_model: crate::models::_entities::medias::ActiveModel = media.into();
     active_model.play_count = Set(play_count + 1);
 
-    active_model.update(&ctx.db).await?;
+    let media = active_model.update(&ctx.db).await?;
+
+    // 记录播放事件（用于按时间范围统计），失败不影响播放
+    if let Err(e) = media_plays::Model::record(&ctx.db, &media, media_plays::KIND_PLAY).await {
+        tracing::warn!("记录媒体 {} 播放事件失败: {}", media_id, e);
+    }
 
     Ok(())
 }
@@ -209,4 +337,6 @@ pub fn routes() -> Routes {
         .prefix("/api/public/media")
         .add("/{access_token}", get(get_media))
         .add("/{access_token}/info", get(get_media_info))
+        .add("/{access_token}/complete", post(report_completion))
+        .add("/{access_token}/subtitles/{subtitle_id}", get(get_subtitle))
 }
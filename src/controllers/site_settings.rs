@@ -5,9 +5,12 @@ use axum::debug_handler;
 use loco_rs::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::models::{site_settings, users};
-
-const DEFAULT_SITE_URL: &str = "http://localhost:5150";
+use crate::controllers::api_auth::ApiAuth;
+use crate::controllers::auth::ClientIp;
+use crate::controllers::two_factor;
+use crate::models::audit_logs::{self, AuditEntry};
+use crate::models::personal_access_tokens::Scope;
+use crate::models::site_settings;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SiteSettingsResponse {
@@ -24,15 +27,16 @@ pub struct UpdateSiteSettingsParams {
 
 /// 获取站点设置
 #[debug_handler]
-pub async fn get_settings(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+pub async fn get_settings(auth: ApiAuth, State(ctx): State<AppContext>) -> Result<Response> {
     // 验证管理员权限
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::Admin)?;
     if !user.is_admin() {
         return unauthorized("Admin permission required");
     }
+    two_factor::require_for_admin(&ctx, &user).await?;
 
     // 获取或创建站点设置
-    let settings = site_settings::Model::get_or_create(&ctx.db, DEFAULT_SITE_URL).await?;
+    let settings = site_settings::Model::current(&ctx.db).await?;
 
     let response = SiteSettingsResponse {
         id: settings.id,
@@ -47,19 +51,33 @@ pub async fn get_settings(auth: auth::JWT, State(ctx): State<AppContext>) -> Res
 /// 更新站点设置
 #[debug_handler]
 pub async fn update_settings(
-    auth: auth::JWT,
+    auth: ApiAuth,
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
     Json(params): Json<UpdateSiteSettingsParams>,
 ) -> Result<Response> {
     // 验证管理员权限
-    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let user = auth.authorize(Scope::Admin)?;
     if !user.is_admin() {
         return unauthorized("Admin permission required");
     }
+    two_factor::require_for_admin(&ctx, &user).await?;
 
     // 更新站点URL
+    let before = site_settings::Model::current(&ctx.db).await?;
     let settings = site_settings::Model::update_url(&ctx.db, params.site_url).await?;
 
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("site_settings.update")
+            .actor(&user)
+            .target("site_settings", settings.id)
+            .before(serde_json::json!({ "site_url": before.site_url }))
+            .after(serde_json::json!({ "site_url": settings.site_url }))
+            .ip(ip),
+    )
+    .await;
+
     let response = SiteSettingsResponse {
         id: settings.id,
         site_url: settings.site_url,
@@ -0,0 +1,224 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unnecessary_struct_initialization)]
+#![allow(clippy::unused_async)]
+use axum::debug_handler;
+use axum::extract::{DefaultBodyLimit, Query};
+use axum::routing::method_routing::delete as axum_delete;
+use axum_extra::extract::Multipart;
+use loco_rs::prelude::*;
+
+use crate::controllers::api_auth::ApiAuth;
+use crate::controllers::auth::ClientIp;
+use crate::controllers::public::{subtitle_response, SubtitleParams};
+use crate::models::_entities::medias;
+use crate::models::audit_logs::{self, AuditEntry};
+use crate::models::media_subtitles::{self, SubtitleData, KINDS, KIND_SUBTITLES};
+use crate::models::personal_access_tokens::Scope;
+use crate::services::subtitles::{self, SubtitleFormat, MAX_SUBTITLE_SIZE};
+use crate::views::subtitles::SubtitleResponse;
+
+async fn load_media(ctx: &AppContext, id: i32, user_id: i32) -> Result<medias::Model> {
+    let item = medias::Entity::find_by_id(id)
+        .filter(medias::Column::UserId.eq(user_id))
+        .filter(medias::Column::DeletedAt.is_null())
+        .one(&ctx.db)
+        .await?;
+    item.ok_or_else(|| Error::NotFound)
+}
+
+async fn load_subtitle(
+    ctx: &AppContext,
+    media_id: i32,
+    subtitle_id: i32,
+) -> Result<media_subtitles::Model> {
+    media_subtitles::Model::find_for_media(&ctx.db, media_id, subtitle_id)
+        .await?
+        .ok_or_else(|| Error::NotFound)
+}
+
+/// 获取媒体的所有字幕
+#[debug_handler]
+pub async fn list(
+    auth: ApiAuth,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksRead)?;
+    let media = load_media(&ctx, id, user.id).await?;
+
+    let subtitles = media_subtitles::Model::find_by_media(&ctx.db, media.id).await?;
+    let responses: Vec<SubtitleResponse> =
+        subtitles.into_iter().map(SubtitleResponse::from).collect();
+
+    format::json(responses)
+}
+
+/// 上传字幕（WebVTT 或 SRT，SRT 会转换为 WebVTT 保存）
+///
+/// multipart 字段：`file`（必填）、`language`（必填，如 `zh-CN`）、
+/// `label`（可选，显示名称）、`kind`（可选，subtitles/captions/transcript）
+#[debug_handler]
+pub async fn upload(
+    auth: ApiAuth,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    mut multipart: Multipart,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::MediaUpload)?;
+    let media = load_media(&ctx, id, user.id).await?;
+
+    let mut content: Option<String> = None;
+    let mut filename: Option<String> = None;
+    let mut language: Option<String> = None;
+    let mut label: Option<String> = None;
+    let mut kind: Option<String> = None;
+
+    // 解析 multipart 数据
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| Error::BadRequest(format!("解析上传数据失败: {e}")))?
+    {
+        let name = field.name().unwrap_or("").to_string();
+        match name.as_str() {
+            "file" => {
+                filename = field.file_name().map(str::to_string);
+                let bytes = field
+                    .bytes()
+                    .await
+                    .map_err(|e| Error::BadRequest(format!("读取字幕文件失败: {e}")))?;
+                if bytes.len() > MAX_SUBTITLE_SIZE {
+                    return Err(Error::BadRequest(format!(
+                        "字幕文件大小超过限制 ({}MB)",
+                        MAX_SUBTITLE_SIZE / 1024 / 1024
+                    )));
+                }
+                content = Some(
+                    String::from_utf8(bytes.to_vec())
+                        .map_err(|_| Error::BadRequest("字幕文件必须是 UTF-8 编码".to_string()))?,
+                );
+            }
+            "language" | "label" | "kind" => {
+                let value = field
+                    .text()
+                    .await
+                    .map_err(|e| Error::BadRequest(format!("读取 {name} 失败: {e}")))?;
+                let value = Some(value.trim().to_string()).filter(|v| !v.is_empty());
+                match name.as_str() {
+                    "language" => language = value,
+                    "label" => label = value,
+                    _ => kind = value,
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let content = content.ok_or_else(|| Error::BadRequest("缺少字幕文件".to_string()))?;
+    let language = language.ok_or_else(|| Error::BadRequest("缺少语言代码".to_string()))?;
+    if !subtitles::is_valid_language(&language) {
+        return Err(Error::BadRequest(format!(
+            "无效的语言代码: {language}（如 zh-CN, en）"
+        )));
+    }
+    let kind = kind.unwrap_or_else(|| KIND_SUBTITLES.to_string());
+    if !KINDS.contains(&kind.as_str()) {
+        return Err(Error::BadRequest(format!(
+            "无效的字幕类型: {kind}（可选 {}）",
+            KINDS.join(", ")
+        )));
+    }
+
+    let source_format = SubtitleFormat::detect(filename.as_deref(), &content)?;
+    let (vtt, cue_count) = subtitles::normalize(&content, source_format)?;
+
+    let subtitle = media_subtitles::Model::upsert(
+        &ctx.db,
+        media.id,
+        SubtitleData {
+            language,
+            label,
+            kind,
+            source_format: source_format.as_str().to_string(),
+            content: vtt,
+            cue_count: i32::try_from(cue_count).unwrap_or(i32::MAX),
+        },
+    )
+    .await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("subtitle.upload")
+            .actor(&user)
+            .target("media", media.id)
+            .after(serde_json::json!({
+                "subtitle_id": subtitle.id,
+                "language": subtitle.language,
+                "kind": subtitle.kind,
+            }))
+            .ip(ip),
+    )
+    .await;
+
+    format::json(SubtitleResponse::from(subtitle))
+}
+
+/// 下载字幕（默认 WebVTT，`?format=txt` 返回纯文本文稿）
+#[debug_handler]
+pub async fn show(
+    auth: ApiAuth,
+    Path((id, subtitle_id)): Path<(i32, i32)>,
+    Query(params): Query<SubtitleParams>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksRead)?;
+    let media = load_media(&ctx, id, user.id).await?;
+    let subtitle = load_subtitle(&ctx, media.id, subtitle_id).await?;
+
+    subtitle_response(&subtitle, params.format.as_deref())
+}
+
+/// 删除字幕
+#[debug_handler]
+pub async fn delete(
+    auth: ApiAuth,
+    Path((id, subtitle_id)): Path<(i32, i32)>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::MediaUpload)?;
+    let media = load_media(&ctx, id, user.id).await?;
+    let subtitle = load_subtitle(&ctx, media.id, subtitle_id).await?;
+    let before = serde_json::json!({
+        "subtitle_id": subtitle.id,
+        "language": subtitle.language,
+        "kind": subtitle.kind,
+    });
+
+    subtitle.delete(&ctx.db).await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("subtitle.delete")
+            .actor(&user)
+            .target("media", media.id)
+            .before(before)
+            .ip(ip),
+    )
+    .await;
+
+    format::empty()
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("/api/media")
+        .add("/{id}/subtitles", get(list))
+        .add(
+            "/{id}/subtitles",
+            post(upload).layer(DefaultBodyLimit::max(MAX_SUBTITLE_SIZE + 64 * 1024)),
+        )
+        .add("/{id}/subtitles/{subtitle_id}", get(show))
+        .add("/{id}/subtitles/{subtitle_id}", axum_delete(delete))
+}
@@ -0,0 +1,151 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unnecessary_struct_initialization)]
+#![allow(clippy::unused_async)]
+use axum::debug_handler;
+use axum::routing::method_routing::delete as axum_delete;
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::controllers::auth::ClientIp;
+use crate::models::audit_logs::{self, AuditEntry};
+use crate::models::personal_access_tokens::{self, Scope};
+use crate::models::users;
+use crate::views::tokens::{CreatedTokenResponse, TokenResponse};
+
+/// 每个用户最多可持有的有效令牌数
+const MAX_ACTIVE_TOKENS: usize = 20;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CreateParams {
+    pub name: String,
+    /// 权限范围：books:read、books:write、media:upload、qrcode:manage、admin
+    pub scopes: Vec<String>,
+    /// 有效天数，不填则永不过期
+    pub expires_in_days: Option<i64>,
+}
+
+/// 列出当前用户的个人访问令牌
+///
+/// 令牌管理只接受登录获得的 JWT，避免令牌自行创建新令牌扩大权限
+#[debug_handler]
+pub async fn list(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let tokens = personal_access_tokens::Model::find_by_user(&ctx.db, user.id).await?;
+    let responses: Vec<TokenResponse> = tokens.into_iter().map(TokenResponse::from).collect();
+    format::json(responses)
+}
+
+/// 创建个人访问令牌，明文只在响应中返回一次
+#[debug_handler]
+pub async fn create(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    Json(params): Json<CreateParams>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+
+    let name = params.name.trim().to_string();
+    if name.is_empty() {
+        return Err(Error::BadRequest("令牌名称不能为空".to_string()));
+    }
+
+    let mut scopes: Vec<Scope> = Vec::new();
+    for value in &params.scopes {
+        let scope = Scope::parse(value.trim()).ok_or_else(|| {
+            Error::BadRequest(format!(
+                "无效的权限范围: {value}（可选 {}）",
+                Scope::ALL.map(Scope::as_str).join(", ")
+            ))
+        })?;
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    if scopes.is_empty() {
+        return Err(Error::BadRequest("至少需要一个权限范围".to_string()));
+    }
+    if scopes.contains(&Scope::Admin) && !user.is_admin() {
+        return unauthorized("只有管理员可以创建 admin 权限的令牌");
+    }
+
+    let expires_at = match params.expires_in_days {
+        Some(days) if !(1..=3650).contains(&days) => {
+            return Err(Error::BadRequest(
+                "有效天数必须在 1 到 3650 之间".to_string(),
+            ));
+        }
+        Some(days) => Some((chrono::Utc::now() + chrono::Duration::days(days)).into()),
+        None => None,
+    };
+
+    let active_count = personal_access_tokens::Model::find_by_user(&ctx.db, user.id)
+        .await?
+        .iter()
+        .filter(|token| token.is_active())
+        .count();
+    if active_count >= MAX_ACTIVE_TOKENS {
+        return Err(Error::BadRequest(format!(
+            "有效令牌数量已达上限 ({MAX_ACTIVE_TOKENS})，请先吊销不再使用的令牌"
+        )));
+    }
+
+    let (token, plaintext) =
+        personal_access_tokens::Model::create_for_user(&ctx.db, user.id, name, &scopes, expires_at)
+            .await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("token.create")
+            .actor(&user)
+            .target("token", token.id)
+            .after(serde_json::json!({
+                "name": token.name,
+                "scopes": token.scopes,
+                "expires_at": token.expires_at,
+            }))
+            .ip(ip),
+    )
+    .await;
+
+    format::json(CreatedTokenResponse {
+        info: TokenResponse::from(token),
+        token: plaintext,
+    })
+}
+
+/// 吊销令牌（保留记录以便查看使用情况）
+#[debug_handler]
+pub async fn revoke(
+    auth: auth::JWT,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let token = personal_access_tokens::Model::find_for_user(&ctx.db, user.id, id)
+        .await?
+        .ok_or_else(|| Error::NotFound)?;
+
+    let token = token.revoke(&ctx.db).await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("token.revoke")
+            .actor(&user)
+            .target("token", token.id)
+            .before(serde_json::json!({ "name": token.name }))
+            .ip(ip),
+    )
+    .await;
+
+    format::json(TokenResponse::from(token))
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("/api/tokens")
+        .add("/", get(list))
+        .add("/", post(create))
+        .add("/{id}", axum_delete(revoke))
+}
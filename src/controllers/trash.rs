@@ -0,0 +1,202 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+use axum::debug_handler;
+use axum::routing::method_routing::delete as axum_delete;
+use loco_rs::prelude::*;
+
+use crate::controllers::api_auth::ApiAuth;
+use crate::controllers::auth::ClientIp;
+use crate::models::_entities::{books, chapters, medias};
+use crate::models::audit_logs::{self, AuditEntry};
+use crate::models::personal_access_tokens::Scope;
+use crate::models::users;
+use crate::services::trash;
+
+/// 回收站中属于当前用户的书籍
+async fn load_trashed_book(ctx: &AppContext, id: i32, user_id: i32) -> Result<books::Model> {
+    books::Entity::find_by_id(id)
+        .filter(books::Column::UserId.eq(user_id))
+        .filter(books::Column::DeletedAt.is_not_null())
+        .one(&ctx.db)
+        .await?
+        .ok_or_else(|| Error::NotFound)
+}
+
+/// 回收站中属于当前用户的章节
+async fn load_trashed_chapter(ctx: &AppContext, id: i32, user_id: i32) -> Result<chapters::Model> {
+    let chapter = chapters::Entity::find_by_id(id)
+        .filter(chapters::Column::DeletedAt.is_not_null())
+        .one(&ctx.db)
+        .await?
+        .ok_or_else(|| Error::NotFound)?;
+    books::Entity::find_by_id(chapter.book_id)
+        .filter(books::Column::UserId.eq(user_id))
+        .one(&ctx.db)
+        .await?
+        .ok_or_else(|| Error::NotFound)?;
+    Ok(chapter)
+}
+
+/// 回收站中属于当前用户的媒体
+async fn load_trashed_media(ctx: &AppContext, id: i32, user_id: i32) -> Result<medias::Model> {
+    medias::Entity::find_by_id(id)
+        .filter(medias::Column::UserId.eq(user_id))
+        .filter(medias::Column::DeletedAt.is_not_null())
+        .one(&ctx.db)
+        .await?
+        .ok_or_else(|| Error::NotFound)
+}
+
+async fn record(
+    ctx: &AppContext,
+    user: &users::Model,
+    action: &str,
+    target: (&str, i32),
+    title: &str,
+    ip: Option<String>,
+) {
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new(action)
+            .actor(user)
+            .target(target.0, target.1)
+            .before(serde_json::json!({ "title": title }))
+            .ip(ip),
+    )
+    .await;
+}
+
+/// 查看回收站
+#[debug_handler]
+pub async fn list(auth: ApiAuth, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksRead)?;
+    format::json(trash::list_for_user(&ctx.db, user.id).await?)
+}
+
+/// 恢复书籍（连同与它一起删除的章节和媒体）
+#[debug_handler]
+pub async fn restore_book(
+    auth: ApiAuth,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksWrite)?;
+    let book = load_trashed_book(&ctx, id, user.id).await?;
+    trash::restore_book(&ctx.db, &book).await?;
+    record(&ctx, &user, "book.restore", ("book", id), &book.title, ip).await;
+    format::empty()
+}
+
+/// 恢复章节（连同与它一起删除的子章节和媒体）
+#[debug_handler]
+pub async fn restore_chapter(
+    auth: ApiAuth,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksWrite)?;
+    let chapter = load_trashed_chapter(&ctx, id, user.id).await?;
+    trash::restore_chapter(&ctx.db, &chapter).await?;
+    record(
+        &ctx,
+        &user,
+        "chapter.restore",
+        ("chapter", id),
+        &chapter.title,
+        ip,
+    )
+    .await;
+    format::empty()
+}
+
+/// 恢复媒体
+#[debug_handler]
+pub async fn restore_media(
+    auth: ApiAuth,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksWrite)?;
+    let media = load_trashed_media(&ctx, id, user.id).await?;
+    trash::restore_media(&ctx.db, &media).await?;
+    record(
+        &ctx,
+        &user,
+        "media.restore",
+        ("media", id),
+        &media.title,
+        ip,
+    )
+    .await;
+    format::empty()
+}
+
+/// 立即永久删除回收站中的书籍
+#[debug_handler]
+pub async fn purge_book(
+    auth: ApiAuth,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksWrite)?;
+    let book = load_trashed_book(&ctx, id, user.id).await?;
+    let title = book.title.clone();
+    trash::purge_book(&ctx, book).await?;
+    record(&ctx, &user, "book.purge", ("book", id), &title, ip).await;
+    format::empty()
+}
+
+/// 立即永久删除回收站中的章节
+#[debug_handler]
+pub async fn purge_chapter(
+    auth: ApiAuth,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksWrite)?;
+    let chapter = load_trashed_chapter(&ctx, id, user.id).await?;
+    trash::purge_chapter(&ctx, &chapter).await?;
+    record(
+        &ctx,
+        &user,
+        "chapter.purge",
+        ("chapter", id),
+        &chapter.title,
+        ip,
+    )
+    .await;
+    format::empty()
+}
+
+/// 立即永久删除回收站中的媒体
+#[debug_handler]
+pub async fn purge_media(
+    auth: ApiAuth,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksWrite)?;
+    let media = load_trashed_media(&ctx, id, user.id).await?;
+    let title = media.title.clone();
+    trash::purge_media(&ctx, media).await?;
+    record(&ctx, &user, "media.purge", ("media", id), &title, ip).await;
+    format::empty()
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("/api/trash")
+        .add("/", get(list))
+        .add("/books/{id}/restore", post(restore_book))
+        .add("/chapters/{id}/restore", post(restore_chapter))
+        .add("/medias/{id}/restore", post(restore_media))
+        .add("/books/{id}", axum_delete(purge_book))
+        .add("/chapters/{id}", axum_delete(purge_chapter))
+        .add("/medias/{id}", axum_delete(purge_media))
+}
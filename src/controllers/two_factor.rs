@@ -0,0 +1,211 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unnecessary_struct_initialization)]
+#![allow(clippy::unused_async)]
+use axum::debug_handler;
+use loco_rs::auth::jwt;
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Map;
+
+use crate::controllers::auth::purpose_signer;
+use crate::models::{user_sessions, user_totp, users};
+use crate::services::qrcode::QRCODE_SERVICE;
+use crate::services::totp;
+use crate::views::auth::{
+    MfaChallengeResponse, RecoveryCodesResponse, TwoFactorSetupResponse, TwoFactorStatusResponse,
+};
+
+/// 验证器应用中显示的发行方名称
+const TOTP_ISSUER: &str = "QCast";
+
+/// 登录第二步的有效期（秒）
+const MFA_TOKEN_EXPIRATION: u64 = 300;
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CodeParams {
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DisableParams {
+    pub password: String,
+    pub code: String,
+}
+
+/// 登录第二步令牌使用独立的签名密钥，不能被当作访问令牌使用
+fn mfa_signer(ctx: &AppContext) -> Result<jwt::JWT> {
+    purpose_signer(ctx, "mfa")
+}
+
+/// 用户已启用两步验证时生成登录第二步的挑战，否则返回 `None`
+pub async fn login_challenge(
+    ctx: &AppContext,
+    user: &users::Model,
+) -> Result<Option<MfaChallengeResponse>> {
+    if !user_totp::Model::is_enabled_for_user(&ctx.db, user.id).await? {
+        return Ok(None);
+    }
+
+    let mfa_token = mfa_signer(ctx)?
+        .generate_token(MFA_TOKEN_EXPIRATION, user.pid.to_string(), Map::new())
+        .or_else(|_| unauthorized("unauthorized!"))?;
+
+    Ok(Some(MfaChallengeResponse {
+        mfa_required: true,
+        mfa_token,
+        expires_in: MFA_TOKEN_EXPIRATION,
+    }))
+}
+
+/// 校验登录第二步的挑战令牌，返回对应的用户
+pub async fn challenge_user(ctx: &AppContext, mfa_token: &str) -> Result<users::Model> {
+    let Ok(token_data) = mfa_signer(ctx)?.validate(mfa_token) else {
+        return unauthorized("验证已过期，请重新登录");
+    };
+    Ok(users::Model::find_by_pid(&ctx.db, &token_data.claims.pid).await?)
+}
+
+/// 校验登录第二步提交的验证码（或恢复码）
+pub async fn verify_login_code(ctx: &AppContext, user: &users::Model, code: &str) -> Result<bool> {
+    let Some(two_factor) = user_totp::Model::find_by_user(&ctx.db, user.id).await? else {
+        return Ok(false);
+    };
+    Ok(two_factor.verify_code(&ctx.db, code).await?)
+}
+
+/// 管理员账号必须先启用两步验证才能使用后台接口
+pub async fn require_for_admin(ctx: &AppContext, user: &users::Model) -> Result<()> {
+    if !user.is_admin() || user_totp::Model::is_enabled_for_user(&ctx.db, user.id).await? {
+        return Ok(());
+    }
+    Err(Error::CustomError(
+        axum::http::StatusCode::FORBIDDEN,
+        loco_rs::controller::ErrorDetail::new(
+            "two_factor_required",
+            "管理员账号需要先启用两步验证",
+        ),
+    ))
+}
+
+async fn load_enabled(ctx: &AppContext, user: &users::Model) -> Result<user_totp::Model> {
+    user_totp::Model::find_by_user(&ctx.db, user.id)
+        .await?
+        .filter(user_totp::Model::is_enabled)
+        .ok_or_else(|| Error::BadRequest("尚未启用两步验证".to_string()))
+}
+
+/// 查看两步验证状态
+#[debug_handler]
+pub async fn status(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let two_factor = user_totp::Model::find_by_user(&ctx.db, user.id)
+        .await?
+        .filter(user_totp::Model::is_enabled);
+
+    format::json(TwoFactorStatusResponse {
+        enabled: two_factor.is_some(),
+        required: user.is_admin(),
+        recovery_codes_remaining: two_factor
+            .as_ref()
+            .map_or(0, user_totp::Model::recovery_codes_remaining),
+    })
+}
+
+/// 开始绑定：生成密钥和扫码用的二维码
+#[debug_handler]
+pub async fn setup(auth: auth::JWT, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    if user_totp::Model::is_enabled_for_user(&ctx.db, user.id).await? {
+        return bad_request("已启用两步验证，如需更换设备请先停用");
+    }
+
+    let two_factor = user_totp::Model::begin_setup(&ctx.db, user.id).await?;
+    let otpauth_url = totp::provisioning_uri(TOTP_ISSUER, &user.email, &two_factor.secret);
+    let qrcode_svg = QRCODE_SERVICE.generate_qrcode_svg_string(&otpauth_url)?;
+
+    format::json(TwoFactorSetupResponse {
+        secret: two_factor.secret,
+        otpauth_url,
+        qrcode_svg,
+    })
+}
+
+/// 提交验证器中的验证码完成绑定，返回恢复码
+#[debug_handler]
+pub async fn enable(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<CodeParams>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let Some(two_factor) = user_totp::Model::find_by_user(&ctx.db, user.id).await? else {
+        return bad_request("请先获取两步验证密钥");
+    };
+    if two_factor.is_enabled() {
+        return bad_request("已启用两步验证");
+    }
+
+    let Some((_, recovery_codes)) = two_factor.enable(&ctx.db, &params.code).await? else {
+        return bad_request("验证码错误");
+    };
+
+    format::json(RecoveryCodesResponse { recovery_codes })
+}
+
+/// 停用两步验证（需要密码和验证码），并退出所有设备
+#[debug_handler]
+pub async fn disable(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<DisableParams>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    if !user.verify_password(&params.password) {
+        return unauthorized("密码错误");
+    }
+
+    let two_factor = load_enabled(&ctx, &user).await?;
+    let record = two_factor.clone();
+    if !two_factor.verify_code(&ctx.db, &params.code).await? {
+        return unauthorized("验证码错误");
+    }
+
+    record.delete(&ctx.db).await?;
+    user_sessions::Model::revoke_all_for_user(&ctx.db, user.id).await?;
+
+    format::json(())
+}
+
+/// 重新生成恢复码（需要验证码）
+#[debug_handler]
+pub async fn regenerate_recovery_codes(
+    auth: auth::JWT,
+    State(ctx): State<AppContext>,
+    Json(params): Json<CodeParams>,
+) -> Result<Response> {
+    let user = users::Model::find_by_pid(&ctx.db, &auth.claims.pid).await?;
+    let two_factor = load_enabled(&ctx, &user).await?;
+    if !two_factor
+        .clone()
+        .verify_code(&ctx.db, &params.code)
+        .await?
+    {
+        return unauthorized("验证码错误");
+    }
+
+    // 校验可能消耗了恢复码，重新读取最新记录
+    let two_factor = load_enabled(&ctx, &user).await?;
+    let recovery_codes = two_factor.regenerate_recovery_codes(&ctx.db).await?;
+
+    format::json(RecoveryCodesResponse { recovery_codes })
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("/api/auth/2fa")
+        .add("/", get(status))
+        .add("/setup", post(setup))
+        .add("/enable", post(enable))
+        .add("/disable", post(disable))
+        .add("/recovery-codes", post(regenerate_recovery_codes))
+}
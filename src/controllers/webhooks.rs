@@ -0,0 +1,261 @@
+#![allow(clippy::missing_errors_doc)]
+#![allow(clippy::unused_async)]
+use axum::debug_handler;
+use axum::extract::Query;
+use axum::routing::method_routing::delete as axum_delete;
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::controllers::api_auth::ApiAuth;
+use crate::controllers::auth::ClientIp;
+use crate::models::audit_logs::{self, AuditEntry};
+use crate::models::personal_access_tokens::Scope;
+use crate::models::webhook_deliveries;
+use crate::models::webhook_endpoints;
+use crate::services::webhooks;
+use crate::views::webhooks::{
+    CreatedWebhookEndpointResponse, WebhookDeliveryResponse, WebhookEndpointResponse,
+};
+
+/// 每个用户最多可配置的地址数
+const MAX_ENDPOINTS: usize = 10;
+
+/// 投递记录默认返回条数
+const DEFAULT_DELIVERY_LIMIT: u64 = 50;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CreateParams {
+    pub url: String,
+    /// 订阅的事件：media.uploaded、media.processed、media.published、media.replaced、media.deleted
+    pub events: Vec<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct UpdateParams {
+    pub url: Option<String>,
+    pub events: Option<Vec<String>>,
+    pub description: Option<String>,
+    pub is_active: Option<bool>,
+    /// 为 true 时重新生成签名密钥
+    #[serde(default)]
+    pub rotate_secret: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DeliveriesQuery {
+    pub limit: Option<u64>,
+}
+
+async fn load_endpoint(
+    ctx: &AppContext,
+    user_id: i32,
+    id: i32,
+) -> Result<webhook_endpoints::Model> {
+    webhook_endpoints::Model::find_for_user(&ctx.db, user_id, id)
+        .await?
+        .ok_or_else(|| Error::NotFound)
+}
+
+fn audit_summary(endpoint: &webhook_endpoints::Model) -> serde_json::Value {
+    serde_json::json!({
+        "url": endpoint.url,
+        "events": endpoint.events,
+        "is_active": endpoint.is_active,
+    })
+}
+
+/// 列出当前用户的 Webhook 地址
+#[debug_handler]
+pub async fn list(auth: ApiAuth, State(ctx): State<AppContext>) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksRead)?;
+    let endpoints = webhook_endpoints::Model::find_by_user(&ctx.db, user.id).await?;
+    let responses: Vec<WebhookEndpointResponse> = endpoints
+        .into_iter()
+        .map(WebhookEndpointResponse::from)
+        .collect();
+    format::json(responses)
+}
+
+/// 添加 Webhook 地址，签名密钥只在响应中返回一次
+#[debug_handler]
+pub async fn create(
+    auth: ApiAuth,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    Json(params): Json<CreateParams>,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksWrite)?;
+    let url = webhooks::validate_url(&params.url)?;
+    let events = webhooks::normalize_events(&params.events)?;
+
+    let count = webhook_endpoints::Model::find_by_user(&ctx.db, user.id)
+        .await?
+        .len();
+    if count >= MAX_ENDPOINTS {
+        return Err(Error::BadRequest(format!(
+            "Webhook 地址数量已达上限 ({MAX_ENDPOINTS})"
+        )));
+    }
+
+    let secret = webhooks::generate_secret();
+    let endpoint = webhook_endpoints::ActiveModel {
+        user_id: Set(user.id),
+        url: Set(url),
+        secret: Set(secret.clone()),
+        events: Set(events),
+        description: Set(params.description.filter(|d| !d.trim().is_empty())),
+        is_active: Set(true),
+        ..Default::default()
+    }
+    .insert(&ctx.db)
+    .await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("webhook.create")
+            .actor(&user)
+            .target("webhook", endpoint.id)
+            .after(audit_summary(&endpoint))
+            .ip(ip),
+    )
+    .await;
+
+    format::json(CreatedWebhookEndpointResponse {
+        info: WebhookEndpointResponse::from(endpoint),
+        secret,
+    })
+}
+
+/// 修改 Webhook 地址；重新生成密钥时返回新密钥
+#[debug_handler]
+pub async fn update(
+    auth: ApiAuth,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    Json(params): Json<UpdateParams>,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksWrite)?;
+    let endpoint = load_endpoint(&ctx, user.id, id).await?;
+    let before = audit_summary(&endpoint);
+
+    let mut item = endpoint.into_active_model();
+    if let Some(url) = &params.url {
+        item.url = Set(webhooks::validate_url(url)?);
+    }
+    if let Some(events) = &params.events {
+        item.events = Set(webhooks::normalize_events(events)?);
+    }
+    if let Some(description) = params.description {
+        item.description = Set(Some(description).filter(|d| !d.trim().is_empty()));
+    }
+    if let Some(is_active) = params.is_active {
+        item.is_active = Set(is_active);
+    }
+    let secret = params.rotate_secret.then(webhooks::generate_secret);
+    if let Some(secret) = &secret {
+        item.secret = Set(secret.clone());
+    }
+    let endpoint = item.update(&ctx.db).await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("webhook.update")
+            .actor(&user)
+            .target("webhook", endpoint.id)
+            .before(before)
+            .after(audit_summary(&endpoint))
+            .ip(ip),
+    )
+    .await;
+
+    let info = WebhookEndpointResponse::from(endpoint);
+    match secret {
+        Some(secret) => format::json(CreatedWebhookEndpointResponse { info, secret }),
+        None => format::json(info),
+    }
+}
+
+/// 删除 Webhook 地址及其投递记录
+#[debug_handler]
+pub async fn remove(
+    auth: ApiAuth,
+    Path(id): Path<i32>,
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksWrite)?;
+    let endpoint = load_endpoint(&ctx, user.id, id).await?;
+    let before = audit_summary(&endpoint);
+
+    webhook_deliveries::Model::delete_by_endpoint(&ctx.db, endpoint.id).await?;
+    endpoint.delete(&ctx.db).await?;
+
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new("webhook.delete")
+            .actor(&user)
+            .target("webhook", id)
+            .before(before)
+            .ip(ip),
+    )
+    .await;
+
+    format::empty()
+}
+
+/// 查看地址最近的投递记录
+#[debug_handler]
+pub async fn list_deliveries(
+    auth: ApiAuth,
+    Path(id): Path<i32>,
+    Query(query): Query<DeliveriesQuery>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksRead)?;
+    let endpoint = load_endpoint(&ctx, user.id, id).await?;
+    let limit = query.limit.unwrap_or(DEFAULT_DELIVERY_LIMIT).clamp(1, 200);
+    let deliveries =
+        webhook_deliveries::Model::find_by_endpoint(&ctx.db, endpoint.id, limit).await?;
+    let responses: Vec<WebhookDeliveryResponse> = deliveries
+        .into_iter()
+        .map(WebhookDeliveryResponse::from)
+        .collect();
+    format::json(responses)
+}
+
+/// 重新投递一条记录
+#[debug_handler]
+pub async fn redeliver(
+    auth: ApiAuth,
+    Path((id, delivery_id)): Path<(i32, i32)>,
+    State(ctx): State<AppContext>,
+) -> Result<Response> {
+    let user = auth.authorize(Scope::BooksWrite)?;
+    let endpoint = load_endpoint(&ctx, user.id, id).await?;
+    let delivery = webhook_deliveries::Entity::find_by_id(delivery_id)
+        .filter(webhook_deliveries::Column::EndpointId.eq(endpoint.id))
+        .one(&ctx.db)
+        .await?
+        .ok_or_else(|| Error::NotFound)?;
+
+    webhooks::redeliver(&ctx, delivery).await?;
+
+    let delivery = webhook_deliveries::Entity::find_by_id(delivery_id)
+        .one(&ctx.db)
+        .await?
+        .ok_or_else(|| Error::NotFound)?;
+    format::json(WebhookDeliveryResponse::from(delivery))
+}
+
+pub fn routes() -> Routes {
+    Routes::new()
+        .prefix("/api/webhooks")
+        .add("/", get(list))
+        .add("/", post(create))
+        .add("/{id}", put(update))
+        .add("/{id}", axum_delete(remove))
+        .add("/{id}/deliveries", get(list_deliveries))
+        .add("/{id}/deliveries/{delivery_id}/redeliver", post(redeliver))
+}
@@ -0,0 +1,49 @@
+// publishing mailer
+#![allow(non_upper_case_globals)]
+
+use loco_rs::prelude::*;
+use serde_json::json;
+
+use crate::models::users;
+
+static published: Dir<'_> = include_dir!("src/mailers/publishing/published");
+static unpublished: Dir<'_> = include_dir!("src/mailers/publishing/unpublished");
+
+#[allow(clippy::module_name_repetitions)]
+pub struct PublishingMailer {}
+impl Mailer for PublishingMailer {}
+impl PublishingMailer {
+    /// 通知作者定时发布或下架已生效，`kind` 为 `published` 或 `unpublished`
+    ///
+    /// # Errors
+    ///
+    /// When email sending is failed
+    pub async fn send_status_change(
+        ctx: &AppContext,
+        user: &users::Model,
+        kind: &str,
+        title: &str,
+    ) -> Result<()> {
+        let template = if kind == "published" {
+            &published
+        } else {
+            &unpublished
+        };
+        Self::mail_template(
+            ctx,
+            template,
+            mailer::Args {
+                to: user.email.to_string(),
+                locals: json!({
+                  "name": user.name,
+                  "title": title,
+                  "domain": ctx.config.server.full_url()
+                }),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,7 @@
+;<html>
+<body>
+<p>{{name}}，你好：</p>
+<p>《{{title}}》已按计划发布，现在可以公开访问。</p>
+<p><a href="{{domain}}">{{domain}}</a></p>
+</body>
+</html>
@@ -0,0 +1,5 @@
+{{name}}，你好：
+
+《{{title}}》已按计划发布，现在可以公开访问。
+
+{{domain}}
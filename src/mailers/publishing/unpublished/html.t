@@ -0,0 +1,7 @@
+;<html>
+<body>
+<p>{{name}}，你好：</p>
+<p>《{{title}}》已到下架时间，公开链接已停止访问。</p>
+<p><a href="{{domain}}">{{domain}}</a></p>
+</body>
+</html>
@@ -0,0 +1 @@
+《{{title}}》已按计划下架
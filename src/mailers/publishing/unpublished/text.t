@@ -0,0 +1,5 @@
+{{name}}，你好：
+
+《{{title}}》已到下架时间，公开链接已停止访问。
+
+{{domain}}
@@ -18,6 +18,16 @@ pub struct Model {
     pub sort_order: Option<i32>,
     pub is_public: Option<bool>,
     pub user_id: i32,
+    pub feed_author: Option<String>,
+    pub feed_owner_email: Option<String>,
+    pub feed_language: Option<String>,
+    pub feed_category: Option<String>,
+    pub feed_explicit: Option<bool>,
+    pub feed_copyright: Option<String>,
+    pub deleted_at: Option<DateTimeWithTimeZone>,
+    pub publish_at: Option<DateTimeWithTimeZone>,
+    pub unpublish_at: Option<DateTimeWithTimeZone>,
+    pub normalize_audio: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
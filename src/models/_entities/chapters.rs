@@ -19,6 +19,7 @@ pub struct Model {
     pub level: Option<i32>,
     #[sea_orm(column_type = "Text", nullable)]
     pub path: Option<String>,
+    pub deleted_at: Option<DateTimeWithTimeZone>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -28,6 +28,16 @@ pub struct Model {
     pub chapter_id: Option<i32>,
     pub book_id: i32,
     pub user_id: i32,
+    pub deleted_at: Option<DateTimeWithTimeZone>,
+    pub publish_at: Option<DateTimeWithTimeZone>,
+    pub unpublish_at: Option<DateTimeWithTimeZone>,
+    pub sort_order: Option<i32>,
+    pub content_hash: Option<String>,
+    pub file_missing_at: Option<DateTimeWithTimeZone>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub audio_analysis: Option<String>,
+    pub normalized_file_path: Option<String>,
+    pub normalized_file_size: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
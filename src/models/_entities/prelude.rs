@@ -1,9 +1,22 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.16
 
+pub use super::audit_logs::Entity as AuditLogs;
 pub use super::books::Entity as Books;
 pub use super::chapters::Entity as Chapters;
+pub use super::media_clips::Entity as MediaClips;
+pub use super::media_placements::Entity as MediaPlacements;
+pub use super::media_plays::Entity as MediaPlays;
+pub use super::media_subtitles::Entity as MediaSubtitles;
+pub use super::media_versions::Entity as MediaVersions;
 pub use super::medias::Entity as Medias;
+pub use super::personal_access_tokens::Entity as PersonalAccessTokens;
 pub use super::site_settings::Entity as SiteSettings;
+pub use super::storage_blobs::Entity as StorageBlobs;
 pub use super::user_group_members::Entity as UserGroupMembers;
 pub use super::user_groups::Entity as UserGroups;
+pub use super::user_identities::Entity as UserIdentities;
+pub use super::user_sessions::Entity as UserSessions;
+pub use super::user_totp::Entity as UserTotp;
 pub use super::users::Entity as Users;
+pub use super::webhook_deliveries::Entity as WebhookDeliveries;
+pub use super::webhook_endpoints::Entity as WebhookEndpoints;
@@ -0,0 +1,222 @@
+pub use super::_entities::audit_logs::{ActiveModel, Column, Entity, Model};
+use super::users;
+use sea_orm::entity::prelude::*;
+use sea_orm::{PaginatorTrait, QueryOrder, Set};
+use serde::{Deserialize, Serialize};
+pub type AuditLogs = Entity;
+
+/// 通过接口发起的操作
+pub const SOURCE_API: &str = "api";
+/// 通过命令行任务发起的操作
+pub const SOURCE_TASK: &str = "task";
+/// 系统自动触发的操作（如登录失败锁定）
+pub const SOURCE_SYSTEM: &str = "system";
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        // 审计日志只追加，不允许修改
+        if insert {
+            Ok(self)
+        } else {
+            Err(DbErr::Custom("audit logs are append-only".to_string()))
+        }
+    }
+}
+
+/// 一条待写入的审计记录
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    pub actor_id: Option<i32>,
+    pub actor_email: Option<String>,
+    pub action: String,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub ip_address: Option<String>,
+    pub source: &'static str,
+}
+
+impl AuditEntry {
+    /// 操作名称使用 `对象.动作` 格式，如 `book.delete`
+    #[must_use]
+    pub fn new(action: &str) -> Self {
+        Self {
+            actor_id: None,
+            actor_email: None,
+            action: action.to_string(),
+            target_type: None,
+            target_id: None,
+            before: None,
+            after: None,
+            ip_address: None,
+            source: SOURCE_API,
+        }
+    }
+
+    #[must_use]
+    pub fn actor(mut self, user: &users::Model) -> Self {
+        self.actor_id = Some(user.id);
+        self.actor_email = Some(user.email.clone());
+        self
+    }
+
+    #[must_use]
+    pub fn target(mut self, target_type: &str, target_id: impl ToString) -> Self {
+        self.target_type = Some(target_type.to_string());
+        self.target_id = Some(target_id.to_string());
+        self
+    }
+
+    /// 变更前的摘要
+    #[must_use]
+    pub fn before(mut self, summary: serde_json::Value) -> Self {
+        self.before = Some(summary);
+        self
+    }
+
+    /// 变更后的摘要
+    #[must_use]
+    pub fn after(mut self, summary: serde_json::Value) -> Self {
+        self.after = Some(summary);
+        self
+    }
+
+    /// 客户端 IP，应取自 `ClientIp`（只有连接来自可信代理时才采用转发头，不能直接读请求头）
+    #[must_use]
+    pub fn ip(mut self, ip_address: Option<String>) -> Self {
+        self.ip_address = ip_address;
+        self
+    }
+
+    #[must_use]
+    pub fn source(mut self, source: &'static str) -> Self {
+        self.source = source;
+        self
+    }
+}
+
+/// 审计日志查询条件
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct AuditQuery {
+    pub actor_id: Option<i32>,
+    /// 精确匹配，或以 `.` 结尾按前缀匹配（如 `book.`）
+    pub action: Option<String>,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub source: Option<String>,
+    pub from: Option<DateTimeWithTimeZone>,
+    pub to: Option<DateTimeWithTimeZone>,
+    pub page: Option<u64>,
+    pub per_page: Option<u64>,
+}
+
+/// 写入审计日志；写入失败只记录错误，不影响已经完成的业务操作
+pub async fn record<C>(db: &C, entry: AuditEntry)
+where
+    C: ConnectionTrait,
+{
+    let action = entry.action.clone();
+    if let Err(err) = Model::create(db, entry).await {
+        tracing::error!(error = err.to_string(), action, "failed to write audit log");
+    }
+}
+
+fn to_text(value: Option<serde_json::Value>) -> Option<String> {
+    value.and_then(|v| serde_json::to_string(&v).ok())
+}
+
+// implement your read-oriented logic here
+impl Model {
+    /// 写入一条审计记录
+    pub async fn create<C>(db: &C, entry: AuditEntry) -> Result<Model, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        ActiveModel {
+            actor_id: Set(entry.actor_id),
+            actor_email: Set(entry.actor_email),
+            action: Set(entry.action),
+            target_type: Set(entry.target_type),
+            target_id: Set(entry.target_id),
+            before_summary: Set(to_text(entry.before)),
+            after_summary: Set(to_text(entry.after)),
+            ip_address: Set(entry.ip_address),
+            source: Set(entry.source.to_string()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+    }
+
+    /// 按条件分页查询（按时间倒序），返回记录、总页数和总数
+    pub async fn search(
+        db: &DatabaseConnection,
+        query: &AuditQuery,
+    ) -> Result<(Vec<Model>, u64, u64), DbErr> {
+        let mut select = Entity::find();
+        if let Some(actor_id) = query.actor_id {
+            select = select.filter(Column::ActorId.eq(actor_id));
+        }
+        if let Some(action) = query.action.as_deref().filter(|a| !a.is_empty()) {
+            select = if action.ends_with('.') {
+                select.filter(Column::Action.starts_with(action))
+            } else {
+                select.filter(Column::Action.eq(action))
+            };
+        }
+        if let Some(target_type) = &query.target_type {
+            select = select.filter(Column::TargetType.eq(target_type));
+        }
+        if let Some(target_id) = &query.target_id {
+            select = select.filter(Column::TargetId.eq(target_id));
+        }
+        if let Some(source) = &query.source {
+            select = select.filter(Column::Source.eq(source));
+        }
+        if let Some(from) = query.from {
+            select = select.filter(Column::CreatedAt.gte(from));
+        }
+        if let Some(to) = query.to {
+            select = select.filter(Column::CreatedAt.lt(to));
+        }
+
+        let page = query.page.unwrap_or(1).max(1);
+        let per_page = query.per_page.unwrap_or(50).clamp(1, 200);
+        let paginator = select
+            .order_by_desc(Column::CreatedAt)
+            .order_by_desc(Column::Id)
+            .paginate(db, per_page);
+
+        let totals = paginator.num_items_and_pages().await?;
+        let items = paginator.fetch_page(page - 1).await?;
+
+        Ok((items, totals.number_of_pages, totals.number_of_items))
+    }
+
+    /// 解析变更前摘要
+    #[must_use]
+    pub fn before_value(&self) -> Option<serde_json::Value> {
+        self.before_summary
+            .as_deref()
+            .and_then(|s| serde_json::from_str(s).ok())
+    }
+
+    /// 解析变更后摘要
+    #[must_use]
+    pub fn after_value(&self) -> Option<serde_json::Value> {
+        self.after_summary
+            .as_deref()
+            .and_then(|s| serde_json::from_str(s).ok())
+    }
+}
+
+// implement your write-oriented logic here
+impl ActiveModel {}
+
+// implement your custom finders, selectors oriented logic here
+impl Entity {}
@@ -1,9 +1,12 @@
 pub use super::_entities::books::{ActiveModel, Column, Entity, Model};
 pub use super::_entities::users;
+use super::_entities::{media_placements, medias};
+use crate::services::publishing;
 use sea_orm::entity::prelude::*;
 use sea_orm::sea_query::{Condition, Expr, Func};
-use sea_orm::QueryOrder;
+use sea_orm::{QueryOrder, QuerySelect, Statement};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 pub type Books = Entity;
 
 #[async_trait::async_trait]
@@ -37,6 +40,7 @@ impl Model {
     /// 获取用户的所有书籍
     pub async fn find_by_user(db: &DatabaseConnection, user_id: i32) -> Result<Vec<Model>, DbErr> {
         Entity::find()
+            .filter(Column::DeletedAt.is_null())
             .filter(Column::UserId.eq(user_id))
             .all(db)
             .await
@@ -48,6 +52,7 @@ impl Model {
         user_id: i32,
     ) -> Result<Vec<Model>, DbErr> {
         Entity::find()
+            .filter(Column::DeletedAt.is_null())
             .filter(Column::UserId.eq(user_id))
             .filter(Column::ParentId.is_null())
             .order_by_asc(Column::SortOrder)
@@ -62,6 +67,7 @@ impl Model {
         parent_id: i32,
     ) -> Result<Vec<Model>, DbErr> {
         Entity::find()
+            .filter(Column::DeletedAt.is_null())
             .filter(Column::ParentId.eq(parent_id))
             .order_by_asc(Column::SortOrder)
             .order_by_asc(Column::CreatedAt)
@@ -80,6 +86,7 @@ impl Model {
         let search_pattern = format!("%{query_lower}%");
 
         Entity::find()
+            .filter(Column::DeletedAt.is_null())
             .filter(Column::UserId.eq(user_id))
             .filter(
                 Condition::any()
@@ -136,6 +143,7 @@ impl Model {
 
         // 获取所有书籍（包括匹配的和父书籍）
         let all_books = Entity::find()
+            .filter(Column::DeletedAt.is_null())
             .filter(Column::UserId.eq(user_id))
             .filter(Column::Id.is_in(book_ids.into_iter().collect::<Vec<_>>()))
             .order_by_asc(Column::SortOrder)
@@ -146,28 +154,30 @@ impl Model {
         Ok(all_books)
     }
 
-    /// 获取书籍的完整树形结构（递归获取所有子书籍）
+    /// 获取书籍的完整树形结构（一次查询取出子树，在内存中组装）
     pub async fn get_tree(db: &DatabaseConnection, book_id: i32) -> Result<BookTree, DbErr> {
         let book = Entity::find_by_id(book_id)
             .one(db)
             .await?
             .ok_or(DbErr::RecordNotFound(format!("Book {} not found", book_id)))?;
 
-        let children = Self::find_children(db, book_id).await?;
-        let mut tree_children = Vec::new();
-
-        // 递归获取子书籍的树形结构
-        for child in children {
-            // 使用 Box::pin 来避免递归 async fn 问题
-            let child_tree_future = Box::pin(Self::get_tree(db, child.id));
-            let child_tree = child_tree_future.await?;
-            tree_children.push(child_tree);
-        }
+        let ids = Self::find_descendant_ids(db, book_id, false).await?;
+        let books = Entity::find()
+            .filter(Column::Id.is_in(ids))
+            .filter(Column::DeletedAt.is_null())
+            .order_by_asc(Column::SortOrder)
+            .order_by_asc(Column::CreatedAt)
+            .all(db)
+            .await?;
+        let stats = Self::media_stats(db, book.user_id).await?;
+        let ancestors_public = Self::find_ancestors(db, book_id)
+            .await?
+            .iter()
+            .all(publishing::book_is_live);
 
-        Ok(BookTree {
-            book,
-            children: tree_children,
-        })
+        build_tree(books, &stats, |b| b.id == book_id, ancestors_public)
+            .pop()
+            .ok_or(DbErr::RecordNotFound(format!("Book {} not found", book_id)))
     }
 
     /// 获取用户的所有书籍的树形结构
@@ -175,15 +185,141 @@ impl Model {
         db: &DatabaseConnection,
         user_id: i32,
     ) -> Result<Vec<BookTree>, DbErr> {
-        let root_books = Self::find_root_books(db, user_id).await?;
-        let mut tree = Vec::new();
+        let books = Entity::find()
+            .filter(Column::DeletedAt.is_null())
+            .filter(Column::UserId.eq(user_id))
+            .order_by_asc(Column::SortOrder)
+            .order_by_asc(Column::CreatedAt)
+            .all(db)
+            .await?;
+        let stats = Self::media_stats(db, user_id).await?;
+
+        Ok(build_tree(books, &stats, |b| b.parent_id.is_none(), true))
+    }
 
-        for book in root_books {
-            let book_tree = Self::get_tree(db, book.id).await?;
-            tree.push(book_tree);
+    /// 用户各书籍（未删除）的媒体数量和总时长（包括附加到书籍的媒体），分组查询得到
+    pub async fn media_stats(
+        db: &DatabaseConnection,
+        user_id: i32,
+    ) -> Result<HashMap<i32, MediaStats>, DbErr> {
+        let rows: Vec<(i32, i64, Option<i64>)> = medias::Entity::find()
+            .select_only()
+            .column(medias::Column::BookId)
+            .column_as(Expr::col(medias::Column::Id).count(), "media_count")
+            .column_as(Expr::col(medias::Column::Duration).sum(), "duration")
+            .filter(medias::Column::UserId.eq(user_id))
+            .filter(medias::Column::DeletedAt.is_null())
+            .group_by(medias::Column::BookId)
+            .into_tuple()
+            .all(db)
+            .await?;
+
+        let mut stats: HashMap<i32, MediaStats> = rows
+            .into_iter()
+            .map(|(book_id, media_count, duration)| {
+                (
+                    book_id,
+                    MediaStats {
+                        media_count,
+                        duration: duration.unwrap_or(0),
+                    },
+                )
+            })
+            .collect();
+        for (book_id, (media_count, duration)) in
+            media_placements::Model::book_stats(db, user_id).await?
+        {
+            *stats.entry(book_id).or_default() += MediaStats {
+                media_count,
+                duration,
+            };
         }
+        Ok(stats)
+    }
 
-        Ok(tree)
+    /// 书籍本身及其所有子孙书籍的 ID
+    ///
+    /// `include_deleted` 为 false 时不会经过已删除的书籍向下查找
+    pub async fn find_descendant_ids<C>(
+        db: &C,
+        book_id: i32,
+        include_deleted: bool,
+    ) -> Result<Vec<i32>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        // UNION 去重，数据中即使存在循环引用查询也会结束
+        let deleted_filter = if include_deleted {
+            ""
+        } else {
+            "WHERE b.deleted_at IS NULL"
+        };
+        let sql = format!(
+            "WITH RECURSIVE subtree(id) AS ( \
+                SELECT id FROM books WHERE id = {book_id} \
+                UNION \
+                SELECT b.id FROM books b JOIN subtree s ON b.parent_id = s.id \
+                {deleted_filter} \
+            ) \
+            SELECT id FROM subtree"
+        );
+        let rows = db
+            .query_all(Statement::from_string(db.get_database_backend(), sql))
+            .await?;
+        rows.iter()
+            .map(|row| row.try_get::<i32>("", "id"))
+            .collect()
+    }
+
+    /// 书籍的所有祖先书籍（不含自身，包括已删除的）
+    pub async fn find_ancestors<C>(db: &C, book_id: i32) -> Result<Vec<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let sql = format!(
+            "WITH RECURSIVE ancestors(id, parent_id) AS ( \
+                SELECT id, parent_id FROM books WHERE id = {book_id} \
+                UNION \
+                SELECT b.id, b.parent_id FROM books b JOIN ancestors a ON b.id = a.parent_id \
+            ) \
+            SELECT books.* FROM books \
+            WHERE books.id IN (SELECT id FROM ancestors) AND books.id <> {book_id}"
+        );
+        Entity::find()
+            .from_raw_sql(Statement::from_string(db.get_database_backend(), sql))
+            .all(db)
+            .await
+    }
+
+    /// 检查移动是否会形成循环引用：新的上级书籍是书籍自身或其子孙
+    pub async fn would_create_cycle<C>(
+        db: &C,
+        book_id: i32,
+        new_parent_id: i32,
+    ) -> Result<bool, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if book_id == new_parent_id {
+            return Ok(true);
+        }
+        let ancestors = Self::find_ancestors(db, new_parent_id).await?;
+        Ok(ancestors.iter().any(|b| b.id == book_id))
+    }
+}
+
+/// 书籍中未删除媒体的统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MediaStats {
+    pub media_count: i64,
+    /// 总时长（秒）
+    pub duration: i64,
+}
+
+impl std::ops::AddAssign for MediaStats {
+    fn add_assign(&mut self, other: Self) {
+        self.media_count += other.media_count;
+        self.duration += other.duration;
     }
 }
 
@@ -191,9 +327,67 @@ impl Model {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BookTree {
     pub book: Model,
+    /// 书籍自身的媒体统计
+    pub media: MediaStats,
+    /// 包含所有子孙书籍的媒体统计
+    pub total: MediaStats,
+    /// 书籍自身及所有上级书籍都已公开时才对外可见
+    pub effective_public: bool,
     pub children: Vec<BookTree>,
 }
 
+fn attach_children(
+    node: &mut BookTree,
+    by_parent: &mut HashMap<i32, Vec<BookTree>>,
+    stats: &HashMap<i32, MediaStats>,
+) {
+    node.media = stats.get(&node.book.id).copied().unwrap_or_default();
+    node.total = node.media;
+    // 取出后再递归，数据中即使存在循环引用也不会重复挂载
+    if let Some(children) = by_parent.remove(&node.book.id) {
+        for mut child in children {
+            child.effective_public = node.effective_public && child.effective_public;
+            attach_children(&mut child, by_parent, stats);
+            node.total += child.total;
+            node.children.push(child);
+        }
+    }
+}
+
+/// 在内存中组装树：`books` 需已按同级顺序排好，返回满足 `is_root` 的书籍及其子树
+///
+/// `ancestors_public` 为根书籍的上级是否全部公开
+fn build_tree(
+    books: Vec<Model>,
+    stats: &HashMap<i32, MediaStats>,
+    is_root: impl Fn(&Model) -> bool,
+    ancestors_public: bool,
+) -> Vec<BookTree> {
+    let mut roots = Vec::new();
+    let mut by_parent: HashMap<i32, Vec<BookTree>> = HashMap::new();
+    for book in books {
+        let root = is_root(&book);
+        let node = BookTree {
+            effective_public: publishing::book_is_live(&book),
+            media: MediaStats::default(),
+            total: MediaStats::default(),
+            children: Vec::new(),
+            book,
+        };
+        if root {
+            roots.push(node);
+        } else if let Some(parent_id) = node.book.parent_id {
+            by_parent.entry(parent_id).or_default().push(node);
+        }
+    }
+
+    for root in &mut roots {
+        root.effective_public = root.effective_public && ancestors_public;
+        attach_children(root, &mut by_parent, stats);
+    }
+    roots
+}
+
 // implement your write-oriented logic here
 impl ActiveModel {}
 
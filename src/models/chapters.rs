@@ -1,11 +1,13 @@
 pub use super::_entities::books;
 pub use super::_entities::chapters::{ActiveModel, Column, Entity, Model};
+use super::_entities::media_placements;
 use sea_orm::entity::prelude::*;
 use sea_orm::sea_query::{Condition, Expr, Func};
 use sea_orm::{
-    DatabaseTransaction, IntoActiveModel, QueryOrder, QuerySelect, Set, TransactionTrait,
+    DatabaseTransaction, IntoActiveModel, QueryOrder, QuerySelect, Set, Statement, TransactionTrait,
 };
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 pub type Chapters = Entity;
 
 /// 章节树状结构
@@ -25,6 +27,64 @@ pub struct ChapterTree {
     pub children: Vec<ChapterTree>,
 }
 
+impl ChapterTree {
+    fn from_model(chapter: Model, media_count: i64) -> Self {
+        Self {
+            id: chapter.id,
+            title: chapter.title,
+            description: chapter.description,
+            sort_order: chapter.sort_order,
+            book_id: chapter.book_id,
+            parent_id: chapter.parent_id,
+            level: chapter.level,
+            path: chapter.path,
+            media_count,
+            created_at: chapter.created_at.naive_utc().and_utc(),
+            updated_at: chapter.updated_at.naive_utc().and_utc(),
+            children: Vec::new(),
+        }
+    }
+}
+
+/// 把 `by_parent` 中的子章节挂到节点下
+fn attach_children(node: &mut ChapterTree, by_parent: &mut HashMap<i32, Vec<ChapterTree>>) {
+    // 取出后再递归，数据中即使存在循环引用也不会重复挂载
+    if let Some(children) = by_parent.remove(&node.id) {
+        for mut child in children {
+            attach_children(&mut child, by_parent);
+            node.children.push(child);
+        }
+    }
+}
+
+/// 在内存中组装树：`chapters` 需已按同级顺序排好，返回满足 `is_root` 的章节及其子树
+///
+/// 父章节不在列表中的章节（例如父章节已删除）不会出现在结果里
+fn build_tree(
+    chapters: Vec<Model>,
+    media_counts: &HashMap<i32, i64>,
+    is_root: impl Fn(&Model) -> bool,
+) -> Vec<ChapterTree> {
+    let mut roots = Vec::new();
+    let mut by_parent: HashMap<i32, Vec<ChapterTree>> = HashMap::new();
+    for chapter in chapters {
+        let root = is_root(&chapter);
+        let parent_id = chapter.parent_id;
+        let count = media_counts.get(&chapter.id).copied().unwrap_or(0);
+        let node = ChapterTree::from_model(chapter, count);
+        if root {
+            roots.push(node);
+        } else if let Some(parent_id) = parent_id {
+            by_parent.entry(parent_id).or_default().push(node);
+        }
+    }
+
+    for root in &mut roots {
+        attach_children(root, &mut by_parent);
+    }
+    roots
+}
+
 /// 章节移动参数
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChapterMoveParams {
@@ -67,6 +127,7 @@ impl Model {
     /// 获取书籍的所有章节
     pub async fn find_by_book(db: &DatabaseConnection, book_id: i32) -> Result<Vec<Model>, DbErr> {
         Entity::find()
+            .filter(Column::DeletedAt.is_null())
             .filter(Column::BookId.eq(book_id))
             .order_by_asc(Column::SortOrder)
             .order_by_asc(Column::CreatedAt)
@@ -79,25 +140,92 @@ impl Model {
         db: &DatabaseConnection,
         book_id: i32,
     ) -> Result<Vec<(Model, i64)>, DbErr> {
+        let chapters = Self::find_by_book(db, book_id).await?;
+        let media_counts = Self::media_counts(db, book_id).await?;
+
+        Ok(chapters
+            .into_iter()
+            .map(|chapter| {
+                let count = media_counts.get(&chapter.id).copied().unwrap_or(0);
+                (chapter, count)
+            })
+            .collect())
+    }
+
+    /// 书籍中各章节（未删除）的媒体数量（包括附加到章节的媒体），分组查询得到
+    pub async fn media_counts(
+        db: &DatabaseConnection,
+        book_id: i32,
+    ) -> Result<HashMap<i32, i64>, DbErr> {
         use crate::models::_entities::medias;
 
-        let chapters = Entity::find()
-            .filter(Column::BookId.eq(book_id))
-            .order_by_asc(Column::SortOrder)
-            .order_by_asc(Column::CreatedAt)
+        let rows: Vec<(Option<i32>, i64)> = medias::Entity::find()
+            .select_only()
+            .column(medias::Column::ChapterId)
+            .column_as(Expr::col(medias::Column::Id).count(), "media_count")
+            .filter(medias::Column::BookId.eq(book_id))
+            .filter(medias::Column::ChapterId.is_not_null())
+            .filter(medias::Column::DeletedAt.is_null())
+            .group_by(medias::Column::ChapterId)
+            .into_tuple()
             .all(db)
             .await?;
 
-        let mut result = Vec::new();
-        for chapter in chapters {
-            let media_count = medias::Entity::find()
-                .filter(medias::Column::ChapterId.eq(chapter.id))
-                .count(db)
-                .await?;
-            result.push((chapter, media_count as i64));
+        let mut counts: HashMap<i32, i64> = rows
+            .into_iter()
+            .filter_map(|(chapter_id, count)| chapter_id.map(|id| (id, count)))
+            .collect();
+        for (chapter_id, count) in media_placements::Model::chapter_counts(db, book_id).await? {
+            *counts.entry(chapter_id).or_default() += count;
         }
+        Ok(counts)
+    }
 
-        Ok(result)
+    /// 章节及其所有子孙章节的 ID（按 `path` 前缀匹配，包括已删除的章节）
+    pub async fn find_subtree_ids<C>(db: &C, chapter: &Model) -> Result<Vec<i32>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let path = chapter
+            .path
+            .clone()
+            .unwrap_or_else(|| chapter.id.to_string());
+        let ids = Entity::find()
+            .filter(Column::BookId.eq(chapter.book_id))
+            .filter(
+                Condition::any()
+                    .add(Column::Id.eq(chapter.id))
+                    .add(Column::Path.starts_with(format!("{path}/"))),
+            )
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|c| c.id)
+            .collect();
+        Ok(ids)
+    }
+
+    /// 用递归 CTE 一次取出章节及其所有未删除的子孙章节（SQLite 和 Postgres 通用）
+    pub async fn find_subtree(
+        db: &DatabaseConnection,
+        chapter_id: i32,
+    ) -> Result<Vec<Model>, DbErr> {
+        // UNION 会去重，即使数据中存在循环引用查询也能结束
+        let sql = format!(
+            "WITH RECURSIVE subtree(id) AS ( \
+                SELECT id FROM chapters WHERE id = {chapter_id} \
+                UNION \
+                SELECT c.id FROM chapters c JOIN subtree s ON c.parent_id = s.id \
+                WHERE c.deleted_at IS NULL \
+            ) \
+            SELECT chapters.* FROM chapters \
+            WHERE chapters.id IN (SELECT id FROM subtree) \
+            ORDER BY chapters.sort_order ASC, chapters.created_at ASC"
+        );
+        Entity::find()
+            .from_raw_sql(Statement::from_string(db.get_database_backend(), sql))
+            .all(db)
+            .await
     }
 
     /// 搜索章节
@@ -111,6 +239,7 @@ impl Model {
         let search_pattern = format!("%{query_lower}%");
 
         Entity::find()
+            .filter(Column::DeletedAt.is_null())
             .filter(Column::BookId.eq(book_id))
             .filter(
                 Condition::any()
@@ -167,6 +296,7 @@ impl Model {
 
         // 获取所有章节（包括匹配的和父章节）
         let all_chapters = Entity::find()
+            .filter(Column::DeletedAt.is_null())
             .filter(Column::BookId.eq(book_id))
             .filter(Column::Id.is_in(chapter_ids.into_iter().collect::<Vec<_>>()))
             .order_by_asc(Column::SortOrder)
@@ -229,6 +359,7 @@ impl Model {
 
             // 找到当前章节前一个章节
             let prev_chapter = Entity::find()
+                .filter(Column::DeletedAt.is_null())
                 .filter(Column::BookId.eq(chapter.book_id))
                 .filter(Column::SortOrder.lt(current_order))
                 .order_by_desc(Column::SortOrder)
@@ -269,6 +400,7 @@ impl Model {
 
             // 找到当前章节后一个章节
             let next_chapter = Entity::find()
+                .filter(Column::DeletedAt.is_null())
                 .filter(Column::BookId.eq(chapter.book_id))
                 .filter(Column::SortOrder.gt(current_order))
                 .order_by_asc(Column::SortOrder)
@@ -307,6 +439,7 @@ impl Model {
         book_id: i32,
     ) -> Result<Vec<Model>, DbErr> {
         Entity::find()
+            .filter(Column::DeletedAt.is_null())
             .filter(Column::BookId.eq(book_id))
             .filter(Column::ParentId.is_null())
             .order_by_asc(Column::SortOrder)
@@ -321,6 +454,7 @@ impl Model {
         parent_id: i32,
     ) -> Result<Vec<Model>, DbErr> {
         Entity::find()
+            .filter(Column::DeletedAt.is_null())
             .filter(Column::ParentId.eq(parent_id))
             .order_by_asc(Column::SortOrder)
             .order_by_asc(Column::CreatedAt)
@@ -328,64 +462,41 @@ impl Model {
             .await
     }
 
-    /// 获取章节的完整树形结构（递归获取所有子章节）
+    /// 获取章节的完整树形结构（包含所有子孙章节）
     pub async fn get_tree(db: &DatabaseConnection, chapter_id: i32) -> Result<ChapterTree, DbErr> {
-        let chapter =
-            Entity::find_by_id(chapter_id)
-                .one(db)
-                .await?
-                .ok_or(DbErr::RecordNotFound(format!(
-                    "Chapter {} not found",
-                    chapter_id
-                )))?;
-
-        // 获取媒体计数
-        let media_count = crate::models::_entities::medias::Entity::find()
-            .filter(crate::models::_entities::medias::Column::ChapterId.eq(chapter_id))
-            .count(db)
-            .await? as i64;
-
-        let children = Self::find_children(db, chapter_id).await?;
-        let mut tree_children = Vec::new();
-
-        // 递归获取子章节的树形结构
-        for child in children {
-            // 使用 Box::pin 来避免递归 async fn 问题
-            let child_tree_future = Box::pin(Self::get_tree(db, child.id));
-            let child_tree = child_tree_future.await?;
-            tree_children.push(child_tree);
-        }
-
-        Ok(ChapterTree {
-            id: chapter.id,
-            title: chapter.title,
-            description: chapter.description,
-            sort_order: chapter.sort_order,
-            book_id: chapter.book_id,
-            parent_id: chapter.parent_id,
-            level: chapter.level,
-            path: chapter.path,
-            media_count,
-            created_at: chapter.created_at.naive_utc().and_utc(),
-            updated_at: chapter.updated_at.naive_utc().and_utc(),
-            children: tree_children,
-        })
+        let chapters = Self::find_subtree(db, chapter_id).await?;
+        let book_id = chapters
+            .iter()
+            .find(|c| c.id == chapter_id)
+            .map(|c| c.book_id)
+            .ok_or(DbErr::RecordNotFound(format!(
+                "Chapter {} not found",
+                chapter_id
+            )))?;
+        let media_counts = Self::media_counts(db, book_id).await?;
+
+        build_tree(chapters, &media_counts, |c| c.id == chapter_id)
+            .into_iter()
+            .next()
+            .ok_or(DbErr::RecordNotFound(format!(
+                "Chapter {} not found",
+                chapter_id
+            )))
     }
 
     /// 获取书籍的所有章节的树形结构
+    ///
+    /// 一次取出书籍的全部章节和媒体计数，在内存中组装，查询次数与章节数量无关
     pub async fn get_book_tree(
         db: &DatabaseConnection,
         book_id: i32,
     ) -> Result<Vec<ChapterTree>, DbErr> {
-        let root_chapters = Self::find_root_chapters(db, book_id).await?;
-        let mut tree = Vec::new();
-
-        for chapter in root_chapters {
-            let chapter_tree = Self::get_tree(db, chapter.id).await?;
-            tree.push(chapter_tree);
-        }
+        let chapters = Self::find_by_book(db, book_id).await?;
+        let media_counts = Self::media_counts(db, book_id).await?;
 
-        Ok(tree)
+        Ok(build_tree(chapters, &media_counts, |c| {
+            c.parent_id.is_none()
+        }))
     }
 
     /// 获取书籍的扁平章节列表（包含层级信息）
@@ -394,37 +505,48 @@ impl Model {
         book_id: i32,
     ) -> Result<Vec<ChapterTree>, DbErr> {
         let chapters = Entity::find()
+            .filter(Column::DeletedAt.is_null())
             .filter(Column::BookId.eq(book_id))
             .order_by_asc(Column::Path)
             .order_by_asc(Column::SortOrder)
             .order_by_asc(Column::CreatedAt)
             .all(db)
             .await?;
+        let media_counts = Self::media_counts(db, book_id).await?;
+
+        // 扁平列表不包含子节点
+        Ok(chapters
+            .into_iter()
+            .map(|chapter| {
+                let count = media_counts.get(&chapter.id).copied().unwrap_or(0);
+                ChapterTree::from_model(chapter, count)
+            })
+            .collect())
+    }
 
-        let mut result = Vec::new();
-        for chapter in chapters {
-            let media_count = crate::models::_entities::medias::Entity::find()
-                .filter(crate::models::_entities::medias::Column::ChapterId.eq(chapter.id))
-                .count(db)
-                .await? as i64;
-
-            result.push(ChapterTree {
-                id: chapter.id,
-                title: chapter.title,
-                description: chapter.description,
-                sort_order: chapter.sort_order,
-                book_id: chapter.book_id,
-                parent_id: chapter.parent_id,
-                level: chapter.level,
-                path: chapter.path,
-                media_count,
-                created_at: chapter.created_at.naive_utc().and_utc(),
-                updated_at: chapter.updated_at.naive_utc().and_utc(),
-                children: Vec::new(), // 扁平列表不包含子节点
-            });
+    /// 获取从顶级章节到当前章节的路径（面包屑），按层级排列
+    pub async fn breadcrumb(db: &DatabaseConnection, chapter_id: i32) -> Result<Vec<Model>, DbErr> {
+        let Some(chapter) = Entity::find_by_id(chapter_id).one(db).await? else {
+            return Ok(Vec::new());
+        };
+
+        let ids: Vec<i32> = chapter
+            .path
+            .as_deref()
+            .map(|path| path.split('/').filter_map(|id| id.parse().ok()).collect())
+            .unwrap_or_default();
+        if ids.len() <= 1 {
+            return Ok(vec![chapter]);
         }
 
-        Ok(result)
+        let mut ancestors = Entity::find()
+            .filter(Column::BookId.eq(chapter.book_id))
+            .filter(Column::Id.is_in(ids.clone()))
+            .all(db)
+            .await?;
+        ancestors.sort_by_key(|c| ids.iter().position(|id| *id == c.id));
+
+        Ok(ancestors)
     }
 
     /// 计算并更新章节的层级和路径
@@ -579,33 +701,33 @@ impl Model {
         Ok(())
     }
 
-    /// 检查移动是否会形成循环引用
-    async fn would_create_cycle(
+    /// 检查移动是否会形成循环引用：新的父章节是章节自身或其祖先链中包含该章节
+    pub async fn would_create_cycle(
         db: &DatabaseConnection,
         chapter_id: i32,
         new_parent_id: i32,
     ) -> Result<bool, DbErr> {
-        let mut current_parent = new_parent_id;
-
-        // 最多检查100层，防止无限循环
-        for _ in 0..100 {
-            if current_parent == chapter_id {
-                return Ok(true);
-            }
-
-            let parent = Entity::find_by_id(current_parent).one(db).await?;
-            if let Some(parent) = parent {
-                if let Some(parent_id) = parent.parent_id {
-                    current_parent = parent_id;
-                } else {
-                    break; // 到达顶级章节
-                }
-            } else {
-                break; // 父章节不存在
-            }
+        if chapter_id == new_parent_id {
+            return Ok(true);
         }
 
-        Ok(false)
+        // 沿 parent_id 一次查出新父章节的整条祖先链
+        let sql = format!(
+            "WITH RECURSIVE ancestors(id, parent_id) AS ( \
+                SELECT id, parent_id FROM chapters WHERE id = {new_parent_id} \
+                UNION \
+                SELECT c.id, c.parent_id FROM chapters c JOIN ancestors a ON c.id = a.parent_id \
+            ) \
+            SELECT COUNT(*) AS count FROM ancestors WHERE id = {chapter_id}"
+        );
+        let count = db
+            .query_one(Statement::from_string(db.get_database_backend(), sql))
+            .await?
+            .map(|row| row.try_get::<i64>("", "count"))
+            .transpose()?
+            .unwrap_or(0);
+
+        Ok(count > 0)
     }
 }
 
@@ -0,0 +1,102 @@
+pub use super::_entities::media_clips::{ActiveModel, Column, Entity, Model};
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::Expr;
+use sea_orm::Set;
+pub type MediaClips = Entity;
+
+/// 等待处理
+pub const STATUS_PENDING: &str = "pending";
+/// 新媒体已生成
+pub const STATUS_SUCCEEDED: &str = "succeeded";
+/// 处理失败，原因见 `error`
+pub const STATUS_FAILED: &str = "failed";
+
+/// 剪辑方式：按帧复制原始数据，不重新编码
+pub const METHOD_COPY: &str = "copy";
+/// 剪辑方式：解码后重新编码
+pub const METHOD_REENCODE: &str = "reencode";
+
+/// 创建剪辑任务所需的数据
+#[derive(Debug, Clone)]
+pub struct ClipRequest {
+    pub source_media_id: i32,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub title: String,
+    pub book_id: i32,
+    pub chapter_id: Option<i32>,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+// implement your read-oriented logic here
+impl Model {
+    /// 创建一条待处理的剪辑任务
+    pub async fn create<C>(db: &C, user_id: i32, request: ClipRequest) -> Result<Model, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        ActiveModel {
+            user_id: Set(user_id),
+            source_media_id: Set(request.source_media_id),
+            start_ms: Set(request.start_ms),
+            end_ms: Set(request.end_ms),
+            title: Set(request.title),
+            book_id: Set(request.book_id),
+            chapter_id: Set(request.chapter_id),
+            status: Set(STATUS_PENDING.to_string()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+    }
+
+    /// 获取用户的剪辑任务
+    pub async fn find_for_user<C>(db: &C, id: i32, user_id: i32) -> Result<Option<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::find_by_id(id)
+            .filter(Column::UserId.eq(user_id))
+            .one(db)
+            .await
+    }
+
+    /// 删除以媒体为来源的剪辑任务（媒体被永久删除时）
+    pub async fn delete_by_media<C>(db: &C, media_id: i32) -> Result<u64, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let result = Entity::delete_many()
+            .filter(Column::SourceMediaId.eq(media_id))
+            .exec(db)
+            .await?;
+        // 剪辑出的媒体被删除时保留任务记录，只去掉对它的引用
+        Entity::update_many()
+            .col_expr(Column::MediaId, Expr::value(Option::<i32>::None))
+            .filter(Column::MediaId.eq(media_id))
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+}
+
+// implement your write-oriented logic here
+impl ActiveModel {}
+
+// implement your custom finders, selectors oriented logic here
+impl Entity {}
@@ -0,0 +1,249 @@
+pub use super::_entities::media_placements::{ActiveModel, Column, Entity, Model};
+use super::_entities::medias;
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::{Expr, Query};
+use sea_orm::{QueryOrder, QuerySelect, Statement};
+use std::collections::HashMap;
+pub type MediaPlacements = Entity;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+/// 未删除媒体的 ID 子查询
+fn live_media_ids() -> sea_orm::sea_query::SelectStatement {
+    Query::select()
+        .column(medias::Column::Id)
+        .from(medias::Entity)
+        .and_where(medias::Column::DeletedAt.is_null())
+        .to_owned()
+}
+
+/// 为附加位置加载对应的媒体，丢弃媒体已删除的位置
+async fn with_live_medias<C>(
+    db: &C,
+    placements: Vec<Model>,
+) -> Result<Vec<(Model, medias::Model)>, DbErr>
+where
+    C: ConnectionTrait,
+{
+    if placements.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let media_by_id: HashMap<i32, medias::Model> = medias::Entity::find()
+        .filter(medias::Column::Id.is_in(placements.iter().map(|p| p.media_id)))
+        .filter(medias::Column::DeletedAt.is_null())
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|media| (media.id, media))
+        .collect();
+
+    Ok(placements
+        .into_iter()
+        .filter_map(|placement| {
+            let media = media_by_id.get(&placement.media_id)?.clone();
+            Some((placement, media))
+        })
+        .collect())
+}
+
+// implement your read-oriented logic here
+impl Model {
+    /// 获取媒体的所有附加位置
+    pub async fn find_by_media<C>(db: &C, media_id: i32) -> Result<Vec<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::find()
+            .filter(Column::MediaId.eq(media_id))
+            .order_by_asc(Column::BookId)
+            .order_by_asc(Column::ChapterId)
+            .order_by_asc(Column::Id)
+            .all(db)
+            .await
+    }
+
+    /// 获取属于指定媒体的附加位置
+    pub async fn find_for_media<C>(db: &C, media_id: i32, id: i32) -> Result<Option<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::find_by_id(id)
+            .filter(Column::MediaId.eq(media_id))
+            .one(db)
+            .await
+    }
+
+    /// 媒体是否已放在指定位置（`chapter_id` 为空表示书籍顶层）
+    pub async fn exists_at<C>(
+        db: &C,
+        media_id: i32,
+        book_id: i32,
+        chapter_id: Option<i32>,
+    ) -> Result<bool, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let chapter_filter = match chapter_id {
+            Some(chapter_id) => Column::ChapterId.eq(chapter_id),
+            None => Column::ChapterId.is_null(),
+        };
+        let existing = Entity::find()
+            .filter(Column::MediaId.eq(media_id))
+            .filter(Column::BookId.eq(book_id))
+            .filter(chapter_filter)
+            .one(db)
+            .await?;
+        Ok(existing.is_some())
+    }
+
+    /// 章节中的附加媒体（只包含未删除的媒体），按位置排序
+    pub async fn find_in_chapters<C>(
+        db: &C,
+        chapter_ids: &[i32],
+    ) -> Result<Vec<(Model, medias::Model)>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let placements = Entity::find()
+            .filter(Column::ChapterId.is_in(chapter_ids.to_vec()))
+            .order_by_asc(Column::SortOrder)
+            .order_by_asc(Column::CreatedAt)
+            .all(db)
+            .await?;
+        with_live_medias(db, placements).await
+    }
+
+    /// 书籍顶层（`chapter_id` 为空）或某个章节中的附加媒体，按位置排序
+    pub async fn find_at<C>(
+        db: &C,
+        book_id: i32,
+        chapter_id: Option<i32>,
+    ) -> Result<Vec<(Model, medias::Model)>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let chapter_filter = match chapter_id {
+            Some(chapter_id) => Column::ChapterId.eq(chapter_id),
+            None => Column::ChapterId.is_null(),
+        };
+        let placements = Entity::find()
+            .filter(Column::BookId.eq(book_id))
+            .filter(chapter_filter)
+            .order_by_asc(Column::SortOrder)
+            .order_by_asc(Column::CreatedAt)
+            .all(db)
+            .await?;
+        with_live_medias(db, placements).await
+    }
+
+    /// 书籍中各章节附加的（未删除）媒体数量，一次分组查询得到
+    pub async fn chapter_counts<C>(db: &C, book_id: i32) -> Result<HashMap<i32, i64>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let rows: Vec<(Option<i32>, i64)> = Entity::find()
+            .select_only()
+            .column(Column::ChapterId)
+            .column_as(Expr::col(Column::Id).count(), "media_count")
+            .filter(Column::BookId.eq(book_id))
+            .filter(Column::ChapterId.is_not_null())
+            .filter(Column::MediaId.in_subquery(live_media_ids()))
+            .group_by(Column::ChapterId)
+            .into_tuple()
+            .all(db)
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|(chapter_id, count)| chapter_id.map(|id| (id, count)))
+            .collect())
+    }
+
+    /// 用户各书籍中附加的媒体数量和总时长，不包括已删除的媒体以及回收站中书籍和章节里的位置
+    pub async fn book_stats<C>(db: &C, user_id: i32) -> Result<HashMap<i32, (i64, i64)>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let sql = format!(
+            "SELECT p.book_id AS book_id, COUNT(p.id) AS media_count, SUM(m.duration) AS duration \
+            FROM media_placements p \
+            JOIN medias m ON m.id = p.media_id \
+            JOIN books b ON b.id = p.book_id AND b.deleted_at IS NULL \
+            LEFT JOIN chapters c ON c.id = p.chapter_id \
+            WHERE m.user_id = {user_id} AND m.deleted_at IS NULL \
+            AND (p.chapter_id IS NULL OR c.deleted_at IS NULL) \
+            GROUP BY p.book_id"
+        );
+        let rows = db
+            .query_all(Statement::from_string(db.get_database_backend(), sql))
+            .await?;
+        rows.iter()
+            .map(|row| {
+                Ok((
+                    row.try_get::<i32>("", "book_id")?,
+                    (
+                        row.try_get::<i64>("", "media_count")?,
+                        row.try_get::<Option<i64>>("", "duration")?.unwrap_or(0),
+                    ),
+                ))
+            })
+            .collect()
+    }
+
+    /// 删除媒体的所有附加位置
+    pub async fn delete_by_media<C>(db: &C, media_id: i32) -> Result<u64, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let result = Entity::delete_many()
+            .filter(Column::MediaId.eq(media_id))
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+
+    /// 删除指定章节中的附加位置
+    pub async fn delete_by_chapters<C>(db: &C, chapter_ids: &[i32]) -> Result<u64, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let result = Entity::delete_many()
+            .filter(Column::ChapterId.is_in(chapter_ids.to_vec()))
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+
+    /// 删除指定书籍中的附加位置
+    pub async fn delete_by_books<C>(db: &C, book_ids: &[i32]) -> Result<u64, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let result = Entity::delete_many()
+            .filter(Column::BookId.is_in(book_ids.to_vec()))
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+}
+
+// implement your write-oriented logic here
+impl ActiveModel {}
+
+// implement your custom finders, selectors oriented logic here
+impl Entity {}
@@ -0,0 +1,112 @@
+pub use super::_entities::media_plays::{ActiveModel, Column, Entity, Model};
+pub use super::_entities::medias;
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::Expr;
+use sea_orm::{FromQueryResult, QuerySelect, Set};
+use std::collections::HashMap;
+pub type MediaPlays = Entity;
+
+/// 播放事件类型：开始播放（与 `play_count` 同步累加）
+pub const KIND_PLAY: &str = "play";
+/// 播放事件类型：播放器上报的完整播放
+pub const KIND_COMPLETE: &str = "complete";
+
+/// 某个媒体在时间范围内的播放统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PlayStats {
+    pub plays: i64,
+    pub completions: i64,
+}
+
+#[derive(Debug, FromQueryResult)]
+struct PlayCountRow {
+    media_id: i32,
+    kind: String,
+    total: i64,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+// implement your read-oriented logic here
+impl Model {
+    /// 记录一次播放事件
+    pub async fn record<C>(db: &C, media: &medias::Model, kind: &str) -> Result<Model, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        ActiveModel {
+            media_id: Set(media.id),
+            book_id: Set(media.book_id),
+            user_id: Set(media.user_id),
+            kind: Set(kind.to_string()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+    }
+
+    /// 按媒体统计时间范围内的播放和完播次数（`to` 为开区间）
+    pub async fn stats_by_media(
+        db: &DatabaseConnection,
+        media_ids: &[i32],
+        from: Option<DateTimeWithTimeZone>,
+        to: Option<DateTimeWithTimeZone>,
+    ) -> Result<HashMap<i32, PlayStats>, DbErr> {
+        if media_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        let mut query = Entity::find()
+            .select_only()
+            .column(Column::MediaId)
+            .column(Column::Kind)
+            .column_as(Expr::col(Column::Id).count(), "total")
+            .filter(Column::MediaId.is_in(media_ids.to_vec()));
+
+        if let Some(from) = from {
+            query = query.filter(Column::CreatedAt.gte(from));
+        }
+        if let Some(to) = to {
+            query = query.filter(Column::CreatedAt.lt(to));
+        }
+
+        let rows = query
+            .group_by(Column::MediaId)
+            .group_by(Column::Kind)
+            .into_model::<PlayCountRow>()
+            .all(db)
+            .await?;
+
+        let mut stats: HashMap<i32, PlayStats> = HashMap::new();
+        for row in rows {
+            let entry = stats.entry(row.media_id).or_default();
+            match row.kind.as_str() {
+                KIND_PLAY => entry.plays += row.total,
+                KIND_COMPLETE => entry.completions += row.total,
+                _ => {}
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+// implement your write-oriented logic here
+impl ActiveModel {}
+
+// implement your custom finders, selectors oriented logic here
+impl Entity {}
@@ -0,0 +1,115 @@
+pub use super::_entities::media_subtitles::{ActiveModel, Column, Entity, Model};
+use sea_orm::entity::prelude::*;
+use sea_orm::{IntoActiveModel, QueryOrder, Set, TryIntoModel};
+pub type MediaSubtitles = Entity;
+
+/// 字幕类型：翻译字幕
+pub const KIND_SUBTITLES: &str = "subtitles";
+/// 字幕类型：听障字幕（包含音效等描述）
+pub const KIND_CAPTIONS: &str = "captions";
+/// 字幕类型：音频课程文稿
+pub const KIND_TRANSCRIPT: &str = "transcript";
+
+/// 支持的字幕类型
+pub const KINDS: &[&str] = &[KIND_SUBTITLES, KIND_CAPTIONS, KIND_TRANSCRIPT];
+
+/// 保存字幕所需的数据（内容已转换为 WebVTT）
+#[derive(Debug, Clone)]
+pub struct SubtitleData {
+    pub language: String,
+    pub label: Option<String>,
+    pub kind: String,
+    pub source_format: String,
+    pub content: String,
+    pub cue_count: i32,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+// implement your read-oriented logic here
+impl Model {
+    /// 获取媒体的所有字幕
+    pub async fn find_by_media<C>(db: &C, media_id: i32) -> Result<Vec<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::find()
+            .filter(Column::MediaId.eq(media_id))
+            .order_by_asc(Column::Kind)
+            .order_by_asc(Column::Language)
+            .all(db)
+            .await
+    }
+
+    /// 获取属于指定媒体的字幕
+    pub async fn find_for_media<C>(db: &C, media_id: i32, id: i32) -> Result<Option<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::find_by_id(id)
+            .filter(Column::MediaId.eq(media_id))
+            .one(db)
+            .await
+    }
+
+    /// 保存字幕：同一媒体、语言和类型已存在时覆盖
+    pub async fn upsert<C>(db: &C, media_id: i32, data: SubtitleData) -> Result<Model, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let existing = Entity::find()
+            .filter(Column::MediaId.eq(media_id))
+            .filter(Column::Language.eq(&data.language))
+            .filter(Column::Kind.eq(&data.kind))
+            .one(db)
+            .await?;
+
+        let mut item = match existing {
+            Some(existing) => existing.into_active_model(),
+            None => ActiveModel {
+                media_id: Set(media_id),
+                language: Set(data.language),
+                kind: Set(data.kind),
+                ..Default::default()
+            },
+        };
+        item.label = Set(data.label);
+        item.source_format = Set(data.source_format);
+        item.content = Set(data.content);
+        item.cue_count = Set(data.cue_count);
+
+        item.save(db).await?.try_into_model()
+    }
+
+    /// 删除媒体的所有字幕
+    pub async fn delete_by_media<C>(db: &C, media_id: i32) -> Result<u64, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let result = Entity::delete_many()
+            .filter(Column::MediaId.eq(media_id))
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+}
+
+// implement your write-oriented logic here
+impl ActiveModel {}
+
+// implement your custom finders, selectors oriented logic here
+impl Entity {}
@@ -0,0 +1,121 @@
+pub use super::_entities::media_versions::{ActiveModel, Column, Entity, Model};
+use super::_entities::medias;
+use super::storage_blobs;
+use sea_orm::entity::prelude::*;
+use sea_orm::{QueryOrder, Set};
+pub type MediaVersions = Entity;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+// implement your read-oriented logic here
+impl Model {
+    /// 获取媒体的所有文件版本（新版本在前）
+    pub async fn find_by_media<C>(db: &C, media_id: i32) -> Result<Vec<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::find()
+            .filter(Column::MediaId.eq(media_id))
+            .order_by_desc(Column::Version)
+            .all(db)
+            .await
+    }
+
+    /// 获取媒体的指定版本
+    pub async fn find_version<C>(
+        db: &C,
+        media_id: i32,
+        version: i32,
+    ) -> Result<Option<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::find()
+            .filter(Column::MediaId.eq(media_id))
+            .filter(Column::Version.eq(version))
+            .one(db)
+            .await
+    }
+
+    /// 把媒体当前的文件记录为一个版本
+    ///
+    /// `uploaded_at` 为空时使用当前时间。版本持有的内容文件引用由保存上传时获取，
+    /// 共用已有文件时使用 [`Model::record_shared`]
+    pub async fn record<C>(
+        db: &C,
+        media: &medias::Model,
+        uploaded_by: Option<i32>,
+        uploaded_at: Option<DateTimeWithTimeZone>,
+    ) -> Result<Model, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut item = ActiveModel {
+            media_id: Set(media.id),
+            version: Set(media.file_version),
+            file_type: Set(media.file_type.clone()),
+            file_path: Set(media.file_path.clone()),
+            file_size: Set(media.file_size),
+            duration: Set(media.duration),
+            mime_type: Set(media.mime_type.clone()),
+            original_filename: Set(media.original_filename.clone()),
+            uploaded_by: Set(uploaded_by),
+            content_hash: Set(media.content_hash.clone()),
+            ..Default::default()
+        };
+        if let Some(uploaded_at) = uploaded_at {
+            item.created_at = Set(uploaded_at);
+            item.updated_at = Set(uploaded_at);
+        }
+        item.insert(db).await
+    }
+
+    /// 记录版本并为共用的内容文件增加一个引用（复制媒体、补记已有文件时使用）
+    pub async fn record_shared<C>(
+        db: &C,
+        media: &medias::Model,
+        uploaded_by: Option<i32>,
+        uploaded_at: Option<DateTimeWithTimeZone>,
+    ) -> Result<Model, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let version = Self::record(db, media, uploaded_by, uploaded_at).await?;
+        if let Some(ref content_hash) = version.content_hash {
+            storage_blobs::Model::acquire(db, content_hash).await?;
+        }
+        Ok(version)
+    }
+
+    /// 删除媒体的所有版本记录
+    pub async fn delete_by_media<C>(db: &C, media_id: i32) -> Result<u64, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let result = Entity::delete_many()
+            .filter(Column::MediaId.eq(media_id))
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+}
+
+// implement your write-oriented logic here
+impl ActiveModel {}
+
+// implement your custom finders, selectors oriented logic here
+impl Entity {}
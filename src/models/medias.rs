@@ -1,15 +1,16 @@
 pub use super::_entities::books;
+use super::_entities::media_placements;
 pub use super::_entities::medias::{ActiveModel, Column, Entity, Model};
 pub use super::_entities::users;
 use sea_orm::entity::prelude::*;
-use sea_orm::sea_query::{Condition, Expr, Func};
-use sea_orm::{QueryOrder, Set};
+use sea_orm::sea_query::{Condition, Expr, Func, SimpleExpr};
+use sea_orm::{ActiveValue, QueryOrder, Set, TransactionTrait};
 use uuid::Uuid;
 pub type Medias = Entity;
 
 #[async_trait::async_trait]
 impl ActiveModelBehavior for ActiveModel {
-    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    async fn before_save<C>(self, db: &C, insert: bool) -> std::result::Result<Self, DbErr>
     where
         C: ConnectionTrait,
     {
@@ -20,6 +21,14 @@ impl ActiveModelBehavior for ActiveModel {
             if this.access_token.is_unchanged() {
                 this.access_token = sea_orm::ActiveValue::Set(Uuid::new_v4().to_string());
             }
+            // 新媒体排在所在位置的最后
+            if this.sort_order.is_not_set() {
+                if let ActiveValue::Set(book_id) = this.book_id {
+                    let chapter_id = this.chapter_id.try_as_ref().copied().flatten();
+                    let next = Model::next_sort_order(db, book_id, chapter_id).await?;
+                    this.sort_order = ActiveValue::Set(Some(next));
+                }
+            }
         }
 
         if !insert && this.updated_at.is_unchanged() {
@@ -30,11 +39,20 @@ impl ActiveModelBehavior for ActiveModel {
     }
 }
 
+/// 章节条件，`chapter_id` 为空时匹配书籍顶层
+fn location_filter<T: ColumnTrait>(column: T, chapter_id: Option<i32>) -> SimpleExpr {
+    match chapter_id {
+        Some(chapter_id) => column.eq(chapter_id),
+        None => column.is_null(),
+    }
+}
+
 // implement your read-oriented logic here
 impl Model {
     /// 获取用户的所有媒体文件
     pub async fn find_by_user(db: &DatabaseConnection, user_id: i32) -> Result<Vec<Model>, DbErr> {
         Entity::find()
+            .filter(Column::DeletedAt.is_null())
             .filter(Column::UserId.eq(user_id))
             .order_by_desc(Column::CreatedAt)
             .all(db)
@@ -44,6 +62,7 @@ impl Model {
     /// 获取书籍的所有媒体文件
     pub async fn find_by_book(db: &DatabaseConnection, book_id: i32) -> Result<Vec<Model>, DbErr> {
         Entity::find()
+            .filter(Column::DeletedAt.is_null())
             .filter(Column::BookId.eq(book_id))
             .order_by_asc(Column::CreatedAt)
             .all(db)
@@ -56,12 +75,98 @@ impl Model {
         chapter_id: i32,
     ) -> Result<Vec<Model>, DbErr> {
         Entity::find()
+            .filter(Column::DeletedAt.is_null())
             .filter(Column::ChapterId.eq(chapter_id))
+            .order_by_asc(Column::SortOrder)
             .order_by_asc(Column::CreatedAt)
             .all(db)
             .await
     }
 
+    /// 位置（章节，`chapter_id` 为空表示书籍顶层）中的下一个排序号
+    ///
+    /// 媒体和附加到该位置的媒体共用一个排序序列
+    pub async fn next_sort_order<C>(
+        db: &C,
+        book_id: i32,
+        chapter_id: Option<i32>,
+    ) -> Result<i32, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let last_media = Entity::find()
+            .filter(Column::BookId.eq(book_id))
+            .filter(location_filter(Column::ChapterId, chapter_id))
+            .filter(Column::DeletedAt.is_null())
+            .filter(Column::SortOrder.is_not_null())
+            .order_by_desc(Column::SortOrder)
+            .one(db)
+            .await?
+            .and_then(|m| m.sort_order);
+        let last_placement = media_placements::Entity::find()
+            .filter(media_placements::Column::BookId.eq(book_id))
+            .filter(location_filter(
+                media_placements::Column::ChapterId,
+                chapter_id,
+            ))
+            .filter(media_placements::Column::SortOrder.is_not_null())
+            .order_by_desc(media_placements::Column::SortOrder)
+            .one(db)
+            .await?
+            .and_then(|p| p.sort_order);
+        Ok(last_media.max(last_placement).unwrap_or(0) + 1)
+    }
+
+    /// 按给定顺序重新编号位置中的媒体（包括附加到该位置的媒体）
+    ///
+    /// `media_ids` 中的每个媒体必须在该位置中，否则整个操作回滚
+    pub async fn reorder_in_location(
+        db: &DatabaseConnection,
+        book_id: i32,
+        chapter_id: Option<i32>,
+        media_ids: &[i32],
+    ) -> Result<(), DbErr> {
+        let txn = db.begin().await?;
+        // 订阅源根据媒体的更新时间判断是否变化，排序变化也要更新
+        let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+
+        for (new_order, &media_id) in media_ids.iter().enumerate() {
+            let sort_order = Expr::value(i32::try_from(new_order + 1).unwrap_or(i32::MAX));
+            let result = Entity::update_many()
+                .col_expr(Column::SortOrder, sort_order.clone())
+                .col_expr(Column::UpdatedAt, Expr::value(now))
+                .filter(Column::Id.eq(media_id))
+                .filter(Column::BookId.eq(book_id))
+                .filter(location_filter(Column::ChapterId, chapter_id))
+                .filter(Column::DeletedAt.is_null())
+                .exec(&txn)
+                .await?;
+            if result.rows_affected > 0 {
+                continue;
+            }
+
+            let result = media_placements::Entity::update_many()
+                .col_expr(media_placements::Column::SortOrder, sort_order)
+                .filter(media_placements::Column::MediaId.eq(media_id))
+                .filter(media_placements::Column::BookId.eq(book_id))
+                .filter(location_filter(
+                    media_placements::Column::ChapterId,
+                    chapter_id,
+                ))
+                .exec(&txn)
+                .await?;
+            if result.rows_affected == 0 {
+                txn.rollback().await?;
+                return Err(DbErr::Custom(format!(
+                    "Media {media_id} is not in this chapter"
+                )));
+            }
+        }
+
+        txn.commit().await?;
+        Ok(())
+    }
+
     /// 搜索媒体文件
     pub async fn search(
         db: &DatabaseConnection,
@@ -73,6 +178,7 @@ impl Model {
         let search_pattern = format!("%{query_lower}%");
 
         Entity::find()
+            .filter(Column::DeletedAt.is_null())
             .filter(Column::UserId.eq(user_id))
             .filter(
                 Condition::any()
@@ -103,6 +209,7 @@ impl Model {
         let search_pattern = format!("%{query_lower}%");
 
         Entity::find()
+            .filter(Column::DeletedAt.is_null())
             .filter(Column::UserId.eq(user_id))
             .filter(Column::BookId.eq(book_id))
             .filter(
@@ -136,6 +243,7 @@ impl Model {
     /// 获取公开的媒体文件
     pub async fn find_public(db: &DatabaseConnection) -> Result<Vec<Model>, DbErr> {
         Entity::find()
+            .filter(Column::DeletedAt.is_null())
             .filter(Column::IsPublic.eq(true))
             .order_by_desc(Column::CreatedAt)
             .all(db)
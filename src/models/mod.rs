@@ -1,8 +1,21 @@
 pub mod _entities;
+pub mod audit_logs;
 pub mod books;
 pub mod chapters;
+pub mod media_clips;
+pub mod media_placements;
+pub mod media_plays;
+pub mod media_subtitles;
+pub mod media_versions;
 pub mod medias;
+pub mod personal_access_tokens;
 pub mod site_settings;
+pub mod storage_blobs;
 pub mod user_group_members;
 pub mod user_groups;
+pub mod user_identities;
+pub mod user_sessions;
+pub mod user_totp;
 pub mod users;
+pub mod webhook_deliveries;
+pub mod webhook_endpoints;
@@ -0,0 +1,265 @@
+pub use super::_entities::personal_access_tokens::{ActiveModel, Column, Entity, Model};
+use sea_orm::entity::prelude::*;
+use sea_orm::{QueryOrder, Set};
+use sha2::{Digest, Sha256};
+pub type PersonalAccessTokens = Entity;
+
+/// 令牌明文前缀，用于区分 JWT 和个人访问令牌，也便于密钥扫描工具识别
+pub const TOKEN_PREFIX: &str = "qcp_";
+
+/// 列表中展示的令牌前缀长度（含 `qcp_`）
+const DISPLAY_PREFIX_LEN: usize = 12;
+
+/// 距上次记录超过该秒数才更新 `last_used_at`，避免每个请求都写库
+const LAST_USED_THROTTLE_SECS: i64 = 60;
+
+/// 令牌权限范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    /// 读取书籍、章节和媒体
+    BooksRead,
+    /// 创建、修改、删除书籍和章节
+    BooksWrite,
+    /// 上传、修改、删除和发布媒体
+    MediaUpload,
+    /// 获取和重新生成二维码
+    QrcodeManage,
+    /// 全部权限（包括管理接口），仅管理员可授予
+    Admin,
+}
+
+impl Scope {
+    pub const ALL: [Self; 5] = [
+        Self::BooksRead,
+        Self::BooksWrite,
+        Self::MediaUpload,
+        Self::QrcodeManage,
+        Self::Admin,
+    ];
+
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::BooksRead => "books:read",
+            Self::BooksWrite => "books:write",
+            Self::MediaUpload => "media:upload",
+            Self::QrcodeManage => "qrcode:manage",
+            Self::Admin => "admin",
+        }
+    }
+
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|scope| scope.as_str() == value)
+    }
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            let mut this = self;
+            this.pid = sea_orm::ActiveValue::Set(Uuid::new_v4());
+            Ok(this)
+        } else if self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+/// 生成新的令牌明文
+#[must_use]
+pub fn generate_token() -> String {
+    format!(
+        "{TOKEN_PREFIX}{}{}",
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    )
+}
+
+/// 计算令牌摘要（数据库中只保存摘要）
+#[must_use]
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+// implement your read-oriented logic here
+impl Model {
+    /// 令牌包含的权限范围
+    #[must_use]
+    pub fn scope_list(&self) -> Vec<Scope> {
+        self.scopes.split(',').filter_map(Scope::parse).collect()
+    }
+
+    /// 是否拥有指定权限（`admin` 包含所有权限）
+    #[must_use]
+    pub fn has_scope(&self, scope: Scope) -> bool {
+        let scopes = self.scope_list();
+        scopes.contains(&Scope::Admin) || scopes.contains(&scope)
+    }
+
+    /// 令牌当前是否可用（未吊销、未过期）
+    #[must_use]
+    pub fn is_active(&self) -> bool {
+        self.revoked_at.is_none()
+            && self
+                .expires_at
+                .is_none_or(|expires_at| expires_at > chrono::Utc::now())
+    }
+
+    /// 获取用户的所有令牌（含已吊销的，按创建时间倒序）
+    pub async fn find_by_user<C>(db: &C, user_id: i32) -> Result<Vec<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .order_by_desc(Column::CreatedAt)
+            .all(db)
+            .await
+    }
+
+    /// 获取属于指定用户的令牌
+    pub async fn find_for_user<C>(db: &C, user_id: i32, id: i32) -> Result<Option<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::find_by_id(id)
+            .filter(Column::UserId.eq(user_id))
+            .one(db)
+            .await
+    }
+
+    /// 根据令牌明文查找可用的令牌
+    pub async fn find_active_by_token<C>(db: &C, token: &str) -> Result<Option<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let item = Entity::find()
+            .filter(Column::TokenHash.eq(hash_token(token)))
+            .one(db)
+            .await?;
+        Ok(item.filter(Model::is_active))
+    }
+
+    /// 创建令牌，返回记录和只展示一次的明文
+    pub async fn create_for_user<C>(
+        db: &C,
+        user_id: i32,
+        name: String,
+        scopes: &[Scope],
+        expires_at: Option<DateTimeWithTimeZone>,
+    ) -> Result<(Model, String), DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let token = generate_token();
+        let scopes: Vec<&str> = scopes.iter().map(|scope| scope.as_str()).collect();
+
+        let item = ActiveModel {
+            user_id: Set(user_id),
+            name: Set(name),
+            token_prefix: Set(token[..DISPLAY_PREFIX_LEN].to_string()),
+            token_hash: Set(hash_token(&token)),
+            scopes: Set(scopes.join(",")),
+            expires_at: Set(expires_at),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+
+        Ok((item, token))
+    }
+
+    /// 记录令牌使用时间（一分钟内重复使用不更新）
+    pub async fn touch<C>(&self, db: &C) -> Result<(), DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let now = chrono::Utc::now();
+        let recently_used = self.last_used_at.is_some_and(|last_used_at| {
+            now.signed_duration_since(last_used_at).num_seconds() < LAST_USED_THROTTLE_SECS
+        });
+        if recently_used {
+            return Ok(());
+        }
+
+        // 直接更新单列，不触发 updated_at
+        Entity::update_many()
+            .col_expr(
+                Column::LastUsedAt,
+                sea_orm::sea_query::Expr::value(DateTimeWithTimeZone::from(now)),
+            )
+            .filter(Column::Id.eq(self.id))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    /// 吊销令牌
+    pub async fn revoke<C>(self, db: &C) -> Result<Model, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if self.revoked_at.is_some() {
+            return Ok(self);
+        }
+        let mut item: ActiveModel = self.into();
+        item.revoked_at = Set(Some(chrono::Utc::now().into()));
+        item.update(db).await
+    }
+
+    /// 吊销用户的所有令牌（修改密码或降低权限后调用），返回吊销数量
+    pub async fn revoke_all_for_user<C>(db: &C, user_id: i32) -> Result<u64, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let now = DateTimeWithTimeZone::from(chrono::Utc::now());
+        let result = Entity::update_many()
+            .col_expr(Column::RevokedAt, sea_orm::sea_query::Expr::value(now))
+            .col_expr(Column::UpdatedAt, sea_orm::sea_query::Expr::value(now))
+            .filter(Column::UserId.eq(user_id))
+            .filter(Column::RevokedAt.is_null())
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+}
+
+// implement your write-oriented logic here
+impl ActiveModel {}
+
+// implement your custom finders, selectors oriented logic here
+impl Entity {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_and_hash_token() {
+        let token = generate_token();
+        assert!(token.starts_with(TOKEN_PREFIX));
+        assert_eq!(token.len(), TOKEN_PREFIX.len() + 64);
+        assert_ne!(token, generate_token());
+
+        let hash = hash_token(&token);
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, hash_token(&token));
+    }
+
+    #[test]
+    fn test_parse_scope() {
+        for scope in Scope::ALL {
+            assert_eq!(Scope::parse(scope.as_str()), Some(scope));
+        }
+        assert_eq!(Scope::parse("books"), None);
+    }
+}
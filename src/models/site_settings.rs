@@ -4,6 +4,9 @@ use serde::{Deserialize, Serialize};
 
 pub use super::_entities::site_settings::{self, ActiveModel, Entity, Model};
 
+/// 尚未配置站点设置时使用的站点地址
+pub const DEFAULT_SITE_URL: &str = "http://localhost:5150";
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct UpdateSiteUrlParams {
     pub site_url: String,
@@ -35,6 +38,25 @@ impl Model {
         Ok(settings)
     }
 
+    /// 获取站点设置，不存在时使用 [`DEFAULT_SITE_URL`] 创建
+    ///
+    /// # Errors
+    ///
+    /// When database query fails
+    pub async fn current(db: &DatabaseConnection) -> ModelResult<Self> {
+        Self::get_or_create(db, DEFAULT_SITE_URL).await
+    }
+
+    /// 站点URL（去掉末尾的 `/`，用于拼接访问地址）
+    ///
+    /// # Errors
+    ///
+    /// When database query fails
+    pub async fn site_url(db: &DatabaseConnection) -> ModelResult<String> {
+        let settings = Self::current(db).await?;
+        Ok(settings.site_url.trim_end_matches('/').to_string())
+    }
+
     /// 更新站点URL
     ///
     /// # Errors
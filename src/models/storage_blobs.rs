@@ -0,0 +1,127 @@
+pub use super::_entities::storage_blobs::{ActiveModel, Column, Entity, Model};
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::{Expr, OnConflict};
+use sea_orm::{QuerySelect, Set};
+pub type StorageBlobs = Entity;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+// implement your read-oriented logic here
+impl Model {
+    /// 按内容摘要查找文件
+    pub async fn find_by_hash<C>(db: &C, content_hash: &str) -> Result<Option<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::find()
+            .filter(Column::ContentHash.eq(content_hash))
+            .one(db)
+            .await
+    }
+
+    /// 按内容摘要查找文件并锁定该行，直到事务结束
+    pub async fn find_by_hash_for_update<C>(
+        db: &C,
+        content_hash: &str,
+    ) -> Result<Option<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::find()
+            .filter(Column::ContentHash.eq(content_hash))
+            .lock_exclusive()
+            .one(db)
+            .await
+    }
+
+    /// 登记新文件并记录第一个引用；
+    /// 同时上传相同内容时另一个请求可能已经登记，这时不插入并返回 `false`
+    pub async fn create_referenced<C>(
+        db: &C,
+        content_hash: &str,
+        file_path: &str,
+        file_size: i64,
+    ) -> Result<bool, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let inserted = Entity::insert(ActiveModel {
+            content_hash: Set(content_hash.to_string()),
+            file_path: Set(file_path.to_string()),
+            file_size: Set(file_size),
+            ref_count: Set(1),
+            ..Default::default()
+        })
+        .on_conflict(
+            OnConflict::column(Column::ContentHash)
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec_without_returning(db)
+        .await?;
+        Ok(inserted > 0)
+    }
+
+    /// 增加一个引用；记录已被删除（最后一个引用刚刚释放）时返回错误，
+    /// 避免引用一个即将被删除的文件
+    pub async fn acquire<C>(db: &C, content_hash: &str) -> Result<(), DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let result = Entity::update_many()
+            .col_expr(Column::RefCount, Expr::col(Column::RefCount).add(1))
+            .filter(Column::ContentHash.eq(content_hash))
+            .exec(db)
+            .await?;
+        if result.rows_affected == 0 {
+            return Err(DbErr::RecordNotUpdated);
+        }
+        Ok(())
+    }
+
+    /// 减少一个引用；没有引用时删除记录并返回它，由调用方删除文件
+    pub async fn release<C>(db: &C, content_hash: &str) -> Result<Option<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::update_many()
+            .col_expr(Column::RefCount, Expr::col(Column::RefCount).sub(1))
+            .filter(Column::ContentHash.eq(content_hash))
+            .filter(Column::RefCount.gt(0))
+            .exec(db)
+            .await?;
+
+        let Some(blob) = Self::find_by_hash(db, content_hash).await? else {
+            return Ok(None);
+        };
+        if blob.ref_count > 0 {
+            return Ok(None);
+        }
+        let result = Entity::delete_many()
+            .filter(Column::Id.eq(blob.id))
+            .filter(Column::RefCount.lte(0))
+            .exec(db)
+            .await?;
+        Ok((result.rows_affected > 0).then_some(blob))
+    }
+}
+
+// implement your write-oriented logic here
+impl ActiveModel {}
+
+// implement your custom finders, selectors oriented logic here
+impl Entity {}
@@ -174,6 +174,38 @@ impl Model {
             .count(db)
             .await?)
     }
+
+    /// 同步用户在受管理组中的成员关系：加入 `desired` 中的组，
+    /// 退出 `managed` 中但不在 `desired` 中的组，其他组不受影响
+    ///
+    /// # Errors
+    ///
+    /// When database query fails
+    pub async fn sync_memberships(
+        db: &DatabaseConnection,
+        user_id: i32,
+        managed: &[i32],
+        desired: &[i32],
+    ) -> ModelResult<()> {
+        let current: Vec<i32> = Self::get_user_groups(db, user_id)
+            .await?
+            .into_iter()
+            .map(|group| group.id)
+            .collect();
+
+        for group_id in desired {
+            if !current.contains(group_id) {
+                Self::add_user(db, *group_id, user_id).await?;
+            }
+        }
+        for group_id in &current {
+            if managed.contains(group_id) && !desired.contains(group_id) {
+                Self::remove_user(db, *group_id, user_id).await?;
+            }
+        }
+
+        Ok(())
+    }
 }
 
 // implement your write-oriented logic here
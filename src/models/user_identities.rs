@@ -0,0 +1,93 @@
+pub use super::_entities::user_identities::{ActiveModel, Column, Entity, Model};
+use sea_orm::entity::prelude::*;
+use sea_orm::Set;
+pub type UserIdentities = Entity;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+// implement your read-oriented logic here
+impl Model {
+    /// 根据身份提供方和 subject 查找已关联的身份
+    pub async fn find_by_subject<C>(
+        db: &C,
+        issuer: &str,
+        subject: &str,
+    ) -> Result<Option<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::find()
+            .filter(Column::Issuer.eq(issuer))
+            .filter(Column::Subject.eq(subject))
+            .one(db)
+            .await
+    }
+
+    /// 将外部身份关联到本地用户
+    pub async fn link<C>(
+        db: &C,
+        user_id: i32,
+        issuer: &str,
+        subject: &str,
+        email: Option<String>,
+    ) -> Result<Model, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        ActiveModel {
+            user_id: Set(user_id),
+            issuer: Set(issuer.to_string()),
+            subject: Set(subject.to_string()),
+            email: Set(email),
+            last_login_at: Set(Some(chrono::Utc::now().into())),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+    }
+
+    /// 记录登录时间，同时更新身份提供方返回的最新邮箱
+    pub async fn record_login<C>(self, db: &C, email: Option<String>) -> Result<Model, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut item: ActiveModel = self.into();
+        if email.is_some() {
+            item.email = Set(email);
+        }
+        item.last_login_at = Set(Some(chrono::Utc::now().into()));
+        item.update(db).await
+    }
+
+    /// 删除用户关联的所有外部身份（删除用户时调用）
+    pub async fn delete_by_user<C>(db: &C, user_id: i32) -> Result<u64, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let result = Entity::delete_many()
+            .filter(Column::UserId.eq(user_id))
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+}
+
+// implement your write-oriented logic here
+impl ActiveModel {}
+
+// implement your custom finders, selectors oriented logic here
+impl Entity {}
@@ -0,0 +1,281 @@
+pub use super::_entities::user_sessions::{ActiveModel, Column, Entity, Model};
+use super::personal_access_tokens::hash_token;
+use sea_orm::entity::prelude::*;
+use sea_orm::{QueryOrder, Set};
+pub type UserSessions = Entity;
+
+/// JWT 中保存会话 ID 的字段名
+pub const SESSION_CLAIM: &str = "sid";
+
+/// 刷新令牌明文前缀
+pub const REFRESH_TOKEN_PREFIX: &str = "qcr_";
+
+/// 刷新令牌有效期（天），每次刷新后重新计算
+pub const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+
+/// 距上次记录超过该秒数才更新 `last_used_at`，避免每个请求都写库
+const LAST_USED_THROTTLE_SECS: i64 = 60;
+
+/// 登录设备信息
+#[derive(Debug, Clone, Default)]
+pub struct DeviceInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            let mut this = self;
+            this.pid = sea_orm::ActiveValue::Set(Uuid::new_v4());
+            Ok(this)
+        } else if self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+/// 生成新的刷新令牌明文
+#[must_use]
+pub fn generate_refresh_token() -> String {
+    format!(
+        "{REFRESH_TOKEN_PREFIX}{}{}",
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    )
+}
+
+fn refresh_expires_at() -> DateTimeWithTimeZone {
+    (chrono::Utc::now() + chrono::Duration::days(REFRESH_TOKEN_TTL_DAYS)).into()
+}
+
+// implement your read-oriented logic here
+impl Model {
+    /// 会话当前是否可用（未吊销、刷新令牌未过期）
+    #[must_use]
+    pub fn is_active(&self) -> bool {
+        self.revoked_at.is_none() && self.expires_at > chrono::Utc::now()
+    }
+
+    /// 创建会话，返回记录和只展示一次的刷新令牌
+    pub async fn create_for_user<C>(
+        db: &C,
+        user_id: i32,
+        device: DeviceInfo,
+    ) -> Result<(Model, String), DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let refresh_token = generate_refresh_token();
+        let now = chrono::Utc::now();
+
+        let item = ActiveModel {
+            user_id: Set(user_id),
+            refresh_token_hash: Set(hash_token(&refresh_token)),
+            user_agent: Set(device.user_agent),
+            ip_address: Set(device.ip_address),
+            last_used_at: Set(Some(now.into())),
+            expires_at: Set(refresh_expires_at()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+
+        Ok((item, refresh_token))
+    }
+
+    /// 根据会话 ID（JWT 中的 `sid`）查找可用的会话
+    pub async fn find_active_by_pid<C>(db: &C, pid: &str) -> Result<Option<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let Ok(pid) = Uuid::parse_str(pid) else {
+            return Ok(None);
+        };
+        let item = Entity::find().filter(Column::Pid.eq(pid)).one(db).await?;
+        Ok(item.filter(Model::is_active))
+    }
+
+    /// 获取用户的所有可用会话（按最近使用倒序）
+    pub async fn find_active_by_user<C>(db: &C, user_id: i32) -> Result<Vec<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let items = Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .filter(Column::RevokedAt.is_null())
+            .order_by_desc(Column::LastUsedAt)
+            .order_by_desc(Column::CreatedAt)
+            .all(db)
+            .await?;
+        Ok(items.into_iter().filter(Model::is_active).collect())
+    }
+
+    /// 获取属于指定用户的会话
+    pub async fn find_for_user<C>(db: &C, user_id: i32, id: i32) -> Result<Option<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::find_by_id(id)
+            .filter(Column::UserId.eq(user_id))
+            .one(db)
+            .await
+    }
+
+    /// 使用刷新令牌换取新的刷新令牌（旧令牌立即失效）
+    ///
+    /// 已轮换掉的旧令牌再次出现说明令牌可能被盗用，此时吊销整个会话并返回 `None`
+    pub async fn rotate<C>(
+        db: &C,
+        refresh_token: &str,
+        device: DeviceInfo,
+    ) -> Result<Option<(Model, String)>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let hash = hash_token(refresh_token);
+
+        let Some(session) = Entity::find()
+            .filter(Column::RefreshTokenHash.eq(&hash))
+            .one(db)
+            .await?
+        else {
+            let reused = Entity::find()
+                .filter(Column::PreviousTokenHash.eq(&hash))
+                .one(db)
+                .await?;
+            if let Some(session) = reused {
+                tracing::warn!(
+                    session_id = session.id,
+                    user_id = session.user_id,
+                    "refresh token reuse detected, revoking session"
+                );
+                session.revoke(db).await?;
+            }
+            return Ok(None);
+        };
+
+        if !session.is_active() {
+            return Ok(None);
+        }
+
+        let id = session.id;
+        let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+        let new_token = generate_refresh_token();
+        let mut item: ActiveModel = session.into();
+        item.previous_token_hash = Set(Some(hash.clone()));
+        item.refresh_token_hash = Set(hash_token(&new_token));
+        item.last_used_at = Set(Some(now));
+        item.updated_at = Set(now);
+        item.expires_at = Set(refresh_expires_at());
+        if device.user_agent.is_some() {
+            item.user_agent = Set(device.user_agent);
+        }
+        if device.ip_address.is_some() {
+            item.ip_address = Set(device.ip_address);
+        }
+
+        // 只有令牌仍是当前令牌时才轮换，同一令牌的并发请求只有一个能成功，
+        // 其余按重复使用处理
+        let result = Entity::update_many()
+            .set(item)
+            .filter(Column::Id.eq(id))
+            .filter(Column::RefreshTokenHash.eq(&hash))
+            .exec(db)
+            .await?;
+        let Some(session) = Entity::find_by_id(id).one(db).await? else {
+            return Ok(None);
+        };
+        if result.rows_affected == 0 {
+            tracing::warn!(
+                session_id = session.id,
+                user_id = session.user_id,
+                "concurrent refresh token reuse detected, revoking session"
+            );
+            session.revoke(db).await?;
+            return Ok(None);
+        }
+
+        Ok(Some((session, new_token)))
+    }
+
+    /// 记录会话使用时间（一分钟内重复使用不更新）
+    pub async fn touch<C>(&self, db: &C) -> Result<(), DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let now = chrono::Utc::now();
+        let recently_used = self.last_used_at.is_some_and(|last_used_at| {
+            now.signed_duration_since(last_used_at).num_seconds() < LAST_USED_THROTTLE_SECS
+        });
+        if recently_used {
+            return Ok(());
+        }
+
+        // 直接更新单列，不触发 updated_at
+        Entity::update_many()
+            .col_expr(
+                Column::LastUsedAt,
+                sea_orm::sea_query::Expr::value(DateTimeWithTimeZone::from(now)),
+            )
+            .filter(Column::Id.eq(self.id))
+            .exec(db)
+            .await?;
+        Ok(())
+    }
+
+    /// 吊销会话（退出登录）
+    pub async fn revoke<C>(self, db: &C) -> Result<Model, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if self.revoked_at.is_some() {
+            return Ok(self);
+        }
+        let mut item: ActiveModel = self.into();
+        item.revoked_at = Set(Some(chrono::Utc::now().into()));
+        item.update(db).await
+    }
+
+    /// 吊销用户的所有会话（退出所有设备、修改密码或权限后调用），返回吊销数量
+    pub async fn revoke_all_for_user<C>(db: &C, user_id: i32) -> Result<u64, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let now = DateTimeWithTimeZone::from(chrono::Utc::now());
+        let result = Entity::update_many()
+            .col_expr(Column::RevokedAt, sea_orm::sea_query::Expr::value(now))
+            .col_expr(Column::UpdatedAt, sea_orm::sea_query::Expr::value(now))
+            .filter(Column::UserId.eq(user_id))
+            .filter(Column::RevokedAt.is_null())
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+
+    /// 删除用户的所有会话（删除用户时调用）
+    pub async fn delete_by_user<C>(db: &C, user_id: i32) -> Result<u64, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let result = Entity::delete_many()
+            .filter(Column::UserId.eq(user_id))
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+}
+
+// implement your write-oriented logic here
+impl ActiveModel {}
+
+// implement your custom finders, selectors oriented logic here
+impl Entity {}
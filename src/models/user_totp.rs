@@ -0,0 +1,162 @@
+pub use super::_entities::user_totp::{ActiveModel, Column, Entity, Model};
+use super::personal_access_tokens::hash_token;
+use crate::services::totp;
+use sea_orm::entity::prelude::*;
+use sea_orm::{IntoActiveModel, Set, TryIntoModel};
+pub type UserTotp = Entity;
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    u64::try_from(chrono::Utc::now().timestamp()).unwrap_or_default()
+}
+
+fn hash_recovery_codes(codes: &[String]) -> String {
+    let hashes: Vec<String> = codes
+        .iter()
+        .map(|code| hash_token(&totp::normalize_recovery_code(code)))
+        .collect();
+    serde_json::to_string(&hashes).unwrap_or_else(|_| "[]".to_string())
+}
+
+// implement your read-oriented logic here
+impl Model {
+    /// 获取用户的两步验证记录（可能尚未完成绑定）
+    pub async fn find_by_user<C>(db: &C, user_id: i32) -> Result<Option<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .one(db)
+            .await
+    }
+
+    /// 用户是否已启用两步验证
+    pub async fn is_enabled_for_user<C>(db: &C, user_id: i32) -> Result<bool, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Ok(Self::find_by_user(db, user_id)
+            .await?
+            .is_some_and(|item| item.is_enabled()))
+    }
+
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled_at.is_some()
+    }
+
+    /// 剩余可用的恢复码数量
+    #[must_use]
+    pub fn recovery_codes_remaining(&self) -> usize {
+        self.recovery_code_hashes().len()
+    }
+
+    fn recovery_code_hashes(&self) -> Vec<String> {
+        serde_json::from_str(&self.recovery_codes).unwrap_or_default()
+    }
+
+    /// 开始绑定：生成新的密钥，覆盖之前未完成的绑定
+    pub async fn begin_setup<C>(db: &C, user_id: i32) -> Result<Model, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let mut item = match Self::find_by_user(db, user_id).await? {
+            Some(existing) => existing.into_active_model(),
+            None => ActiveModel {
+                user_id: Set(user_id),
+                ..Default::default()
+            },
+        };
+        item.secret = Set(totp::generate_secret());
+        item.enabled_at = Set(None);
+        item.recovery_codes = Set("[]".to_string());
+        item.last_used_step = Set(None);
+        item.save(db).await?.try_into_model()
+    }
+
+    /// 使用验证器应用中的验证码完成绑定，返回只展示一次的恢复码
+    ///
+    /// 验证码错误时返回 `None`
+    pub async fn enable<C>(self, db: &C, code: &str) -> Result<Option<(Model, Vec<String>)>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let Some(step) = totp::verify(&self.secret, code, unix_now(), None) else {
+            return Ok(None);
+        };
+
+        let codes = totp::generate_recovery_codes();
+        let mut item = self.into_active_model();
+        item.enabled_at = Set(Some(chrono::Utc::now().into()));
+        item.recovery_codes = Set(hash_recovery_codes(&codes));
+        item.last_used_step = Set(i64::try_from(step).ok());
+        let item = item.update(db).await?;
+
+        Ok(Some((item, codes)))
+    }
+
+    /// 校验验证码或恢复码（恢复码使用后作废），返回是否通过
+    pub async fn verify_code<C>(self, db: &C, code: &str) -> Result<bool, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !self.is_enabled() {
+            return Ok(false);
+        }
+
+        let last_used_step = self.last_used_step.and_then(|s| u64::try_from(s).ok());
+        if let Some(step) = totp::verify(&self.secret, code, unix_now(), last_used_step) {
+            let mut item = self.into_active_model();
+            item.last_used_step = Set(i64::try_from(step).ok());
+            item.update(db).await?;
+            return Ok(true);
+        }
+
+        let hash = hash_token(&totp::normalize_recovery_code(code));
+        let mut hashes = self.recovery_code_hashes();
+        let Some(index) = hashes.iter().position(|h| *h == hash) else {
+            return Ok(false);
+        };
+        hashes.remove(index);
+
+        let mut item = self.into_active_model();
+        item.recovery_codes =
+            Set(serde_json::to_string(&hashes).unwrap_or_else(|_| "[]".to_string()));
+        item.update(db).await?;
+        Ok(true)
+    }
+
+    /// 重新生成恢复码（旧恢复码全部作废）
+    pub async fn regenerate_recovery_codes<C>(self, db: &C) -> Result<Vec<String>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let codes = totp::generate_recovery_codes();
+        let mut item = self.into_active_model();
+        item.recovery_codes = Set(hash_recovery_codes(&codes));
+        item.update(db).await?;
+        Ok(codes)
+    }
+}
+
+// implement your write-oriented logic here
+impl ActiveModel {}
+
+// implement your custom finders, selectors oriented logic here
+impl Entity {}
@@ -6,6 +6,9 @@ use serde::{Deserialize, Serialize};
 use serde_json::Map;
 use uuid::Uuid;
 
+use super::personal_access_tokens;
+use super::user_sessions::{self, SESSION_CLAIM};
+
 pub use super::_entities::users::{self, ActiveModel, Entity, Model};
 
 pub const MAGIC_LINK_LENGTH: i8 = 32;
@@ -265,6 +268,24 @@ impl Model {
             .generate_token(expiration, self.pid.to_string(), Map::new())
             .map_err(ModelError::from)
     }
+
+    /// 生成绑定登录会话的访问令牌，会话吊销后令牌随即失效
+    ///
+    /// # Errors
+    ///
+    /// when could not convert user claims to jwt token
+    pub fn generate_session_jwt(
+        &self,
+        secret: &str,
+        expiration: u64,
+        session_pid: &Uuid,
+    ) -> ModelResult<String> {
+        let mut claims = Map::new();
+        claims.insert(SESSION_CLAIM.to_string(), session_pid.to_string().into());
+        jwt::JWT::new(secret)
+            .generate_token(expiration, self.pid.to_string(), claims)
+            .map_err(ModelError::from)
+    }
 }
 
 impl ActiveModel {
@@ -336,7 +357,12 @@ impl ActiveModel {
             ActiveValue::set(hash::hash_password(password).map_err(|e| ModelError::Any(e.into()))?);
         self.reset_token = ActiveValue::Set(None);
         self.reset_sent_at = ActiveValue::Set(None);
-        self.update(db).await.map_err(ModelError::from)
+        let user = self.update(db).await.map_err(ModelError::from)?;
+
+        // 修改密码后所有设备都需要重新登录，个人访问令牌也一并吊销
+        user_sessions::Model::revoke_all_for_user(db, user.id).await?;
+        personal_access_tokens::Model::revoke_all_for_user(db, user.id).await?;
+        Ok(user)
     }
 
     /// Creates a magic link token for passwordless authentication.
@@ -445,11 +471,24 @@ impl Model {
             .await?
             .ok_or_else(|| ModelError::EntityNotFound)?;
 
+        let role_changed = user.is_staff != is_staff || user.is_superuser != is_superuser;
+        let demoted = (user.is_staff && !is_staff) || (user.is_superuser && !is_superuser);
+
         let mut active_user: users::ActiveModel = user.into();
         active_user.is_staff = ActiveValue::Set(is_staff);
         active_user.is_superuser = ActiveValue::Set(is_superuser);
 
-        active_user.update(db).await.map_err(ModelError::from)
+        let user = active_user.update(db).await.map_err(ModelError::from)?;
+
+        // 权限变更后已签发的令牌需要重新登录获取
+        if role_changed {
+            user_sessions::Model::revoke_all_for_user(db, user.id).await?;
+        }
+        // 降低权限后按原权限创建的个人访问令牌不再可用
+        if demoted {
+            personal_access_tokens::Model::revoke_all_for_user(db, user.id).await?;
+        }
+        Ok(user)
     }
 
     /// 统计总用户数
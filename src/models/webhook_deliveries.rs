@@ -0,0 +1,101 @@
+pub use super::_entities::webhook_deliveries::{ActiveModel, Column, Entity, Model};
+use sea_orm::entity::prelude::*;
+use sea_orm::{QueryOrder, QuerySelect, Set};
+pub type WebhookDeliveries = Entity;
+
+/// 等待投递或等待重试
+pub const STATUS_PENDING: &str = "pending";
+/// 对方返回 2xx
+pub const STATUS_SUCCEEDED: &str = "succeeded";
+/// 重试次数用尽仍未成功
+pub const STATUS_FAILED: &str = "failed";
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+// implement your read-oriented logic here
+impl Model {
+    /// 创建一条待投递记录
+    pub async fn create<C>(
+        db: &C,
+        endpoint_id: i32,
+        event: &str,
+        payload: String,
+    ) -> Result<Model, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        ActiveModel {
+            endpoint_id: Set(endpoint_id),
+            event: Set(event.to_string()),
+            payload: Set(payload),
+            status: Set(STATUS_PENDING.to_string()),
+            attempts: Set(0),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+    }
+
+    /// 获取地址最近的投递记录（新的在前）
+    pub async fn find_by_endpoint<C>(
+        db: &C,
+        endpoint_id: i32,
+        limit: u64,
+    ) -> Result<Vec<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::find()
+            .filter(Column::EndpointId.eq(endpoint_id))
+            .order_by_desc(Column::Id)
+            .limit(limit)
+            .all(db)
+            .await
+    }
+
+    /// 获取已到重试时间的投递
+    pub async fn find_due<C>(db: &C) -> Result<Vec<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let now: DateTimeWithTimeZone = chrono::Utc::now().into();
+        Entity::find()
+            .filter(Column::Status.eq(STATUS_PENDING))
+            .filter(Column::NextAttemptAt.lte(now))
+            .order_by_asc(Column::NextAttemptAt)
+            .all(db)
+            .await
+    }
+
+    /// 删除地址的所有投递记录
+    pub async fn delete_by_endpoint<C>(db: &C, endpoint_id: i32) -> Result<u64, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        let result = Entity::delete_many()
+            .filter(Column::EndpointId.eq(endpoint_id))
+            .exec(db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+}
+
+// implement your write-oriented logic here
+impl ActiveModel {}
+
+// implement your custom finders, selectors oriented logic here
+impl Entity {}
@@ -0,0 +1,103 @@
+pub use super::_entities::webhook_endpoints::{ActiveModel, Column, Entity, Model};
+use sea_orm::entity::prelude::*;
+use sea_orm::QueryOrder;
+pub type WebhookEndpoints = Entity;
+
+/// 媒体上传完成
+pub const EVENT_MEDIA_UPLOADED: &str = "media.uploaded";
+/// 媒体处理完成（二维码等附属资源已生成）
+pub const EVENT_MEDIA_PROCESSED: &str = "media.processed";
+/// 媒体已公开（手动或定时发布）
+pub const EVENT_MEDIA_PUBLISHED: &str = "media.published";
+/// 媒体文件已替换（包括回滚到历史版本）
+pub const EVENT_MEDIA_REPLACED: &str = "media.replaced";
+/// 媒体已删除（移入回收站或永久删除，`data.permanent` 区分）
+pub const EVENT_MEDIA_DELETED: &str = "media.deleted";
+
+/// 支持订阅的事件
+pub const EVENTS: &[&str] = &[
+    EVENT_MEDIA_UPLOADED,
+    EVENT_MEDIA_PROCESSED,
+    EVENT_MEDIA_PUBLISHED,
+    EVENT_MEDIA_REPLACED,
+    EVENT_MEDIA_DELETED,
+];
+
+#[async_trait::async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(self, _db: &C, insert: bool) -> std::result::Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if !insert && self.updated_at.is_unchanged() {
+            let mut this = self;
+            this.updated_at = sea_orm::ActiveValue::Set(chrono::Utc::now().into());
+            Ok(this)
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+// implement your read-oriented logic here
+impl Model {
+    /// 获取用户的所有 Webhook 地址
+    pub async fn find_by_user<C>(db: &C, user_id: i32) -> Result<Vec<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .order_by_asc(Column::Id)
+            .all(db)
+            .await
+    }
+
+    /// 获取属于指定用户的 Webhook 地址
+    pub async fn find_for_user<C>(db: &C, user_id: i32, id: i32) -> Result<Option<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Entity::find_by_id(id)
+            .filter(Column::UserId.eq(user_id))
+            .one(db)
+            .await
+    }
+
+    /// 获取订阅了指定事件的有效地址
+    pub async fn find_subscribers<C>(db: &C, user_id: i32, event: &str) -> Result<Vec<Model>, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        Ok(Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .filter(Column::IsActive.eq(true))
+            .all(db)
+            .await?
+            .into_iter()
+            .filter(|endpoint| endpoint.subscribes_to(event))
+            .collect())
+    }
+
+    /// 订阅的事件列表
+    #[must_use]
+    pub fn event_list(&self) -> Vec<String> {
+        self.events
+            .split(',')
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[must_use]
+    pub fn subscribes_to(&self, event: &str) -> bool {
+        self.events.split(',').any(|e| e.trim() == event)
+    }
+}
+
+// implement your write-oriented logic here
+impl ActiveModel {}
+
+// implement your custom finders, selectors oriented logic here
+impl Entity {}
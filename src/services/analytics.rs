@@ -0,0 +1,475 @@
+use bytes::Bytes;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use futures_util::Stream;
+use loco_rs::prelude::*;
+use sea_orm::{DatabaseConnection, QueryOrder, QuerySelect, Select};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+
+use crate::models::_entities::{books, chapters, medias};
+use crate::models::media_plays::{self, PlayStats};
+use crate::services::podcast_feed::order_by_chapter_tree;
+
+/// 每页导出的媒体数量（逐本书读取，每次只汇总一页的播放事件）
+const EXPORT_PAGE_SIZE: usize = 200;
+
+/// 导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    /// 解析导出格式参数
+    ///
+    /// # Errors
+    ///
+    /// Will return error if the format is not `csv` or `json`
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.to_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            other => Err(Error::BadRequest(format!(
+                "不支持的导出格式: {other}（可选 csv, json）"
+            ))),
+        }
+    }
+
+    #[must_use]
+    pub const fn content_type(self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Json => "application/json",
+        }
+    }
+
+    #[must_use]
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// 导出过滤条件
+#[derive(Debug, Clone, Default)]
+pub struct ExportFilter {
+    /// 仅导出该用户的媒体（CLI 任务可以不指定，导出全部）
+    pub user_id: Option<i32>,
+    pub book_id: Option<i32>,
+    /// 起始时间（包含）
+    pub from: Option<DateTime<Utc>>,
+    /// 结束时间（不包含）
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl ExportFilter {
+    /// 解析 `YYYY-MM-DD` 格式的日期范围，结束日期包含当天
+    ///
+    /// # Errors
+    ///
+    /// Will return error if a date cannot be parsed or the range is reversed
+    pub fn with_date_range(mut self, from: Option<&str>, to: Option<&str>) -> Result<Self> {
+        let parse = |value: &str| {
+            NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+                .map_err(|_| Error::BadRequest(format!("无效的日期: {value}（格式 YYYY-MM-DD）")))
+        };
+
+        if let Some(from) = from.filter(|v| !v.trim().is_empty()) {
+            let date = parse(from)?;
+            self.from = date.and_hms_opt(0, 0, 0).map(|dt| dt.and_utc());
+        }
+        if let Some(to) = to.filter(|v| !v.trim().is_empty()) {
+            let date = parse(to)? + Duration::days(1);
+            self.to = date.and_hms_opt(0, 0, 0).map(|dt| dt.and_utc());
+        }
+
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if from >= to {
+                return Err(Error::BadRequest("开始日期不能晚于结束日期".to_string()));
+            }
+        }
+
+        Ok(self)
+    }
+}
+
+/// 单个媒体的统计导出行
+#[derive(Debug, Clone, Serialize)]
+pub struct MediaAnalyticsRow {
+    pub media_id: i32,
+    pub title: String,
+    pub file_type: String,
+    pub book_id: i32,
+    pub book_title: String,
+    pub chapter_id: Option<i32>,
+    pub chapter_path: Option<String>,
+    pub duration: Option<i32>,
+    /// 媒体累计播放次数（不受时间范围限制）
+    pub total_plays: i32,
+    /// 时间范围内的播放次数
+    pub plays: i64,
+    /// 时间范围内的完播次数
+    pub completions: i64,
+    /// 完播率（0-1）
+    pub completion_rate: f64,
+    /// 时间范围内按完播估算的收听时长（秒）
+    pub listened_seconds: i64,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+const CSV_HEADER: &str = "media_id,title,file_type,book_id,book_title,chapter_id,chapter_path,duration,total_plays,plays,completions,completion_rate,listened_seconds,created_at\n";
+
+impl MediaAnalyticsRow {
+    #[allow(clippy::cast_precision_loss)]
+    fn new(
+        media: medias::Model,
+        book_title: String,
+        chapter_path: Option<String>,
+        stats: PlayStats,
+    ) -> Self {
+        let completion_rate = if stats.plays > 0 {
+            (stats.completions as f64 / stats.plays as f64).min(1.0)
+        } else {
+            0.0
+        };
+        let listened_seconds = stats.completions * i64::from(media.duration.unwrap_or(0));
+
+        Self {
+            media_id: media.id,
+            title: media.title,
+            file_type: media.file_type,
+            book_id: media.book_id,
+            book_title,
+            chapter_id: media.chapter_id,
+            chapter_path,
+            duration: media.duration,
+            total_plays: media.play_count,
+            plays: stats.plays,
+            completions: stats.completions,
+            completion_rate,
+            listened_seconds,
+            created_at: media.created_at.into(),
+        }
+    }
+
+    /// 转换为一行 CSV（包含换行符）
+    #[must_use]
+    pub fn to_csv_line(&self) -> String {
+        let fields = [
+            self.media_id.to_string(),
+            csv_escape(&self.title),
+            csv_escape(&self.file_type),
+            self.book_id.to_string(),
+            csv_escape(&self.book_title),
+            self.chapter_id.map(|id| id.to_string()).unwrap_or_default(),
+            self.chapter_path
+                .as_deref()
+                .map(csv_escape)
+                .unwrap_or_default(),
+            self.duration.map(|d| d.to_string()).unwrap_or_default(),
+            self.total_plays.to_string(),
+            self.plays.to_string(),
+            self.completions.to_string(),
+            format!("{:.4}", self.completion_rate),
+            self.listened_seconds.to_string(),
+            self.created_at.to_rfc3339(),
+        ];
+
+        let mut line = fields.join(",");
+        line.push('\n');
+        line
+    }
+}
+
+/// 按 RFC 4180 转义 CSV 字段
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// 统计数据导出器：逐本书读取媒体，分页汇总播放事件
+pub struct AnalyticsExporter {
+    db: DatabaseConnection,
+    filter: ExportFilter,
+    book_titles: HashMap<i32, String>,
+    book_chapters: HashMap<i32, HashMap<i32, chapters::Model>>,
+    /// 尚未导出的书籍，首次读取时加载
+    pending_books: Option<VecDeque<i32>>,
+    /// 当前书籍中尚未导出的媒体，已按章节树排好顺序
+    pending_medias: VecDeque<medias::Model>,
+}
+
+impl AnalyticsExporter {
+    #[must_use]
+    pub fn new(db: DatabaseConnection, filter: ExportFilter) -> Self {
+        Self {
+            db,
+            filter,
+            book_titles: HashMap::new(),
+            book_chapters: HashMap::new(),
+            pending_books: None,
+            pending_medias: VecDeque::new(),
+        }
+    }
+
+    /// 符合过滤条件的未删除媒体
+    fn medias_query(&self) -> Select<medias::Entity> {
+        let mut query = medias::Entity::find().filter(medias::Column::DeletedAt.is_null());
+        if let Some(user_id) = self.filter.user_id {
+            query = query.filter(medias::Column::UserId.eq(user_id));
+        }
+        if let Some(book_id) = self.filter.book_id {
+            query = query.filter(medias::Column::BookId.eq(book_id));
+        }
+        query
+    }
+
+    /// 下一本有媒体需要导出的书籍
+    async fn next_book(&mut self) -> Result<Option<i32>> {
+        if self.pending_books.is_none() {
+            let book_ids: Vec<i32> = self
+                .medias_query()
+                .select_only()
+                .column(medias::Column::BookId)
+                .distinct()
+                .order_by_asc(medias::Column::BookId)
+                .into_tuple()
+                .all(&self.db)
+                .await?;
+            self.pending_books = Some(book_ids.into());
+        }
+        Ok(self.pending_books.as_mut().and_then(VecDeque::pop_front))
+    }
+
+    /// 读取书籍中的媒体，按章节树的位置和章节内的排序排列，与播放列表的顺序一致
+    async fn load_book_medias(&mut self, book_id: i32) -> Result<()> {
+        let book_chapters = chapters::Entity::find()
+            .filter(chapters::Column::BookId.eq(book_id))
+            .order_by_asc(chapters::Column::SortOrder)
+            .order_by_asc(chapters::Column::Id)
+            .all(&self.db)
+            .await?;
+        let medias = self
+            .medias_query()
+            .filter(medias::Column::BookId.eq(book_id))
+            .order_by_asc(medias::Column::SortOrder)
+            .order_by_asc(medias::Column::Id)
+            .all(&self.db)
+            .await?;
+
+        self.pending_medias = order_by_chapter_tree(&book_chapters, medias).into();
+        self.book_chapters.insert(
+            book_id,
+            book_chapters
+                .into_iter()
+                .map(|chapter| (chapter.id, chapter))
+                .collect(),
+        );
+        Ok(())
+    }
+
+    /// 读取下一页导出数据，返回空列表表示已经读完
+    ///
+    /// # Errors
+    ///
+    /// When database query fails
+    pub async fn fetch_page(&mut self) -> Result<Vec<MediaAnalyticsRow>> {
+        while self.pending_medias.is_empty() {
+            let Some(book_id) = self.next_book().await? else {
+                return Ok(Vec::new());
+            };
+            self.load_book_medias(book_id).await?;
+        }
+
+        let page_size = self.pending_medias.len().min(EXPORT_PAGE_SIZE);
+        let medias_page: Vec<medias::Model> = self.pending_medias.drain(..page_size).collect();
+
+        let media_ids: Vec<i32> = medias_page.iter().map(|m| m.id).collect();
+        let stats = media_plays::Model::stats_by_media(
+            &self.db,
+            &media_ids,
+            self.filter.from.map(Into::into),
+            self.filter.to.map(Into::into),
+        )
+        .await?;
+
+        let mut rows = Vec::with_capacity(medias_page.len());
+        for media in medias_page {
+            let book_title = self.book_title(media.book_id).await?;
+            let chapter_path = match media.chapter_id {
+                Some(chapter_id) => self.chapter_path(media.book_id, chapter_id).await?,
+                None => None,
+            };
+            let media_stats = stats.get(&media.id).copied().unwrap_or_default();
+            rows.push(MediaAnalyticsRow::new(
+                media,
+                book_title,
+                chapter_path,
+                media_stats,
+            ));
+        }
+
+        Ok(rows)
+    }
+
+    /// 获取书籍标题（带缓存）
+    async fn book_title(&mut self, book_id: i32) -> Result<String> {
+        if let Some(title) = self.book_titles.get(&book_id) {
+            return Ok(title.clone());
+        }
+
+        let title = books::Entity::find_by_id(book_id)
+            .one(&self.db)
+            .await?
+            .map(|book| book.title)
+            .unwrap_or_default();
+        self.book_titles.insert(book_id, title.clone());
+        Ok(title)
+    }
+
+    /// 根据章节 `path` 拼接完整的章节标题路径，如 "第一单元 / 第二课"
+    async fn chapter_path(&mut self, book_id: i32, chapter_id: i32) -> Result<Option<String>> {
+        if !self.book_chapters.contains_key(&book_id) {
+            let chapters = chapters::Entity::find()
+                .filter(chapters::Column::BookId.eq(book_id))
+                .all(&self.db)
+                .await?
+                .into_iter()
+                .map(|chapter| (chapter.id, chapter))
+                .collect();
+            self.book_chapters.insert(book_id, chapters);
+        }
+
+        let Some(chapters) = self.book_chapters.get(&book_id) else {
+            return Ok(None);
+        };
+        let Some(chapter) = chapters.get(&chapter_id) else {
+            return Ok(None);
+        };
+
+        let ids: Vec<i32> = chapter.path.as_deref().map_or_else(
+            || vec![chapter_id],
+            |path| path.split('/').filter_map(|id| id.parse().ok()).collect(),
+        );
+
+        let titles: Vec<&str> = ids
+            .iter()
+            .filter_map(|id| chapters.get(id).map(|c| c.title.as_str()))
+            .collect();
+
+        Ok(Some(titles.join(" / ")))
+    }
+
+    /// 转换为字节流，供 HTTP 响应或文件写入逐块消费
+    pub fn into_stream(
+        self,
+        format: ExportFormat,
+    ) -> impl Stream<Item = std::result::Result<Bytes, std::io::Error>> + Send + 'static {
+        enum Stage {
+            Header,
+            Page,
+            Done,
+        }
+
+        futures_util::stream::unfold(
+            (self, Stage::Header, false),
+            move |(mut exporter, stage, mut has_rows)| async move {
+                match stage {
+                    Stage::Header => {
+                        let header = match format {
+                            ExportFormat::Csv => CSV_HEADER,
+                            ExportFormat::Json => "[",
+                        };
+                        Some((
+                            Ok(Bytes::from_static(header.as_bytes())),
+                            (exporter, Stage::Page, has_rows),
+                        ))
+                    }
+                    Stage::Page => match exporter.fetch_page().await {
+                        Ok(rows) if rows.is_empty() => {
+                            let footer = match format {
+                                ExportFormat::Csv => "",
+                                ExportFormat::Json => "]\n",
+                            };
+                            Some((
+                                Ok(Bytes::from_static(footer.as_bytes())),
+                                (exporter, Stage::Done, has_rows),
+                            ))
+                        }
+                        Ok(rows) => {
+                            let mut chunk = String::new();
+                            for row in &rows {
+                                match format {
+                                    ExportFormat::Csv => chunk.push_str(&row.to_csv_line()),
+                                    ExportFormat::Json => {
+                                        if has_rows {
+                                            chunk.push(',');
+                                        }
+                                        chunk.push_str(
+                                            &serde_json::to_string(row).unwrap_or_default(),
+                                        );
+                                    }
+                                }
+                                has_rows = true;
+                            }
+                            Some((Ok(Bytes::from(chunk)), (exporter, Stage::Page, has_rows)))
+                        }
+                        Err(e) => {
+                            tracing::error!("导出统计数据失败: {}", e);
+                            Some((
+                                Err(std::io::Error::other(e.to_string())),
+                                (exporter, Stage::Done, has_rows),
+                            ))
+                        }
+                    },
+                    Stage::Done => None,
+                }
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_export_format() {
+        assert_eq!(ExportFormat::parse("CSV").unwrap(), ExportFormat::Csv);
+        assert_eq!(ExportFormat::parse("json").unwrap(), ExportFormat::Json);
+        assert!(ExportFormat::parse("xlsx").is_err());
+    }
+
+    #[test]
+    fn test_csv_escape() {
+        assert_eq!(csv_escape("plain"), "plain");
+        assert_eq!(csv_escape("a,b"), "\"a,b\"");
+        assert_eq!(csv_escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_date_range_is_inclusive() {
+        let filter = ExportFilter::default()
+            .with_date_range(Some("2025-10-01"), Some("2025-10-31"))
+            .unwrap();
+
+        assert_eq!(
+            filter.from.unwrap().to_rfc3339(),
+            "2025-10-01T00:00:00+00:00"
+        );
+        assert_eq!(filter.to.unwrap().to_rfc3339(), "2025-11-01T00:00:00+00:00");
+
+        assert!(ExportFilter::default()
+            .with_date_range(Some("2025-10-31"), Some("2025-10-01"))
+            .is_err());
+        assert!(ExportFilter::default()
+            .with_date_range(Some("10/01/2025"), None)
+            .is_err());
+    }
+}
@@ -0,0 +1,434 @@
+//! 音频剪辑：从已有媒体的一段时间范围生成新媒体
+//!
+//! MP3 按帧复制原始数据，不重新编码，剪辑点落在所在的帧边界（每帧约 26 毫秒）；
+//! 其他格式解码后写成 16 位 PCM WAV，16 位 PCM 的 WAV 来源剪辑后数据不变。
+//! 剪辑由处理任务执行，新媒体有自己的访问令牌和二维码，结果记录在 `media_clips`。
+use loco_rs::prelude::*;
+use sea_orm::{QuerySelect, TransactionTrait};
+use std::io::{BufWriter, Write};
+use std::ops::ControlFlow;
+use std::path::Path;
+use symphonia::core::codecs::CODEC_TYPE_NULL;
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::units::TimeBase;
+use uuid::Uuid;
+
+use crate::models::_entities::{books, medias};
+use crate::models::media_clips::{self, METHOD_COPY, METHOD_REENCODE};
+use crate::models::webhook_endpoints::EVENT_MEDIA_UPLOADED;
+use crate::models::{media_versions, site_settings};
+use crate::services::audio_metadata::format_hint;
+use crate::services::audio_processing::{self, decode_pcm, WavWriter};
+use crate::services::content_store;
+use crate::services::qrcode::QRCODE_SERVICE;
+use crate::services::storage::{self, TempUpload, STORAGE_SERVICE};
+use crate::services::webhooks;
+use crate::workers::media_processing::{MediaProcessingArgs, MediaProcessingWorker, ProcessingJob};
+
+/// 剪辑的最短时长
+pub const MIN_CLIP_MS: i64 = 1000;
+
+/// 剪辑结果文件的信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClipOutput {
+    /// `copy` 或 `reencode`
+    pub method: &'static str,
+    pub mime_type: &'static str,
+    pub extension: &'static str,
+    pub duration_ms: u64,
+}
+
+/// 校验剪辑范围；`duration` 为原媒体时长（秒），未知时不检查上限
+pub fn validate_range(start_ms: i64, end_ms: i64, duration: Option<i32>) -> Result<()> {
+    if start_ms < 0 {
+        return Err(Error::BadRequest("开始时间不能为负数".to_string()));
+    }
+    if end_ms.saturating_sub(start_ms) < MIN_CLIP_MS {
+        return Err(Error::BadRequest(format!(
+            "结束时间必须比开始时间晚至少 {MIN_CLIP_MS} 毫秒"
+        )));
+    }
+    if let Some(duration) = duration.filter(|d| *d > 0) {
+        if start_ms >= i64::from(duration) * 1000 {
+            return Err(Error::BadRequest("开始时间超出媒体时长".to_string()));
+        }
+    }
+    Ok(())
+}
+
+fn format_timestamp(ms: i64) -> String {
+    let seconds = ms / 1000;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// 默认标题：原标题加时间范围
+#[must_use]
+pub fn default_title(title: &str, start_ms: i64, end_ms: i64) -> String {
+    format!(
+        "{title}（{}-{}）",
+        format_timestamp(start_ms),
+        format_timestamp(end_ms)
+    )
+}
+
+/// 能按帧复制的格式
+fn supports_frame_copy(mime_type: &str) -> bool {
+    matches!(mime_type, "audio/mpeg" | "audio/mp3")
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn timestamp_to_ms(time_base: TimeBase, ts: u64) -> u64 {
+    let time = time_base.calc_time(ts);
+    time.seconds * 1000 + (time.frac * 1000.0) as u64
+}
+
+/// 剪辑音频写入 `output`（CPU 密集，应在阻塞线程中调用）
+///
+/// # Errors
+///
+/// Will return error if the source cannot be read or the range contains no audio
+pub fn cut_file(
+    source: &Path,
+    mime_type: &str,
+    start_ms: u64,
+    end_ms: u64,
+    output: &Path,
+) -> Result<ClipOutput> {
+    if supports_frame_copy(mime_type) {
+        copy_frames(source, mime_type, start_ms, end_ms, output)
+    } else {
+        reencode(source, mime_type, start_ms, end_ms, output)
+    }
+}
+
+/// 复制与范围有重叠的完整帧
+fn copy_frames(
+    source: &Path,
+    mime_type: &str,
+    start_ms: u64,
+    end_ms: u64,
+    output: &Path,
+) -> Result<ClipOutput> {
+    let file =
+        std::fs::File::open(source).map_err(|e| Error::Message(format!("无法打开文件: {e}")))?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    let probed = symphonia::default::get_probe()
+        .format(
+            &format_hint(source, mime_type),
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| Error::Message(format!("无法识别音频格式: {e}")))?;
+    let mut format = probed.format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| Error::Message("未找到音频轨道".to_string()))?;
+    let track_id = track.id;
+    let time_base = track
+        .codec_params
+        .time_base
+        .or_else(|| {
+            track
+                .codec_params
+                .sample_rate
+                .map(|rate| TimeBase::new(1, rate))
+        })
+        .ok_or_else(|| Error::Message("无法确定音频的时间基准".to_string()))?;
+
+    let mut out = BufWriter::new(std::fs::File::create(output)?);
+    let mut first_ms: Option<u64> = None;
+    let mut last_ms: u64 = 0;
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break;
+            }
+            Err(e) => return Err(Error::Message(format!("读取音频失败: {e}"))),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let begin = timestamp_to_ms(time_base, packet.ts());
+        let end = timestamp_to_ms(time_base, packet.ts() + packet.dur());
+        if end <= start_ms {
+            continue;
+        }
+        if begin >= end_ms {
+            break;
+        }
+        out.write_all(packet.buf())?;
+        first_ms.get_or_insert(begin);
+        last_ms = end;
+    }
+    out.flush()?;
+    out.get_ref().sync_all()?;
+
+    let first_ms = first_ms.ok_or_else(|| Error::Message("剪辑范围内没有音频".to_string()))?;
+    Ok(ClipOutput {
+        method: METHOD_COPY,
+        mime_type: "audio/mpeg",
+        extension: "mp3",
+        duration_ms: last_ms - first_ms,
+    })
+}
+
+/// 解码范围内的采样，写成 WAV
+fn reencode(
+    source: &Path,
+    mime_type: &str,
+    start_ms: u64,
+    end_ms: u64,
+    output: &Path,
+) -> Result<ClipOutput> {
+    let mut writer: Option<(WavWriter, audio_processing::PcmSpec)> = None;
+    let mut index: u64 = 0;
+    let mut written: u64 = 0;
+    decode_pcm(source, mime_type, &mut |samples, spec| {
+        if writer.is_none() {
+            writer = Some((WavWriter::create(output, spec)?, spec));
+        }
+        let Some((wav, first_spec)) = writer.as_mut() else {
+            return Ok(ControlFlow::Continue(()));
+        };
+        if spec != *first_spec {
+            // 中途改变格式的数据包不参与处理
+            return Ok(ControlFlow::Continue(()));
+        }
+        let rate = u64::from(spec.rate);
+        let (start, end) = (start_ms * rate / 1000, end_ms * rate / 1000);
+        for frame in samples.chunks_exact(spec.channels) {
+            if index >= end {
+                return Ok(ControlFlow::Break(()));
+            }
+            if index >= start {
+                wav.write_frame(frame, 1.0)?;
+                written += 1;
+            }
+            index += 1;
+        }
+        Ok(ControlFlow::Continue(()))
+    })?;
+
+    let Some((wav, spec)) = writer else {
+        return Err(Error::Message("音频没有可解码的内容".to_string()));
+    };
+    wav.finish()?;
+    if written == 0 {
+        return Err(Error::Message("剪辑范围内没有音频".to_string()));
+    }
+    Ok(ClipOutput {
+        method: METHOD_REENCODE,
+        mime_type: audio_processing::WAV_MIME_TYPE,
+        extension: "wav",
+        duration_ms: written * 1000 / u64::from(spec.rate.max(1)),
+    })
+}
+
+/// 提交剪辑任务；失败只记录日志，任务保持待处理状态
+pub async fn enqueue(ctx: &AppContext, clip: &media_clips::Model) {
+    if let Err(err) = MediaProcessingWorker::perform_later(
+        ctx,
+        MediaProcessingArgs {
+            media_id: clip.source_media_id,
+            job: ProcessingJob::Clip { clip_id: clip.id },
+        },
+    )
+    .await
+    {
+        tracing::error!(
+            error = err.to_string(),
+            clip_id = clip.id,
+            "failed to enqueue media clip"
+        );
+    }
+}
+
+/// 执行剪辑任务，结果（新媒体或失败原因）记录在任务中
+pub async fn process_clip(ctx: &AppContext, clip_id: i32) -> Result<()> {
+    let Some(clip) = media_clips::Entity::find_by_id(clip_id)
+        .one(&ctx.db)
+        .await?
+    else {
+        return Ok(());
+    };
+    if clip.status != media_clips::STATUS_PENDING {
+        return Ok(());
+    }
+
+    match create_clip_media(ctx, &clip).await {
+        // 任务已由另一次执行完成
+        Ok(None) => {}
+        Ok(Some(media)) => {
+            tracing::info!("剪辑任务 {} 完成，生成媒体 {}", clip.id, media.id);
+
+            webhooks::dispatch(
+                ctx,
+                media.user_id,
+                EVENT_MEDIA_UPLOADED,
+                webhooks::media_data(&media),
+            )
+            .await;
+            if let Some(book) = books::Entity::find_by_id(media.book_id)
+                .one(&ctx.db)
+                .await?
+            {
+                audio_processing::enqueue(ctx, &book, &media).await;
+            }
+        }
+        Err(e) => {
+            // 剪辑失败通常是文件本身的问题，重试没有意义，记录原因后结束
+            tracing::warn!("剪辑任务 {} 失败: {}", clip.id, e);
+            let mut active_model = clip.into_active_model();
+            active_model.status = Set(media_clips::STATUS_FAILED.to_string());
+            active_model.error = Set(Some(e.to_string()));
+            active_model.update(&ctx.db).await?;
+        }
+    }
+    Ok(())
+}
+
+/// 剪辑文件并创建新媒体，新媒体和任务结果在同一个事务中保存
+async fn create_clip_media(
+    ctx: &AppContext,
+    clip: &media_clips::Model,
+) -> Result<Option<medias::Model>> {
+    let source = medias::Entity::find_by_id(clip.source_media_id)
+        .one(&ctx.db)
+        .await?
+        .filter(|m| m.deleted_at.is_none())
+        .ok_or_else(|| Error::Message("原媒体不存在或已删除".to_string()))?;
+
+    let source_path = STORAGE_SERVICE.resolve(&source.file_path);
+    let mime_type = source.mime_type.clone().unwrap_or_default();
+    let start_ms = u64::try_from(clip.start_ms).unwrap_or(0);
+    let end_ms = u64::try_from(clip.end_ms).unwrap_or(0);
+    let temp_path = storage::temp_upload_path();
+    let cut = {
+        let temp_path = temp_path.clone();
+        tokio::task::spawn_blocking(move || {
+            cut_file(&source_path, &mime_type, start_ms, end_ms, &temp_path)
+        })
+        .await
+        .map_err(|e| Error::Message(format!("剪辑任务失败: {e}")))?
+    };
+    let output = match cut {
+        Ok(output) => output,
+        Err(e) => {
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return Err(e);
+        }
+    };
+
+    let upload = TempUpload {
+        size: tokio::fs::metadata(&temp_path).await?.len(),
+        content_hash: storage::file_sha256(&temp_path).await?,
+        path: temp_path,
+    };
+    let stem = source
+        .original_filename
+        .as_deref()
+        .and_then(|name| Path::new(name).file_stem())
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("clip");
+    let filename = format!("{stem}-clip.{}", output.extension);
+    let file_path =
+        content_store::store_upload(&ctx.db, &STORAGE_SERVICE, &upload, &filename).await?;
+
+    let content_hash = upload.content_hash.clone();
+    let saved = save_clip_media(ctx, clip, &source, &output, &upload, file_path, filename).await;
+    let media = match saved {
+        Ok(Some(media)) => media,
+        result => {
+            // 保存上传时已为新媒体增加了引用，没有创建媒体时释放
+            if let Err(err) = content_store::release(&ctx.db, &content_hash).await {
+                tracing::warn!("释放剪辑文件引用失败: {}", err);
+            }
+            return result;
+        }
+    };
+    let access_url = media.access_url.clone().unwrap_or_default();
+
+    // 二维码生成失败不影响剪辑结果，存储检查会为缺少二维码的媒体补上
+    match QRCODE_SERVICE
+        .generate_media_qrcode(media.id, &access_url)
+        .await
+    {
+        Ok(qrcode_path) => {
+            let mut active_model = media.into_active_model();
+            active_model.qr_code_path = Set(Some(qrcode_path));
+            Ok(Some(active_model.update(&ctx.db).await?))
+        }
+        Err(e) => {
+            tracing::warn!("为剪辑媒体 {} 生成二维码失败: {}", media.id, e);
+            Ok(Some(media))
+        }
+    }
+}
+
+/// 创建剪辑生成的媒体及其版本记录，并把任务标记为完成；任务已不在等待中时不创建
+async fn save_clip_media(
+    ctx: &AppContext,
+    clip: &media_clips::Model,
+    source: &medias::Model,
+    output: &ClipOutput,
+    upload: &TempUpload,
+    file_path: String,
+    filename: String,
+) -> Result<Option<medias::Model>> {
+    let site_url = site_settings::Model::site_url(&ctx.db).await?;
+    let access_token = Uuid::new_v4().to_string();
+    let access_url = format!("{site_url}/public/{access_token}");
+    let duration_ms = i64::try_from(output.duration_ms).unwrap_or(i64::MAX);
+
+    let txn = ctx.db.begin().await?;
+    let Some(pending) = media_clips::Entity::find_by_id(clip.id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?
+        .filter(|c| c.status == media_clips::STATUS_PENDING)
+    else {
+        return Ok(None);
+    };
+    let sort_order = medias::Model::next_sort_order(&txn, clip.book_id, clip.chapter_id).await?;
+    let media = medias::ActiveModel {
+        user_id: Set(clip.user_id),
+        book_id: Set(clip.book_id),
+        chapter_id: Set(clip.chapter_id),
+        title: Set(clip.title.clone()),
+        description: Set(source.description.clone()),
+        file_type: Set(source.file_type.clone()),
+        file_path: Set(file_path),
+        file_size: Set(Some(i64::try_from(upload.size).unwrap_or(i64::MAX))),
+        duration: Set(i32::try_from((duration_ms + 500) / 1000).ok()),
+        mime_type: Set(Some(output.mime_type.to_string())),
+        access_token: Set(access_token),
+        access_url: Set(Some(access_url)),
+        qr_code_path: Set(None),
+        file_version: Set(1),
+        original_filename: Set(Some(filename)),
+        play_count: Set(0),
+        is_public: Set(false),
+        sort_order: Set(Some(sort_order)),
+        content_hash: Set(Some(upload.content_hash.clone())),
+        ..Default::default()
+    }
+    .insert(&txn)
+    .await?;
+    media_versions::Model::record(&txn, &media, Some(clip.user_id), None).await?;
+
+    let mut active_model = pending.into_active_model();
+    active_model.status = Set(media_clips::STATUS_SUCCEEDED.to_string());
+    active_model.method = Set(Some(output.method.to_string()));
+    active_model.media_id = Set(Some(media.id));
+    active_model.update(&txn).await?;
+    txn.commit().await?;
+    Ok(Some(media))
+}
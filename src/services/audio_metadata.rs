@@ -5,6 +5,27 @@ use symphonia::core::io::MediaSourceStream;
 use symphonia::core::probe::Hint;
 use symphonia::default::get_probe;
 
+/// 根据扩展名（没有时根据 MIME 类型）生成格式提示
+pub(crate) fn format_hint(file_path: &Path, mime_type: &str) -> Hint {
+    let mut hint = Hint::new();
+
+    let extension = file_path.extension().and_then(|s| s.to_str());
+    let ext_to_use = extension.unwrap_or({
+        match mime_type {
+            "audio/mpeg" | "audio/mp3" => "mp3",
+            "audio/mp4" | "audio/x-m4a" => "m4a",
+            "audio/flac" => "flac",
+            "audio/ogg" => "ogg",
+            "audio/wav" | "audio/x-wav" => "wav",
+            "audio/opus" => "opus",
+            _ => "mp3", // 默认设为mp3
+        }
+    });
+
+    hint.with_extension(ext_to_use);
+    hint
+}
+
 /// 音频元数据结构体
 #[derive(Debug, Clone, Default)]
 pub struct AudioMetadata {
@@ -40,23 +61,7 @@ impl AudioMetadataService {
         let mss = MediaSourceStream::new(Box::new(file), Default::default());
 
         // 创建格式提示
-        let mut hint = Hint::new();
-
-        // 根据MIME类型设置格式提示
-        let extension = file_path.extension().and_then(|s| s.to_str());
-        let ext_to_use = extension.unwrap_or({
-            match mime_type {
-                "audio/mpeg" | "audio/mp3" => "mp3",
-                "audio/mp4" | "audio/x-m4a" => "m4a",
-                "audio/flac" => "flac",
-                "audio/ogg" => "ogg",
-                "audio/wav" | "audio/x-wav" => "wav",
-                "audio/opus" => "opus",
-                _ => "mp3", // 默认设为mp3
-            }
-        });
-
-        hint.with_extension(ext_to_use);
+        let hint = format_hint(file_path, mime_type);
 
         // 探测格式
         let format_opts = FormatOptions {
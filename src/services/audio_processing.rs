@@ -0,0 +1,761 @@
+//! 音频处理：按 EBU R128 测量响度，生成音量统一、去掉首尾静音的播放版本
+//!
+//! 用 symphonia 解码三遍：第一遍找到首尾静音的位置，第二遍测量保留部分的响度和采样峰值，
+//! 第三遍按增益调整、裁掉首尾静音后用 FFmpeg 编码为 AAC（M4A）。增益受峰值限制，不会削波。
+//! 书籍开启 `normalize_audio` 后，上传或替换音频时由处理任务生成，播放时代替原文件；
+//! 测量结果以 JSON 保存在 `medias.audio_analysis`。
+//! 码率很低的来源重新编码后可能变大，
+//! 播放版本超过原文件 [`MAX_RENDITION_GROWTH`] 倍时不保留，继续播放原文件，只保存测量结果。
+use ebur128::{EbuR128, Mode};
+use loco_rs::prelude::*;
+use sea_orm::sea_query::Expr;
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+
+use crate::models::_entities::{books, medias};
+use crate::services::audio_metadata::format_hint;
+use crate::services::storage::STORAGE_SERVICE;
+use crate::workers::media_processing::{MediaProcessingArgs, MediaProcessingWorker, ProcessingJob};
+
+/// 默认目标响度（LUFS），与常见播客平台一致
+pub const DEFAULT_TARGET_LUFS: f64 = -16.0;
+
+/// 播放版本的 MIME 类型
+pub const NORMALIZED_MIME_TYPE: &str = "audio/mp4";
+
+/// `WavWriter` 写出的文件（如剪辑结果）的 MIME 类型
+pub const WAV_MIME_TYPE: &str = "audio/wav";
+
+/// 调整后采样峰值的上限（dBFS）
+const PEAK_CEILING_DBFS: f64 = -1.0;
+
+/// 低于该电平视为静音（dBFS）
+const SILENCE_THRESHOLD_DBFS: f64 = -50.0;
+
+/// 裁剪静音时在有声部分前后保留的时长
+const SILENCE_PADDING_MS: u64 = 100;
+
+const NORMALIZED_NAME: &str = "normalized.m4a";
+
+/// 播放版本每个声道的码率（bit/s）
+const RENDITION_BIT_RATE_PER_CHANNEL: usize = 64_000;
+
+/// 播放版本最多可以是原文件的多少倍
+pub const MAX_RENDITION_GROWTH: u64 = 2;
+
+/// 目标响度（`AUDIO_TARGET_LUFS`，-70 到 0 之间）
+#[must_use]
+pub fn target_lufs() -> f64 {
+    std::env::var("AUDIO_TARGET_LUFS")
+        .ok()
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .filter(|lufs| (-70.0..=0.0).contains(lufs))
+        .unwrap_or(DEFAULT_TARGET_LUFS)
+}
+
+/// 响度测量和处理结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoudnessAnalysis {
+    /// 裁掉首尾静音后的整体响度（LUFS）
+    pub integrated_lufs: f64,
+    /// 裁掉首尾静音后的采样峰值（dBFS）
+    pub sample_peak_dbfs: f64,
+    pub target_lufs: f64,
+    /// 实际使用的增益，受峰值限制时小于目标与原响度之差
+    pub gain_db: f64,
+    pub trimmed_start_ms: u64,
+    pub trimmed_end_ms: u64,
+    /// 播放版本的时长
+    pub duration_ms: u64,
+}
+
+impl LoudnessAnalysis {
+    /// 读取媒体保存的测量结果
+    #[must_use]
+    pub fn from_media(media: &medias::Model) -> Option<Self> {
+        media
+            .audio_analysis
+            .as_deref()
+            .and_then(|json| serde_json::from_str(json).ok())
+    }
+}
+
+fn db_to_gain(db: f64) -> f64 {
+    10f64.powf(db / 20.0)
+}
+
+fn frames_to_ms(frames: u64, rate: u32) -> u64 {
+    frames * 1000 / u64::from(rate.max(1))
+}
+
+/// 解码得到的 PCM 格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PcmSpec {
+    pub channels: usize,
+    pub rate: u32,
+}
+
+/// 接收解码出的交错采样，返回 `Break` 时停止解码
+pub(crate) type FrameHandler<'a> = dyn FnMut(&[f32], PcmSpec) -> Result<ControlFlow<()>> + 'a;
+
+/// 逐个数据包解码，把交错排列的采样交给 `on_frames`，返回 `Break` 时提前结束
+pub(crate) fn decode_pcm(
+    path: &Path,
+    mime_type: &str,
+    on_frames: &mut FrameHandler<'_>,
+) -> Result<()> {
+    let file =
+        std::fs::File::open(path).map_err(|e| Error::Message(format!("无法打开文件: {e}")))?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    let probed = symphonia::default::get_probe()
+        .format(
+            &format_hint(path, mime_type),
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| Error::Message(format!("无法识别音频格式: {e}")))?;
+    let mut format = probed.format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| Error::Message("未找到音频轨道".to_string()))?;
+    let track_id = track.id;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|e| Error::Message(format!("不支持的音频编码: {e}")))?;
+
+    let mut buffer: Option<SampleBuffer<f32>> = None;
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break;
+            }
+            Err(e) => return Err(Error::Message(format!("读取音频失败: {e}"))),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // 个别损坏的数据包跳过
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(Error::Message(format!("解码音频失败: {e}"))),
+        };
+
+        let spec = *decoded.spec();
+        let channels = spec.channels.count();
+        if buffer
+            .as_ref()
+            .is_some_and(|b| b.capacity() < decoded.capacity() * channels)
+        {
+            buffer = None;
+        }
+        let samples = buffer.get_or_insert_with(|| {
+            SampleBuffer::new(u64::try_from(decoded.capacity()).unwrap_or(u64::MAX), spec)
+        });
+        samples.copy_interleaved_ref(decoded);
+        let flow = on_frames(
+            samples.samples(),
+            PcmSpec {
+                channels,
+                rate: spec.rate,
+            },
+        )?;
+        if flow.is_break() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// 流式写入 16 位 PCM WAV，结束时回填长度；16 位来源不加增益时原样写回
+pub(crate) struct WavWriter {
+    out: BufWriter<std::fs::File>,
+    data_bytes: u32,
+}
+
+impl WavWriter {
+    pub(crate) fn create(path: &Path, spec: PcmSpec) -> std::io::Result<Self> {
+        let channels = u16::try_from(spec.channels)
+            .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidInput, "声道数过多"))?;
+        let block_align = channels * 2;
+        let mut out = BufWriter::new(std::fs::File::create(path)?);
+        out.write_all(b"RIFF")?;
+        out.write_all(&0u32.to_le_bytes())?;
+        out.write_all(b"WAVEfmt ")?;
+        out.write_all(&16u32.to_le_bytes())?;
+        out.write_all(&1u16.to_le_bytes())?;
+        out.write_all(&channels.to_le_bytes())?;
+        out.write_all(&spec.rate.to_le_bytes())?;
+        out.write_all(&(spec.rate * u32::from(block_align)).to_le_bytes())?;
+        out.write_all(&block_align.to_le_bytes())?;
+        out.write_all(&16u16.to_le_bytes())?;
+        out.write_all(b"data")?;
+        out.write_all(&0u32.to_le_bytes())?;
+        Ok(Self { out, data_bytes: 0 })
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    pub(crate) fn write_frame(&mut self, frame: &[f32], gain: f32) -> std::io::Result<()> {
+        for sample in frame {
+            let value = (sample * gain * 32768.0).round().clamp(-32768.0, 32767.0) as i16;
+            self.out.write_all(&value.to_le_bytes())?;
+        }
+        let bytes = u32::try_from(frame.len() * 2).unwrap_or(u32::MAX);
+        self.data_bytes = self.data_bytes.checked_add(bytes).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "音频过长，超过 WAV 大小限制",
+            )
+        })?;
+        Ok(())
+    }
+
+    pub(crate) fn finish(mut self) -> std::io::Result<()> {
+        self.out.flush()?;
+        let mut file = self.out.into_inner().map_err(|e| e.into_error())?;
+        file.seek(SeekFrom::Start(4))?;
+        file.write_all(&(36 + self.data_bytes).to_le_bytes())?;
+        file.seek(SeekFrom::Start(40))?;
+        file.write_all(&self.data_bytes.to_le_bytes())?;
+        file.sync_all()
+    }
+}
+
+fn encode_error(e: ffmpeg_next::Error) -> Error {
+    Error::Message(format!("无法编码播放版本: {e}"))
+}
+
+/// 用 FFmpeg 把 PCM 编码为 AAC，写入 MP4（M4A）容器
+struct AacWriter {
+    output: ffmpeg_next::format::context::Output,
+    encoder: ffmpeg_next::encoder::Audio,
+    layout: ffmpeg_next::ChannelLayout,
+    spec: PcmSpec,
+    /// 编码器每帧的采样数
+    frame_size: usize,
+    /// 还不够一帧的交错采样
+    pending: Vec<f32>,
+    pts: i64,
+}
+
+impl AacWriter {
+    fn create(path: &Path, spec: PcmSpec) -> Result<Self> {
+        ffmpeg_next::init().map_err(|e| Error::Message(format!("FFmpeg 初始化失败: {e}")))?;
+
+        let codec = ffmpeg_next::encoder::find(ffmpeg_next::codec::Id::AAC)
+            .ok_or_else(|| Error::Message("FFmpeg 不支持 AAC 编码".to_string()))?
+            .audio()
+            .map_err(encode_error)?;
+        let channels =
+            i32::try_from(spec.channels).map_err(|_| Error::Message("声道数过多".to_string()))?;
+        let rate =
+            i32::try_from(spec.rate).map_err(|_| Error::Message("采样率过高".to_string()))?;
+        let layout = ffmpeg_next::ChannelLayout::default(channels);
+
+        // 输出是临时文件名，按 M4A 格式写入
+        let mut output = ffmpeg_next::format::output_as(path, "ipod").map_err(encode_error)?;
+        let global_header = output
+            .format()
+            .flags()
+            .contains(ffmpeg_next::format::flag::Flags::GLOBAL_HEADER);
+        let encoder = {
+            let mut stream = output.add_stream(codec).map_err(encode_error)?;
+            let mut encoder =
+                ffmpeg_next::codec::context::Context::from_parameters(stream.parameters())
+                    .map_err(encode_error)?
+                    .encoder()
+                    .audio()
+                    .map_err(encode_error)?;
+            encoder.set_rate(rate);
+            encoder.set_channel_layout(layout);
+            encoder.set_format(ffmpeg_next::format::Sample::F32(
+                ffmpeg_next::format::sample::Type::Planar,
+            ));
+            encoder.set_bit_rate(RENDITION_BIT_RATE_PER_CHANNEL * spec.channels);
+            encoder.set_time_base((1, rate));
+            if global_header {
+                encoder.set_flags(ffmpeg_next::codec::flag::Flags::GLOBAL_HEADER);
+            }
+            let encoder = encoder.open_as(codec).map_err(encode_error)?;
+            stream.set_time_base((1, rate));
+            stream.set_parameters(&encoder);
+            encoder
+        };
+        output.write_header().map_err(encode_error)?;
+
+        let frame_size = usize::try_from(encoder.frame_size())
+            .ok()
+            .filter(|size| *size > 0)
+            .unwrap_or(1024);
+        Ok(Self {
+            output,
+            encoder,
+            layout,
+            spec,
+            frame_size,
+            pending: Vec::new(),
+            pts: 0,
+        })
+    }
+
+    /// 加上增益后写入交错排列的采样，凑够一帧就编码
+    fn write_frames(&mut self, samples: &[f32], gain: f32) -> Result<()> {
+        self.pending.extend(
+            samples
+                .iter()
+                .map(|sample| (sample * gain).clamp(-1.0, 1.0)),
+        );
+        while self.pending.len() >= self.frame_size * self.spec.channels {
+            self.encode(self.frame_size)?;
+        }
+        Ok(())
+    }
+
+    /// 把开头 `frames` 帧转成平面格式交给编码器
+    fn encode(&mut self, frames: usize) -> Result<()> {
+        let channels = self.spec.channels;
+        let mut frame = ffmpeg_next::frame::Audio::new(
+            ffmpeg_next::format::Sample::F32(ffmpeg_next::format::sample::Type::Planar),
+            frames,
+            self.layout,
+        );
+        frame.set_rate(self.spec.rate);
+        for channel in 0..channels {
+            for (i, value) in frame.plane_mut::<f32>(channel).iter_mut().enumerate() {
+                *value = self.pending[i * channels + channel];
+            }
+        }
+        frame.set_pts(Some(self.pts));
+        self.pts += i64::try_from(frames).unwrap_or(i64::MAX);
+        self.pending.drain(..frames * channels);
+
+        self.encoder.send_frame(&frame).map_err(encode_error)?;
+        self.write_packets()
+    }
+
+    /// 取出编码好的数据包写入文件
+    fn write_packets(&mut self) -> Result<()> {
+        let encoder_time_base = self.encoder.time_base();
+        let stream_time_base = self
+            .output
+            .stream(0)
+            .map_or(encoder_time_base, |stream| stream.time_base());
+        let mut packet = ffmpeg_next::Packet::empty();
+        while self.encoder.receive_packet(&mut packet).is_ok() {
+            packet.set_stream(0);
+            packet.rescale_ts(encoder_time_base, stream_time_base);
+            packet
+                .write_interleaved(&mut self.output)
+                .map_err(encode_error)?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<()> {
+        let remaining = self.pending.len() / self.spec.channels;
+        if remaining > 0 {
+            self.encode(remaining)?;
+        }
+        self.encoder.send_eof().map_err(encode_error)?;
+        self.write_packets()?;
+        self.output.write_trailer().map_err(encode_error)
+    }
+}
+
+/// 解码并把 `[start, end)` 范围内的帧（交错排列）交给 `on_range`，格式与 `spec` 不同的数据包跳过
+fn decode_range(
+    source: &Path,
+    mime_type: &str,
+    spec: PcmSpec,
+    (start, end): (u64, u64),
+    on_range: &mut dyn FnMut(&[f32]) -> Result<()>,
+) -> Result<()> {
+    let mut index: u64 = 0;
+    decode_pcm(source, mime_type, &mut |samples, frame_spec| {
+        if frame_spec != spec {
+            return Ok(ControlFlow::Continue(()));
+        }
+        let frames = u64::try_from(samples.len() / spec.channels).unwrap_or(u64::MAX);
+        let from = start.clamp(index, index + frames) - index;
+        let to = end.clamp(index, index + frames) - index;
+        if from < to {
+            let offset = |frame: u64| usize::try_from(frame).unwrap_or(usize::MAX) * spec.channels;
+            on_range(&samples[offset(from)..offset(to)])?;
+        }
+        index += frames;
+        if index >= end {
+            return Ok(ControlFlow::Break(()));
+        }
+        Ok(ControlFlow::Continue(()))
+    })
+}
+
+/// 测量响度并生成播放版本（CPU 密集，应在阻塞线程中调用）
+///
+/// # Errors
+///
+/// Will return error if the source cannot be decoded, is silent, or the output cannot be written
+#[allow(clippy::cast_possible_truncation)]
+pub fn normalize_file(
+    source: &Path,
+    mime_type: &str,
+    output: &Path,
+    target_lufs: f64,
+) -> Result<LoudnessAnalysis> {
+    // 第一遍：找到首尾静音的位置
+    let threshold = db_to_gain(SILENCE_THRESHOLD_DBFS) as f32;
+    let mut first_spec: Option<PcmSpec> = None;
+    let mut total_frames: u64 = 0;
+    let mut first_loud: Option<u64> = None;
+    let mut last_loud: Option<u64> = None;
+    decode_pcm(source, mime_type, &mut |samples, spec| {
+        if *first_spec.get_or_insert(spec) != spec {
+            // 中途改变格式的数据包不参与处理
+            return Ok(ControlFlow::Continue(()));
+        }
+        for frame in samples.chunks_exact(spec.channels) {
+            if frame.iter().any(|s| s.abs() > threshold) {
+                first_loud.get_or_insert(total_frames);
+                last_loud = Some(total_frames);
+            }
+            total_frames += 1;
+        }
+        Ok(ControlFlow::Continue(()))
+    })?;
+
+    let Some(spec) = first_spec else {
+        return Err(Error::Message("音频没有可解码的内容".to_string()));
+    };
+    let (Some(first_loud), Some(last_loud)) = (first_loud, last_loud) else {
+        return Err(Error::Message("音频中没有声音".to_string()));
+    };
+    let padding = u64::from(spec.rate) * SILENCE_PADDING_MS / 1000;
+    let start = first_loud.saturating_sub(padding);
+    let end = (last_loud + 1 + padding).min(total_frames);
+
+    // 第二遍：只测量保留的部分，增益按播放版本实际的响度计算
+    let channels = u32::try_from(spec.channels).unwrap_or(u32::MAX);
+    let mut analyzer = EbuR128::new(channels, spec.rate, Mode::I | Mode::SAMPLE_PEAK)
+        .map_err(|e| Error::Message(format!("无法测量响度: {e}")))?;
+    decode_range(source, mime_type, spec, (start, end), &mut |samples| {
+        analyzer
+            .add_frames_f32(samples)
+            .map_err(|e| Error::Message(format!("无法测量响度: {e}")))
+    })?;
+    let integrated_lufs = analyzer
+        .loudness_global()
+        .map_err(|e| Error::Message(format!("无法测量响度: {e}")))?;
+    if !integrated_lufs.is_finite() {
+        return Err(Error::Message("音频中没有声音".to_string()));
+    }
+    let mut peak: f64 = 0.0;
+    for channel in 0..channels {
+        let channel_peak = analyzer
+            .sample_peak(channel)
+            .map_err(|e| Error::Message(format!("无法测量峰值: {e}")))?;
+        peak = peak.max(channel_peak);
+    }
+    let sample_peak_dbfs = 20.0 * peak.log10();
+    let gain_db = (target_lufs - integrated_lufs).min(PEAK_CEILING_DBFS - sample_peak_dbfs);
+
+    // 第三遍：调整增益并编码裁剪后的版本，写完再改名，避免留下不完整的文件
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let partial = output.with_extension("part");
+    let gain = db_to_gain(gain_db) as f32;
+    let written = AacWriter::create(&partial, spec).and_then(|mut writer| {
+        decode_range(source, mime_type, spec, (start, end), &mut |samples| {
+            writer.write_frames(samples, gain)
+        })?;
+        writer.finish()
+    });
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&partial);
+        return Err(e);
+    }
+    std::fs::rename(&partial, output)?;
+
+    Ok(LoudnessAnalysis {
+        integrated_lufs,
+        sample_peak_dbfs,
+        target_lufs,
+        gain_db,
+        trimmed_start_ms: frames_to_ms(start, spec.rate),
+        trimmed_end_ms: frames_to_ms(total_frames - end, spec.rate),
+        duration_ms: frames_to_ms(end - start, spec.rate),
+    })
+}
+
+/// 播放版本的大小是否可以接受（原文件大小未知时按可以接受处理）
+#[must_use]
+pub fn keeps_rendition(source_size: Option<i64>, rendition_size: u64) -> bool {
+    source_size
+        .and_then(|size| u64::try_from(size).ok())
+        .filter(|size| *size > 0)
+        .is_none_or(|size| rendition_size <= size.saturating_mul(MAX_RENDITION_GROWTH))
+}
+
+/// 书籍开启了音频处理且媒体是音频
+#[must_use]
+pub fn should_normalize(book: &books::Model, media: &medias::Model) -> bool {
+    book.normalize_audio && media.file_type == "audio" && media.deleted_at.is_none()
+}
+
+/// 需要时提交生成播放版本的任务；失败只记录日志，不影响上传等业务操作
+pub async fn enqueue(ctx: &AppContext, book: &books::Model, media: &medias::Model) {
+    if !should_normalize(book, media) {
+        return;
+    }
+    if let Err(err) = MediaProcessingWorker::perform_later(
+        ctx,
+        MediaProcessingArgs {
+            media_id: media.id,
+            job: ProcessingJob::Normalize,
+        },
+    )
+    .await
+    {
+        tracing::error!(
+            error = err.to_string(),
+            media_id = media.id,
+            "failed to enqueue audio normalization"
+        );
+    }
+}
+
+/// 为书籍中还没有播放版本的音频提交处理任务，返回提交的数量
+pub async fn enqueue_book(ctx: &AppContext, book: &books::Model) -> Result<usize> {
+    if !book.normalize_audio {
+        return Ok(0);
+    }
+    let pending = medias::Entity::find()
+        .filter(medias::Column::BookId.eq(book.id))
+        .filter(medias::Column::FileType.eq("audio"))
+        .filter(medias::Column::DeletedAt.is_null())
+        .filter(medias::Column::NormalizedFilePath.is_null())
+        .filter(medias::Column::AudioAnalysis.is_null())
+        .all(&ctx.db)
+        .await?;
+    for media in &pending {
+        enqueue(ctx, book, media).await;
+    }
+    Ok(pending.len())
+}
+
+/// 删除媒体的播放版本文件（替换或回滚文件、永久删除媒体时）
+pub async fn remove_rendition(media: &medias::Model) {
+    let Some(ref key) = media.normalized_file_path else {
+        return;
+    };
+    let path = STORAGE_SERVICE.resolve(key);
+    if let Err(e) = tokio::fs::remove_file(&path).await {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            tracing::warn!("删除播放版本失败: {:?}, 错误: {}", path, e);
+        }
+    }
+}
+
+/// 文件改变后清除旧的测量结果和播放版本记录（文件需另行用 [`remove_rendition`] 删除）
+pub fn clear_rendition(active_model: &mut medias::ActiveModel) {
+    active_model.audio_analysis = Set(None);
+    active_model.normalized_file_path = Set(None);
+    active_model.normalized_file_size = Set(None);
+}
+
+/// 为媒体当前的文件生成播放版本并保存测量结果
+pub async fn normalize_media(db: &DatabaseConnection, media_id: i32) -> Result<()> {
+    let Some(media) = medias::Entity::find_by_id(media_id).one(db).await? else {
+        return Ok(());
+    };
+    if media.file_type != "audio" || media.deleted_at.is_some() {
+        return Ok(());
+    }
+
+    let source = STORAGE_SERVICE.resolve(&media.file_path);
+    let output = STORAGE_SERVICE.get_rendition_path(media.id, media.file_version, NORMALIZED_NAME);
+    let mime_type = media.mime_type.clone().unwrap_or_default();
+    let target = target_lufs();
+    let analysis = {
+        let output = output.clone();
+        tokio::task::spawn_blocking(move || normalize_file(&source, &mime_type, &output, target))
+            .await
+            .map_err(|e| Error::Message(format!("音频处理任务失败: {e}")))??
+    };
+    let size = tokio::fs::metadata(&output).await?.len();
+    let rendition = if keeps_rendition(media.file_size, size) {
+        Some((
+            STORAGE_SERVICE.key_for(&output),
+            i64::try_from(size).unwrap_or(i64::MAX),
+        ))
+    } else {
+        tracing::info!(
+            "媒体 {} 的播放版本（{} 字节）比原文件大太多，继续播放原文件",
+            media_id,
+            size
+        );
+        let _ = tokio::fs::remove_file(&output).await;
+        None
+    };
+
+    // 处理期间文件被替换或媒体被删除时丢弃结果
+    let analysis = serde_json::to_string(&analysis).map_err(|e| Error::Message(format!("{e}")))?;
+    let (key, size) = rendition.unzip();
+    let updated = medias::Entity::update_many()
+        .col_expr(medias::Column::AudioAnalysis, Expr::value(analysis))
+        .col_expr(medias::Column::NormalizedFilePath, Expr::value(key.clone()))
+        .col_expr(medias::Column::NormalizedFileSize, Expr::value(size))
+        .filter(medias::Column::Id.eq(media_id))
+        .filter(medias::Column::FileVersion.eq(media.file_version))
+        .filter(medias::Column::DeletedAt.is_null())
+        .exec(db)
+        .await?;
+    if updated.rows_affected == 0 {
+        if key.is_some() {
+            let _ = tokio::fs::remove_file(&output).await;
+        }
+        return Ok(());
+    }
+    if media.normalized_file_path.is_some() && media.normalized_file_path != key {
+        remove_rendition(&media).await;
+    }
+
+    if key.is_some() {
+        tracing::info!("已生成媒体 {} 的播放版本: {:?}", media_id, output);
+    }
+    Ok(())
+}
+
+/// 播放时使用的文件
+#[derive(Debug)]
+pub struct PlaybackFile {
+    pub path: PathBuf,
+    pub mime_type: Option<String>,
+}
+
+/// 播放地址返回的内容类型，与 [`playback_file`] 的选择一致（不检查文件是否存在）
+#[must_use]
+pub fn playback_mime_type<'a>(book: &books::Model, media: &'a medias::Model) -> Option<&'a str> {
+    if book.normalize_audio && media.normalized_file_path.is_some() {
+        return Some(NORMALIZED_MIME_TYPE);
+    }
+    media.mime_type.as_deref()
+}
+
+/// 书籍开启了音频处理且播放版本已生成时使用播放版本，否则使用原文件
+pub async fn playback_file(db: &DatabaseConnection, media: &medias::Model) -> Result<PlaybackFile> {
+    if let Some(ref key) = media.normalized_file_path {
+        let enabled = books::Entity::find_by_id(media.book_id)
+            .one(db)
+            .await?
+            .is_some_and(|book| book.normalize_audio);
+        let path = STORAGE_SERVICE.resolve(key);
+        if enabled && tokio::fs::metadata(&path).await.is_ok() {
+            return Ok(PlaybackFile {
+                path,
+                mime_type: Some(NORMALIZED_MIME_TYPE.to_string()),
+            });
+        }
+    }
+    Ok(PlaybackFile {
+        path: STORAGE_SERVICE.resolve(&media.file_path),
+        mime_type: media.mime_type.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// 写入 8 kHz 单声道 WAV：1 秒静音、1 秒 440 Hz 正弦波、1 秒静音
+    fn write_test_wav(path: &Path, amplitude: f32) {
+        let spec = PcmSpec {
+            channels: 1,
+            rate: 8000,
+        };
+        let mut writer = WavWriter::create(path, spec).unwrap();
+        for i in 0..24_000u32 {
+            let sample = if (8000..16_000).contains(&i) {
+                #[allow(clippy::cast_precision_loss)]
+                let t = i as f32 / 8000.0;
+                amplitude * (2.0 * std::f32::consts::PI * 440.0 * t).sin()
+            } else {
+                0.0
+            };
+            writer.write_frame(&[sample], 1.0).unwrap();
+        }
+        writer.finish().unwrap();
+    }
+
+    #[test]
+    fn test_normalize_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("quiet.wav");
+        let output = temp_dir.path().join("out/normalized.m4a");
+        write_test_wav(&source, 0.05);
+
+        let analysis = normalize_file(&source, "audio/wav", &output, -16.0).unwrap();
+
+        assert!(analysis.integrated_lufs < -25.0);
+        assert!(analysis.gain_db > 0.0);
+        // 首尾静音各裁掉约 0.9 秒（保留 0.1 秒）
+        assert!((850..=950).contains(&analysis.trimmed_start_ms));
+        assert!((850..=950).contains(&analysis.trimmed_end_ms));
+        assert!((1150..=1250).contains(&analysis.duration_ms));
+        assert!(output.exists());
+        assert!(!output.with_extension("part").exists());
+
+        // 再次测量处理后的文件，响度接近目标
+        let again = temp_dir.path().join("again.m4a");
+        let second = normalize_file(&output, NORMALIZED_MIME_TYPE, &again, -16.0).unwrap();
+        assert!((second.integrated_lufs + 16.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_gain_is_limited_by_peak() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("loud.wav");
+        write_test_wav(&source, 0.9);
+
+        // 目标响度很高时增益受峰值限制
+        let analysis =
+            normalize_file(&source, "audio/wav", &temp_dir.path().join("o.m4a"), -1.0).unwrap();
+        assert!(analysis.sample_peak_dbfs + analysis.gain_db <= PEAK_CEILING_DBFS + 0.01);
+    }
+
+    #[test]
+    fn test_keeps_rendition() {
+        assert!(keeps_rendition(Some(1_000_000), 900_000));
+        assert!(keeps_rendition(Some(1_000_000), 2_000_000));
+        // 码率很低的来源重新编码后可能大很多
+        assert!(!keeps_rendition(Some(1_000_000), 11_000_000));
+        assert!(keeps_rendition(None, 11_000_000));
+        assert!(keeps_rendition(Some(0), 11_000_000));
+    }
+
+    #[test]
+    fn test_silent_file_is_rejected() {
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("silent.wav");
+        write_test_wav(&source, 0.0);
+        let output = temp_dir.path().join("silent-out.m4a");
+
+        assert!(normalize_file(&source, "audio/wav", &output, -16.0).is_err());
+        assert!(!output.exists());
+    }
+}
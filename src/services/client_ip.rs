@@ -0,0 +1,205 @@
+//! 客户端 IP 识别
+//!
+//! 只有直接连接来自可信反向代理（`TRUSTED_PROXIES`，逗号分隔的 IP 或 CIDR，
+//! 如 `127.0.0.1,10.0.0.0/8`）时才采用 `X-Forwarded-For` / `X-Real-IP`，
+//! 否则使用连接地址，避免客户端伪造请求头绕过按 IP 的限流或写入虚假的审计记录。
+//! 未配置时不信任任何代理。
+use std::net::IpAddr;
+
+use axum::http::HeaderMap;
+
+/// 可信代理的地址段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProxyRange {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl ProxyRange {
+    /// 解析单个地址（`10.0.0.1`）或地址段（`10.0.0.0/8`、`fd00::/8`）
+    #[must_use]
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let (address, prefix) = match value.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix.trim().parse::<u8>().ok()?)),
+            None => (value, None),
+        };
+        let network: IpAddr = address.trim().parse().ok()?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(max);
+        (prefix <= max).then_some(Self { network, prefix })
+    }
+
+    #[must_use]
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+            IpAddr::V4(_) => ip,
+        };
+        match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix))
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// 读取 `TRUSTED_PROXIES` 配置，无效的条目记录日志后忽略
+#[must_use]
+pub fn trusted_proxies() -> Vec<ProxyRange> {
+    std::env::var("TRUSTED_PROXIES")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .filter_map(|item| {
+            let range = ProxyRange::parse(item);
+            if range.is_none() {
+                tracing::warn!(value = item, "ignoring invalid TRUSTED_PROXIES entry");
+            }
+            range
+        })
+        .collect()
+}
+
+fn is_trusted(trusted: &[ProxyRange], ip: IpAddr) -> bool {
+    trusted.iter().any(|range| range.contains(ip))
+}
+
+fn header_ip(headers: &HeaderMap, name: &str) -> Option<IpAddr> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok())
+}
+
+/// 根据连接地址和代理请求头确定客户端 IP
+///
+/// 连接来自可信代理时，从 `X-Forwarded-For` 的最右边开始跳过可信代理，
+/// 取第一个不可信的地址（更左边的地址可能由客户端伪造）；没有该请求头时使用 `X-Real-IP`。
+#[must_use]
+pub fn resolve(
+    peer: Option<IpAddr>,
+    headers: &HeaderMap,
+    trusted: &[ProxyRange],
+) -> Option<IpAddr> {
+    let peer = peer?;
+    if !is_trusted(trusted, peer) {
+        return Some(peer);
+    }
+
+    let forwarded: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .collect();
+    if forwarded.is_empty() {
+        return Some(header_ip(headers, "x-real-ip").unwrap_or(peer));
+    }
+
+    let mut client = peer;
+    for item in forwarded.iter().rev() {
+        let Ok(ip) = item.parse::<IpAddr>() else {
+            // 无法解析的地址之后的内容都不可信
+            break;
+        };
+        client = ip;
+        if !is_trusted(trusted, ip) {
+            break;
+        }
+    }
+    Some(client)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    fn ip(value: &str) -> Option<IpAddr> {
+        Some(value.parse().unwrap())
+    }
+
+    #[test]
+    fn test_proxy_range() {
+        let range = ProxyRange::parse("10.0.0.0/8").unwrap();
+        assert!(range.contains("10.20.30.40".parse().unwrap()));
+        assert!(range.contains("::ffff:10.0.0.1".parse().unwrap()));
+        assert!(!range.contains("11.0.0.1".parse().unwrap()));
+
+        let single = ProxyRange::parse("127.0.0.1").unwrap();
+        assert!(single.contains("127.0.0.1".parse().unwrap()));
+        assert!(!single.contains("127.0.0.2".parse().unwrap()));
+
+        assert!(ProxyRange::parse("fd00::/8")
+            .unwrap()
+            .contains("fd12::1".parse().unwrap()));
+        assert!(ProxyRange::parse("0.0.0.0/0")
+            .unwrap()
+            .contains("8.8.8.8".parse().unwrap()));
+        assert_eq!(ProxyRange::parse("10.0.0.0/33"), None);
+        assert_eq!(ProxyRange::parse("proxy.local"), None);
+    }
+
+    #[test]
+    fn test_resolve_ignores_headers_from_untrusted_peers() {
+        let spoofed = headers(&[
+            ("x-forwarded-for", "203.0.113.7"),
+            ("x-real-ip", "203.0.113.8"),
+        ]);
+        assert_eq!(
+            resolve(ip("198.51.100.1"), &spoofed, &[]),
+            ip("198.51.100.1")
+        );
+
+        let trusted = [ProxyRange::parse("10.0.0.0/8").unwrap()];
+        assert_eq!(
+            resolve(ip("198.51.100.1"), &spoofed, &trusted),
+            ip("198.51.100.1")
+        );
+        assert_eq!(resolve(None, &spoofed, &trusted), None);
+    }
+
+    #[test]
+    fn test_resolve_walks_forwarded_chain_from_the_right() {
+        let trusted = [ProxyRange::parse("10.0.0.0/8").unwrap()];
+
+        let chain = headers(&[("x-forwarded-for", "1.2.3.4, 203.0.113.7, 10.0.0.2")]);
+        assert_eq!(resolve(ip("10.0.0.1"), &chain, &trusted), ip("203.0.113.7"));
+
+        let split = headers(&[
+            ("x-forwarded-for", "203.0.113.7"),
+            ("x-forwarded-for", "10.0.0.2"),
+        ]);
+        assert_eq!(resolve(ip("10.0.0.1"), &split, &trusted), ip("203.0.113.7"));
+
+        let real_ip = headers(&[("x-real-ip", "203.0.113.9")]);
+        assert_eq!(
+            resolve(ip("10.0.0.1"), &real_ip, &trusted),
+            ip("203.0.113.9")
+        );
+
+        let garbage = headers(&[("x-forwarded-for", "203.0.113.7, unknown")]);
+        assert_eq!(resolve(ip("10.0.0.1"), &garbage, &trusted), ip("10.0.0.1"));
+    }
+}
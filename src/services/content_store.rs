@@ -0,0 +1,122 @@
+//! 内容寻址存储：上传的文件按 SHA-256 存放，内容相同的上传共用一个文件
+//!
+//! `storage_blobs.ref_count` 是引用该文件的媒体版本数（媒体当前的文件也记录为版本），
+//! 保存上传时（由随后记录的版本持有）或复制媒体时增加，清理或永久删除版本时减少，
+//! 降到 0 时才删除磁盘上的文件。增减引用都锁定记录所在的行，
+//! 保存上传和释放最后一个引用不会交错执行。
+//! 启用之前上传的文件没有摘要，仍按路径判断是否与其他媒体共用。
+use loco_rs::prelude::*;
+use sea_orm::{DatabaseTransaction, TransactionTrait};
+use std::path::Path;
+
+use crate::models::storage_blobs;
+use crate::services::storage::{StorageService, TempUpload, STORAGE_SERVICE};
+
+/// 删除不再需要的文件
+async fn discard_file(path: &Path) {
+    if let Err(e) = tokio::fs::remove_file(path).await {
+        tracing::warn!("删除临时文件失败: {:?}, 错误: {}", path, e);
+    }
+}
+
+/// 保存上传的临时文件，返回媒体使用的存储键
+///
+/// 已有相同内容的文件时直接共用，否则移动到按摘要存储的位置并登记。
+/// 返回时已为调用方增加了一个引用，调用方随后记录的版本持有该引用。
+pub async fn store_upload<C>(
+    db: &C,
+    storage: &StorageService,
+    upload: &TempUpload,
+    filename: &str,
+) -> Result<String>
+where
+    C: TransactionTrait,
+{
+    let txn = db.begin().await?;
+    let file_path = store_locked(&txn, storage, upload, filename).await?;
+    txn.commit().await?;
+    Ok(file_path)
+}
+
+async fn store_locked(
+    txn: &DatabaseTransaction,
+    storage: &StorageService,
+    upload: &TempUpload,
+    filename: &str,
+) -> Result<String> {
+    if let Some(blob) = share_existing(txn, storage, upload).await? {
+        return Ok(blob);
+    }
+
+    let path = storage
+        .move_temp_to_blob(&upload.path, &upload.content_hash, filename)
+        .await?;
+    let file_path = storage.key_for(&path);
+    let created = storage_blobs::Model::create_referenced(
+        txn,
+        &upload.content_hash,
+        &file_path,
+        i64::try_from(upload.size).unwrap_or(i64::MAX),
+    )
+    .await?;
+    if created {
+        return Ok(file_path);
+    }
+
+    // 同时上传了相同内容的另一个请求先登记，使用它的文件
+    let blob = storage_blobs::Model::find_by_hash_for_update(txn, &upload.content_hash)
+        .await?
+        .ok_or_else(|| Error::Message("内容文件登记失败".to_string()))?;
+    storage_blobs::Model::acquire(txn, &upload.content_hash).await?;
+    if storage.resolve(&blob.file_path) != path {
+        discard_file(&path).await;
+    }
+    Ok(blob.file_path)
+}
+
+/// 已有相同内容的文件时锁定记录并增加引用，返回共用文件的存储键
+async fn share_existing(
+    txn: &DatabaseTransaction,
+    storage: &StorageService,
+    upload: &TempUpload,
+) -> Result<Option<String>> {
+    let Some(blob) =
+        storage_blobs::Model::find_by_hash_for_update(txn, &upload.content_hash).await?
+    else {
+        return Ok(None);
+    };
+    storage_blobs::Model::acquire(txn, &upload.content_hash).await?;
+
+    let blob_path = storage.resolve(&blob.file_path);
+    if tokio::fs::metadata(&blob_path).await.is_ok() {
+        discard_file(&upload.path).await;
+        tracing::info!("上传内容与已有文件相同，共用文件: {}", blob.file_path);
+    } else {
+        // 登记的文件丢失了，用这次上传的内容放回原位置，其他引用也随之恢复
+        tracing::warn!("内容文件丢失，使用新上传的内容恢复: {}", blob.file_path);
+        storage.move_file(&upload.path, &blob_path).await?;
+    }
+    Ok(Some(blob.file_path))
+}
+
+/// 释放一个版本对内容文件的引用，最后一个引用释放时删除文件
+///
+/// 在删除记录的事务提交前删除文件，同时保存相同内容的上传会等待该事务结束后重新登记。
+pub async fn release<C>(db: &C, content_hash: &str) -> Result<()>
+where
+    C: TransactionTrait,
+{
+    let txn = db.begin().await?;
+    if let Some(blob) = storage_blobs::Model::release(&txn, content_hash).await? {
+        let file_path = STORAGE_SERVICE.resolve(&blob.file_path);
+        if tokio::fs::metadata(&file_path).await.is_ok() {
+            if let Err(e) = tokio::fs::remove_file(&file_path).await {
+                tracing::warn!("删除内容文件失败: {:?}, 错误: {}", file_path, e);
+            } else {
+                tracing::info!("已删除不再被引用的内容文件: {:?}", file_path);
+            }
+        }
+    }
+    txn.commit().await?;
+    Ok(())
+}
@@ -0,0 +1,418 @@
+//! 跨书籍移动章节，复制章节子树和整本书
+//!
+//! 移动会把章节子树（包括回收站中的子章节）和其中的媒体一起转到目标书籍；
+//! 复制只复制未删除的内容，新媒体使用新的访问令牌并默认不公开。
+//! 复制时可以选择共用原媒体文件而不复制文件内容，删除文件前会检查是否仍被其他媒体使用。
+//! 章节和书籍中的附加位置随之移动或复制，仍指向原来的媒体。
+//! 所有数据库修改在同一个事务中完成，`level` / `path` 在事务内重新计算。
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use loco_rs::prelude::*;
+use sea_orm::sea_query::Expr;
+use sea_orm::{DatabaseConnection, DatabaseTransaction, QueryOrder, TransactionTrait};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::models::_entities::{books, medias};
+use crate::models::{chapters, media_placements, media_subtitles, media_versions};
+use crate::services::locations;
+use crate::services::storage::STORAGE_SERVICE;
+
+/// 复制的条目数
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct CopySummary {
+    pub chapters: u64,
+    pub medias: u64,
+}
+
+/// 同级章节的下一个排序号
+async fn next_sort_order(
+    txn: &DatabaseTransaction,
+    book_id: i32,
+    parent_id: Option<i32>,
+) -> Result<i32> {
+    let mut query = chapters::Entity::find()
+        .filter(chapters::Column::BookId.eq(book_id))
+        .filter(chapters::Column::DeletedAt.is_null());
+    query = match parent_id {
+        Some(parent_id) => query.filter(chapters::Column::ParentId.eq(parent_id)),
+        None => query.filter(chapters::Column::ParentId.is_null()),
+    };
+    let last = query
+        .order_by_desc(chapters::Column::SortOrder)
+        .one(txn)
+        .await?;
+    Ok(last.and_then(|c| c.sort_order).unwrap_or(0) + 1)
+}
+
+/// 把章节子树连同其中的媒体移动到另一本书
+pub async fn move_chapter_to_book(
+    db: &DatabaseConnection,
+    chapter: &chapters::Model,
+    target_book_id: i32,
+    new_parent_id: Option<i32>,
+    new_sort_order: Option<i32>,
+) -> Result<chapters::Model> {
+    if target_book_id == chapter.book_id {
+        return Err(Error::BadRequest(
+            "章节已在目标书籍中，请使用章节移动接口调整位置".to_string(),
+        ));
+    }
+    locations::ensure_chapter_in_book(db, target_book_id, new_parent_id).await?;
+
+    let txn = db.begin().await?;
+    let ids = chapters::Model::find_subtree_ids(&txn, chapter).await?;
+    let sort_order = match new_sort_order {
+        Some(sort_order) => sort_order,
+        None => next_sort_order(&txn, target_book_id, new_parent_id).await?,
+    };
+
+    chapters::Entity::update_many()
+        .col_expr(chapters::Column::BookId, Expr::value(target_book_id))
+        .filter(chapters::Column::Id.is_in(ids.clone()))
+        .exec(&txn)
+        .await?;
+    medias::Entity::update_many()
+        .col_expr(medias::Column::BookId, Expr::value(target_book_id))
+        .filter(medias::Column::ChapterId.is_in(ids.clone()))
+        .exec(&txn)
+        .await?;
+    media_placements::Entity::update_many()
+        .col_expr(
+            media_placements::Column::BookId,
+            Expr::value(target_book_id),
+        )
+        .filter(media_placements::Column::ChapterId.is_in(ids))
+        .exec(&txn)
+        .await?;
+    chapters::Entity::update_many()
+        .col_expr(chapters::Column::ParentId, Expr::value(new_parent_id))
+        .col_expr(chapters::Column::SortOrder, Expr::value(sort_order))
+        .filter(chapters::Column::Id.eq(chapter.id))
+        .exec(&txn)
+        .await?;
+    chapters::Model::update_level_and_path_txn(&txn, chapter.id, new_parent_id).await?;
+
+    txn.commit().await?;
+    chapters::Entity::find_by_id(chapter.id)
+        .one(db)
+        .await?
+        .ok_or_else(|| Error::NotFound)
+}
+
+/// 附加位置复制后仍指向原媒体，不复制媒体本身
+async fn copy_placement(
+    txn: &DatabaseTransaction,
+    placement: &media_placements::Model,
+    book_id: i32,
+    chapter_id: Option<i32>,
+) -> Result<()> {
+    media_placements::ActiveModel {
+        media_id: Set(placement.media_id),
+        book_id: Set(book_id),
+        chapter_id: Set(chapter_id),
+        sort_order: Set(placement.sort_order),
+        title: Set(placement.title.clone()),
+        ..Default::default()
+    }
+    .insert(txn)
+    .await?;
+    Ok(())
+}
+
+/// 一次复制过程中的状态：新复制的文件在失败时需要删除
+struct Copier<'a> {
+    site_url: &'a str,
+    share_files: bool,
+    created_files: Vec<PathBuf>,
+    summary: CopySummary,
+}
+
+impl<'a> Copier<'a> {
+    fn new(site_url: &'a str, share_files: bool) -> Self {
+        Self {
+            site_url,
+            share_files,
+            created_files: Vec::new(),
+            summary: CopySummary::default(),
+        }
+    }
+
+    /// 复制失败时删除已经复制的文件
+    async fn cleanup(&self) {
+        for path in &self.created_files {
+            if let Err(e) = tokio::fs::remove_file(path).await {
+                tracing::warn!("删除复制失败留下的文件失败: {:?}, 错误: {}", path, e);
+            }
+        }
+    }
+
+    async fn copy_media(
+        &mut self,
+        txn: &DatabaseTransaction,
+        media: &medias::Model,
+        book: &books::Model,
+        chapter_id: Option<i32>,
+    ) -> Result<medias::Model> {
+        // 按内容存储的文件总是共用，引用数随版本记录增加
+        let file_path = if self.share_files || media.content_hash.is_some() {
+            media.file_path.clone()
+        } else {
+            let source = STORAGE_SERVICE.resolve(&media.file_path);
+            if tokio::fs::metadata(&source).await.is_err() {
+                return Err(Error::BadRequest(format!(
+                    "媒体「{}」的文件不存在，无法复制",
+                    media.title
+                )));
+            }
+            let copied = STORAGE_SERVICE
+                .copy_media_file(book.user_id, book.id, &source)
+                .await?;
+            self.created_files.push(copied.path.clone());
+            STORAGE_SERVICE.key_for(&copied.path)
+        };
+
+        let access_token = Uuid::new_v4().to_string();
+        let copy = medias::ActiveModel {
+            user_id: Set(book.user_id),
+            book_id: Set(book.id),
+            chapter_id: Set(chapter_id),
+            title: Set(media.title.clone()),
+            description: Set(media.description.clone()),
+            file_type: Set(media.file_type.clone()),
+            file_path: Set(file_path),
+            file_size: Set(media.file_size),
+            duration: Set(media.duration),
+            mime_type: Set(media.mime_type.clone()),
+            access_token: Set(access_token.clone()),
+            access_url: Set(Some(format!(
+                "{}/public/{}",
+                self.site_url.trim_end_matches('/'),
+                access_token
+            ))),
+            qr_code_path: Set(None),
+            file_version: Set(1),
+            original_filename: Set(media.original_filename.clone()),
+            play_count: Set(0),
+            is_public: Set(false),
+            sort_order: Set(media.sort_order),
+            content_hash: Set(media.content_hash.clone()),
+            ..Default::default()
+        }
+        .insert(txn)
+        .await?;
+        media_versions::Model::record_shared(txn, &copy, Some(book.user_id), None).await?;
+
+        for subtitle in media_subtitles::Model::find_by_media(txn, media.id).await? {
+            media_subtitles::ActiveModel {
+                media_id: Set(copy.id),
+                language: Set(subtitle.language),
+                label: Set(subtitle.label),
+                kind: Set(subtitle.kind),
+                source_format: Set(subtitle.source_format),
+                content: Set(subtitle.content),
+                cue_count: Set(subtitle.cue_count),
+                ..Default::default()
+            }
+            .insert(txn)
+            .await?;
+        }
+
+        self.summary.medias += 1;
+        Ok(copy)
+    }
+
+    /// 复制章节子树（父章节先于子章节插入），返回新的顶层章节
+    async fn copy_subtree(
+        &mut self,
+        txn: &DatabaseTransaction,
+        chapter: &chapters::Model,
+        book: &books::Model,
+        parent_id: Option<i32>,
+    ) -> Result<chapters::Model> {
+        let subtree = chapters::Entity::find()
+            .filter(
+                chapters::Column::Id.is_in(chapters::Model::find_subtree_ids(txn, chapter).await?),
+            )
+            .filter(chapters::Column::DeletedAt.is_null())
+            .order_by_asc(chapters::Column::Level)
+            .order_by_asc(chapters::Column::SortOrder)
+            .order_by_asc(chapters::Column::CreatedAt)
+            .all(txn)
+            .await?;
+        let mut by_parent: HashMap<i32, Vec<chapters::Model>> = HashMap::new();
+        for item in &subtree {
+            if let Some(item_parent) = item.parent_id {
+                by_parent.entry(item_parent).or_default().push(item.clone());
+            }
+        }
+
+        let sort_order = next_sort_order(txn, book.id, parent_id).await?;
+        let mut pending = vec![(chapter.clone(), parent_id, Some(sort_order))];
+        let mut id_map: HashMap<i32, i32> = HashMap::new();
+        let mut root = None;
+        while let Some((source, new_parent_id, sort_order)) = pending.pop() {
+            let copy = chapters::ActiveModel {
+                book_id: Set(book.id),
+                parent_id: Set(new_parent_id),
+                title: Set(source.title.clone()),
+                description: Set(source.description.clone()),
+                sort_order: Set(sort_order),
+                ..Default::default()
+            }
+            .insert(txn)
+            .await?;
+            self.summary.chapters += 1;
+            id_map.insert(source.id, copy.id);
+            if root.is_none() {
+                root = Some(copy.id);
+            }
+            for child in by_parent.remove(&source.id).unwrap_or_default() {
+                let sort_order = child.sort_order;
+                pending.push((child, Some(copy.id), sort_order));
+            }
+        }
+        let root = root.ok_or_else(|| Error::NotFound)?;
+        chapters::Model::update_level_and_path_txn(txn, root, parent_id).await?;
+
+        let source_medias = medias::Entity::find()
+            .filter(medias::Column::ChapterId.is_in(id_map.keys().copied().collect::<Vec<_>>()))
+            .filter(medias::Column::DeletedAt.is_null())
+            .order_by_asc(medias::Column::Id)
+            .all(txn)
+            .await?;
+        for media in source_medias {
+            let chapter_id = media.chapter_id.and_then(|id| id_map.get(&id).copied());
+            self.copy_media(txn, &media, book, chapter_id).await?;
+        }
+
+        let placements = media_placements::Entity::find()
+            .filter(
+                media_placements::Column::ChapterId
+                    .is_in(id_map.keys().copied().collect::<Vec<_>>()),
+            )
+            .all(txn)
+            .await?;
+        for placement in placements {
+            let chapter_id = placement.chapter_id.and_then(|id| id_map.get(&id).copied());
+            copy_placement(txn, &placement, book.id, chapter_id).await?;
+        }
+
+        chapters::Entity::find_by_id(root)
+            .one(txn)
+            .await?
+            .ok_or_else(|| Error::NotFound)
+    }
+}
+
+/// 复制章节子树到目标书籍（可以是同一本书）的指定位置
+pub async fn copy_chapter(
+    db: &DatabaseConnection,
+    chapter: &chapters::Model,
+    target_book: &books::Model,
+    parent_id: Option<i32>,
+    site_url: &str,
+    share_files: bool,
+) -> Result<(chapters::Model, CopySummary)> {
+    locations::ensure_chapter_in_book(db, target_book.id, parent_id).await?;
+
+    let txn = db.begin().await?;
+    let mut copier = Copier::new(site_url, share_files);
+    match copier
+        .copy_subtree(&txn, chapter, target_book, parent_id)
+        .await
+    {
+        Ok(copy) => {
+            txn.commit().await?;
+            Ok((copy, copier.summary))
+        }
+        Err(err) => {
+            drop(txn);
+            copier.cleanup().await;
+            Err(err)
+        }
+    }
+}
+
+async fn copy_book_content(
+    txn: &DatabaseTransaction,
+    copier: &mut Copier<'_>,
+    book: &books::Model,
+) -> Result<books::Model> {
+    let copy = books::ActiveModel {
+        user_id: Set(book.user_id),
+        title: Set(format!("{}（副本）", book.title)),
+        description: Set(book.description.clone()),
+        cover_image: Set(book.cover_image.clone()),
+        parent_id: Set(book.parent_id),
+        sort_order: Set(book.sort_order),
+        is_public: Set(Some(false)),
+        feed_author: Set(book.feed_author.clone()),
+        feed_owner_email: Set(book.feed_owner_email.clone()),
+        feed_language: Set(book.feed_language.clone()),
+        feed_category: Set(book.feed_category.clone()),
+        feed_explicit: Set(book.feed_explicit),
+        feed_copyright: Set(book.feed_copyright.clone()),
+        normalize_audio: Set(book.normalize_audio),
+        ..Default::default()
+    }
+    .insert(txn)
+    .await?;
+
+    let roots = chapters::Entity::find()
+        .filter(chapters::Column::BookId.eq(book.id))
+        .filter(chapters::Column::ParentId.is_null())
+        .filter(chapters::Column::DeletedAt.is_null())
+        .order_by_asc(chapters::Column::SortOrder)
+        .order_by_asc(chapters::Column::CreatedAt)
+        .all(txn)
+        .await?;
+    for root in &roots {
+        copier.copy_subtree(txn, root, &copy, None).await?;
+    }
+
+    // 不属于任何章节的媒体
+    let book_medias = medias::Entity::find()
+        .filter(medias::Column::BookId.eq(book.id))
+        .filter(medias::Column::ChapterId.is_null())
+        .filter(medias::Column::DeletedAt.is_null())
+        .order_by_asc(medias::Column::Id)
+        .all(txn)
+        .await?;
+    for media in &book_medias {
+        copier.copy_media(txn, media, &copy, None).await?;
+    }
+    let book_placements = media_placements::Entity::find()
+        .filter(media_placements::Column::BookId.eq(book.id))
+        .filter(media_placements::Column::ChapterId.is_null())
+        .all(txn)
+        .await?;
+    for placement in &book_placements {
+        copy_placement(txn, placement, copy.id, None).await?;
+    }
+
+    Ok(copy)
+}
+
+/// 复制整本书（章节结构、媒体和字幕），新书默认不公开
+pub async fn copy_book(
+    db: &DatabaseConnection,
+    book: &books::Model,
+    site_url: &str,
+    share_files: bool,
+) -> Result<(books::Model, CopySummary)> {
+    let txn = db.begin().await?;
+    let mut copier = Copier::new(site_url, share_files);
+    match copy_book_content(&txn, &mut copier, book).await {
+        Ok(copy) => {
+            txn.commit().await?;
+            Ok((copy, copier.summary))
+        }
+        Err(err) => {
+            drop(txn);
+            copier.cleanup().await;
+            Err(err)
+        }
+    }
+}
@@ -0,0 +1,226 @@
+use url::Url;
+
+/// 音频播放器的默认嵌入尺寸
+const AUDIO_SIZE: (u32, u32) = (480, 120);
+/// 视频播放器的默认嵌入尺寸
+const VIDEO_SIZE: (u32, u32) = (640, 360);
+/// 书籍播放列表的默认嵌入尺寸
+const PLAYLIST_SIZE: (u32, u32) = (480, 360);
+
+/// 嵌入播放器配置
+#[derive(Debug, Clone)]
+pub struct EmbedService {
+    /// 是否允许任意来源嵌入（需要显式配置 `*`）
+    allow_any: bool,
+    /// 除本站外允许嵌入的来源
+    allowed_origins: Vec<String>,
+}
+
+/// oEmbed URL 解析结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EmbedTarget {
+    Media(String),
+    Book(i32),
+}
+
+impl EmbedService {
+    /// 解析逗号分隔的来源列表，如 `https://lms.example.edu,https://blog.example.com`，
+    /// `*` 表示允许任意来源；为空时只允许本站嵌入
+    #[must_use]
+    pub fn new(allowed_origins: &str) -> Self {
+        let mut origins = Vec::new();
+        for origin in allowed_origins.split(',').map(str::trim) {
+            if origin.is_empty() {
+                continue;
+            }
+            if origin == "*" {
+                return Self {
+                    allow_any: true,
+                    allowed_origins: Vec::new(),
+                };
+            }
+            match Url::parse(origin) {
+                Ok(url) if matches!(url.scheme(), "http" | "https") => {
+                    origins.push(url.origin().ascii_serialization());
+                }
+                _ => tracing::warn!("忽略无效的嵌入来源: {}", origin),
+            }
+        }
+
+        Self {
+            allow_any: false,
+            allowed_origins: origins,
+        }
+    }
+
+    /// 生成 `Content-Security-Policy` 头的值
+    #[must_use]
+    pub fn content_security_policy(&self) -> String {
+        if self.allow_any {
+            return "frame-ancestors *".to_string();
+        }
+        let mut policy = "frame-ancestors 'self'".to_string();
+        for origin in &self.allowed_origins {
+            policy.push(' ');
+            policy.push_str(origin);
+        }
+        policy
+    }
+
+    /// 解析 oEmbed 请求中的 URL，仅接受本站的媒体访问地址、嵌入地址和书籍嵌入地址
+    #[must_use]
+    pub fn resolve_url(site_url: &str, url: &str) -> Option<EmbedTarget> {
+        let site = Url::parse(site_url).ok()?;
+        let url = Url::parse(url).ok()?;
+        if url.host_str() != site.host_str() {
+            return None;
+        }
+
+        let segments: Vec<&str> = url
+            .path_segments()?
+            .filter(|segment| !segment.is_empty())
+            .collect();
+
+        match segments.as_slice() {
+            ["public", token]
+            | ["public", "media", token]
+            | ["api", "public", "media", token]
+            | ["embed", "media", token] => Some(EmbedTarget::Media((*token).to_string())),
+            ["embed", "books", id] => id.parse().ok().map(EmbedTarget::Book),
+            _ => None,
+        }
+    }
+
+    /// 媒体嵌入页地址
+    #[must_use]
+    pub fn media_embed_url(site_url: &str, access_token: &str) -> String {
+        format!(
+            "{}/embed/media/{}",
+            site_url.trim_end_matches('/'),
+            access_token
+        )
+    }
+
+    /// 书籍播放列表嵌入页地址
+    #[must_use]
+    pub fn book_embed_url(site_url: &str, book_id: i32) -> String {
+        format!("{}/embed/books/{}", site_url.trim_end_matches('/'), book_id)
+    }
+
+    /// 计算嵌入尺寸，按 `maxwidth`/`maxheight` 等比缩小
+    #[must_use]
+    pub fn embed_size(
+        target: &EmbedTarget,
+        file_type: Option<&str>,
+        max_width: Option<u32>,
+        max_height: Option<u32>,
+    ) -> (u32, u32) {
+        let (width, height) = match (target, file_type) {
+            (EmbedTarget::Book(_), _) => PLAYLIST_SIZE,
+            (EmbedTarget::Media(_), Some("video")) => VIDEO_SIZE,
+            (EmbedTarget::Media(_), _) => AUDIO_SIZE,
+        };
+
+        let mut scale = 1.0_f64;
+        if let Some(max_width) = max_width.filter(|w| *w > 0 && *w < width) {
+            scale = scale.min(f64::from(max_width) / f64::from(width));
+        }
+        if let Some(max_height) = max_height.filter(|h| *h > 0 && *h < height) {
+            scale = scale.min(f64::from(max_height) / f64::from(height));
+        }
+
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        (
+            (f64::from(width) * scale).floor() as u32,
+            (f64::from(height) * scale).floor() as u32,
+        )
+    }
+
+    /// 生成 oEmbed 返回的 iframe 代码
+    #[must_use]
+    pub fn iframe_html(src: &str, title: &str, width: u32, height: u32) -> String {
+        format!(
+            "<iframe src=\"{}\" width=\"{}\" height=\"{}\" title=\"{}\" frameborder=\"0\" allow=\"autoplay; encrypted-media\" loading=\"lazy\"></iframe>",
+            html_escape(src),
+            width,
+            height,
+            html_escape(title)
+        )
+    }
+}
+
+fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// 全局嵌入播放器配置，从 `EMBED_ALLOWED_ORIGINS` 环境变量读取
+/// （默认只允许本站嵌入，设置为 `*` 时允许任意来源）
+pub static EMBED_SERVICE: std::sync::LazyLock<EmbedService> = std::sync::LazyLock::new(|| {
+    let allowed_origins = std::env::var("EMBED_ALLOWED_ORIGINS").unwrap_or_default();
+    EmbedService::new(&allowed_origins)
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_security_policy() {
+        assert_eq!(
+            EmbedService::new("*").content_security_policy(),
+            "frame-ancestors *"
+        );
+        assert_eq!(
+            EmbedService::new("https://lms.example.edu/, invalid, http://blog.example.com:8080")
+                .content_security_policy(),
+            "frame-ancestors 'self' https://lms.example.edu http://blog.example.com:8080"
+        );
+        assert_eq!(
+            EmbedService::new("").content_security_policy(),
+            "frame-ancestors 'self'"
+        );
+        assert_eq!(
+            EmbedService::new("invalid").content_security_policy(),
+            "frame-ancestors 'self'"
+        );
+    }
+
+    #[test]
+    fn test_resolve_url() {
+        let site = "http://localhost:5150";
+        assert_eq!(
+            EmbedService::resolve_url(site, "http://localhost:5150/public/abc"),
+            Some(EmbedTarget::Media("abc".to_string()))
+        );
+        assert_eq!(
+            EmbedService::resolve_url(site, "http://localhost:5150/embed/books/3"),
+            Some(EmbedTarget::Book(3))
+        );
+        assert_eq!(
+            EmbedService::resolve_url(site, "http://evil.example.com/public/abc"),
+            None
+        );
+        assert_eq!(
+            EmbedService::resolve_url(site, "http://localhost:5150/api/books/3"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_embed_size() {
+        let media = EmbedTarget::Media("abc".to_string());
+        assert_eq!(
+            EmbedService::embed_size(&media, Some("audio"), None, None),
+            (480, 120)
+        );
+        assert_eq!(
+            EmbedService::embed_size(&media, Some("video"), Some(320), None),
+            (320, 180)
+        );
+    }
+}
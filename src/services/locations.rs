@@ -0,0 +1,43 @@
+//! 内容位置（书籍顶层，或书籍中的章节）的校验
+//!
+//! 移动、排序、附加、剪辑媒体以及跨书移动和复制章节时，
+//! 目标位置必须是当前用户未删除的书籍，章节必须是该书中未删除的章节。
+use loco_rs::prelude::*;
+
+use crate::models::_entities::{books, chapters};
+
+/// 章节必须是书籍中未删除的章节，未指定章节时表示书籍顶层
+pub async fn ensure_chapter_in_book<C>(db: &C, book_id: i32, chapter_id: Option<i32>) -> Result<()>
+where
+    C: ConnectionTrait,
+{
+    if let Some(chapter_id) = chapter_id {
+        chapters::Entity::find_by_id(chapter_id)
+            .filter(chapters::Column::BookId.eq(book_id))
+            .filter(chapters::Column::DeletedAt.is_null())
+            .one(db)
+            .await?
+            .ok_or_else(|| Error::BadRequest("目标章节不存在".to_string()))?;
+    }
+    Ok(())
+}
+
+/// 校验用户可以写入的位置，返回目标书籍
+pub async fn validate<C>(
+    db: &C,
+    user_id: i32,
+    book_id: i32,
+    chapter_id: Option<i32>,
+) -> Result<books::Model>
+where
+    C: ConnectionTrait,
+{
+    let book = books::Entity::find_by_id(book_id)
+        .filter(books::Column::UserId.eq(user_id))
+        .filter(books::Column::DeletedAt.is_null())
+        .one(db)
+        .await?
+        .ok_or_else(|| Error::BadRequest("目标书籍不存在".to_string()))?;
+    ensure_chapter_in_book(db, book_id, chapter_id).await?;
+    Ok(book)
+}
@@ -0,0 +1,181 @@
+//! 媒体文件版本：替换文件时保留旧文件，支持回滚和按数量清理
+//!
+//! 每次上传或替换文件都会记录一个版本，媒体的 `file_version` 指向当前使用的版本。
+//! 回滚只切换媒体使用的文件，`access_token` 和公开链接保持不变。
+//! 版本记录引用的内容文件由 [`content_store`] 按引用数删除。
+use loco_rs::prelude::*;
+use sea_orm::{DatabaseConnection, PaginatorTrait};
+use std::path::PathBuf;
+
+use crate::models::_entities::medias;
+use crate::models::media_versions;
+use crate::services::audio_processing;
+use crate::services::content_store;
+use crate::services::storage::STORAGE_SERVICE;
+
+/// 默认每个媒体保留的版本数（包含当前版本）
+pub const DEFAULT_RETENTION_COUNT: usize = 5;
+
+/// 每个媒体保留的版本数（`MEDIA_VERSION_RETENTION`，至少 1 个）
+#[must_use]
+pub fn retention_count() -> usize {
+    std::env::var("MEDIA_VERSION_RETENTION")
+        .ok()
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .filter(|count| *count > 0)
+        .unwrap_or(DEFAULT_RETENTION_COUNT)
+}
+
+/// 文件是否还被其他媒体或其他媒体的历史版本使用（复制内容时可以共用文件）
+pub async fn is_file_shared<C>(db: &C, path: &str, media_id: i32) -> Result<bool>
+where
+    C: ConnectionTrait,
+{
+    let medias = medias::Entity::find()
+        .filter(medias::Column::FilePath.eq(path))
+        .filter(medias::Column::Id.ne(media_id))
+        .count(db)
+        .await?;
+    if medias > 0 {
+        return Ok(true);
+    }
+    let versions = media_versions::Entity::find()
+        .filter(media_versions::Column::FilePath.eq(path))
+        .filter(media_versions::Column::MediaId.ne(media_id))
+        .count(db)
+        .await?;
+    Ok(versions > 0)
+}
+
+/// 删除版本文件；按内容存储的文件释放引用，
+/// 其他文件在媒体当前仍在使用或与其他媒体共用时不删除
+async fn remove_file(
+    db: &DatabaseConnection,
+    version: &media_versions::Model,
+    media: &medias::Model,
+) -> Result<()> {
+    if let Some(ref content_hash) = version.content_hash {
+        return content_store::release(db, content_hash).await;
+    }
+    let path = version.file_path.as_str();
+    if path == media.file_path || is_file_shared(db, path, media.id).await? {
+        return Ok(());
+    }
+    let file_path = STORAGE_SERVICE.resolve(path);
+    if tokio::fs::metadata(&file_path).await.is_ok() {
+        if let Err(e) = tokio::fs::remove_file(&file_path).await {
+            tracing::warn!("删除历史版本文件失败: {:?}, 错误: {}", file_path, e);
+        }
+    }
+    Ok(())
+}
+
+/// 确保媒体当前的文件已记录为版本（兼容启用版本记录之前上传的媒体）
+pub async fn ensure_current_recorded(db: &DatabaseConnection, media: &medias::Model) -> Result<()> {
+    if media_versions::Model::find_version(db, media.id, media.file_version)
+        .await?
+        .is_none()
+    {
+        media_versions::Model::record_shared(
+            db,
+            media,
+            Some(media.user_id),
+            Some(media.updated_at),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// 下一个版本号（回滚后当前版本不一定是最新的版本）
+pub async fn next_version(db: &DatabaseConnection, media: &medias::Model) -> Result<i32> {
+    let latest = media_versions::Model::find_by_media(db, media.id)
+        .await?
+        .first()
+        .map_or(0, |v| v.version);
+    Ok(latest.max(media.file_version) + 1)
+}
+
+/// 回滚到指定版本，返回更新后的媒体
+pub async fn rollback(
+    db: &DatabaseConnection,
+    media: medias::Model,
+    version: i32,
+) -> Result<medias::Model> {
+    let target = media_versions::Model::find_version(db, media.id, version)
+        .await?
+        .ok_or_else(|| Error::NotFound)?;
+    if target.version == media.file_version {
+        return Err(Error::BadRequest("该版本已经是当前版本".to_string()));
+    }
+    if tokio::fs::metadata(STORAGE_SERVICE.resolve(&target.file_path))
+        .await
+        .is_err()
+    {
+        return Err(Error::BadRequest(
+            "该版本的文件已不存在，无法回滚".to_string(),
+        ));
+    }
+
+    let previous = media.clone();
+    let mut active_model: medias::ActiveModel = media.into();
+    active_model.file_type = Set(target.file_type);
+    active_model.file_path = Set(target.file_path);
+    active_model.file_size = Set(target.file_size);
+    active_model.duration = Set(target.duration);
+    active_model.mime_type = Set(target.mime_type);
+    active_model.original_filename = Set(target.original_filename);
+    active_model.file_version = Set(target.version);
+    active_model.content_hash = Set(target.content_hash);
+    active_model.file_missing_at = Set(None);
+    audio_processing::clear_rendition(&mut active_model);
+    // access_token 保持不变
+
+    let updated = active_model.update(db).await?;
+    audio_processing::remove_rendition(&previous).await;
+    Ok(updated)
+}
+
+/// 按保留数量清理旧版本：始终保留当前版本，其余按版本号从新到旧保留，返回删除的版本数
+pub async fn prune(db: &DatabaseConnection, media: &medias::Model, keep: usize) -> Result<u64> {
+    let keep = keep.max(1);
+    let mut kept = 1;
+    let mut removed = 0;
+
+    for version in media_versions::Model::find_by_media(db, media.id).await? {
+        if version.version == media.file_version {
+            continue;
+        }
+        if kept < keep {
+            kept += 1;
+            continue;
+        }
+        remove_file(db, &version, media).await?;
+        version.delete(db).await?;
+        removed += 1;
+    }
+
+    Ok(removed)
+}
+
+/// 永久删除媒体时删除所有版本记录并释放内容文件的引用
+///
+/// 返回其他不再使用的版本文件，由调用方在事务提交后删除。
+pub async fn purge_all<C>(db: &C, media: &medias::Model) -> Result<Vec<PathBuf>>
+where
+    C: ConnectionTrait + TransactionTrait,
+{
+    let mut unused_files = Vec::new();
+    for version in media_versions::Model::find_by_media(db, media.id).await? {
+        if let Some(ref content_hash) = version.content_hash {
+            content_store::release(db, content_hash).await?;
+            continue;
+        }
+        let path = version.file_path.as_str();
+        if path != media.file_path && !is_file_shared(db, path, media.id).await? {
+            unused_files.push(STORAGE_SERVICE.resolve(path));
+        }
+    }
+    media_versions::Model::delete_by_media(db, media.id).await?;
+    Ok(unused_files)
+}
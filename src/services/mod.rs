@@ -1,5 +1,23 @@
+pub mod analytics;
+pub mod audio_clipping;
 #[allow(clippy::duplicate_mod)]
 pub mod audio_metadata;
+pub mod audio_processing;
+pub mod client_ip;
+pub mod content_store;
+pub mod content_transfer;
+pub mod embed;
+pub mod locations;
+pub mod media_versions;
+pub mod oidc;
+pub mod podcast_feed;
+pub mod publishing;
 pub mod qrcode;
+pub mod rate_limit;
 pub mod storage;
+pub mod storage_integrity;
+pub mod subtitles;
+pub mod totp;
+pub mod trash;
 pub mod video_metadata;
+pub mod webhooks;
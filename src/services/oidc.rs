@@ -0,0 +1,538 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, RwLock};
+use std::time::{Duration, Instant};
+
+use axum::http::StatusCode;
+use data_encoding::BASE64URL_NOPAD;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{Algorithm, DecodingKey, Validation};
+use loco_rs::prelude::*;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use url::Url;
+use uuid::Uuid;
+
+/// 发现文档和 JWKS 的缓存时间
+const METADATA_TTL: Duration = Duration::from_secs(3600);
+
+/// 请求身份提供方的超时时间
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 默认申请的权限范围
+const DEFAULT_SCOPES: &str = "openid email profile";
+
+/// ID Token 只接受非对称签名算法
+const ALLOWED_ALGORITHMS: &[Algorithm] = &[
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+static HTTP_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    reqwest::Client::builder()
+        .timeout(HTTP_TIMEOUT)
+        .build()
+        .expect("failed to build http client")
+});
+
+static METADATA_CACHE: LazyLock<RwLock<HashMap<String, (Instant, ProviderMetadata)>>> =
+    LazyLock::new(Default::default);
+
+static JWKS_CACHE: LazyLock<RwLock<HashMap<String, (Instant, JwkSet)>>> =
+    LazyLock::new(Default::default);
+
+/// 单点登录配置
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    pub issuer: String,
+    pub client_id: String,
+    /// 公开客户端（只使用 PKCE）不需要密钥
+    pub client_secret: Option<String>,
+    pub redirect_uri: String,
+    pub scopes: String,
+    /// 邮箱没有对应的本地账号时自动创建
+    pub auto_create_users: bool,
+    /// ID Token 中保存用户组的字段名，未配置时不同步用户组
+    pub groups_claim: Option<String>,
+    /// 身份提供方用户组到本地用户组名称的映射，为空时按同名匹配
+    pub group_mapping: Vec<(String, String)>,
+}
+
+impl OidcConfig {
+    /// 从环境变量读取配置，`OIDC_ISSUER`、`OIDC_CLIENT_ID`、`OIDC_REDIRECT_URI`
+    /// 任一未设置时视为未启用单点登录
+    ///
+    /// 可选变量：`OIDC_CLIENT_SECRET`、`OIDC_SCOPES`、`OIDC_AUTO_CREATE_USERS`（`true`/`1`）、
+    /// `OIDC_GROUPS_CLAIM`、`OIDC_GROUP_MAPPING`（如 `teachers=教师组,staff=工作人员`）
+    #[must_use]
+    pub fn from_env() -> Option<Self> {
+        let var = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+
+        Some(Self {
+            issuer: var("OIDC_ISSUER")?.trim_end_matches('/').to_string(),
+            client_id: var("OIDC_CLIENT_ID")?,
+            client_secret: var("OIDC_CLIENT_SECRET"),
+            redirect_uri: var("OIDC_REDIRECT_URI")?,
+            scopes: var("OIDC_SCOPES").unwrap_or_else(|| DEFAULT_SCOPES.to_string()),
+            auto_create_users: var("OIDC_AUTO_CREATE_USERS")
+                .is_some_and(|v| matches!(v.to_ascii_lowercase().as_str(), "1" | "true" | "yes")),
+            groups_claim: var("OIDC_GROUPS_CLAIM"),
+            group_mapping: var("OIDC_GROUP_MAPPING")
+                .map(|v| parse_group_mapping(&v))
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// 解析逗号分隔的 `外部组=本地组` 映射，忽略格式错误的项
+#[must_use]
+pub fn parse_group_mapping(value: &str) -> Vec<(String, String)> {
+    value
+        .split(',')
+        .filter_map(|pair| {
+            let (external, local) = pair.split_once('=')?;
+            let (external, local) = (external.trim(), local.trim());
+            (!external.is_empty() && !local.is_empty())
+                .then(|| (external.to_string(), local.to_string()))
+        })
+        .collect()
+}
+
+/// 身份提供方发现文档（`/.well-known/openid-configuration`）中用到的字段
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+/// ID Token 声明
+#[derive(Debug, Clone, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub name: Option<String>,
+    pub preferred_username: Option<String>,
+    pub nonce: Option<String>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, serde_json::Value>,
+}
+
+impl IdTokenClaims {
+    /// 身份提供方确认过的邮箱
+    ///
+    /// 只有 `email_verified` 明确为 `true` 时才返回，未声明的邮箱可能由用户在身份提供方随意填写，
+    /// 不能用来关联已有账号
+    #[must_use]
+    pub fn verified_email(&self) -> Option<String> {
+        if self.email_verified != Some(true) {
+            return None;
+        }
+        self.email
+            .as_deref()
+            .map(str::trim)
+            .filter(|email| !email.is_empty())
+            .map(str::to_lowercase)
+    }
+
+    /// 读取用户组声明，支持字符串数组或单个字符串
+    #[must_use]
+    pub fn groups(&self, claim: &str) -> Vec<String> {
+        match self.extra.get(claim) {
+            Some(serde_json::Value::Array(items)) => items
+                .iter()
+                .filter_map(serde_json::Value::as_str)
+                .map(str::to_string)
+                .collect(),
+            Some(serde_json::Value::String(group)) => vec![group.clone()],
+            _ => Vec::new(),
+        }
+    }
+
+    /// 自动创建账号时使用的显示名称
+    #[must_use]
+    pub fn display_name(&self) -> String {
+        self.name
+            .as_deref()
+            .or(self.preferred_username.as_deref())
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .or_else(|| {
+                self.email
+                    .as_deref()
+                    .and_then(|email| email.split('@').next())
+                    .map(str::to_string)
+            })
+            .unwrap_or_else(|| self.sub.clone())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: Option<String>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+fn provider_error(message: impl Into<String>) -> Error {
+    Error::CustomError(
+        StatusCode::BAD_GATEWAY,
+        loco_rs::controller::ErrorDetail::new("oidc_provider_error".to_string(), message.into()),
+    )
+}
+
+/// 生成随机的 state / nonce
+#[must_use]
+pub fn random_token() -> String {
+    Uuid::new_v4().simple().to_string()
+}
+
+/// 生成 PKCE 校验码（43 个字符），返回 `(code_verifier, code_challenge)`
+#[must_use]
+pub fn generate_pkce() -> (String, String) {
+    let mut bytes = Vec::with_capacity(32);
+    bytes.extend_from_slice(Uuid::new_v4().as_bytes());
+    bytes.extend_from_slice(Uuid::new_v4().as_bytes());
+    let verifier = BASE64URL_NOPAD.encode(&bytes);
+    let challenge = pkce_challenge(&verifier);
+    (verifier, challenge)
+}
+
+/// 计算 S256 方式的 `code_challenge`
+#[must_use]
+pub fn pkce_challenge(verifier: &str) -> String {
+    BASE64URL_NOPAD.encode(&Sha256::digest(verifier.as_bytes()))
+}
+
+async fn fetch_json<T: serde::de::DeserializeOwned>(url: &str) -> Result<T> {
+    let response = HTTP_CLIENT
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| provider_error(format!("无法连接身份提供方: {e}")))?;
+    if !response.status().is_success() {
+        return Err(provider_error(format!(
+            "身份提供方返回错误状态: {}",
+            response.status()
+        )));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| provider_error(format!("身份提供方响应格式错误: {e}")))
+}
+
+/// 获取身份提供方的发现文档（缓存一小时）
+///
+/// # Errors
+///
+/// When the provider is unreachable or its issuer does not match the configuration
+pub async fn discover(config: &OidcConfig) -> Result<ProviderMetadata> {
+    if let Some((fetched_at, metadata)) = METADATA_CACHE
+        .read()
+        .ok()
+        .and_then(|cache| cache.get(&config.issuer).cloned())
+    {
+        if fetched_at.elapsed() < METADATA_TTL {
+            return Ok(metadata);
+        }
+    }
+
+    let url = format!("{}/.well-known/openid-configuration", config.issuer);
+    let metadata: ProviderMetadata = fetch_json(&url).await?;
+    if metadata.issuer.trim_end_matches('/') != config.issuer {
+        return Err(provider_error("发现文档中的 issuer 与配置不一致"));
+    }
+
+    if let Ok(mut cache) = METADATA_CACHE.write() {
+        cache.insert(config.issuer.clone(), (Instant::now(), metadata.clone()));
+    }
+    Ok(metadata)
+}
+
+/// 获取签名公钥；缓存中找不到 `kid` 时重新拉取（身份提供方轮换了密钥）
+async fn signing_keys(metadata: &ProviderMetadata, kid: Option<&str>) -> Result<JwkSet> {
+    let cached = JWKS_CACHE
+        .read()
+        .ok()
+        .and_then(|cache| cache.get(&metadata.jwks_uri).cloned());
+    if let Some((fetched_at, jwks)) = cached {
+        let has_key = kid.is_none_or(|kid| jwks.find(kid).is_some());
+        if has_key && fetched_at.elapsed() < METADATA_TTL {
+            return Ok(jwks);
+        }
+    }
+
+    let jwks: JwkSet = fetch_json(&metadata.jwks_uri).await?;
+    if let Ok(mut cache) = JWKS_CACHE.write() {
+        cache.insert(metadata.jwks_uri.clone(), (Instant::now(), jwks.clone()));
+    }
+    Ok(jwks)
+}
+
+/// 生成跳转到身份提供方的授权地址（授权码模式 + PKCE）
+///
+/// # Errors
+///
+/// When the authorization endpoint is not a valid URL
+pub fn authorization_url(
+    metadata: &ProviderMetadata,
+    config: &OidcConfig,
+    state: &str,
+    nonce: &str,
+    code_challenge: &str,
+) -> Result<String> {
+    let mut url =
+        Url::parse(&metadata.authorization_endpoint).map_err(|_| provider_error("授权地址无效"))?;
+    url.query_pairs_mut()
+        .append_pair("response_type", "code")
+        .append_pair("client_id", &config.client_id)
+        .append_pair("redirect_uri", &config.redirect_uri)
+        .append_pair("scope", &config.scopes)
+        .append_pair("state", state)
+        .append_pair("nonce", nonce)
+        .append_pair("code_challenge", code_challenge)
+        .append_pair("code_challenge_method", "S256");
+    Ok(url.to_string())
+}
+
+/// 使用授权码换取 ID Token
+///
+/// # Errors
+///
+/// When the provider rejects the code or does not return an ID token
+pub async fn exchange_code(
+    metadata: &ProviderMetadata,
+    config: &OidcConfig,
+    code: &str,
+    code_verifier: &str,
+) -> Result<String> {
+    let form = [
+        ("grant_type", "authorization_code"),
+        ("code", code),
+        ("redirect_uri", config.redirect_uri.as_str()),
+        ("client_id", config.client_id.as_str()),
+        ("code_verifier", code_verifier),
+    ];
+    let mut request = HTTP_CLIENT.post(&metadata.token_endpoint).form(&form);
+    if let Some(secret) = &config.client_secret {
+        request = request.basic_auth(&config.client_id, Some(secret));
+    }
+
+    let response = request
+        .send()
+        .await
+        .map_err(|e| provider_error(format!("无法连接身份提供方: {e}")))?;
+    let status = response.status();
+    let body: TokenResponse = response
+        .json()
+        .await
+        .map_err(|e| provider_error(format!("身份提供方响应格式错误: {e}")))?;
+
+    if let Some(error) = body.error {
+        tracing::info!(
+            error = %error,
+            description = ?body.error_description,
+            "oidc code exchange rejected"
+        );
+        return Err(Error::Unauthorized("授权码无效或已过期".to_string()));
+    }
+    if !status.is_success() {
+        return Err(provider_error(format!("身份提供方返回错误状态: {status}")));
+    }
+    body.id_token
+        .ok_or_else(|| provider_error("身份提供方未返回 ID Token"))
+}
+
+/// 校验 ID Token 的签名、issuer、audience、有效期和 nonce
+///
+/// # Errors
+///
+/// When the token is invalid
+pub async fn validate_id_token(
+    metadata: &ProviderMetadata,
+    config: &OidcConfig,
+    id_token: &str,
+    nonce: &str,
+) -> Result<IdTokenClaims> {
+    let invalid = || Error::Unauthorized("ID Token 无效".to_string());
+
+    let header = jsonwebtoken::decode_header(id_token).map_err(|_| invalid())?;
+    if !ALLOWED_ALGORITHMS.contains(&header.alg) {
+        return Err(invalid());
+    }
+
+    let jwks = signing_keys(metadata, header.kid.as_deref()).await?;
+    let jwk = match header.kid.as_deref() {
+        Some(kid) => jwks.find(kid),
+        // 未指定 kid 时只允许身份提供方只有一个密钥
+        None if jwks.keys.len() == 1 => jwks.keys.first(),
+        None => None,
+    }
+    .ok_or_else(invalid)?;
+    let key = DecodingKey::from_jwk(jwk).map_err(|_| invalid())?;
+
+    let mut validation = Validation::new(header.alg);
+    validation.set_audience(&[&config.client_id]);
+    validation.set_issuer(&[&config.issuer, &metadata.issuer]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+    let claims = jsonwebtoken::decode::<IdTokenClaims>(id_token, &key, &validation)
+        .map_err(|e| {
+            tracing::info!(error = e.to_string(), "oidc id token rejected");
+            invalid()
+        })?
+        .claims;
+
+    if claims.nonce.as_deref() != Some(nonce) {
+        return Err(invalid());
+    }
+    Ok(claims)
+}
+
+/// 根据身份提供方的用户组计算本地用户组成员关系，返回 `(受管理的组, 应加入的组)`
+///
+/// 配置了映射时只管理映射中出现的本地组；未配置映射时按组名同名匹配，
+/// 所有本地组都由身份提供方管理
+#[must_use]
+pub fn resolve_groups(
+    mapping: &[(String, String)],
+    external_groups: &[String],
+    local_groups: &[(i32, String)],
+) -> (Vec<i32>, Vec<i32>) {
+    let find_local = |name: &str| {
+        local_groups
+            .iter()
+            .find(|(_, local)| local == name)
+            .map(|(id, _)| *id)
+    };
+
+    let (managed, desired): (Vec<i32>, Vec<i32>) = if mapping.is_empty() {
+        (
+            local_groups.iter().map(|(id, _)| *id).collect(),
+            external_groups
+                .iter()
+                .filter_map(|group| find_local(group))
+                .collect(),
+        )
+    } else {
+        (
+            mapping
+                .iter()
+                .filter_map(|(_, local)| find_local(local))
+                .collect(),
+            mapping
+                .iter()
+                .filter(|(external, _)| external_groups.contains(external))
+                .filter_map(|(_, local)| find_local(local))
+                .collect(),
+        )
+    };
+
+    let dedup = |mut ids: Vec<i32>| {
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    };
+    (dedup(managed), dedup(desired))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pkce_challenge() {
+        // RFC 7636 附录 B
+        assert_eq!(
+            pkce_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+
+        let (verifier, challenge) = generate_pkce();
+        assert_eq!(verifier.len(), 43);
+        assert_eq!(challenge, pkce_challenge(&verifier));
+    }
+
+    #[test]
+    fn test_parse_group_mapping() {
+        assert_eq!(
+            parse_group_mapping(" teachers = 教师组, invalid, =x, staff=工作人员 "),
+            vec![
+                ("teachers".to_string(), "教师组".to_string()),
+                ("staff".to_string(), "工作人员".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_groups() {
+        let local = vec![(1, "教师组".to_string()), (2, "学生组".to_string())];
+        let external = vec!["教师组".to_string(), "未知组".to_string()];
+
+        assert_eq!(
+            resolve_groups(&[], &external, &local),
+            (vec![1, 2], vec![1])
+        );
+
+        let mapping = parse_group_mapping("teachers=教师组,missing=不存在");
+        assert_eq!(
+            resolve_groups(&mapping, &["teachers".to_string()], &local),
+            (vec![1], vec![1])
+        );
+        assert_eq!(
+            resolve_groups(&mapping, &external, &local),
+            (vec![1], vec![])
+        );
+    }
+
+    #[test]
+    fn test_claims_helpers() {
+        let claims: IdTokenClaims = serde_json::from_value(serde_json::json!({
+            "sub": "abc",
+            "email": "Teacher@Example.com",
+            "email_verified": true,
+            "groups": ["a", "b", 1],
+            "role": "single",
+        }))
+        .unwrap();
+
+        assert_eq!(
+            claims.verified_email().as_deref(),
+            Some("teacher@example.com")
+        );
+        assert_eq!(claims.groups("groups"), vec!["a", "b"]);
+        assert_eq!(claims.groups("role"), vec!["single"]);
+        assert!(claims.groups("missing").is_empty());
+        assert_eq!(claims.display_name(), "Teacher");
+
+        let unverified: IdTokenClaims = serde_json::from_value(serde_json::json!({
+            "sub": "abc",
+            "email": "a@example.com",
+            "email_verified": false,
+        }))
+        .unwrap();
+        assert_eq!(unverified.verified_email(), None);
+
+        let undeclared: IdTokenClaims = serde_json::from_value(serde_json::json!({
+            "sub": "abc",
+            "email": "a@example.com",
+        }))
+        .unwrap();
+        assert_eq!(undeclared.verified_email(), None);
+    }
+}
@@ -0,0 +1,409 @@
+use chrono::{DateTime, Utc};
+use loco_rs::prelude::*;
+use sea_orm::{DatabaseConnection, QueryOrder};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+
+use crate::models::_entities::{books, chapters, medias, users};
+use crate::models::media_placements;
+use crate::services::audio_processing;
+use crate::services::publishing;
+
+/// 未设置 `feed_language` 时使用的默认语言
+const DEFAULT_LANGUAGE: &str = "zh-cn";
+
+/// 书籍订阅源地址
+#[must_use]
+pub fn feed_url(site_url: &str, book_id: i32) -> String {
+    format!(
+        "{}/api/public/books/{}/feed.xml",
+        site_url.trim_end_matches('/'),
+        book_id
+    )
+}
+
+/// 媒体公开访问地址（播客客户端下载用）
+#[must_use]
+pub fn enclosure_url(site_url: &str, access_token: &str) -> String {
+    format!(
+        "{}/api/public/media/{}",
+        site_url.trim_end_matches('/'),
+        access_token
+    )
+}
+
+/// 播客 RSS 订阅源（RSS 2.0 + iTunes 命名空间）
+#[derive(Debug, Clone)]
+pub struct PodcastFeed {
+    pub book: books::Model,
+    pub author: String,
+    pub site_url: String,
+    /// 按章节树顺序排列的公开媒体
+    pub episodes: Vec<medias::Model>,
+    /// 订阅源内容最后修改时间（书籍、章节、媒体、站点设置中最新的 `updated_at`）
+    pub last_modified: DateTime<Utc>,
+}
+
+impl PodcastFeed {
+    /// 加载书籍的订阅源数据
+    ///
+    /// # Errors
+    ///
+    /// When database query fails
+    pub async fn load(
+        db: &DatabaseConnection,
+        book: books::Model,
+        site_url: &str,
+        settings_updated_at: DateTime<Utc>,
+    ) -> Result<Self> {
+        let chapters = chapters::Entity::find()
+            .filter(chapters::Column::BookId.eq(book.id))
+            .filter(chapters::Column::DeletedAt.is_null())
+            .order_by_asc(chapters::Column::SortOrder)
+            .order_by_asc(chapters::Column::CreatedAt)
+            .order_by_asc(chapters::Column::Id)
+            .all(db)
+            .await?;
+
+        let medias = load_live_medias(db, book.id, &chapters).await?;
+
+        let author = match book.feed_author.as_deref().filter(|a| !a.trim().is_empty()) {
+            Some(author) => author.to_string(),
+            None => users::Entity::find_by_id(book.user_id)
+                .one(db)
+                .await?
+                .map(|user| user.name)
+                .unwrap_or_default(),
+        };
+
+        let mut last_modified: DateTime<Utc> = book.updated_at.into();
+        last_modified = last_modified.max(settings_updated_at);
+        for chapter in &chapters {
+            last_modified = last_modified.max(chapter.updated_at.into());
+        }
+        for media in &medias {
+            last_modified = last_modified.max(media.updated_at.into());
+        }
+
+        Ok(Self {
+            episodes: medias,
+            book,
+            author,
+            site_url: site_url.trim_end_matches('/').to_string(),
+            last_modified,
+        })
+    }
+
+    /// 生成 RSS XML
+    #[must_use]
+    pub fn to_xml(&self) -> String {
+        let book = &self.book;
+        let feed_url = feed_url(&self.site_url, book.id);
+        let description = book.description.as_deref().unwrap_or(&book.title);
+        let language = book
+            .feed_language
+            .as_deref()
+            .filter(|l| !l.trim().is_empty())
+            .unwrap_or(DEFAULT_LANGUAGE);
+        let explicit = book.feed_explicit.unwrap_or(false);
+
+        let mut xml = String::new();
+        xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(concat!(
+            "<rss version=\"2.0\"",
+            " xmlns:itunes=\"http://www.itunes.com/dtds/podcast-1.0.dtd\"",
+            " xmlns:atom=\"http://www.w3.org/2005/Atom\">\n"
+        ));
+        xml.push_str("<channel>\n");
+        push_element(&mut xml, "title", &book.title);
+        push_element(&mut xml, "link", &self.site_url);
+        xml.push_str(&format!(
+            "<atom:link href=\"{}\" rel=\"self\" type=\"application/rss+xml\"/>\n",
+            xml_escape(&feed_url)
+        ));
+        push_element(&mut xml, "description", description);
+        push_element(&mut xml, "language", language);
+        if let Some(copyright) = book.feed_copyright.as_deref().filter(|c| !c.is_empty()) {
+            push_element(&mut xml, "copyright", copyright);
+        }
+        push_element(&mut xml, "lastBuildDate", &self.last_modified.to_rfc2822());
+        push_element(&mut xml, "generator", "QCast");
+
+        push_element(&mut xml, "itunes:author", &self.author);
+        push_element(&mut xml, "itunes:summary", description);
+        push_element(&mut xml, "itunes:type", "serial");
+        push_element(
+            &mut xml,
+            "itunes:explicit",
+            if explicit { "true" } else { "false" },
+        );
+        if let Some(email) = book.feed_owner_email.as_deref().filter(|e| !e.is_empty()) {
+            xml.push_str("<itunes:owner>\n");
+            push_element(&mut xml, "itunes:name", &self.author);
+            push_element(&mut xml, "itunes:email", email);
+            xml.push_str("</itunes:owner>\n");
+        }
+        if let Some(category) = book.feed_category.as_deref().filter(|c| !c.is_empty()) {
+            xml.push_str(&format!(
+                "<itunes:category text=\"{}\"/>\n",
+                xml_escape(category)
+            ));
+        }
+        if let Some(cover) = book.cover_image.as_deref().filter(|c| !c.is_empty()) {
+            let cover_url = absolute_url(&self.site_url, cover);
+            xml.push_str(&format!(
+                "<itunes:image href=\"{}\"/>\n",
+                xml_escape(&cover_url)
+            ));
+            xml.push_str("<image>\n");
+            push_element(&mut xml, "url", &cover_url);
+            push_element(&mut xml, "title", &book.title);
+            push_element(&mut xml, "link", &self.site_url);
+            xml.push_str("</image>\n");
+        }
+
+        for (index, media) in self.episodes.iter().enumerate() {
+            self.push_item(&mut xml, index + 1, media);
+        }
+
+        xml.push_str("</channel>\n");
+        xml.push_str("</rss>\n");
+        xml
+    }
+
+    fn push_item(&self, xml: &mut String, episode: usize, media: &medias::Model) {
+        let created_at: DateTime<Utc> = media.created_at.into();
+        // 开启了音频处理时，订阅地址返回的是处理后（去掉首尾静音）的版本
+        let (file_size, mime_type, duration) = match media.normalized_file_size {
+            Some(size) if self.book.normalize_audio => (
+                Some(size),
+                audio_processing::NORMALIZED_MIME_TYPE,
+                audio_processing::LoudnessAnalysis::from_media(media)
+                    .and_then(|analysis| i32::try_from((analysis.duration_ms + 500) / 1000).ok())
+                    .or(media.duration),
+            ),
+            _ => (
+                media.file_size,
+                media
+                    .mime_type
+                    .as_deref()
+                    .unwrap_or("application/octet-stream"),
+                media.duration,
+            ),
+        };
+
+        xml.push_str("<item>\n");
+        push_element(xml, "title", &media.title);
+        if let Some(description) = media.description.as_deref().filter(|d| !d.is_empty()) {
+            push_element(xml, "description", description);
+            push_element(xml, "itunes:summary", description);
+        }
+        xml.push_str(&format!(
+            "<guid isPermaLink=\"false\">{}</guid>\n",
+            xml_escape(&media.access_token)
+        ));
+        push_element(xml, "pubDate", &created_at.to_rfc2822());
+        xml.push_str(&format!(
+            "<enclosure url=\"{}\" length=\"{}\" type=\"{}\"/>\n",
+            xml_escape(&enclosure_url(&self.site_url, &media.access_token)),
+            file_size.unwrap_or(0).max(0),
+            xml_escape(mime_type)
+        ));
+        if let Some(duration) = duration {
+            push_element(xml, "itunes:duration", &format_duration(duration));
+        }
+        push_element(xml, "itunes:episode", &episode.to_string());
+        push_element(xml, "itunes:episodeType", "full");
+        xml.push_str("</item>\n");
+    }
+}
+
+/// 根据 XML 内容生成强 `ETag`（SHA-256 摘要，不随进程和版本变化）
+#[must_use]
+pub fn etag(content: &str) -> String {
+    format!("\"{}\"", hex::encode(Sha256::digest(content.as_bytes())))
+}
+
+/// 书籍中对外公开的媒体，包括附加到书籍顶层和各章节的媒体，按章节树顺序排列
+///
+/// 附加的媒体按附加位置的章节和排序号排列，使用附加时设置的标题；
+/// 同一媒体在书籍中出现多次时只保留第一次。
+///
+/// # Errors
+///
+/// When database query fails
+pub async fn load_live_medias<C>(
+    db: &C,
+    book_id: i32,
+    chapters: &[chapters::Model],
+) -> Result<Vec<medias::Model>>
+where
+    C: ConnectionTrait,
+{
+    let mut medias = medias::Entity::find()
+        .filter(medias::Column::BookId.eq(book_id))
+        .filter(publishing::live_medias_condition())
+        .filter(medias::Column::DeletedAt.is_null())
+        .order_by_asc(medias::Column::SortOrder)
+        .order_by_asc(medias::Column::CreatedAt)
+        .order_by_asc(medias::Column::Id)
+        .all(db)
+        .await?;
+
+    let chapter_ids: Vec<i32> = chapters.iter().map(|c| c.id).collect();
+    let mut placed = media_placements::Model::find_at(db, book_id, None).await?;
+    placed.extend(media_placements::Model::find_in_chapters(db, &chapter_ids).await?);
+    medias.extend(
+        placed
+            .into_iter()
+            .filter(|(_, media)| publishing::media_is_live(media))
+            .map(|(placement, mut media)| {
+                media.chapter_id = placement.chapter_id;
+                media.sort_order = placement.sort_order;
+                if let Some(title) = placement.title {
+                    media.title = title;
+                }
+                media
+            }),
+    );
+    // 没有排序号的旧数据排在最后
+    medias.sort_by_key(|media| (media.sort_order.is_none(), media.sort_order));
+
+    let mut seen = HashSet::new();
+    let medias = order_by_chapter_tree(chapters, medias)
+        .into_iter()
+        .filter(|media| seen.insert(media.id))
+        .collect();
+    Ok(medias)
+}
+
+/// 按章节树深度优先顺序排列媒体：未归属章节的媒体排在最前，
+/// 同一章节内保持传入的顺序（调用方按排序号查询）
+#[must_use]
+pub fn order_by_chapter_tree(
+    chapters: &[chapters::Model],
+    medias: Vec<medias::Model>,
+) -> Vec<medias::Model> {
+    // chapters 已按 sort_order 排序，这里只需按父节点分组
+    let mut children: HashMap<Option<i32>, Vec<i32>> = HashMap::new();
+    for chapter in chapters {
+        children
+            .entry(chapter.parent_id)
+            .or_default()
+            .push(chapter.id);
+    }
+
+    let mut positions: HashMap<i32, usize> = HashMap::new();
+    let mut stack: Vec<i32> = children
+        .get(&None)
+        .map(|roots| roots.iter().rev().copied().collect())
+        .unwrap_or_default();
+    while let Some(chapter_id) = stack.pop() {
+        if positions.contains_key(&chapter_id) {
+            continue; // 避免循环
+        }
+        positions.insert(chapter_id, positions.len() + 1);
+        if let Some(kids) = children.get(&Some(chapter_id)) {
+            stack.extend(kids.iter().rev().copied());
+        }
+    }
+
+    let mut medias = medias;
+    // sort_by_key 是稳定排序，保留查询时的排序号顺序；
+    // 父章节缺失的孤立章节排在最后
+    medias.sort_by_key(|media| match media.chapter_id {
+        None => 0,
+        Some(chapter_id) => positions.get(&chapter_id).copied().unwrap_or(usize::MAX),
+    });
+    medias
+}
+
+/// 将秒数格式化为 `HH:MM:SS`
+fn format_duration(seconds: i32) -> String {
+    let seconds = seconds.max(0);
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        (seconds % 3600) / 60,
+        seconds % 60
+    )
+}
+
+/// 将站内相对路径转换为绝对 URL
+#[must_use]
+pub fn absolute_url(site_url: &str, path: &str) -> String {
+    if path.starts_with("http://") || path.starts_with("https://") {
+        path.to_string()
+    } else {
+        format!(
+            "{}/{}",
+            site_url.trim_end_matches('/'),
+            path.trim_start_matches('/')
+        )
+    }
+}
+
+fn push_element(xml: &mut String, name: &str, value: &str) {
+    xml.push_str(&format!("<{name}>{}</{name}>\n", xml_escape(value)));
+}
+
+/// 转义 XML 特殊字符，并去除 XML 1.0 不允许的控制字符
+fn xml_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xml_escape() {
+        assert_eq!(xml_escape("a & b"), "a &amp; b");
+        assert_eq!(
+            xml_escape("<tag attr=\"x\">"),
+            "&lt;tag attr=&quot;x&quot;&gt;"
+        );
+        assert_eq!(xml_escape("bell\u{7}"), "bell");
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(0), "00:00:00");
+        assert_eq!(format_duration(125), "00:02:05");
+        assert_eq!(format_duration(3725), "01:02:05");
+    }
+
+    #[test]
+    fn test_absolute_url() {
+        assert_eq!(
+            absolute_url("http://example.com/", "/uploads/cover.png"),
+            "http://example.com/uploads/cover.png"
+        );
+        assert_eq!(
+            absolute_url("http://example.com", "https://cdn.example.com/c.png"),
+            "https://cdn.example.com/c.png"
+        );
+    }
+
+    #[test]
+    fn test_etag_is_stable() {
+        assert_eq!(
+            etag(""),
+            "\"e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855\""
+        );
+        assert_ne!(etag("<rss/>"), etag("<rss />"));
+    }
+}
@@ -0,0 +1,266 @@
+//! 定时发布：按 `publish_at` / `unpublish_at` 控制书籍和媒体的公开时间窗口
+//!
+//! 公开访问时直接按时间窗口判断，不依赖后台任务是否已经运行；
+//! 后台任务负责把到期的条目切换为公开或下架，并通知作者。
+use axum::http::StatusCode;
+use loco_rs::prelude::*;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::{Condition, DatabaseConnection};
+use serde::{Deserialize, Serialize};
+
+use crate::mailers::publishing::PublishingMailer;
+use crate::models::_entities::{books, medias};
+use crate::models::audit_logs::{self, AuditEntry};
+use crate::models::users;
+use crate::models::webhook_endpoints::EVENT_MEDIA_PUBLISHED;
+use crate::services::webhooks;
+
+/// 设置发布时间窗口的参数，两个时间都为空表示取消定时
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ScheduleParams {
+    pub publish_at: Option<DateTimeWithTimeZone>,
+    pub unpublish_at: Option<DateTimeWithTimeZone>,
+}
+
+impl ScheduleParams {
+    /// 下架时间必须晚于发布时间
+    pub fn validate(&self) -> Result<()> {
+        if let (Some(publish_at), Some(unpublish_at)) = (self.publish_at, self.unpublish_at) {
+            if unpublish_at <= publish_at {
+                return Err(Error::BadRequest("下架时间必须晚于发布时间".to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 一次定时任务切换状态的条目数
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct ScheduleSummary {
+    pub published_books: u64,
+    pub unpublished_books: u64,
+    pub published_medias: u64,
+    pub unpublished_medias: u64,
+}
+
+fn now() -> DateTimeWithTimeZone {
+    chrono::Utc::now().into()
+}
+
+/// 发布时间是否已到
+#[must_use]
+pub fn is_due(at: Option<DateTimeWithTimeZone>) -> bool {
+    at.is_some_and(|at| at <= now())
+}
+
+/// 是否对外公开：已公开，或定时发布时间已到但后台任务尚未切换状态
+#[must_use]
+pub fn is_live(is_public: bool, publish_at: Option<DateTimeWithTimeZone>) -> bool {
+    is_public || is_due(publish_at)
+}
+
+/// 媒体当前是否对外公开，与 [`live_medias_condition`] 的判断一致
+#[must_use]
+pub fn media_is_live(media: &medias::Model) -> bool {
+    media.deleted_at.is_none()
+        && is_live(media.is_public, media.publish_at)
+        && media.publish_at.is_none_or(|at| at <= now())
+        && !is_due(media.unpublish_at)
+}
+
+/// 书籍自身当前是否对外公开（未删除、已公开且未过下架时间）
+#[must_use]
+pub fn book_is_live(book: &books::Model) -> bool {
+    book.deleted_at.is_none()
+        && is_live(book.is_public.unwrap_or(false), book.publish_at)
+        && !is_due(book.unpublish_at)
+}
+
+/// 公开状态按书籍层级继承：任一上级书籍未公开时，书籍也不对外可见
+pub async fn check_book_ancestors(db: &DatabaseConnection, book: &books::Model) -> Result<()> {
+    let ancestors = books::Model::find_ancestors(db, book.id).await?;
+    if ancestors.iter().all(book_is_live) {
+        Ok(())
+    } else {
+        Err(Error::NotFound)
+    }
+}
+
+/// 检查公开时间窗口：未到发布时间返回 403，已过下架时间返回 410
+pub fn check_window(
+    publish_at: Option<DateTimeWithTimeZone>,
+    unpublish_at: Option<DateTimeWithTimeZone>,
+) -> Result<()> {
+    if is_due(unpublish_at) {
+        return Err(Error::CustomError(
+            StatusCode::GONE,
+            loco_rs::controller::ErrorDetail::new("expired".to_string(), "内容已下架".to_string()),
+        ));
+    }
+    if let Some(publish_at) = publish_at.filter(|at| *at > now()) {
+        return Err(Error::CustomError(
+            StatusCode::FORBIDDEN,
+            loco_rs::controller::ErrorDetail::new(
+                "not_yet_published".to_string(),
+                format!("内容将于 {} 发布", publish_at.to_rfc3339()),
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// 当前对外公开的媒体（用于订阅源、播放列表等批量查询）
+#[must_use]
+pub fn live_medias_condition() -> Condition {
+    let now = now();
+    Condition::all()
+        .add(
+            Condition::any()
+                .add(medias::Column::IsPublic.eq(true))
+                .add(medias::Column::PublishAt.lte(now)),
+        )
+        .add(
+            Condition::any()
+                .add(medias::Column::PublishAt.is_null())
+                .add(medias::Column::PublishAt.lte(now)),
+        )
+        .add(
+            Condition::any()
+                .add(medias::Column::UnpublishAt.is_null())
+                .add(medias::Column::UnpublishAt.gt(now)),
+        )
+}
+
+async fn notify(ctx: &AppContext, user_id: i32, kind: &str, title: &str) {
+    let user = match users::Entity::find_by_id(user_id).one(&ctx.db).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return,
+        Err(err) => {
+            tracing::warn!(
+                error = err.to_string(),
+                "failed to load owner for notification"
+            );
+            return;
+        }
+    };
+    if let Err(err) = PublishingMailer::send_status_change(ctx, &user, kind, title).await {
+        tracing::warn!(
+            error = err.to_string(),
+            user_id,
+            kind,
+            "failed to send publishing notification"
+        );
+    }
+}
+
+async fn record(ctx: &AppContext, action: &str, target_type: &str, id: i32, title: &str) {
+    audit_logs::record(
+        &ctx.db,
+        AuditEntry::new(action)
+            .target(target_type, id)
+            .after(serde_json::json!({ "title": title }))
+            .source(audit_logs::SOURCE_SYSTEM),
+    )
+    .await;
+}
+
+/// 切换到期的媒体：到发布时间的公开，到下架时间的取消公开
+async fn run_medias(ctx: &AppContext, summary: &mut ScheduleSummary) -> Result<()> {
+    let due = medias::Entity::find()
+        .filter(medias::Column::DeletedAt.is_null())
+        .filter(medias::Column::IsPublic.eq(false))
+        .filter(medias::Column::PublishAt.lte(now()))
+        .all(&ctx.db)
+        .await?;
+    for media in due {
+        if is_due(media.unpublish_at) {
+            continue;
+        }
+        let (id, user_id, title) = (media.id, media.user_id, media.title.clone());
+        let mut item = media.into_active_model();
+        item.is_public = Set(true);
+        item.publish_at = Set(None);
+        let media = item.update(&ctx.db).await?;
+        summary.published_medias += 1;
+        record(ctx, "media.scheduled_publish", "media", id, &title).await;
+        notify(ctx, user_id, "published", &title).await;
+        webhooks::dispatch(
+            ctx,
+            user_id,
+            EVENT_MEDIA_PUBLISHED,
+            webhooks::media_data(&media),
+        )
+        .await;
+    }
+
+    let expired = medias::Entity::find()
+        .filter(medias::Column::DeletedAt.is_null())
+        .filter(medias::Column::IsPublic.eq(true))
+        .filter(medias::Column::UnpublishAt.lte(now()))
+        .all(&ctx.db)
+        .await?;
+    for media in expired {
+        let (id, user_id, title) = (media.id, media.user_id, media.title.clone());
+        let mut item = media.into_active_model();
+        item.is_public = Set(false);
+        item.update(&ctx.db).await?;
+        summary.unpublished_medias += 1;
+        record(ctx, "media.scheduled_unpublish", "media", id, &title).await;
+        notify(ctx, user_id, "unpublished", &title).await;
+    }
+
+    Ok(())
+}
+
+/// 切换到期的书籍
+async fn run_books(ctx: &AppContext, summary: &mut ScheduleSummary) -> Result<()> {
+    let due = books::Entity::find()
+        .filter(books::Column::DeletedAt.is_null())
+        .filter(
+            books::Column::IsPublic
+                .eq(false)
+                .or(books::Column::IsPublic.is_null()),
+        )
+        .filter(books::Column::PublishAt.lte(now()))
+        .all(&ctx.db)
+        .await?;
+    for book in due {
+        if is_due(book.unpublish_at) {
+            continue;
+        }
+        let (id, user_id, title) = (book.id, book.user_id, book.title.clone());
+        let mut item = book.into_active_model();
+        item.is_public = Set(Some(true));
+        item.publish_at = Set(None);
+        item.update(&ctx.db).await?;
+        summary.published_books += 1;
+        record(ctx, "book.scheduled_publish", "book", id, &title).await;
+        notify(ctx, user_id, "published", &title).await;
+    }
+
+    let expired = books::Entity::find()
+        .filter(books::Column::DeletedAt.is_null())
+        .filter(books::Column::IsPublic.eq(true))
+        .filter(books::Column::UnpublishAt.lte(now()))
+        .all(&ctx.db)
+        .await?;
+    for book in expired {
+        let (id, user_id, title) = (book.id, book.user_id, book.title.clone());
+        let mut item = book.into_active_model();
+        item.is_public = Set(Some(false));
+        item.update(&ctx.db).await?;
+        summary.unpublished_books += 1;
+        record(ctx, "book.scheduled_unpublish", "book", id, &title).await;
+        notify(ctx, user_id, "unpublished", &title).await;
+    }
+
+    Ok(())
+}
+
+/// 处理所有到期的定时发布和下架
+pub async fn run_due(ctx: &AppContext) -> Result<ScheduleSummary> {
+    let mut summary = ScheduleSummary::default();
+    run_books(ctx, &mut summary).await?;
+    run_medias(ctx, &mut summary).await?;
+    Ok(summary)
+}
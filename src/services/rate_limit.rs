@@ -0,0 +1,536 @@
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use loco_rs::prelude::*;
+
+/// 连续锁定的计数保留时间，超过后锁定时长重新从基础值开始
+const LOCKOUT_HISTORY: Duration = Duration::from_secs(24 * 3600);
+
+/// 内存存储的键数量超过该值时清理已过期的计数
+const MEMORY_PRUNE_THRESHOLD: usize = 10_000;
+
+/// 需要限流的认证操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Login,
+    ForgotPassword,
+    MagicLink,
+    ResendVerification,
+}
+
+impl Action {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Login => "login",
+            Self::ForgotPassword => "forgot",
+            Self::MagicLink => "magic_link",
+            Self::ResendVerification => "resend_verification",
+        }
+    }
+}
+
+/// 限流配置
+#[derive(Debug, Clone)]
+pub struct RateLimitConfig {
+    pub enabled: bool,
+    /// 每个 IP 在窗口内对同一操作的最大请求数
+    pub ip_max_requests: u64,
+    pub ip_window: Duration,
+    /// 每个账号在窗口内最多发送的邮件数（找回密码、登录链接、验证邮件）
+    pub email_max_requests: u64,
+    pub email_window: Duration,
+    /// 窗口内连续登录失败达到该次数后锁定账号
+    pub max_failures: u64,
+    pub failure_window: Duration,
+    /// 首次锁定时长，之后每次锁定翻倍，不超过 `lockout_max`
+    pub lockout_base: Duration,
+    pub lockout_max: Duration,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            ip_max_requests: 30,
+            ip_window: Duration::from_secs(15 * 60),
+            email_max_requests: 3,
+            email_window: Duration::from_secs(15 * 60),
+            max_failures: 5,
+            failure_window: Duration::from_secs(15 * 60),
+            lockout_base: Duration::from_secs(60),
+            lockout_max: Duration::from_secs(3600),
+        }
+    }
+}
+
+impl RateLimitConfig {
+    /// 从环境变量读取配置，未设置的项使用默认值
+    ///
+    /// `RATE_LIMIT_ENABLED`、`RATE_LIMIT_IP_MAX`、`RATE_LIMIT_IP_WINDOW_SECS`、
+    /// `RATE_LIMIT_EMAIL_MAX`、`RATE_LIMIT_EMAIL_WINDOW_SECS`、`LOGIN_MAX_FAILURES`、
+    /// `LOGIN_FAILURE_WINDOW_SECS`、`LOGIN_LOCKOUT_SECS`、`LOGIN_LOCKOUT_MAX_SECS`
+    #[must_use]
+    pub fn from_env() -> Self {
+        let number = |name: &str| std::env::var(name).ok()?.trim().parse::<u64>().ok();
+        let seconds = |name: &str| number(name).map(Duration::from_secs);
+        let defaults = Self::default();
+
+        Self {
+            enabled: std::env::var("RATE_LIMIT_ENABLED")
+                .map_or(true, |v| !matches!(v.trim(), "0" | "false" | "no")),
+            ip_max_requests: number("RATE_LIMIT_IP_MAX").unwrap_or(defaults.ip_max_requests),
+            ip_window: seconds("RATE_LIMIT_IP_WINDOW_SECS").unwrap_or(defaults.ip_window),
+            email_max_requests: number("RATE_LIMIT_EMAIL_MAX")
+                .unwrap_or(defaults.email_max_requests),
+            email_window: seconds("RATE_LIMIT_EMAIL_WINDOW_SECS").unwrap_or(defaults.email_window),
+            max_failures: number("LOGIN_MAX_FAILURES").unwrap_or(defaults.max_failures),
+            failure_window: seconds("LOGIN_FAILURE_WINDOW_SECS").unwrap_or(defaults.failure_window),
+            lockout_base: seconds("LOGIN_LOCKOUT_SECS").unwrap_or(defaults.lockout_base),
+            lockout_max: seconds("LOGIN_LOCKOUT_MAX_SECS").unwrap_or(defaults.lockout_max),
+        }
+    }
+
+    /// 第 `lockouts` 次锁定的时长（逐次翻倍）
+    #[must_use]
+    pub fn lockout_duration(&self, lockouts: u64) -> Duration {
+        let exponent = u32::try_from(lockouts.saturating_sub(1).min(16)).unwrap_or(16);
+        self.lockout_base
+            .saturating_mul(2u32.pow(exponent))
+            .min(self.lockout_max)
+    }
+}
+
+/// 限流计数器存储：计数在首次写入时设置过期时间
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// 存储类型名称
+    fn backend(&self) -> &'static str;
+
+    /// 计数加一，返回当前计数和剩余有效期
+    async fn increment(&self, key: &str, window: Duration) -> Result<(u64, Duration)>;
+
+    /// 读取计数和剩余有效期，不存在或已过期时返回 `None`
+    async fn get(&self, key: &str) -> Result<Option<(u64, Duration)>>;
+
+    async fn set(&self, key: &str, value: u64, ttl: Duration) -> Result<()>;
+
+    async fn remove(&self, key: &str) -> Result<()>;
+}
+
+/// 进程内存储，多实例部署时各实例分别计数
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    entries: Mutex<HashMap<String, (u64, Instant)>>,
+}
+
+impl MemoryStore {
+    fn entries(&self) -> std::sync::MutexGuard<'_, HashMap<String, (u64, Instant)>> {
+        self.entries
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryStore {
+    fn backend(&self) -> &'static str {
+        "memory"
+    }
+
+    async fn increment(&self, key: &str, window: Duration) -> Result<(u64, Duration)> {
+        let now = Instant::now();
+        let mut entries = self.entries();
+        if entries.len() > MEMORY_PRUNE_THRESHOLD {
+            entries.retain(|_, (_, expires_at)| *expires_at > now);
+        }
+
+        let entry = entries.entry(key.to_string()).or_insert((0, now + window));
+        if entry.1 <= now {
+            *entry = (0, now + window);
+        }
+        entry.0 += 1;
+        Ok((entry.0, entry.1 - now))
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<(u64, Duration)>> {
+        let now = Instant::now();
+        Ok(self
+            .entries()
+            .get(key)
+            .filter(|(_, expires_at)| *expires_at > now)
+            .map(|(count, expires_at)| (*count, *expires_at - now)))
+    }
+
+    async fn set(&self, key: &str, value: u64, ttl: Duration) -> Result<()> {
+        self.entries()
+            .insert(key.to_string(), (value, Instant::now() + ttl));
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> Result<()> {
+        self.entries().remove(key);
+        Ok(())
+    }
+}
+
+/// Redis（或兼容协议的服务）存储，多实例部署时共享计数
+#[cfg(feature = "redis")]
+pub struct RedisStore {
+    client: redis::Client,
+    connection: tokio::sync::OnceCell<redis::aio::ConnectionManager>,
+}
+
+#[cfg(feature = "redis")]
+impl RedisStore {
+    /// # Errors
+    ///
+    /// When the url is not a valid redis url
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(Self {
+            client: redis::Client::open(url).map_err(redis_error)?,
+            connection: tokio::sync::OnceCell::new(),
+        })
+    }
+
+    async fn connection(&self) -> Result<redis::aio::ConnectionManager> {
+        self.connection
+            .get_or_try_init(|| self.client.get_connection_manager())
+            .await
+            .cloned()
+            .map_err(redis_error)
+    }
+}
+
+#[cfg(feature = "redis")]
+fn redis_error(err: redis::RedisError) -> Error {
+    Error::Message(format!("限流存储访问失败: {err}"))
+}
+
+#[cfg(feature = "redis")]
+fn duration_millis(duration: Duration) -> i64 {
+    i64::try_from(duration.as_millis())
+        .unwrap_or(i64::MAX)
+        .max(1)
+}
+
+#[cfg(feature = "redis")]
+#[async_trait]
+impl RateLimitStore for RedisStore {
+    fn backend(&self) -> &'static str {
+        "redis"
+    }
+
+    async fn increment(&self, key: &str, window: Duration) -> Result<(u64, Duration)> {
+        use redis::AsyncCommands;
+
+        let mut conn = self.connection().await?;
+        let count: u64 = conn.incr(key, 1).await.map_err(redis_error)?;
+        let mut ttl: i64 = conn.pttl(key).await.map_err(redis_error)?;
+        // 新建的计数（或意外丢失过期时间的计数）设置有效期
+        if ttl < 0 {
+            ttl = duration_millis(window);
+            let () = conn.pexpire(key, ttl).await.map_err(redis_error)?;
+        }
+        Ok((count, Duration::from_millis(ttl.unsigned_abs())))
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<(u64, Duration)>> {
+        use redis::AsyncCommands;
+
+        let mut conn = self.connection().await?;
+        let count: Option<u64> = conn.get(key).await.map_err(redis_error)?;
+        let ttl: i64 = conn.pttl(key).await.map_err(redis_error)?;
+        Ok(count
+            .filter(|_| ttl > 0)
+            .map(|count| (count, Duration::from_millis(ttl.unsigned_abs()))))
+    }
+
+    async fn set(&self, key: &str, value: u64, ttl: Duration) -> Result<()> {
+        use redis::AsyncCommands;
+
+        let mut conn = self.connection().await?;
+        let millis = u64::try_from(duration_millis(ttl)).unwrap_or(1);
+        let () = conn
+            .pset_ex(key, value, millis)
+            .await
+            .map_err(redis_error)?;
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> Result<()> {
+        use redis::AsyncCommands;
+
+        let mut conn = self.connection().await?;
+        let () = conn.del(key).await.map_err(redis_error)?;
+        Ok(())
+    }
+}
+
+/// 认证接口限流器
+///
+/// - 按来源 IP 限制每种操作的请求频率；
+/// - 按账号限制邮件发送频率；
+/// - 账号连续登录失败后锁定，锁定时长逐次翻倍。
+///
+/// 存储不可用时记录日志并放行，避免限流故障导致所有人无法登录。
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    store: Box<dyn RateLimitStore>,
+}
+
+impl RateLimiter {
+    #[must_use]
+    pub fn new(config: RateLimitConfig, store: Box<dyn RateLimitStore>) -> Self {
+        Self { config, store }
+    }
+
+    #[must_use]
+    pub fn backend(&self) -> &'static str {
+        self.store.backend()
+    }
+
+    /// 计数加一，超出上限时返回需要等待的时间
+    async fn hit(&self, key: &str, max: u64, window: Duration) -> Option<Duration> {
+        if !self.config.enabled {
+            return None;
+        }
+        match self.store.increment(key, window).await {
+            Ok((count, ttl)) if count > max => Some(ttl),
+            Ok(_) => None,
+            Err(err) => {
+                tracing::error!(error = err.to_string(), key, "rate limit store failed");
+                None
+            }
+        }
+    }
+
+    /// 按来源 IP 限流，无法确定来源时不限制
+    pub async fn check_ip(&self, action: Action, ip: Option<&str>) -> Option<Duration> {
+        let ip = ip?;
+        self.hit(
+            &format!("ratelimit:ip:{}:{ip}", action.as_str()),
+            self.config.ip_max_requests,
+            self.config.ip_window,
+        )
+        .await
+    }
+
+    /// 来源 IP 已超出上限时返回需要等待的时间（不计数，配合 [`Self::check_ip`] 只统计失败的请求）
+    pub async fn ip_limited(&self, action: Action, ip: Option<&str>) -> Option<Duration> {
+        let ip = ip?;
+        if !self.config.enabled {
+            return None;
+        }
+        match self
+            .store
+            .get(&format!("ratelimit:ip:{}:{ip}", action.as_str()))
+            .await
+        {
+            Ok(Some((count, ttl))) if count >= self.config.ip_max_requests => Some(ttl),
+            Ok(_) => None,
+            Err(err) => {
+                tracing::error!(error = err.to_string(), "rate limit store failed");
+                None
+            }
+        }
+    }
+
+    /// 按账号限制发送邮件的频率
+    pub async fn check_email(&self, action: Action, account: &str) -> Option<Duration> {
+        self.hit(
+            &format!("ratelimit:email:{}:{account}", action.as_str()),
+            self.config.email_max_requests,
+            self.config.email_window,
+        )
+        .await
+    }
+
+    /// 账号被锁定时返回剩余锁定时间
+    pub async fn lockout_remaining(&self, account: &str) -> Option<Duration> {
+        if !self.config.enabled {
+            return None;
+        }
+        match self.store.get(&locked_key(account)).await {
+            Ok(locked) => locked.map(|(_, ttl)| ttl),
+            Err(err) => {
+                tracing::error!(error = err.to_string(), "rate limit store failed");
+                None
+            }
+        }
+    }
+
+    /// 记录一次登录失败，达到上限时锁定账号并返回锁定时长
+    pub async fn record_failure(&self, account: &str) -> Option<Duration> {
+        self.hit(
+            &failures_key(account),
+            self.config.max_failures.saturating_sub(1),
+            self.config.failure_window,
+        )
+        .await?;
+
+        let result = async {
+            let (lockouts, _) = self
+                .store
+                .increment(&lockouts_key(account), LOCKOUT_HISTORY)
+                .await?;
+            let duration = self.config.lockout_duration(lockouts);
+            self.store
+                .set(&locked_key(account), lockouts, duration)
+                .await?;
+            self.store.remove(&failures_key(account)).await?;
+            Ok::<_, Error>(duration)
+        }
+        .await;
+
+        match result {
+            Ok(duration) => Some(duration),
+            Err(err) => {
+                tracing::error!(error = err.to_string(), "rate limit store failed");
+                None
+            }
+        }
+    }
+
+    /// 登录成功后清除失败计数
+    pub async fn record_success(&self, account: &str) {
+        if !self.config.enabled {
+            return;
+        }
+        if let Err(err) = self.store.remove(&failures_key(account)).await {
+            tracing::error!(error = err.to_string(), "rate limit store failed");
+        }
+    }
+
+    /// 解除账号锁定，并清除失败和锁定历史
+    ///
+    /// # Errors
+    ///
+    /// When the store is unavailable
+    pub async fn unlock(&self, account: &str) -> Result<()> {
+        self.store.remove(&locked_key(account)).await?;
+        self.store.remove(&failures_key(account)).await?;
+        self.store.remove(&lockouts_key(account)).await?;
+        Ok(())
+    }
+}
+
+fn failures_key(account: &str) -> String {
+    format!("ratelimit:failures:{account}")
+}
+
+fn lockouts_key(account: &str) -> String {
+    format!("ratelimit:lockouts:{account}")
+}
+
+fn locked_key(account: &str) -> String {
+    format!("ratelimit:locked:{account}")
+}
+
+fn build_store() -> Box<dyn RateLimitStore> {
+    let Some(url) = std::env::var("RATE_LIMIT_REDIS_URL")
+        .ok()
+        .filter(|url| !url.trim().is_empty())
+    else {
+        return Box::new(MemoryStore::default());
+    };
+
+    #[cfg(feature = "redis")]
+    {
+        match RedisStore::new(url.trim()) {
+            Ok(store) => return Box::new(store),
+            Err(err) => tracing::error!(error = err.to_string(), "invalid RATE_LIMIT_REDIS_URL"),
+        }
+    }
+    #[cfg(not(feature = "redis"))]
+    tracing::warn!(
+        url = %url,
+        "RATE_LIMIT_REDIS_URL is set but the `redis` feature is disabled, using memory store"
+    );
+
+    Box::new(MemoryStore::default())
+}
+
+/// 全局限流器，配置从环境变量读取，设置 `RATE_LIMIT_REDIS_URL`
+/// （需启用 `redis` 特性）时使用 Redis 存储
+pub static RATE_LIMITER: LazyLock<RateLimiter> =
+    LazyLock::new(|| RateLimiter::new(RateLimitConfig::from_env(), build_store()));
+
+/// 完播上报去重：同一来源 IP 对同一媒体的完播在窗口内只统计一次
+///
+/// 这是统计口径而不是限流，不受 `RATE_LIMIT_ENABLED` 影响，使用独立的存储。
+pub struct CompletionDedup {
+    window: Duration,
+    store: Box<dyn RateLimitStore>,
+}
+
+impl CompletionDedup {
+    /// 默认去重窗口
+    pub const DEFAULT_WINDOW: Duration = Duration::from_secs(3600);
+
+    #[must_use]
+    pub fn new(window: Duration, store: Box<dyn RateLimitStore>) -> Self {
+        Self { window, store }
+    }
+
+    /// 完播上报是否需要统计：同一来源 IP 在窗口内对同一媒体重复上报时返回 `false`，
+    /// 无法确定来源或存储不可用时照常统计
+    pub async fn first_completion(&self, access_token: &str, ip: Option<&str>) -> bool {
+        let Some(ip) = ip else {
+            return true;
+        };
+        let key = format!("completion:{access_token}:{ip}");
+        match self.store.increment(&key, self.window).await {
+            Ok((count, _)) => count <= 1,
+            Err(err) => {
+                tracing::error!(
+                    error = err.to_string(),
+                    key,
+                    "completion dedup store failed"
+                );
+                true
+            }
+        }
+    }
+}
+
+/// 全局完播去重，窗口由 `COMPLETION_DEDUP_WINDOW_SECS` 设置，
+/// 存储后端的选择与 [`RATE_LIMITER`] 相同
+pub static COMPLETION_DEDUP: LazyLock<CompletionDedup> = LazyLock::new(|| {
+    let window = std::env::var("COMPLETION_DEDUP_WINDOW_SECS")
+        .ok()
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map_or(CompletionDedup::DEFAULT_WINDOW, Duration::from_secs);
+    CompletionDedup::new(window, build_store())
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lockout_duration_doubles_until_max() {
+        let config = RateLimitConfig::default();
+        assert_eq!(config.lockout_duration(1), Duration::from_secs(60));
+        assert_eq!(config.lockout_duration(2), Duration::from_secs(120));
+        assert_eq!(config.lockout_duration(4), Duration::from_secs(480));
+        assert_eq!(config.lockout_duration(7), Duration::from_secs(3600));
+        assert_eq!(config.lockout_duration(1000), Duration::from_secs(3600));
+    }
+
+    #[tokio::test]
+    async fn test_completion_counted_once_per_ip() {
+        let dedup = CompletionDedup::new(
+            CompletionDedup::DEFAULT_WINDOW,
+            Box::new(MemoryStore::default()),
+        );
+        assert!(dedup.first_completion("token", Some("10.0.0.1")).await);
+        assert!(!dedup.first_completion("token", Some("10.0.0.1")).await);
+        assert!(dedup.first_completion("token", Some("10.0.0.2")).await);
+        assert!(dedup.first_completion("other", Some("10.0.0.1")).await);
+        assert!(dedup.first_completion("token", None).await);
+        assert!(dedup.first_completion("token", None).await);
+    }
+}
@@ -1,8 +1,78 @@
 use loco_rs::prelude::*;
+use sha2::{Digest, Sha256};
 use std::path::{Path, PathBuf};
 use tokio::fs;
 use uuid::Uuid;
 
+/// 计算文件内容的 SHA-256（十六进制小写）
+///
+/// # Errors
+///
+/// Will return error if the file cannot be read
+pub async fn file_sha256(path: &Path) -> Result<String> {
+    use tokio::io::AsyncReadExt;
+
+    let mut file = fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// 复制文件并校验内容：目标已存在且内容一致时不再复制（用于可中断后重新执行的迁移），
+/// 返回是否实际复制。`expected_hash` 为空时以源文件的摘要为准
+///
+/// # Errors
+///
+/// Will return error if the copy fails or the copied content does not match
+pub async fn copy_verified(from: &Path, to: &Path, expected_hash: Option<&str>) -> Result<bool> {
+    let expected = match expected_hash {
+        Some(hash) => hash.to_string(),
+        None => file_sha256(from).await?,
+    };
+    if fs::metadata(to).await.is_ok() && file_sha256(to).await? == expected {
+        return Ok(false);
+    }
+
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    // 先写到临时文件，校验通过后再改名，中断时不会留下内容不完整的目标文件
+    let partial = to.with_extension("part");
+    fs::copy(from, &partial)
+        .await
+        .map_err(|e| Error::Message(format!("复制文件失败: {e}")))?;
+    let actual = file_sha256(&partial).await?;
+    if actual != expected {
+        let _ = fs::remove_file(&partial).await;
+        return Err(Error::Message(format!(
+            "文件摘要不一致: {} (应为 {expected}，实际 {actual})",
+            from.display()
+        )));
+    }
+    fs::rename(&partial, to).await?;
+    Ok(true)
+}
+
+/// 上传临时文件的文件名前缀和后缀（位于系统临时目录）
+pub const TEMP_UPLOAD_PREFIX: &str = "upload_";
+pub const TEMP_UPLOAD_SUFFIX: &str = ".tmp";
+
+/// 新的上传临时文件路径；中断后残留的文件由存储检查清理
+#[must_use]
+pub fn temp_upload_path() -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "{TEMP_UPLOAD_PREFIX}{}{TEMP_UPLOAD_SUFFIX}",
+        Uuid::new_v4()
+    ))
+}
+
 #[derive(Debug, Clone)]
 pub struct StorageService {
     base_path: PathBuf,
@@ -16,6 +86,15 @@ pub struct UploadedFile {
     pub path: PathBuf,
 }
 
+/// 流式写入临时位置的上传文件
+#[derive(Debug)]
+pub struct TempUpload {
+    pub path: PathBuf,
+    pub size: u64,
+    /// 文件内容的 SHA-256（十六进制小写）
+    pub content_hash: String,
+}
+
 #[derive(Debug)]
 pub struct FileValidationError {
     pub message: String,
@@ -28,6 +107,35 @@ impl StorageService {
         }
     }
 
+    /// 存储根目录
+    pub fn base_path(&self) -> &Path {
+        &self.base_path
+    }
+
+    /// 文件在存储中的键：相对于存储根目录、使用 `/` 分隔的路径，数据库中只保存键，
+    /// 更换存储目录后不需要修改记录。不在存储目录下的路径原样返回
+    pub fn key_for(&self, path: &Path) -> String {
+        let relative = path.strip_prefix(&self.base_path).unwrap_or(path);
+        if relative.is_absolute() {
+            return relative.to_string_lossy().to_string();
+        }
+        relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// 键对应的文件路径；兼容旧记录中的绝对路径和包含存储根目录的路径
+    pub fn resolve(&self, key: &str) -> PathBuf {
+        let path = Path::new(key);
+        if path.is_absolute() || path.starts_with(&self.base_path) {
+            path.to_path_buf()
+        } else {
+            self.base_path.join(path)
+        }
+    }
+
     /// 获取用户的媒体文件存储路径
     pub fn get_user_media_path(&self, user_id: i32, book_id: i32) -> PathBuf {
         self.base_path
@@ -43,6 +151,23 @@ impl StorageService {
         self.base_path.join("qrcodes")
     }
 
+    /// 媒体处理后生成的文件路径：`renditions/<媒体 ID>/v<文件版本>-<名称>`
+    pub fn get_rendition_path(&self, media_id: i32, file_version: i32, name: &str) -> PathBuf {
+        self.base_path
+            .join("renditions")
+            .join(media_id.to_string())
+            .join(format!("v{file_version}-{name}"))
+    }
+
+    /// 按内容摘要存储的文件路径：`blobs/ab/cd/<摘要>.<扩展名>`
+    pub fn get_blob_path(&self, content_hash: &str, extension: &str) -> PathBuf {
+        self.base_path
+            .join("blobs")
+            .join(content_hash.get(0..2).unwrap_or("00"))
+            .join(content_hash.get(2..4).unwrap_or("00"))
+            .join(format!("{content_hash}.{extension}"))
+    }
+
     /// 确保目录存在
     async fn ensure_dir_exists(&self, path: &Path) -> Result<()> {
         if !path.exists() {
@@ -277,39 +402,45 @@ impl StorageService {
         }
     }
 
-    /// 流式保存文件到临时位置（不占用内存）
-    /// 返回临时文件路径和文件大小
+    /// 流式保存文件到临时位置（不占用内存），写入的同时计算 SHA-256
     pub async fn save_to_temp<S, E>(
         &self,
-        mut stream: S,
+        stream: S,
         original_filename: &str,
         content_type: &str,
         max_size: u64,
-    ) -> Result<(PathBuf, u64)>
+    ) -> Result<TempUpload>
     where
-        S: futures_util::Stream<Item = Result<bytes::Bytes, E>> + Unpin,
+        S: futures_util::Stream<Item = Result<bytes::Bytes, E>>,
         E: std::fmt::Display,
     {
         use futures_util::StreamExt;
         use tokio::io::AsyncWriteExt;
 
+        let mut stream = std::pin::pin!(stream);
+
         // 验证文件类型
         self.validate_file_type(original_filename, content_type)?;
 
         // 创建临时文件
-        let temp_dir = std::env::temp_dir();
-        let temp_filename = format!("upload_{}.tmp", Uuid::new_v4());
-        let temp_path = temp_dir.join(temp_filename);
+        let temp_path = temp_upload_path();
 
         let mut file = tokio::fs::File::create(&temp_path)
             .await
             .map_err(|e| Error::Message(format!("创建临时文件失败: {e}")))?;
 
         let mut total_size: u64 = 0;
+        let mut hasher = Sha256::new();
 
         // 逐块读取并写入
         while let Some(chunk_result) = stream.next().await {
-            let chunk = chunk_result.map_err(|e| Error::Message(format!("读取数据块失败: {e}")))?;
+            let chunk = match chunk_result {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    let _ = tokio::fs::remove_file(&temp_path).await;
+                    return Err(Error::Message(format!("读取数据块失败: {e}")));
+                }
+            };
 
             total_size += chunk.len() as u64;
 
@@ -324,6 +455,7 @@ impl StorageService {
             }
 
             // 写入块
+            hasher.update(&chunk);
             file.write_all(&chunk)
                 .await
                 .map_err(|e| Error::Message(format!("写入数据失败: {e}")))?;
@@ -334,48 +466,79 @@ impl StorageService {
             .await
             .map_err(|e| Error::Message(format!("刷新缓冲区失败: {e}")))?;
 
-        Ok((temp_path, total_size))
+        Ok(TempUpload {
+            path: temp_path,
+            size: total_size,
+            content_hash: hex::encode(hasher.finalize()),
+        })
     }
 
-    /// 从临时文件移动文件到永久存储位置（避免内存加载）
+    /// 把临时文件移动到按内容摘要存储的位置，返回最终路径
     ///
     /// # Errors
     ///
-    /// Will return error if file validation fails or file operations fail
-    pub async fn move_temp_file(
+    /// Will return error if file operations fail
+    pub async fn move_temp_to_blob(
         &self,
-        user_id: i32,
-        book_id: i32,
         temp_file_path: &Path,
+        content_hash: &str,
         filename: &str,
-        content_type: &str,
-        file_size: u64,
-    ) -> Result<UploadedFile> {
-        // 验证文件类型
-        self.validate_file_type(filename, content_type)?;
+    ) -> Result<PathBuf> {
+        let extension = Path::new(filename)
+            .extension()
+            .and_then(|s| s.to_str())
+            .unwrap_or("bin")
+            .to_lowercase();
+        let final_path = self.get_blob_path(content_hash, &extension);
+        self.move_file(temp_file_path, &final_path).await?;
+        Ok(final_path)
+    }
+
+    /// 移动文件到指定位置，目标目录不存在时创建
+    ///
+    /// # Errors
+    ///
+    /// Will return error if file operations fail
+    pub async fn move_file(&self, from: &Path, to: &Path) -> Result<()> {
+        if let Some(parent) = to.parent() {
+            self.ensure_dir_exists(parent).await?;
+        }
 
-        // 获取目标路径
+        // 临时目录可能与存储目录不在同一个文件系统，重命名失败时改为复制
+        if fs::rename(from, to).await.is_err() {
+            fs::copy(from, to)
+                .await
+                .map_err(|e| Error::Message(format!("移动文件失败: {e}")))?;
+            let _ = fs::remove_file(from).await;
+        }
+        Ok(())
+    }
+
+    /// 复制媒体文件到用户书籍的存储目录（复制章节或书籍时使用）
+    pub async fn copy_media_file(
+        &self,
+        user_id: i32,
+        book_id: i32,
+        source_path: &Path,
+    ) -> Result<UploadedFile> {
         let media_path = self.get_user_media_path(user_id, book_id);
         self.ensure_dir_exists(&media_path).await?;
 
-        // 生成唯一文件名
-        let file_extension = Path::new(filename)
+        let file_extension = source_path
             .extension()
             .and_then(|s| s.to_str())
             .unwrap_or("bin");
-
         let unique_filename = format!("{}.{}", Uuid::new_v4(), file_extension);
         let final_path = media_path.join(&unique_filename);
 
-        // 移动文件（这是原子操作，比复制+删除更高效）
-        fs::rename(temp_file_path, &final_path)
+        let size = fs::copy(source_path, &final_path)
             .await
-            .map_err(|e| Error::Message(format!("移动文件失败: {e}")))?;
+            .map_err(|e| Error::Message(format!("复制文件失败: {e}")))?;
 
         Ok(UploadedFile {
             filename: unique_filename,
-            size: file_size,
-            content_type: content_type.to_string(),
+            size,
+            content_type: String::new(),
             path: final_path,
         })
     }
@@ -449,6 +612,86 @@ mod tests {
             .is_err());
     }
 
+    #[tokio::test]
+    async fn test_save_to_temp_hashes_content() {
+        let temp_dir = TempDir::new().unwrap();
+        let storage = StorageService::new(temp_dir.path());
+
+        let chunks: Vec<std::result::Result<bytes::Bytes, std::io::Error>> = vec![
+            Ok(bytes::Bytes::from_static(b"test audio ")),
+            Ok(bytes::Bytes::from_static(b"data")),
+        ];
+        let upload = storage
+            .save_to_temp(
+                futures_util::stream::iter(chunks),
+                "test.mp3",
+                "audio/mpeg",
+                1024,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(upload.size, 15);
+        assert_eq!(
+            upload.content_hash,
+            hex::encode(Sha256::digest(b"test audio data"))
+        );
+
+        let blob_path = storage
+            .move_temp_to_blob(&upload.path, &upload.content_hash, "Test.MP3")
+            .await
+            .unwrap();
+        assert!(blob_path.starts_with(temp_dir.path().join("blobs")));
+        assert!(blob_path.to_string_lossy().ends_with(".mp3"));
+        assert!(!upload.path.exists());
+        assert_eq!(
+            tokio::fs::read(&blob_path).await.unwrap(),
+            b"test audio data"
+        );
+    }
+
+    #[test]
+    fn test_storage_keys() {
+        let storage = StorageService::new("uploads");
+        let path = storage.get_blob_path("abcdef", "mp3");
+
+        let key = storage.key_for(&path);
+        assert_eq!(key, "blobs/ab/cd/abcdef.mp3");
+        assert_eq!(storage.resolve(&key), path);
+
+        // 旧记录中包含存储根目录的路径和绝对路径保持不变
+        assert_eq!(
+            storage.resolve("uploads/users/1/books/2/media/a.mp3"),
+            PathBuf::from("uploads/users/1/books/2/media/a.mp3")
+        );
+        assert_eq!(
+            storage.key_for(Path::new("uploads/users/1/books/2/media/a.mp3")),
+            "users/1/books/2/media/a.mp3"
+        );
+        assert_eq!(storage.resolve("/srv/a.mp3"), PathBuf::from("/srv/a.mp3"));
+        assert_eq!(storage.key_for(Path::new("/srv/a.mp3")), "/srv/a.mp3");
+    }
+
+    #[tokio::test]
+    async fn test_copy_verified() {
+        let temp_dir = TempDir::new().unwrap();
+        let from = temp_dir.path().join("a.mp3");
+        let to = temp_dir.path().join("target/users/1/a.mp3");
+        tokio::fs::write(&from, b"audio").await.unwrap();
+        let hash = hex::encode(Sha256::digest(b"audio"));
+
+        assert!(copy_verified(&from, &to, Some(&hash)).await.unwrap());
+        assert_eq!(tokio::fs::read(&to).await.unwrap(), b"audio");
+        // 再次执行时目标已一致，不再复制
+        assert!(!copy_verified(&from, &to, None).await.unwrap());
+
+        // 源文件与登记的摘要不一致时不写入目标
+        let other = temp_dir.path().join("target/users/1/b.mp3");
+        assert!(copy_verified(&from, &other, Some("0000")).await.is_err());
+        assert!(!other.exists());
+        assert!(!other.with_extension("part").exists());
+    }
+
     #[tokio::test]
     async fn test_determine_file_type() {
         let temp_dir = TempDir::new().unwrap();
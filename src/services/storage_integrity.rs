@@ -0,0 +1,419 @@
+//! 存储完整性检查：对比磁盘上的文件和数据库记录
+//!
+//! 检查媒体文件是否存在、`users/*/books/*/media`、`blobs` 和 `renditions` 下没有任何记录引用的文件、
+//! 系统临时目录中残留的上传临时文件，以及已删除媒体的二维码；可选按内容摘要校验文件。
+//! 默认只报告，`apply` 时修复：删除多余文件、为缺少二维码的媒体重新生成、
+//! 标记文件丢失的媒体（`medias.file_missing_at`）。校验和不一致只报告，不做修改。
+use loco_rs::prelude::*;
+use sea_orm::sea_query::Expr;
+use sea_orm::DatabaseConnection;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::models::_entities::{media_versions, medias};
+use crate::models::storage_blobs;
+use crate::services::qrcode::{QRCodeService, QRCODE_SERVICE};
+use crate::services::storage::{
+    self, StorageService, STORAGE_SERVICE, TEMP_UPLOAD_PREFIX, TEMP_UPLOAD_SUFFIX,
+};
+
+/// 最近修改的文件可能属于正在进行的上传（文件已落盘、记录尚未写入），不视为多余
+const ORPHAN_GRACE: Duration = Duration::from_secs(60 * 60);
+
+/// 超过该时间的上传临时文件视为残留
+const STALE_TEMP_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// 检查选项
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct CheckOptions {
+    /// 读取文件内容并与登记的 SHA-256 比对（较慢）
+    #[serde(default)]
+    pub verify_checksums: bool,
+    /// 执行修复，否则只报告
+    #[serde(default)]
+    pub apply: bool,
+}
+
+/// 磁盘上多余的文件
+#[derive(Debug, Clone, Serialize)]
+pub struct StrayFile {
+    pub path: String,
+    pub size: u64,
+}
+
+/// 文件不存在的媒体
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingMediaFile {
+    pub media_id: i32,
+    pub user_id: i32,
+    pub title: String,
+    pub file_path: String,
+    /// 数据库中记录的文件大小
+    pub file_size: Option<i64>,
+    /// 媒体在回收站中
+    pub trashed: bool,
+}
+
+/// 内容与登记的摘要不一致的文件
+#[derive(Debug, Clone, Serialize)]
+pub struct ChecksumMismatch {
+    pub file_path: String,
+    pub content_hash: String,
+    pub actual_hash: String,
+}
+
+/// 修复结果
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct RepairSummary {
+    pub deleted_files: usize,
+    pub freed_bytes: u64,
+    pub regenerated_qrcodes: usize,
+    pub marked_missing: usize,
+    /// 文件已恢复、取消丢失标记的媒体数
+    pub cleared_missing: usize,
+    pub failures: Vec<String>,
+}
+
+/// 检查报告
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct IntegrityReport {
+    pub applied: bool,
+    pub checksums_verified: bool,
+    pub missing_files: Vec<MissingMediaFile>,
+    pub orphan_files: Vec<StrayFile>,
+    pub stale_temp_files: Vec<StrayFile>,
+    pub orphan_qrcodes: Vec<StrayFile>,
+    /// 缺少二维码文件的有效媒体
+    pub missing_qrcodes: Vec<i32>,
+    pub checksum_mismatches: Vec<ChecksumMismatch>,
+    /// 删除多余文件可释放的字节数
+    pub reclaimable_bytes: u64,
+    pub repairs: RepairSummary,
+}
+
+impl IntegrityReport {
+    /// 没有发现任何问题
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.missing_files.is_empty()
+            && self.orphan_files.is_empty()
+            && self.stale_temp_files.is_empty()
+            && self.orphan_qrcodes.is_empty()
+            && self.missing_qrcodes.is_empty()
+            && self.checksum_mismatches.is_empty()
+    }
+
+    fn stray_files(&self) -> impl Iterator<Item = &StrayFile> {
+        self.orphan_files
+            .iter()
+            .chain(&self.stale_temp_files)
+            .chain(&self.orphan_qrcodes)
+    }
+}
+
+/// 统一路径写法（去掉 `./` 等），数据库中的路径和遍历得到的路径才能直接比较
+fn normalize(path: &Path) -> PathBuf {
+    path.components().collect()
+}
+
+/// 修改时间早于 `age` 之前
+fn older_than(metadata: &std::fs::Metadata, age: Duration) -> bool {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|elapsed| elapsed >= age)
+}
+
+/// 递归列出目录下的所有文件，目录不存在时返回空
+async fn walk_files(root: &Path) -> Result<Vec<(PathBuf, std::fs::Metadata)>> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let mut entries = match tokio::fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(Error::Message(format!("读取目录失败 {dir:?}: {e}"))),
+        };
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else if metadata.is_file() {
+                files.push((entry.path(), metadata));
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// 使用默认的存储目录、二维码目录和系统临时目录检查
+pub async fn run(db: &DatabaseConnection, options: CheckOptions) -> Result<IntegrityReport> {
+    check(
+        db,
+        &STORAGE_SERVICE,
+        &QRCODE_SERVICE,
+        &std::env::temp_dir(),
+        options,
+    )
+    .await
+}
+
+/// 对比存储和数据库，`options.apply` 时执行修复
+pub async fn check(
+    db: &DatabaseConnection,
+    storage: &StorageService,
+    qrcodes: &QRCodeService,
+    temp_dir: &Path,
+    options: CheckOptions,
+) -> Result<IntegrityReport> {
+    let mut report = IntegrityReport {
+        applied: options.apply,
+        checksums_verified: options.verify_checksums,
+        ..Default::default()
+    };
+
+    let all_medias = medias::Entity::find().all(db).await?;
+    let versions = media_versions::Entity::find().all(db).await?;
+    let blobs = storage_blobs::Entity::find().all(db).await?;
+
+    // 媒体文件是否存在
+    let mut restored = Vec::new();
+    for media in &all_medias {
+        let exists = tokio::fs::metadata(storage.resolve(&media.file_path))
+            .await
+            .is_ok_and(|m| m.is_file());
+        if !exists {
+            report.missing_files.push(MissingMediaFile {
+                media_id: media.id,
+                user_id: media.user_id,
+                title: media.title.clone(),
+                file_path: media.file_path.clone(),
+                file_size: media.file_size,
+                trashed: media.deleted_at.is_some(),
+            });
+        } else if media.file_missing_at.is_some() {
+            restored.push(media.id);
+        }
+    }
+
+    // 没有记录引用的文件；引用数为 0 的内容文件也视为多余
+    let referenced: HashSet<PathBuf> = all_medias
+        .iter()
+        .map(|m| m.file_path.as_str())
+        .chain(versions.iter().map(|v| v.file_path.as_str()))
+        .chain(
+            all_medias
+                .iter()
+                .filter_map(|m| m.normalized_file_path.as_deref()),
+        )
+        .chain(
+            blobs
+                .iter()
+                .filter(|b| b.ref_count > 0)
+                .map(|b| b.file_path.as_str()),
+        )
+        .map(|key| normalize(&storage.resolve(key)))
+        .collect();
+    for root in [
+        storage.base_path().join("users"),
+        storage.base_path().join("blobs"),
+        storage.base_path().join("renditions"),
+    ] {
+        for (path, metadata) in walk_files(&root).await? {
+            if !referenced.contains(&normalize(&path)) && older_than(&metadata, ORPHAN_GRACE) {
+                report.orphan_files.push(StrayFile {
+                    path: path.to_string_lossy().to_string(),
+                    size: metadata.len(),
+                });
+            }
+        }
+    }
+
+    // 上传中断留下的临时文件
+    let mut entries = match tokio::fs::read_dir(temp_dir).await {
+        Ok(entries) => Some(entries),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(Error::Message(format!("读取临时目录失败: {e}"))),
+    };
+    while let Some(entry) = match entries.as_mut() {
+        Some(entries) => entries.next_entry().await?,
+        None => None,
+    } {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if !name.starts_with(TEMP_UPLOAD_PREFIX) || !name.ends_with(TEMP_UPLOAD_SUFFIX) {
+            continue;
+        }
+        let metadata = entry.metadata().await?;
+        if metadata.is_file() && older_than(&metadata, STALE_TEMP_AFTER) {
+            report.stale_temp_files.push(StrayFile {
+                path: entry.path().to_string_lossy().to_string(),
+                size: metadata.len(),
+            });
+        }
+    }
+
+    // 二维码：文件名是媒体 ID，媒体已永久删除的二维码是多余的（回收站中的媒体保留二维码用于恢复）
+    let media_ids: HashSet<i32> = all_medias.iter().map(|m| m.id).collect();
+    for (path, metadata) in walk_files(&qrcodes.get_qrcode_path()).await? {
+        let Some(media_id) = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse::<i32>().ok())
+        else {
+            continue;
+        };
+        if !media_ids.contains(&media_id) {
+            report.orphan_qrcodes.push(StrayFile {
+                path: path.to_string_lossy().to_string(),
+                size: metadata.len(),
+            });
+        }
+    }
+    for media in all_medias
+        .iter()
+        .filter(|m| m.deleted_at.is_none() && m.access_url.is_some())
+    {
+        let name = media.id.to_string();
+        if !qrcodes.qrcode_exists(&name, "svg") && !qrcodes.qrcode_exists(&name, "png") {
+            report.missing_qrcodes.push(media.id);
+        }
+    }
+
+    if options.verify_checksums {
+        for blob in blobs.iter().filter(|b| b.ref_count > 0) {
+            let path = storage.resolve(&blob.file_path);
+            if tokio::fs::metadata(&path).await.is_err() {
+                // 文件不存在已在媒体检查中报告
+                continue;
+            }
+            let actual_hash = storage::file_sha256(&path).await?;
+            if actual_hash != blob.content_hash {
+                report.checksum_mismatches.push(ChecksumMismatch {
+                    file_path: blob.file_path.clone(),
+                    content_hash: blob.content_hash.clone(),
+                    actual_hash,
+                });
+            }
+        }
+    }
+
+    report.reclaimable_bytes = report.stray_files().map(|f| f.size).sum();
+
+    if options.apply {
+        // 引用数为 0 的登记随文件一起删除
+        let unreferenced_blobs: Vec<(PathBuf, i32)> = blobs
+            .iter()
+            .filter(|b| b.ref_count <= 0)
+            .map(|b| (normalize(&storage.resolve(&b.file_path)), b.id))
+            .collect();
+        repair(
+            db,
+            qrcodes,
+            &all_medias,
+            &restored,
+            &unreferenced_blobs,
+            &mut report,
+        )
+        .await?;
+    }
+
+    Ok(report)
+}
+
+/// 按报告执行修复
+async fn repair(
+    db: &DatabaseConnection,
+    qrcodes: &QRCodeService,
+    all_medias: &[medias::Model],
+    restored: &[i32],
+    unreferenced_blobs: &[(PathBuf, i32)],
+    report: &mut IntegrityReport,
+) -> Result<()> {
+    let mut repairs = RepairSummary::default();
+
+    let mut deleted_paths = HashSet::new();
+    for file in report.stray_files() {
+        match tokio::fs::remove_file(&file.path).await {
+            Ok(()) => {
+                repairs.deleted_files += 1;
+                repairs.freed_bytes += file.size;
+                deleted_paths.insert(normalize(Path::new(&file.path)));
+            }
+            Err(e) => repairs
+                .failures
+                .push(format!("删除文件失败 {}: {e}", file.path)),
+        }
+    }
+    let deleted_blobs: Vec<i32> = unreferenced_blobs
+        .iter()
+        .filter(|(path, _)| deleted_paths.contains(path))
+        .map(|(_, id)| *id)
+        .collect();
+    if !deleted_blobs.is_empty() {
+        storage_blobs::Entity::delete_many()
+            .filter(storage_blobs::Column::RefCount.lte(0))
+            .filter(storage_blobs::Column::Id.is_in(deleted_blobs))
+            .exec(db)
+            .await?;
+    }
+
+    for media_id in &report.missing_qrcodes {
+        let Some(access_url) = all_medias
+            .iter()
+            .find(|m| m.id == *media_id)
+            .and_then(|m| m.access_url.as_deref())
+        else {
+            continue;
+        };
+        match qrcodes.generate_media_qrcode(*media_id, access_url).await {
+            Ok(qr_code_path) => {
+                medias::Entity::update_many()
+                    .col_expr(medias::Column::QrCodePath, Expr::value(qr_code_path))
+                    .filter(medias::Column::Id.eq(*media_id))
+                    .exec(db)
+                    .await?;
+                repairs.regenerated_qrcodes += 1;
+            }
+            Err(e) => repairs
+                .failures
+                .push(format!("生成二维码失败 (媒体 {media_id}): {e}")),
+        }
+    }
+
+    let newly_missing: Vec<i32> = report
+        .missing_files
+        .iter()
+        .map(|m| m.media_id)
+        .filter(|id| {
+            all_medias
+                .iter()
+                .any(|m| m.id == *id && m.file_missing_at.is_none())
+        })
+        .collect();
+    if !newly_missing.is_empty() {
+        let now: sea_orm::prelude::DateTimeWithTimeZone = chrono::Utc::now().into();
+        let result = medias::Entity::update_many()
+            .col_expr(medias::Column::FileMissingAt, Expr::value(now))
+            .filter(medias::Column::Id.is_in(newly_missing))
+            .exec(db)
+            .await?;
+        repairs.marked_missing = usize::try_from(result.rows_affected).unwrap_or_default();
+    }
+    if !restored.is_empty() {
+        let result = medias::Entity::update_many()
+            .col_expr(
+                medias::Column::FileMissingAt,
+                Expr::value(Option::<sea_orm::prelude::DateTimeWithTimeZone>::None),
+            )
+            .filter(medias::Column::Id.is_in(restored.to_vec()))
+            .exec(db)
+            .await?;
+        repairs.cleared_missing = usize::try_from(result.rows_affected).unwrap_or_default();
+    }
+
+    report.repairs = repairs;
+    Ok(())
+}
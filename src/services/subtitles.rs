@@ -0,0 +1,328 @@
+use loco_rs::prelude::*;
+
+/// 字幕文件大小上限（2MB）
+pub const MAX_SUBTITLE_SIZE: usize = 2 * 1024 * 1024;
+
+/// 字幕文本中的标签，如 `<v 老师>`、`<b>`
+static TAG_REGEX: std::sync::LazyLock<regex::Regex> =
+    std::sync::LazyLock::new(|| regex::Regex::new(r"<[^>]*>").expect("valid regex"));
+
+/// 字幕源格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleFormat {
+    Vtt,
+    Srt,
+}
+
+impl SubtitleFormat {
+    /// 根据文件扩展名或内容判断格式
+    ///
+    /// # Errors
+    ///
+    /// Will return error if the format is neither WebVTT nor SRT
+    pub fn detect(filename: Option<&str>, content: &str) -> Result<Self> {
+        if strip_bom(content).starts_with("WEBVTT") {
+            return Ok(Self::Vtt);
+        }
+
+        let extension = filename
+            .and_then(|name| std::path::Path::new(name).extension())
+            .and_then(|ext| ext.to_str())
+            .map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("srt") => Ok(Self::Srt),
+            Some("vtt") => Err(Error::BadRequest(
+                "WebVTT 文件必须以 WEBVTT 开头".to_string(),
+            )),
+            // 没有扩展名时，按 SRT 的序号 + 时间轴结构尝试解析
+            None if content.contains("-->") => Ok(Self::Srt),
+            _ => Err(Error::BadRequest(
+                "不支持的字幕格式，仅支持 WebVTT (.vtt) 和 SRT (.srt)".to_string(),
+            )),
+        }
+    }
+
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Vtt => "vtt",
+            Self::Srt => "srt",
+        }
+    }
+}
+
+/// 单条字幕
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cue {
+    pub identifier: Option<String>,
+    /// 开始时间（毫秒）
+    pub start: u64,
+    /// 结束时间（毫秒）
+    pub end: u64,
+    /// WebVTT 的 cue 设置（如 `align:start`），SRT 没有
+    pub settings: Option<String>,
+    pub text: String,
+}
+
+/// 解析字幕并校验时间轴
+///
+/// # Errors
+///
+/// Will return error if a cue is malformed, its end time is not after the start time,
+/// cues are out of order, or the file contains no cues
+pub fn parse(content: &str, format: SubtitleFormat) -> Result<Vec<Cue>> {
+    let content = strip_bom(content).replace("\r\n", "\n").replace('\r', "\n");
+    let mut blocks = content
+        .split("\n\n")
+        .map(|block| block.trim_matches('\n'))
+        .filter(|block| !block.trim().is_empty());
+
+    if format == SubtitleFormat::Vtt {
+        // 跳过 WEBVTT 头部
+        blocks.next();
+    }
+
+    let mut cues: Vec<Cue> = Vec::new();
+    for block in blocks {
+        // WebVTT 的注释、样式和区域定义不是字幕
+        if format == SubtitleFormat::Vtt
+            && (block.starts_with("NOTE")
+                || block.starts_with("STYLE")
+                || block.starts_with("REGION"))
+        {
+            continue;
+        }
+
+        let number = cues.len() + 1;
+        let mut lines = block.lines();
+        let first = lines.next().unwrap_or_default();
+        let (identifier, timing) = if first.contains("-->") {
+            (None, first)
+        } else {
+            let timing = lines
+                .next()
+                .ok_or_else(|| Error::BadRequest(format!("第 {number} 条字幕缺少时间轴")))?;
+            (Some(first.trim().to_string()), timing)
+        };
+
+        let (start, end, settings) = parse_timing(timing).ok_or_else(|| {
+            Error::BadRequest(format!("第 {number} 条字幕时间轴格式无效: {timing}"))
+        })?;
+
+        if end <= start {
+            return Err(Error::BadRequest(format!(
+                "第 {number} 条字幕的结束时间必须晚于开始时间: {timing}"
+            )));
+        }
+        if let Some(previous) = cues.last() {
+            if start < previous.start {
+                return Err(Error::BadRequest(format!(
+                    "第 {number} 条字幕的开始时间早于上一条字幕: {timing}"
+                )));
+            }
+        }
+
+        cues.push(Cue {
+            // SRT 的序号没有意义，不保留
+            identifier: identifier.filter(|_| format == SubtitleFormat::Vtt),
+            start,
+            end,
+            settings,
+            text: lines.collect::<Vec<_>>().join("\n"),
+        });
+    }
+
+    if cues.is_empty() {
+        return Err(Error::BadRequest("字幕文件中没有有效的字幕".to_string()));
+    }
+
+    Ok(cues)
+}
+
+/// 生成 WebVTT 内容
+#[must_use]
+pub fn to_vtt(cues: &[Cue]) -> String {
+    let mut vtt = String::from("WEBVTT\n\n");
+    for cue in cues {
+        if let Some(identifier) = &cue.identifier {
+            vtt.push_str(identifier);
+            vtt.push('\n');
+        }
+        vtt.push_str(&format_timestamp(cue.start));
+        vtt.push_str(" --> ");
+        vtt.push_str(&format_timestamp(cue.end));
+        if let Some(settings) = &cue.settings {
+            vtt.push(' ');
+            vtt.push_str(settings);
+        }
+        vtt.push('\n');
+        // 文本中出现 "-->" 会被当成时间轴，按规范替换
+        vtt.push_str(&cue.text.replace("-->", "--&gt;"));
+        vtt.push_str("\n\n");
+    }
+    vtt
+}
+
+/// 提取纯文本文稿（去除时间轴和标签），用于下载或全文展示
+#[must_use]
+pub fn to_plain_text(cues: &[Cue]) -> String {
+    let mut lines: Vec<String> = Vec::with_capacity(cues.len());
+    for cue in cues {
+        let text = TAG_REGEX.replace_all(&cue.text, "");
+        let text = text
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&nbsp;", " ")
+            .replace("&amp;", "&");
+        let text = text.trim();
+        if !text.is_empty() {
+            lines.push(text.to_string());
+        }
+    }
+    lines.join("\n")
+}
+
+/// 解析字幕并转换为 WebVTT，返回内容和字幕条数
+///
+/// # Errors
+///
+/// Will return error if the subtitle cannot be parsed
+pub fn normalize(content: &str, format: SubtitleFormat) -> Result<(String, usize)> {
+    let cues = parse(content, format)?;
+    Ok((to_vtt(&cues), cues.len()))
+}
+
+/// 校验语言代码（BCP 47，如 `zh-CN`、`en`）
+#[must_use]
+pub fn is_valid_language(language: &str) -> bool {
+    let mut parts = language.split('-');
+    let Some(primary) = parts.next() else {
+        return false;
+    };
+    (2..=3).contains(&primary.len())
+        && primary.chars().all(|c| c.is_ascii_alphabetic())
+        && parts.all(|part| {
+            (1..=8).contains(&part.len()) && part.chars().all(|c| c.is_ascii_alphanumeric())
+        })
+}
+
+fn strip_bom(content: &str) -> &str {
+    content.strip_prefix('\u{feff}').unwrap_or(content)
+}
+
+/// 解析时间轴行：`00:00:01.000 --> 00:00:02.500 align:start`
+fn parse_timing(line: &str) -> Option<(u64, u64, Option<String>)> {
+    let (start, rest) = line.split_once("-->")?;
+    let mut rest = rest.split_whitespace();
+    let end = rest.next()?;
+    let settings: Vec<&str> = rest.collect();
+
+    Some((
+        parse_timestamp(start.trim())?,
+        parse_timestamp(end)?,
+        (!settings.is_empty()).then(|| settings.join(" ")),
+    ))
+}
+
+/// 解析时间戳：`hh:mm:ss.mmm` 或 `mm:ss.mmm`，兼容 SRT 的逗号分隔
+fn parse_timestamp(value: &str) -> Option<u64> {
+    let (clock, millis) = value.split_once(['.', ','])?;
+    if millis.len() != 3 || !millis.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let parts: Vec<&str> = clock.split(':').collect();
+    let (hours, minutes, seconds) = match parts.as_slice() {
+        [h, m, s] => (*h, *m, *s),
+        [m, s] => ("0", *m, *s),
+        _ => return None,
+    };
+    if minutes.len() != 2 || seconds.len() != 2 {
+        return None;
+    }
+
+    let hours: u64 = hours.parse().ok()?;
+    let minutes: u64 = minutes.parse().ok()?;
+    let seconds: u64 = seconds.parse().ok()?;
+    let millis: u64 = millis.parse().ok()?;
+    if minutes >= 60 || seconds >= 60 {
+        return None;
+    }
+
+    Some(((hours * 60 + minutes) * 60 + seconds) * 1000 + millis)
+}
+
+fn format_timestamp(millis: u64) -> String {
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        (millis / 60_000) % 60,
+        (millis / 1000) % 60,
+        millis % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_srt_to_vtt() {
+        let srt = "1\r\n00:00:01,000 --> 00:00:02,500\r\n你好\r\n\r\n2\r\n00:00:03,000 --> 00:00:04,000\r\n第二行\r\n多行字幕\r\n";
+        let (vtt, count) = normalize(srt, SubtitleFormat::Srt).unwrap();
+        assert_eq!(count, 2);
+        assert_eq!(
+            vtt,
+            "WEBVTT\n\n00:00:01.000 --> 00:00:02.500\n你好\n\n00:00:03.000 --> 00:00:04.000\n第二行\n多行字幕\n\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_vtt_with_notes_and_settings() {
+        let vtt = "WEBVTT - 课程字幕\n\nNOTE 这是注释\n\nintro\n00:01.000 --> 00:02.000 align:start\n<v 老师>同学们好\n";
+        let cues = parse(vtt, SubtitleFormat::Vtt).unwrap();
+        assert_eq!(cues.len(), 1);
+        assert_eq!(cues[0].identifier.as_deref(), Some("intro"));
+        assert_eq!(cues[0].start, 1000);
+        assert_eq!(cues[0].settings.as_deref(), Some("align:start"));
+        assert_eq!(to_plain_text(&cues), "同学们好");
+    }
+
+    #[test]
+    fn test_reject_invalid_timing() {
+        let reversed = "1\n00:00:02,000 --> 00:00:01,000\n错误\n";
+        assert!(parse(reversed, SubtitleFormat::Srt).is_err());
+
+        let unordered =
+            "WEBVTT\n\n00:00:05.000 --> 00:00:06.000\nA\n\n00:00:01.000 --> 00:00:02.000\nB\n";
+        assert!(parse(unordered, SubtitleFormat::Vtt).is_err());
+
+        let malformed = "1\n00:00:61,000 --> 00:01:02,000\n错误\n";
+        assert!(parse(malformed, SubtitleFormat::Srt).is_err());
+
+        assert!(parse("WEBVTT\n\n", SubtitleFormat::Vtt).is_err());
+    }
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(
+            SubtitleFormat::detect(Some("a.vtt"), "WEBVTT\n").unwrap(),
+            SubtitleFormat::Vtt
+        );
+        assert_eq!(
+            SubtitleFormat::detect(Some("a.SRT"), "1\n").unwrap(),
+            SubtitleFormat::Srt
+        );
+        assert!(SubtitleFormat::detect(Some("a.txt"), "hello").is_err());
+    }
+
+    #[test]
+    fn test_is_valid_language() {
+        assert!(is_valid_language("zh-CN"));
+        assert!(is_valid_language("en"));
+        assert!(is_valid_language("zh-Hans-CN"));
+        assert!(!is_valid_language("chinese"));
+        assert!(!is_valid_language("zh_CN"));
+        assert!(!is_valid_language(""));
+    }
+}
@@ -0,0 +1,180 @@
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use uuid::Uuid;
+
+/// 验证码位数
+pub const DIGITS: u32 = 6;
+
+/// 验证码时间窗口（秒）
+pub const PERIOD: u64 = 30;
+
+/// 允许前后各一个时间窗口的时钟偏差
+const SKEW: u64 = 1;
+
+/// 每次生成的恢复码数量
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+/// 生成 160 位密钥（Base32 编码，可直接填入验证器应用）
+#[must_use]
+pub fn generate_secret() -> String {
+    let mut bytes = Vec::with_capacity(20);
+    bytes.extend_from_slice(Uuid::new_v4().as_bytes());
+    bytes.extend_from_slice(&Uuid::new_v4().as_bytes()[..4]);
+    BASE32_NOPAD.encode(&bytes)
+}
+
+/// 生成验证器应用扫码用的 `otpauth://` 地址
+#[must_use]
+pub fn provisioning_uri(issuer: &str, account: &str, secret: &str) -> String {
+    let mut uri = url::Url::parse("otpauth://totp/").expect("valid otpauth uri");
+    uri.set_path(&format!("{issuer}:{account}"));
+    uri.query_pairs_mut()
+        .append_pair("secret", secret)
+        .append_pair("issuer", issuer)
+        .append_pair("algorithm", "SHA1")
+        .append_pair("digits", &DIGITS.to_string())
+        .append_pair("period", &PERIOD.to_string());
+    uri.to_string()
+}
+
+/// 计算指定时间的验证码，密钥无效时返回 `None`
+#[must_use]
+pub fn code_at(secret: &str, unix_time: u64) -> Option<String> {
+    let key = decode_secret(secret)?;
+    Some(hotp(&key, unix_time / PERIOD))
+}
+
+/// 校验验证码，成功时返回匹配的时间步
+///
+/// `last_used_step` 及之前的时间步不再接受，防止同一验证码被重放
+#[must_use]
+pub fn verify(
+    secret: &str,
+    code: &str,
+    unix_time: u64,
+    last_used_step: Option<u64>,
+) -> Option<u64> {
+    let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    if code.len() != DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let key = decode_secret(secret)?;
+
+    let current = unix_time / PERIOD;
+    (current.saturating_sub(SKEW)..=current + SKEW)
+        .filter(|step| last_used_step.is_none_or(|last| *step > last))
+        .find(|step| constant_time_eq(hotp(&key, *step).as_bytes(), code.as_bytes()))
+}
+
+/// 生成一组恢复码（形如 `a1b2c-d3e4f`）
+#[must_use]
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let hex = Uuid::new_v4().simple().to_string();
+            format!("{}-{}", &hex[..5], &hex[5..10])
+        })
+        .collect()
+}
+
+/// 规范化用户输入的恢复码（忽略大小写、空格和连字符）
+#[must_use]
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn decode_secret(secret: &str) -> Option<Vec<u8>> {
+    let normalized: String = secret
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '=')
+        .map(|c| c.to_ascii_uppercase())
+        .collect();
+    BASE32_NOPAD.decode(normalized.as_bytes()).ok()
+}
+
+/// RFC 4226 HOTP
+fn hotp(key: &[u8], counter: u64) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+    format!(
+        "{:0width$}",
+        binary % 10u32.pow(DIGITS),
+        width = DIGITS as usize
+    )
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// RFC 6238 附录 B 的测试密钥 "12345678901234567890"
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn test_rfc6238_vectors() {
+        assert_eq!(code_at(RFC_SECRET, 59).as_deref(), Some("287082"));
+        assert_eq!(
+            code_at(RFC_SECRET, 1_111_111_109).as_deref(),
+            Some("081804")
+        );
+        assert_eq!(
+            code_at(RFC_SECRET, 1_234_567_890).as_deref(),
+            Some("005924")
+        );
+        assert_eq!(
+            code_at(RFC_SECRET, 2_000_000_000).as_deref(),
+            Some("279037")
+        );
+    }
+
+    #[test]
+    fn test_verify_with_skew_and_replay() {
+        let now = 1_111_111_109;
+        let step = now / PERIOD;
+        let previous = code_at(RFC_SECRET, now - PERIOD).unwrap();
+
+        assert_eq!(verify(RFC_SECRET, "081804", now, None), Some(step));
+        assert_eq!(verify(RFC_SECRET, "081 804", now, None), Some(step));
+        assert_eq!(verify(RFC_SECRET, &previous, now, None), Some(step - 1));
+        // 已使用过的时间步不能再次使用
+        assert_eq!(verify(RFC_SECRET, "081804", now, Some(step)), None);
+        assert_eq!(verify(RFC_SECRET, "000000", now, None), None);
+        assert_eq!(verify(RFC_SECRET, "abc", now, None), None);
+    }
+
+    #[test]
+    fn test_generate_secret_and_uri() {
+        let secret = generate_secret();
+        assert_eq!(secret.len(), 32);
+        assert!(code_at(&secret, 0).is_some());
+
+        let uri = provisioning_uri("QCast", "user@example.com", &secret);
+        assert!(uri.starts_with("otpauth://totp/QCast:user@example.com?secret="));
+        assert!(uri.contains("&issuer=QCast"));
+    }
+
+    #[test]
+    fn test_recovery_codes() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert_eq!(codes[0].len(), 11);
+        assert_eq!(normalize_recovery_code(" A1B2C-D3E4F "), "a1b2cd3e4f");
+    }
+}
@@ -0,0 +1,645 @@
+//! 回收站：书籍、章节和媒体的软删除、恢复与过期清理
+//!
+//! 删除书籍或章节时，其下仍有效的章节和媒体会使用同一个删除时间一起移入回收站；
+//! 恢复时只恢复删除时间相同的条目，之前单独删除的内容仍留在回收站中。
+//! 删除书籍时子书籍默认移到上级书籍下，也可以选择一起移入回收站。
+//! 媒体移入回收站（包括随书籍、章节一起删除）和永久删除时都会触发 `media.deleted` 事件。
+use chrono::SubsecRound;
+use loco_rs::prelude::*;
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::Expr;
+use sea_orm::{DatabaseConnection, QueryOrder, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::models::_entities::{books, medias};
+use crate::models::chapters;
+use crate::models::webhook_endpoints::EVENT_MEDIA_DELETED;
+use crate::models::{media_clips, media_placements, media_subtitles};
+use crate::services::audio_processing;
+use crate::services::media_versions;
+use crate::services::qrcode::QRCODE_SERVICE;
+use crate::services::storage::STORAGE_SERVICE;
+use crate::services::webhooks;
+
+/// 默认保留天数，超过后永久删除
+pub const DEFAULT_RETENTION_DAYS: i64 = 30;
+
+/// 回收站保留天数（`TRASH_RETENTION_DAYS`，至少 1 天）
+#[must_use]
+pub fn retention_days() -> i64 {
+    std::env::var("TRASH_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .filter(|days| *days > 0)
+        .unwrap_or(DEFAULT_RETENTION_DAYS)
+}
+
+/// 删除时间（保留到毫秒，保证写入数据库后仍能按相同时间匹配级联删除的条目）
+fn deletion_time() -> DateTimeWithTimeZone {
+    chrono::Utc::now().trunc_subsecs(3).into()
+}
+
+/// 回收站中的一个条目
+#[derive(Debug, Clone, Serialize)]
+pub struct TrashItem {
+    pub id: i32,
+    pub title: String,
+    pub book_id: Option<i32>,
+    pub deleted_at: DateTimeWithTimeZone,
+    /// 计划永久删除的时间
+    pub purge_at: DateTimeWithTimeZone,
+}
+
+/// 用户回收站内容（只列出直接删除的条目，随上级一起删除的内容随上级恢复）
+#[derive(Debug, Clone, Serialize)]
+pub struct TrashListing {
+    pub books: Vec<TrashItem>,
+    pub chapters: Vec<TrashItem>,
+    pub medias: Vec<TrashItem>,
+    pub retention_days: i64,
+}
+
+/// 一次清理永久删除的条目数
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct PurgeSummary {
+    pub books: u64,
+    pub chapters: u64,
+    pub medias: u64,
+}
+
+fn trash_item(
+    id: i32,
+    title: &str,
+    book_id: Option<i32>,
+    deleted_at: DateTimeWithTimeZone,
+    retention_days: i64,
+) -> TrashItem {
+    TrashItem {
+        id,
+        title: title.to_string(),
+        book_id,
+        deleted_at,
+        purge_at: deleted_at + chrono::Duration::days(retention_days),
+    }
+}
+
+/// 通知订阅者媒体已删除，`permanent` 区分移入回收站和永久删除
+async fn notify_deleted(ctx: &AppContext, deleted: &[medias::Model], permanent: bool) {
+    for media in deleted {
+        let mut data = webhooks::media_data(media);
+        data["permanent"] = serde_json::json!(permanent);
+        webhooks::dispatch(ctx, media.user_id, EVENT_MEDIA_DELETED, data).await;
+    }
+}
+
+/// 删除书籍时如何处理子书籍
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChildBooks {
+    /// 子书籍移到被删除书籍的上级下（默认）
+    #[default]
+    Reparent,
+    /// 子书籍连同其中的内容一起移入回收站
+    Cascade,
+}
+
+/// 将书籍及其下所有章节和媒体移入回收站，子书籍按 `children` 处理
+pub async fn trash_book(ctx: &AppContext, book: &books::Model, children: ChildBooks) -> Result<()> {
+    let now = deletion_time();
+    let txn = ctx.db.begin().await?;
+
+    let ids = match children {
+        ChildBooks::Cascade => books::Model::find_descendant_ids(&txn, book.id, false).await?,
+        ChildBooks::Reparent => {
+            books::Entity::update_many()
+                .col_expr(books::Column::ParentId, Expr::value(book.parent_id))
+                .filter(books::Column::ParentId.eq(book.id))
+                .filter(books::Column::DeletedAt.is_null())
+                .exec(&txn)
+                .await?;
+            vec![book.id]
+        }
+    };
+
+    books::Entity::update_many()
+        .col_expr(books::Column::DeletedAt, Expr::value(now))
+        .filter(books::Column::Id.is_in(ids.clone()))
+        .filter(books::Column::DeletedAt.is_null())
+        .exec(&txn)
+        .await?;
+    chapters::Entity::update_many()
+        .col_expr(chapters::Column::DeletedAt, Expr::value(now))
+        .filter(chapters::Column::BookId.is_in(ids.clone()))
+        .filter(chapters::Column::DeletedAt.is_null())
+        .exec(&txn)
+        .await?;
+    let trashed = medias::Entity::find()
+        .filter(medias::Column::BookId.is_in(ids.clone()))
+        .filter(medias::Column::DeletedAt.is_null())
+        .all(&txn)
+        .await?;
+    medias::Entity::update_many()
+        .col_expr(medias::Column::DeletedAt, Expr::value(now))
+        .filter(medias::Column::BookId.is_in(ids))
+        .filter(medias::Column::DeletedAt.is_null())
+        .exec(&txn)
+        .await?;
+
+    txn.commit().await?;
+    notify_deleted(ctx, &trashed, false).await;
+    Ok(())
+}
+
+/// 将章节、所有子章节以及其中的媒体移入回收站
+pub async fn trash_chapter(ctx: &AppContext, chapter: &chapters::Model) -> Result<()> {
+    let now = deletion_time();
+    let txn = ctx.db.begin().await?;
+    let ids = chapters::Model::find_subtree_ids(&txn, chapter).await?;
+
+    chapters::Entity::update_many()
+        .col_expr(chapters::Column::DeletedAt, Expr::value(now))
+        .filter(chapters::Column::Id.is_in(ids.clone()))
+        .filter(chapters::Column::DeletedAt.is_null())
+        .exec(&txn)
+        .await?;
+    let trashed = medias::Entity::find()
+        .filter(medias::Column::ChapterId.is_in(ids.clone()))
+        .filter(medias::Column::DeletedAt.is_null())
+        .all(&txn)
+        .await?;
+    medias::Entity::update_many()
+        .col_expr(medias::Column::DeletedAt, Expr::value(now))
+        .filter(medias::Column::ChapterId.is_in(ids))
+        .filter(medias::Column::DeletedAt.is_null())
+        .exec(&txn)
+        .await?;
+
+    txn.commit().await?;
+    notify_deleted(ctx, &trashed, false).await;
+    Ok(())
+}
+
+/// 将媒体移入回收站（文件和二维码保留到永久删除时）
+pub async fn trash_media(ctx: &AppContext, media: &medias::Model) -> Result<()> {
+    medias::Entity::update_many()
+        .col_expr(medias::Column::DeletedAt, Expr::value(deletion_time()))
+        .filter(medias::Column::Id.eq(media.id))
+        .exec(&ctx.db)
+        .await?;
+    notify_deleted(ctx, std::slice::from_ref(media), false).await;
+    Ok(())
+}
+
+/// 恢复书籍以及与它一起删除的子书籍、章节和媒体
+///
+/// 上级书籍仍然有效时回到原来的位置，否则作为顶级书籍恢复
+pub async fn restore_book(db: &DatabaseConnection, book: &books::Model) -> Result<()> {
+    let Some(deleted_at) = book.deleted_at else {
+        return Ok(());
+    };
+
+    let parent_active = match book.parent_id {
+        Some(parent_id) => books::Entity::find_by_id(parent_id)
+            .filter(books::Column::DeletedAt.is_null())
+            .one(db)
+            .await?
+            .is_some(),
+        None => true,
+    };
+
+    let cleared = Expr::value(Option::<DateTimeWithTimeZone>::None);
+    let txn = db.begin().await?;
+
+    let descendants = books::Model::find_descendant_ids(&txn, book.id, true).await?;
+    let ids: Vec<i32> = books::Entity::find()
+        .filter(books::Column::Id.is_in(descendants))
+        .filter(books::Column::DeletedAt.eq(deleted_at))
+        .all(&txn)
+        .await?
+        .into_iter()
+        .map(|b| b.id)
+        .collect();
+
+    books::Entity::update_many()
+        .col_expr(books::Column::DeletedAt, cleared.clone())
+        .filter(books::Column::Id.is_in(ids.clone()))
+        .exec(&txn)
+        .await?;
+    chapters::Entity::update_many()
+        .col_expr(chapters::Column::DeletedAt, cleared.clone())
+        .filter(chapters::Column::BookId.is_in(ids.clone()))
+        .filter(chapters::Column::DeletedAt.eq(deleted_at))
+        .exec(&txn)
+        .await?;
+    medias::Entity::update_many()
+        .col_expr(medias::Column::DeletedAt, cleared)
+        .filter(medias::Column::BookId.is_in(ids))
+        .filter(medias::Column::DeletedAt.eq(deleted_at))
+        .exec(&txn)
+        .await?;
+
+    if !parent_active {
+        books::Entity::update_many()
+            .col_expr(books::Column::ParentId, Expr::value(Option::<i32>::None))
+            .filter(books::Column::Id.eq(book.id))
+            .exec(&txn)
+            .await?;
+    }
+
+    txn.commit().await?;
+    Ok(())
+}
+
+/// 所属书籍已在回收站时不能单独恢复其中的内容
+async fn ensure_book_active(db: &DatabaseConnection, book_id: i32) -> Result<()> {
+    let book = books::Entity::find_by_id(book_id)
+        .one(db)
+        .await?
+        .ok_or_else(|| Error::NotFound)?;
+    if book.deleted_at.is_some() {
+        return Err(Error::BadRequest(
+            "所属书籍在回收站中，请先恢复书籍".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// 恢复章节、与它一起删除的子章节和媒体
+///
+/// 上级章节仍然有效时回到原来的位置，否则作为顶级章节恢复到书籍末尾
+pub async fn restore_chapter(db: &DatabaseConnection, chapter: &chapters::Model) -> Result<()> {
+    let Some(deleted_at) = chapter.deleted_at else {
+        return Ok(());
+    };
+    ensure_book_active(db, chapter.book_id).await?;
+
+    let parent_active = match chapter.parent_id {
+        Some(parent_id) => chapters::Entity::find_by_id(parent_id)
+            .filter(chapters::Column::DeletedAt.is_null())
+            .one(db)
+            .await?
+            .is_some(),
+        None => true,
+    };
+
+    let cleared = Expr::value(Option::<DateTimeWithTimeZone>::None);
+    let txn = db.begin().await?;
+    let ids = chapters::Model::find_subtree_ids(&txn, chapter).await?;
+
+    chapters::Entity::update_many()
+        .col_expr(chapters::Column::DeletedAt, cleared.clone())
+        .filter(chapters::Column::Id.is_in(ids.clone()))
+        .filter(chapters::Column::DeletedAt.eq(deleted_at))
+        .exec(&txn)
+        .await?;
+    medias::Entity::update_many()
+        .col_expr(medias::Column::DeletedAt, cleared)
+        .filter(medias::Column::ChapterId.is_in(ids))
+        .filter(medias::Column::DeletedAt.eq(deleted_at))
+        .exec(&txn)
+        .await?;
+
+    if !parent_active {
+        let next_order = chapters::Entity::find()
+            .filter(chapters::Column::BookId.eq(chapter.book_id))
+            .filter(chapters::Column::ParentId.is_null())
+            .filter(chapters::Column::DeletedAt.is_null())
+            .order_by_desc(chapters::Column::SortOrder)
+            .one(&txn)
+            .await?
+            .and_then(|c| c.sort_order)
+            .unwrap_or(0)
+            + 1;
+        chapters::Entity::update_many()
+            .col_expr(chapters::Column::ParentId, Expr::value(Option::<i32>::None))
+            .col_expr(chapters::Column::SortOrder, Expr::value(next_order))
+            .filter(chapters::Column::Id.eq(chapter.id))
+            .exec(&txn)
+            .await?;
+        crate::models::chapters::Model::update_level_and_path_txn(&txn, chapter.id, None).await?;
+    }
+
+    txn.commit().await?;
+    Ok(())
+}
+
+/// 恢复媒体；所在章节已不存在或仍在回收站时，恢复到书籍根目录
+pub async fn restore_media(db: &DatabaseConnection, media: &medias::Model) -> Result<()> {
+    if media.deleted_at.is_none() {
+        return Ok(());
+    }
+    ensure_book_active(db, media.book_id).await?;
+
+    let chapter_active = match media.chapter_id {
+        Some(chapter_id) => chapters::Entity::find_by_id(chapter_id)
+            .filter(chapters::Column::DeletedAt.is_null())
+            .one(db)
+            .await?
+            .is_some(),
+        None => true,
+    };
+
+    let mut update = medias::Entity::update_many()
+        .col_expr(
+            medias::Column::DeletedAt,
+            Expr::value(Option::<DateTimeWithTimeZone>::None),
+        )
+        .filter(medias::Column::Id.eq(media.id));
+    if !chapter_active {
+        update = update.col_expr(medias::Column::ChapterId, Expr::value(Option::<i32>::None));
+    }
+    update.exec(db).await?;
+    Ok(())
+}
+
+/// 永久删除媒体：删除文件、历史版本、二维码、字幕和数据库记录
+///
+/// 数据库记录在同一个事务中删除，提交后再删除不再使用的文件，
+/// 删除失败时媒体和文件都保持原样。
+pub async fn purge_media(ctx: &AppContext, media: medias::Model) -> Result<()> {
+    let txn = ctx.db.begin().await?;
+    // 按内容存储的文件由版本记录引用，在清理版本时按引用数删除；
+    // 其他文件可能是复制出来的媒体与其他媒体共用的
+    let mut unused_files = media_versions::purge_all(&txn, &media).await?;
+    if media.content_hash.is_none()
+        && !media_versions::is_file_shared(&txn, &media.file_path, media.id).await?
+    {
+        unused_files.push(STORAGE_SERVICE.resolve(&media.file_path));
+    }
+    media_subtitles::Model::delete_by_media(&txn, media.id).await?;
+    media_placements::Model::delete_by_media(&txn, media.id).await?;
+    media_clips::Model::delete_by_media(&txn, media.id).await?;
+    media.clone().delete(&txn).await?;
+    txn.commit().await?;
+
+    for file_path in unused_files {
+        if tokio::fs::metadata(&file_path).await.is_ok() {
+            if let Err(e) = tokio::fs::remove_file(&file_path).await {
+                tracing::warn!("删除媒体文件失败: {:?}, 错误: {}", file_path, e);
+            } else {
+                tracing::info!("已删除媒体文件: {:?}", file_path);
+            }
+        }
+    }
+
+    if let Some(ref qr_path) = media.qr_code_path {
+        let qr_full_path =
+            std::path::Path::new("assets/static").join(qr_path.trim_start_matches('/'));
+        if tokio::fs::metadata(&qr_full_path).await.is_ok() {
+            if let Err(e) = tokio::fs::remove_file(&qr_full_path).await {
+                tracing::warn!("删除二维码文件失败: {:?}, 错误: {}", qr_full_path, e);
+            } else {
+                tracing::info!("已删除二维码文件: {:?}", qr_full_path);
+            }
+        }
+    }
+    if let Err(e) = QRCODE_SERVICE.delete_media_qrcode(media.id).await {
+        tracing::warn!("删除媒体 {} 的二维码失败: {}", media.id, e);
+    }
+    audio_processing::remove_rendition(&media).await;
+
+    notify_deleted(ctx, &[media], true).await;
+    Ok(())
+}
+
+/// 永久删除章节子树及其中已删除的媒体，返回删除的章节数和媒体数
+pub async fn purge_chapter(ctx: &AppContext, chapter: &chapters::Model) -> Result<(u64, u64)> {
+    let db = &ctx.db;
+    let ids = chapters::Model::find_subtree_ids(db, chapter).await?;
+
+    let trashed_medias = medias::Entity::find()
+        .filter(medias::Column::ChapterId.is_in(ids.clone()))
+        .filter(medias::Column::DeletedAt.is_not_null())
+        .all(db)
+        .await?;
+    let media_count = trashed_medias.len() as u64;
+    for media in trashed_medias {
+        purge_media(ctx, media).await?;
+    }
+
+    media_placements::Model::delete_by_chapters(db, &ids).await?;
+    let result = chapters::Entity::delete_many()
+        .filter(chapters::Column::Id.is_in(ids))
+        .exec(db)
+        .await?;
+    Ok((result.rows_affected, media_count))
+}
+
+/// 永久删除书籍、回收站中的子书籍及其下所有章节和媒体
+///
+/// 仍然有效的子书籍移到被删除书籍的上级下
+pub async fn purge_book(ctx: &AppContext, book: books::Model) -> Result<PurgeSummary> {
+    let db = &ctx.db;
+    let descendants = books::Model::find_descendant_ids(db, book.id, true).await?;
+    let live: Vec<i32> = books::Entity::find()
+        .filter(books::Column::Id.is_in(descendants.clone()))
+        .filter(books::Column::DeletedAt.is_null())
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|b| b.id)
+        .collect();
+    books::Entity::update_many()
+        .col_expr(books::Column::ParentId, Expr::value(book.parent_id))
+        .filter(books::Column::Id.is_in(live.clone()))
+        .filter(books::Column::ParentId.is_not_in(live.clone()))
+        .exec(db)
+        .await?;
+    let ids: Vec<i32> = descendants
+        .into_iter()
+        .filter(|id| !live.contains(id))
+        .collect();
+
+    let book_medias = medias::Entity::find()
+        .filter(medias::Column::BookId.is_in(ids.clone()))
+        .all(db)
+        .await?;
+    let media_count = book_medias.len() as u64;
+    for media in book_medias {
+        purge_media(ctx, media).await?;
+    }
+
+    media_placements::Model::delete_by_books(db, &ids).await?;
+    let chapter_result = chapters::Entity::delete_many()
+        .filter(chapters::Column::BookId.is_in(ids.clone()))
+        .exec(db)
+        .await?;
+    let book_result = books::Entity::delete_many()
+        .filter(books::Column::Id.is_in(ids))
+        .exec(db)
+        .await?;
+    Ok(PurgeSummary {
+        books: book_result.rows_affected,
+        chapters: chapter_result.rows_affected,
+        medias: media_count,
+    })
+}
+
+/// 永久删除在回收站中超过保留天数的条目
+pub async fn purge_expired(ctx: &AppContext, retention_days: i64) -> Result<PurgeSummary> {
+    let db = &ctx.db;
+    let cutoff: DateTimeWithTimeZone =
+        (chrono::Utc::now() - chrono::Duration::days(retention_days)).into();
+    let mut summary = PurgeSummary::default();
+
+    let expired_books = books::Entity::find()
+        .filter(books::Column::DeletedAt.lt(cutoff))
+        .all(db)
+        .await?;
+    for book in expired_books {
+        // 可能已随上级书籍一起删除
+        let still_exists = books::Entity::find_by_id(book.id).one(db).await?.is_some();
+        if still_exists {
+            let purged = purge_book(ctx, book).await?;
+            summary.books += purged.books;
+            summary.chapters += purged.chapters;
+            summary.medias += purged.medias;
+        }
+    }
+
+    // 按层级从上到下处理，子章节随上级章节一起删除
+    let expired_chapters = chapters::Entity::find()
+        .filter(chapters::Column::DeletedAt.lt(cutoff))
+        .order_by_asc(chapters::Column::Level)
+        .all(db)
+        .await?;
+    for chapter in expired_chapters {
+        let still_exists = chapters::Entity::find_by_id(chapter.id)
+            .one(db)
+            .await?
+            .is_some();
+        if still_exists {
+            let (chapters, medias) = purge_chapter(ctx, &chapter).await?;
+            summary.chapters += chapters;
+            summary.medias += medias;
+        }
+    }
+
+    let expired_medias = medias::Entity::find()
+        .filter(medias::Column::DeletedAt.lt(cutoff))
+        .all(db)
+        .await?;
+    for media in expired_medias {
+        purge_media(ctx, media).await?;
+        summary.medias += 1;
+    }
+
+    Ok(summary)
+}
+
+/// 列出用户回收站中直接删除的书籍、章节和媒体
+pub async fn list_for_user(db: &DatabaseConnection, user_id: i32) -> Result<TrashListing> {
+    let retention_days = retention_days();
+
+    let user_books = books::Entity::find()
+        .filter(books::Column::UserId.eq(user_id))
+        .all(db)
+        .await?;
+    let book_deleted_at: HashMap<i32, Option<DateTimeWithTimeZone>> = user_books
+        .iter()
+        .map(|book| (book.id, book.deleted_at))
+        .collect();
+
+    let trashed_chapters = chapters::Entity::find()
+        .filter(chapters::Column::BookId.is_in(book_deleted_at.keys().copied().collect::<Vec<_>>()))
+        .filter(chapters::Column::DeletedAt.is_not_null())
+        .order_by_desc(chapters::Column::DeletedAt)
+        .all(db)
+        .await?;
+    let chapter_deleted_at: HashMap<i32, Option<DateTimeWithTimeZone>> = trashed_chapters
+        .iter()
+        .map(|chapter| (chapter.id, chapter.deleted_at))
+        .collect();
+
+    let trashed_medias = medias::Entity::find()
+        .filter(medias::Column::UserId.eq(user_id))
+        .filter(medias::Column::DeletedAt.is_not_null())
+        .order_by_desc(medias::Column::DeletedAt)
+        .all(db)
+        .await?;
+
+    // 与上级使用相同删除时间的条目是级联删除的
+    let cascaded = |deleted_at: Option<DateTimeWithTimeZone>,
+                    parents: &[Option<Option<DateTimeWithTimeZone>>]| {
+        parents
+            .iter()
+            .any(|parent| parent.flatten().is_some() && parent.flatten() == deleted_at)
+    };
+
+    let mut books: Vec<TrashItem> = user_books
+        .iter()
+        .filter(|book| {
+            !cascaded(
+                book.deleted_at,
+                &[book
+                    .parent_id
+                    .and_then(|id| book_deleted_at.get(&id).copied())],
+            )
+        })
+        .filter_map(|book| {
+            book.deleted_at
+                .map(|at| trash_item(book.id, &book.title, None, at, retention_days))
+        })
+        .collect();
+    books.sort_by_key(|b| std::cmp::Reverse(b.deleted_at));
+
+    let chapters = trashed_chapters
+        .iter()
+        .filter(|chapter| {
+            !cascaded(
+                chapter.deleted_at,
+                &[
+                    book_deleted_at.get(&chapter.book_id).copied(),
+                    chapter
+                        .parent_id
+                        .and_then(|id| chapter_deleted_at.get(&id).copied()),
+                ],
+            )
+        })
+        .filter_map(|chapter| {
+            chapter.deleted_at.map(|at| {
+                trash_item(
+                    chapter.id,
+                    &chapter.title,
+                    Some(chapter.book_id),
+                    at,
+                    retention_days,
+                )
+            })
+        })
+        .collect();
+
+    let medias = trashed_medias
+        .iter()
+        .filter(|media| {
+            !cascaded(
+                media.deleted_at,
+                &[
+                    book_deleted_at.get(&media.book_id).copied(),
+                    media
+                        .chapter_id
+                        .and_then(|id| chapter_deleted_at.get(&id).copied()),
+                ],
+            )
+        })
+        .filter_map(|media| {
+            media.deleted_at.map(|at| {
+                trash_item(
+                    media.id,
+                    &media.title,
+                    Some(media.book_id),
+                    at,
+                    retention_days,
+                )
+            })
+        })
+        .collect();
+
+    Ok(TrashListing {
+        books,
+        chapters,
+        medias,
+        retention_days,
+    })
+}
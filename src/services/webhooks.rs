@@ -0,0 +1,394 @@
+//! Webhook：把媒体生命周期事件以签名 JSON 推送到用户配置的地址
+//!
+//! 事件发生时为每个订阅地址创建一条投递记录并交给后台 worker 发送；
+//! 失败后按指数退避设置下次尝试时间，由定时任务 `retry_webhooks` 重新入队。
+//!
+//! 签名：`X-Qcast-Signature: sha256=<hex>`，为
+//! `HMAC-SHA256(secret, "{X-Qcast-Timestamp}.{body}")`。
+//!
+//! 地址由用户填写，投递时每次都重新解析并拒绝本机、内网等地址，不跟随重定向，
+//! 投递记录只保存响应状态码和耗时。
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+
+use hmac::{Hmac, Mac};
+use loco_rs::prelude::*;
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::models::_entities::medias;
+use crate::models::webhook_deliveries::{self, STATUS_FAILED, STATUS_PENDING, STATUS_SUCCEEDED};
+use crate::models::webhook_endpoints::{self, EVENTS};
+use crate::workers::webhook_delivery::{WebhookDeliveryArgs, WebhookDeliveryWorker};
+
+/// 最多尝试次数（含首次投递）
+pub const MAX_ATTEMPTS: i32 = 6;
+
+/// 首次重试的等待时间，之后每次翻倍
+const BASE_BACKOFF_SECS: i64 = 30;
+
+/// 请求超时
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+static HTTP_CLIENT: LazyLock<reqwest::Client> = LazyLock::new(|| {
+    reqwest::Client::builder()
+        .timeout(HTTP_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .no_proxy()
+        .dns_resolver(Arc::new(PublicResolver))
+        .build()
+        .expect("failed to build http client")
+});
+
+/// 是否为不允许投递的地址：本机、内网、链路本地、唯一本地、未指定及其他保留地址
+#[must_use]
+pub fn is_forbidden_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || a == 0
+                // 100.64.0.0/10 运营商级 NAT
+                || (a == 100 && (b & 0xC0) == 64)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_forbidden_ip(IpAddr::V4(mapped));
+            }
+            let first = ip.segments()[0];
+            ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // fc00::/7 唯一本地地址
+                || (first & 0xFE00) == 0xFC00
+                // fe80::/10 链路本地地址
+                || (first & 0xFFC0) == 0xFE80
+        }
+    }
+}
+
+/// 解析域名，任一地址不允许投递时报错
+async fn resolve_public(host: &str, port: u16) -> std::result::Result<Vec<SocketAddr>, String> {
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|err| format!("无法解析 Webhook 地址 {host}: {err}"))?
+        .collect();
+    if addrs.is_empty() {
+        return Err(format!("无法解析 Webhook 地址 {host}"));
+    }
+    if let Some(addr) = addrs.iter().find(|addr| is_forbidden_ip(addr.ip())) {
+        return Err(format!(
+            "Webhook 地址 {host} 指向内网或保留地址 {}",
+            addr.ip()
+        ));
+    }
+    Ok(addrs)
+}
+
+/// HTTP 客户端使用的解析器：每次建立连接都重新检查，防止检查后域名被改指到内网（DNS 重绑定）
+struct PublicResolver;
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs = resolve_public(&host, 0).await?;
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+/// 投递前检查目标地址；IP 字面量不经过解析器，需在这里单独检查
+async fn check_target(value: &str) -> std::result::Result<(), String> {
+    let parsed = url::Url::parse(value).map_err(|err| format!("Webhook 地址无效: {err}"))?;
+    match parsed.host() {
+        Some(url::Host::Ipv4(ip)) if is_forbidden_ip(IpAddr::V4(ip)) => {
+            Err(format!("Webhook 地址指向内网或保留地址 {ip}"))
+        }
+        Some(url::Host::Ipv6(ip)) if is_forbidden_ip(IpAddr::V6(ip)) => {
+            Err(format!("Webhook 地址指向内网或保留地址 {ip}"))
+        }
+        Some(url::Host::Domain(host)) => {
+            resolve_public(host, parsed.port_or_known_default().unwrap_or(80)).await?;
+            Ok(())
+        }
+        Some(_) => Ok(()),
+        None => Err("Webhook 地址缺少主机名".to_string()),
+    }
+}
+
+/// 生成签名密钥
+#[must_use]
+pub fn generate_secret() -> String {
+    format!(
+        "whsec_{}{}",
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    )
+}
+
+/// 只接受 http/https 地址；明显指向本机或内网的地址直接拒绝，域名在投递时再检查
+pub fn validate_url(value: &str) -> Result<String> {
+    let value = value.trim();
+    let parsed = match url::Url::parse(value) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") && parsed.has_host() => parsed,
+        _ => {
+            return Err(Error::BadRequest(
+                "Webhook 地址必须是有效的 http 或 https 地址".to_string(),
+            ))
+        }
+    };
+    let forbidden = match parsed.host() {
+        Some(url::Host::Ipv4(ip)) => is_forbidden_ip(IpAddr::V4(ip)),
+        Some(url::Host::Ipv6(ip)) => is_forbidden_ip(IpAddr::V6(ip)),
+        Some(url::Host::Domain(host)) => {
+            let host = host.trim_end_matches('.').to_ascii_lowercase();
+            host == "localhost" || host.ends_with(".localhost")
+        }
+        None => false,
+    };
+    if forbidden {
+        return Err(Error::BadRequest(
+            "Webhook 地址不能指向本机或内网".to_string(),
+        ));
+    }
+    Ok(value.to_string())
+}
+
+/// 校验订阅事件并去重，返回逗号分隔的存储格式
+pub fn normalize_events(events: &[String]) -> Result<String> {
+    let mut normalized: Vec<&str> = Vec::new();
+    for event in events {
+        let event = EVENTS.iter().find(|e| **e == event.trim()).ok_or_else(|| {
+            Error::BadRequest(format!(
+                "不支持的事件: {event}（可选 {}）",
+                EVENTS.join(", ")
+            ))
+        })?;
+        if !normalized.contains(event) {
+            normalized.push(event);
+        }
+    }
+    if normalized.is_empty() {
+        return Err(Error::BadRequest("至少需要订阅一个事件".to_string()));
+    }
+    Ok(normalized.join(","))
+}
+
+/// 计算签名
+#[must_use]
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{timestamp}.{body}").as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// 事件中携带的媒体信息
+#[must_use]
+pub fn media_data(media: &medias::Model) -> serde_json::Value {
+    serde_json::json!({
+        "id": media.id,
+        "title": media.title,
+        "book_id": media.book_id,
+        "chapter_id": media.chapter_id,
+        "file_type": media.file_type,
+        "file_version": media.file_version,
+        "duration": media.duration,
+        "is_public": media.is_public,
+        "access_url": media.access_url,
+    })
+}
+
+/// 第 `attempts` 次失败后的等待时间
+fn backoff(attempts: i32) -> chrono::Duration {
+    let exponent = u32::try_from(attempts.saturating_sub(1))
+        .unwrap_or(0)
+        .min(10);
+    chrono::Duration::seconds(BASE_BACKOFF_SECS * 2_i64.pow(exponent))
+}
+
+async fn enqueue(ctx: &AppContext, delivery_id: i32) {
+    if let Err(err) =
+        WebhookDeliveryWorker::perform_later(ctx, WebhookDeliveryArgs { delivery_id }).await
+    {
+        tracing::error!(
+            error = err.to_string(),
+            delivery_id,
+            "failed to enqueue webhook delivery"
+        );
+    }
+}
+
+/// 向订阅了事件的地址分发；失败只记录日志，不影响触发事件的业务操作
+pub async fn dispatch(ctx: &AppContext, user_id: i32, event: &str, data: serde_json::Value) {
+    let endpoints = match webhook_endpoints::Model::find_subscribers(&ctx.db, user_id, event).await
+    {
+        Ok(endpoints) => endpoints,
+        Err(err) => {
+            tracing::error!(error = err.to_string(), event, "failed to load webhooks");
+            return;
+        }
+    };
+    if endpoints.is_empty() {
+        return;
+    }
+
+    let payload = serde_json::json!({
+        "id": Uuid::new_v4().to_string(),
+        "event": event,
+        "created_at": chrono::Utc::now().to_rfc3339(),
+        "data": data,
+    })
+    .to_string();
+
+    for endpoint in endpoints {
+        match webhook_deliveries::Model::create(&ctx.db, endpoint.id, event, payload.clone()).await
+        {
+            Ok(delivery) => enqueue(ctx, delivery.id).await,
+            Err(err) => tracing::error!(
+                error = err.to_string(),
+                endpoint_id = endpoint.id,
+                event,
+                "failed to create webhook delivery"
+            ),
+        }
+    }
+}
+
+/// 错误信息附带底层原因（如解析器拒绝的地址）
+fn error_message(err: &reqwest::Error) -> String {
+    let mut message = err.to_string();
+    let mut source = std::error::Error::source(err);
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
+/// 发送一次投递并记录结果
+pub async fn deliver(ctx: &AppContext, delivery_id: i32) -> Result<()> {
+    let Some(delivery) = webhook_deliveries::Entity::find_by_id(delivery_id)
+        .one(&ctx.db)
+        .await?
+    else {
+        return Ok(());
+    };
+    if delivery.status == STATUS_SUCCEEDED {
+        return Ok(());
+    }
+    let endpoint = webhook_endpoints::Entity::find_by_id(delivery.endpoint_id)
+        .one(&ctx.db)
+        .await?;
+
+    let attempts = delivery.attempts + 1;
+    let mut item = delivery.clone().into_active_model();
+    item.attempts = Set(attempts);
+    item.next_attempt_at = Set(None);
+
+    let Some(endpoint) = endpoint.filter(|e| e.is_active) else {
+        item.status = Set(STATUS_FAILED.to_string());
+        item.error = Set(Some("Webhook 地址已删除或停用".to_string()));
+        item.update(&ctx.db).await?;
+        return Ok(());
+    };
+
+    let timestamp = chrono::Utc::now().timestamp();
+    let started = Instant::now();
+    let result = match check_target(&endpoint.url).await {
+        Ok(()) => HTTP_CLIENT
+            .post(&endpoint.url)
+            .header("content-type", "application/json")
+            .header("user-agent", "qcast-webhooks")
+            .header("x-qcast-event", &delivery.event)
+            .header("x-qcast-delivery", delivery.id.to_string())
+            .header("x-qcast-timestamp", timestamp.to_string())
+            .header(
+                "x-qcast-signature",
+                sign(&endpoint.secret, timestamp, &delivery.payload),
+            )
+            .body(delivery.payload.clone())
+            .send()
+            .await
+            .map_err(|err| error_message(&err)),
+        Err(message) => Err(message),
+    };
+    let latency_ms = i32::try_from(started.elapsed().as_millis()).unwrap_or(i32::MAX);
+
+    // 不读取响应内容：重定向不跟随，3xx 视为失败
+    let succeeded = match result {
+        Ok(response) => {
+            let status = response.status();
+            item.response_status = Set(Some(i32::from(status.as_u16())));
+            item.latency_ms = Set(Some(latency_ms));
+            item.error = Set(if status.is_success() {
+                None
+            } else {
+                Some(format!("HTTP {status}"))
+            });
+            status.is_success()
+        }
+        Err(message) => {
+            item.response_status = Set(None);
+            item.latency_ms = Set(None);
+            item.error = Set(Some(message));
+            false
+        }
+    };
+
+    if succeeded {
+        item.status = Set(STATUS_SUCCEEDED.to_string());
+        item.delivered_at = Set(Some(chrono::Utc::now().into()));
+    } else if attempts >= MAX_ATTEMPTS {
+        item.status = Set(STATUS_FAILED.to_string());
+    } else {
+        item.status = Set(STATUS_PENDING.to_string());
+        item.next_attempt_at = Set(Some((chrono::Utc::now() + backoff(attempts)).into()));
+    }
+    item.update(&ctx.db).await?;
+
+    if !succeeded {
+        tracing::warn!(
+            delivery_id,
+            endpoint_id = endpoint.id,
+            attempts,
+            "webhook delivery failed"
+        );
+    }
+    Ok(())
+}
+
+/// 把已到重试时间的投递重新入队，返回入队数量
+pub async fn retry_due(ctx: &AppContext) -> Result<usize> {
+    let due = webhook_deliveries::Model::find_due(&ctx.db).await?;
+    let count = due.len();
+    for delivery in due {
+        let id = delivery.id;
+        // 清除重试时间，避免 worker 执行前被重复入队
+        let mut item = delivery.into_active_model();
+        item.next_attempt_at = Set(None);
+        item.update(&ctx.db).await?;
+        enqueue(ctx, id).await;
+    }
+    Ok(count)
+}
+
+/// 手动重新投递（不受重试次数限制）
+pub async fn redeliver(ctx: &AppContext, delivery: webhook_deliveries::Model) -> Result<()> {
+    let id = delivery.id;
+    let mut item = delivery.into_active_model();
+    item.status = Set(STATUS_PENDING.to_string());
+    item.next_attempt_at = Set(None);
+    item.update(&ctx.db).await?;
+    enqueue(ctx, id).await;
+    Ok(())
+}
@@ -1,4 +1,5 @@
-use crate::models::users;
+use crate::models::audit_logs::{self, AuditEntry};
+use crate::models::{personal_access_tokens, user_sessions, users};
 use loco_rs::prelude::*;
 use sea_orm::ActiveValue;
 
@@ -67,10 +68,33 @@ impl Task for ChangeUserPassword {
             .await
             .map_err(|e| Error::Message(format!("更新密码失败: {}", e)))?;
 
+        // 吊销所有登录会话，已签发的令牌随即失效
+        let revoked = user_sessions::Model::revoke_all_for_user(&app_context.db, updated_user.id)
+            .await
+            .map_err(|e| Error::Message(format!("吊销登录会话失败: {}", e)))?;
+        let revoked_tokens =
+            personal_access_tokens::Model::revoke_all_for_user(&app_context.db, updated_user.id)
+                .await
+                .map_err(|e| Error::Message(format!("吊销访问令牌失败: {}", e)))?;
+
+        audit_logs::record(
+            &app_context.db,
+            AuditEntry::new("user.change_password")
+                .target("user", updated_user.id)
+                .after(serde_json::json!({
+                    "revoked_sessions": revoked,
+                    "revoked_tokens": revoked_tokens,
+                }))
+                .source(audit_logs::SOURCE_TASK),
+        )
+        .await;
+
         println!("✅ 用户密码修改成功!");
         println!("   邮箱: {}", updated_user.email);
         println!("   名称: {}", updated_user.name);
         println!("   用户ID: {}", updated_user.id);
+        println!("   已退出 {} 个登录会话", revoked);
+        println!("   已吊销 {} 个访问令牌", revoked_tokens);
 
         Ok(())
     }
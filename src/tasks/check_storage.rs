@@ -0,0 +1,142 @@
+use crate::models::audit_logs::{self, AuditEntry};
+use crate::services::storage_integrity::{self, CheckOptions, StrayFile};
+use loco_rs::prelude::*;
+
+pub struct CheckStorage;
+
+/// 解析 `true`/`false` 参数，未提供时为 false
+fn flag(vars: &task::Vars, name: &str) -> Result<bool> {
+    match vars.cli_arg(name) {
+        Ok(value) => match value.trim() {
+            "true" | "1" | "yes" => Ok(true),
+            "false" | "0" | "no" => Ok(false),
+            _ => {
+                println!("❌ 无效的参数 {name}: {value}");
+                println!();
+                println!("📖 使用方法:");
+                println!("   cargo loco task check_storage [apply:true] [verify:true]");
+                println!();
+                println!("💡 示例:");
+                println!("   cargo loco task check_storage verify:true");
+                println!("   cargo loco task check_storage apply:true");
+                Err(Error::Message(format!("无效的参数 {name}: {value}")))
+            }
+        },
+        Err(_) => Ok(false),
+    }
+}
+
+fn print_files(title: &str, files: &[StrayFile]) {
+    if files.is_empty() {
+        return;
+    }
+    println!("{title} ({}):", files.len());
+    for file in files {
+        println!("   {} ({} 字节)", file.path, file.size);
+    }
+}
+
+#[async_trait]
+impl Task for CheckStorage {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "check_storage".to_string(),
+            detail: "检查存储文件与数据库是否一致，apply:true 时修复，verify:true 时校验文件摘要"
+                .to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, vars: &task::Vars) -> Result<()> {
+        let options = CheckOptions {
+            apply: flag(vars, "apply")?,
+            verify_checksums: flag(vars, "verify")?,
+        };
+
+        println!(
+            "🔍 开始检查存储（{}）...",
+            if options.apply {
+                "修复模式"
+            } else {
+                "只检查，不修改"
+            }
+        );
+        println!();
+
+        let report = storage_integrity::run(&app_context.db, options).await?;
+
+        if !report.missing_files.is_empty() {
+            println!("❌ 文件丢失的媒体 ({}):", report.missing_files.len());
+            for missing in &report.missing_files {
+                println!(
+                    "   #{} {} -> {} ({} 字节{})",
+                    missing.media_id,
+                    missing.title,
+                    missing.file_path,
+                    missing.file_size.unwrap_or_default(),
+                    if missing.trashed {
+                        "，在回收站中"
+                    } else {
+                        ""
+                    }
+                );
+            }
+        }
+        print_files("🗂️  没有记录的文件", &report.orphan_files);
+        print_files("🧹 残留的上传临时文件", &report.stale_temp_files);
+        print_files("🔳 已删除媒体的二维码", &report.orphan_qrcodes);
+        if !report.missing_qrcodes.is_empty() {
+            println!("⚠️  缺少二维码的媒体: {:?}", report.missing_qrcodes);
+        }
+        for mismatch in &report.checksum_mismatches {
+            println!(
+                "❌ 摘要不一致: {} (登记 {}，实际 {})",
+                mismatch.file_path, mismatch.content_hash, mismatch.actual_hash
+            );
+        }
+
+        if options.apply {
+            let repairs = &report.repairs;
+            audit_logs::record(
+                &app_context.db,
+                AuditEntry::new("storage.repair")
+                    .after(serde_json::json!({
+                        "missing_files": report.missing_files.len(),
+                        "checksum_mismatches": report.checksum_mismatches.len(),
+                        "deleted_files": repairs.deleted_files,
+                        "freed_bytes": repairs.freed_bytes,
+                        "regenerated_qrcodes": repairs.regenerated_qrcodes,
+                        "marked_missing": repairs.marked_missing,
+                        "cleared_missing": repairs.cleared_missing,
+                    }))
+                    .source(audit_logs::SOURCE_TASK),
+            )
+            .await;
+        }
+
+        println!();
+        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        if report.is_clean() {
+            println!("✅ 存储与数据库一致");
+        } else {
+            println!("📈 可释放空间: {} 字节", report.reclaimable_bytes);
+        }
+        if options.apply {
+            let repairs = &report.repairs;
+            println!("🔧 修复结果:");
+            println!(
+                "   删除文件: {} ({} 字节)",
+                repairs.deleted_files, repairs.freed_bytes
+            );
+            println!("   重新生成二维码: {}", repairs.regenerated_qrcodes);
+            println!("   标记文件丢失: {}", repairs.marked_missing);
+            println!("   取消丢失标记: {}", repairs.cleared_missing);
+            for failure in &repairs.failures {
+                println!("   ⚠️  {failure}");
+            }
+        } else if !report.is_clean() {
+            println!("💡 使用 apply:true 执行修复");
+        }
+
+        Ok(())
+    }
+}
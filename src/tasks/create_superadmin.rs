@@ -1,3 +1,4 @@
+use crate::models::audit_logs::{self, AuditEntry};
 use crate::models::users;
 use loco_rs::prelude::*;
 use sea_orm::ActiveValue;
@@ -75,6 +76,15 @@ impl Task for CreateSuperadmin {
         .insert(&app_context.db)
         .await?;
 
+        audit_logs::record(
+            &app_context.db,
+            AuditEntry::new("user.create_superadmin")
+                .target("user", user.id)
+                .after(serde_json::json!({ "email": user.email, "name": user.name }))
+                .source(audit_logs::SOURCE_TASK),
+        )
+        .await;
+
         println!("✅ 超级管理员创建成功!");
         println!("   邮箱: {}", user.email);
         println!("   名称: {}", user.name);
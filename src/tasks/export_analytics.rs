@@ -0,0 +1,82 @@
+use crate::models::users;
+use crate::services::analytics::{AnalyticsExporter, ExportFilter, ExportFormat};
+use futures_util::StreamExt;
+use loco_rs::prelude::*;
+use tokio::io::AsyncWriteExt;
+
+pub struct ExportAnalytics;
+
+#[async_trait]
+impl Task for ExportAnalytics {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "export_analytics".to_string(),
+            detail: "导出媒体播放统计（CSV/JSON），支持按用户、书籍和日期范围过滤".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, vars: &task::Vars) -> Result<()> {
+        let format = ExportFormat::parse(vars.cli_arg("format").map_or("csv", String::as_str))?;
+
+        let user_id = match vars.cli_arg("email") {
+            Ok(email) => match users::Model::find_by_email(&app_context.db, email).await {
+                Ok(user) => Some(user.id),
+                Err(ModelError::EntityNotFound) => {
+                    return Err(Error::Message(format!("用户 {} 不存在", email)));
+                }
+                Err(e) => return Err(Error::Model(e)),
+            },
+            Err(_) => None,
+        };
+
+        let book_id = match vars.cli_arg("book_id") {
+            Ok(book_id) => Some(
+                book_id
+                    .parse::<i32>()
+                    .map_err(|_| Error::Message(format!("无效的书籍ID: {}", book_id)))?,
+            ),
+            Err(_) => None,
+        };
+
+        let filter = ExportFilter {
+            user_id,
+            book_id,
+            ..Default::default()
+        }
+        .with_date_range(
+            vars.cli_arg("from").ok().map(String::as_str),
+            vars.cli_arg("to").ok().map(String::as_str),
+        )?;
+
+        let mut stream =
+            Box::pin(AnalyticsExporter::new(app_context.db.clone(), filter).into_stream(format));
+
+        // 未指定 output 时输出到标准输出，便于重定向或管道处理
+        if let Ok(output) = vars.cli_arg("output") {
+            let mut file = tokio::fs::File::create(output)
+                .await
+                .map_err(|e| Error::Message(format!("创建导出文件失败: {}", e)))?;
+
+            let mut written: u64 = 0;
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk.map_err(|e| Error::Message(format!("导出失败: {}", e)))?;
+                written += chunk.len() as u64;
+                file.write_all(&chunk).await?;
+            }
+            file.flush().await?;
+
+            println!("✅ 统计数据已导出: {} ({} 字节)", output, written);
+        } else {
+            use std::io::Write;
+
+            let mut stdout = std::io::stdout();
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk.map_err(|e| Error::Message(format!("导出失败: {}", e)))?;
+                stdout.write_all(&chunk)?;
+            }
+            stdout.flush()?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,295 @@
+use crate::models::_entities::{media_versions, medias, storage_blobs};
+use crate::models::audit_logs::{self, AuditEntry};
+use crate::services::storage::{self, StorageService};
+use loco_rs::prelude::*;
+use sea_orm::sea_query::Expr;
+use sea_orm::DatabaseConnection;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+pub struct MigrateStorage;
+
+/// 保存文件位置的表
+#[derive(Clone, Copy)]
+enum Table {
+    Medias,
+    MediaVersions,
+    StorageBlobs,
+    Renditions,
+}
+
+impl Table {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Medias => "媒体",
+            Self::MediaVersions => "历史版本",
+            Self::StorageBlobs => "内容文件",
+            Self::Renditions => "播放版本",
+        }
+    }
+}
+
+/// 需要迁移的一条记录
+struct Entry {
+    table: Table,
+    id: i32,
+    file_path: String,
+    content_hash: Option<String>,
+}
+
+async fn rewrite_path(db: &DatabaseConnection, entry: &Entry, key: &str) -> Result<()> {
+    let key = key.to_string();
+    match entry.table {
+        Table::Medias => {
+            medias::Entity::update_many()
+                .col_expr(medias::Column::FilePath, Expr::value(key))
+                .filter(medias::Column::Id.eq(entry.id))
+                .exec(db)
+                .await?;
+        }
+        Table::MediaVersions => {
+            media_versions::Entity::update_many()
+                .col_expr(media_versions::Column::FilePath, Expr::value(key))
+                .filter(media_versions::Column::Id.eq(entry.id))
+                .exec(db)
+                .await?;
+        }
+        Table::StorageBlobs => {
+            storage_blobs::Entity::update_many()
+                .col_expr(storage_blobs::Column::FilePath, Expr::value(key))
+                .filter(storage_blobs::Column::Id.eq(entry.id))
+                .exec(db)
+                .await?;
+        }
+        Table::Renditions => {
+            medias::Entity::update_many()
+                .col_expr(medias::Column::NormalizedFilePath, Expr::value(key))
+                .filter(medias::Column::Id.eq(entry.id))
+                .exec(db)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+fn same_dir(a: &Path, b: &Path) -> bool {
+    let a: PathBuf = a.components().collect();
+    let b: PathBuf = b.components().collect();
+    a == b
+}
+
+#[async_trait]
+impl Task for MigrateStorage {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "migrate_storage".to_string(),
+            detail:
+                "把媒体文件、历史版本、播放版本和二维码复制到新的存储目录并校验，记录改为存储键（可重复执行）"
+                    .to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, vars: &task::Vars) -> Result<()> {
+        let default_path = std::env::var("STORAGE_PATH").unwrap_or_else(|_| "uploads".to_string());
+        let from = vars
+            .cli_arg("from")
+            .map_or(default_path, |v| v.trim().to_string());
+        let to = vars
+            .cli_arg("to")
+            .map_or_else(|_| from.clone(), |v| v.trim().to_string());
+        if from.is_empty() || to.is_empty() {
+            println!("❌ 存储目录不能为空");
+            println!();
+            println!("📖 使用方法:");
+            println!("   cargo loco task migrate_storage [from:<原存储目录>] [to:<新存储目录>]");
+            println!();
+            println!("💡 示例:");
+            println!("   cargo loco task migrate_storage from:uploads to:/data/qcast");
+            println!("   cargo loco task migrate_storage   # 只把记录改为存储键，不复制文件");
+            return Err(Error::Message("存储目录不能为空".to_string()));
+        }
+
+        let source = StorageService::new(&from);
+        let target = StorageService::new(&to);
+        let copy_files = !same_dir(source.base_path(), target.base_path());
+
+        println!("🔄 开始迁移存储...");
+        println!("📍 原存储目录: {from}");
+        if copy_files {
+            println!("📍 新存储目录: {to}");
+        } else {
+            println!("📍 存储目录不变，只把记录改为存储键");
+        }
+        println!();
+
+        let db = &app_context.db;
+        let mut entries = Vec::new();
+        for media in medias::Entity::find().all(db).await? {
+            if let Some(rendition) = media.normalized_file_path {
+                entries.push(Entry {
+                    table: Table::Renditions,
+                    id: media.id,
+                    file_path: rendition,
+                    content_hash: None,
+                });
+            }
+            entries.push(Entry {
+                table: Table::Medias,
+                id: media.id,
+                file_path: media.file_path,
+                content_hash: media.content_hash,
+            });
+        }
+        for version in media_versions::Entity::find().all(db).await? {
+            entries.push(Entry {
+                table: Table::MediaVersions,
+                id: version.id,
+                file_path: version.file_path,
+                content_hash: version.content_hash,
+            });
+        }
+        for blob in storage_blobs::Entity::find().all(db).await? {
+            entries.push(Entry {
+                table: Table::StorageBlobs,
+                id: blob.id,
+                file_path: blob.file_path,
+                content_hash: Some(blob.content_hash),
+            });
+        }
+
+        let total_count = entries.len();
+        println!("📊 找到 {total_count} 条文件记录");
+        println!();
+
+        let mut copied_count = 0;
+        let mut skipped_count = 0;
+        let mut rewritten_count = 0;
+        let mut failed_count = 0;
+        // 多条记录共用同一个文件时只复制一次
+        let mut handled: HashMap<String, std::result::Result<(), String>> = HashMap::new();
+
+        for (index, entry) in entries.iter().enumerate() {
+            let progress = index + 1;
+            print!(
+                "⏳ 处理中 ({progress}/{total_count}) - {} ID: {} ... ",
+                entry.table.label(),
+                entry.id
+            );
+
+            let source_path = source.resolve(&entry.file_path);
+            let key = source.key_for(&source_path);
+            if Path::new(&key).is_absolute() {
+                println!("⚠️  (不在原存储目录中，跳过: {})", entry.file_path);
+                failed_count += 1;
+                continue;
+            }
+
+            let outcome = if let Some(outcome) = handled.get(&key) {
+                outcome.clone()
+            } else {
+                let result = if !copy_files {
+                    Ok(false)
+                } else if tokio::fs::metadata(&source_path).await.is_ok() {
+                    storage::copy_verified(
+                        &source_path,
+                        &target.resolve(&key),
+                        entry.content_hash.as_deref(),
+                    )
+                    .await
+                } else if tokio::fs::metadata(target.resolve(&key)).await.is_ok() {
+                    // 原文件已不在，但之前的执行已经复制过
+                    Ok(false)
+                } else {
+                    Err(Error::Message("原文件不存在".to_string()))
+                };
+                let outcome = match result {
+                    Ok(copied) => {
+                        if copied {
+                            copied_count += 1;
+                        } else {
+                            skipped_count += 1;
+                        }
+                        Ok(())
+                    }
+                    Err(e) => Err(e.to_string()),
+                };
+                handled.insert(key.clone(), outcome.clone());
+                outcome
+            };
+            if let Err(e) = outcome {
+                println!("❌ ({e})");
+                failed_count += 1;
+                continue;
+            }
+
+            // 文件确认在新位置后才改写记录，中断后重新执行会从未完成的记录继续
+            if entry.file_path != key {
+                if let Err(e) = rewrite_path(db, entry, &key).await {
+                    println!("❌ (更新记录失败: {e})");
+                    failed_count += 1;
+                    continue;
+                }
+                rewritten_count += 1;
+            }
+            println!("✅");
+        }
+
+        // 二维码没有数据库记录，按目录复制
+        let mut qrcode_count = 0;
+        if copy_files {
+            let qrcode_dir = source.get_qrcode_path();
+            if let Ok(mut dir) = tokio::fs::read_dir(&qrcode_dir).await {
+                while let Some(item) = dir.next_entry().await? {
+                    let path = item.path();
+                    if !item.file_type().await?.is_file() {
+                        continue;
+                    }
+                    let destination = target.get_qrcode_path().join(item.file_name());
+                    match storage::copy_verified(&path, &destination, None).await {
+                        Ok(_) => qrcode_count += 1,
+                        Err(e) => {
+                            println!("❌ 二维码复制失败 {}: {e}", path.display());
+                            failed_count += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        audit_logs::record(
+            db,
+            AuditEntry::new("storage.migrate")
+                .after(serde_json::json!({
+                    "from": from,
+                    "to": to,
+                    "total": total_count,
+                    "copied": copied_count,
+                    "skipped": skipped_count,
+                    "rewritten": rewritten_count,
+                    "qrcodes": qrcode_count,
+                    "failed": failed_count,
+                }))
+                .source(audit_logs::SOURCE_TASK),
+        )
+        .await;
+
+        println!();
+        println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+        println!("📈 迁移完成统计:");
+        println!("   记录总数: {total_count}");
+        println!("   复制文件: {copied_count}");
+        println!("   已存在（跳过）: {skipped_count}");
+        println!("   改写记录: {rewritten_count}");
+        println!("   二维码: {qrcode_count}");
+        if failed_count > 0 {
+            println!("   失败: {failed_count} ❌");
+            println!();
+            println!("💡 处理失败的记录后可以重新执行，已完成的文件不会重复复制");
+        } else if copy_files {
+            println!();
+            println!("💡 确认无误后把 STORAGE_PATH 设置为 {to} 并重启服务，再删除原存储目录");
+        }
+
+        Ok(())
+    }
+}
@@ -1,5 +1,13 @@
 pub mod change_user_password;
+pub mod check_storage;
 pub mod create_superadmin;
+pub mod export_analytics;
 pub mod list_admins;
+pub mod migrate_storage;
+pub mod prune_media_versions;
+pub mod purge_trash;
 pub mod regenerate_media_urls;
+pub mod retry_webhooks;
+pub mod run_publish_schedule;
 pub mod set_admin_status;
+pub mod unlock_account;
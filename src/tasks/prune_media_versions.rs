@@ -0,0 +1,46 @@
+use crate::models::_entities::medias;
+use crate::services::media_versions;
+use loco_rs::prelude::*;
+
+pub struct PruneMediaVersions;
+
+#[async_trait]
+impl Task for PruneMediaVersions {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "prune_media_versions".to_string(),
+            detail: "按保留数量清理媒体的历史文件版本".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, vars: &task::Vars) -> Result<()> {
+        let keep = match vars.cli_arg("keep") {
+            Ok(keep) => match keep.trim().parse::<usize>() {
+                Ok(keep) if keep > 0 => keep,
+                _ => {
+                    println!("❌ 无效的保留数量: {keep}");
+                    println!();
+                    println!("📖 使用方法:");
+                    println!("   cargo loco task prune_media_versions [keep:<保留版本数>]");
+                    println!();
+                    println!("💡 示例:");
+                    println!("   cargo loco task prune_media_versions keep:3");
+                    return Err(Error::Message(format!("无效的保留数量: {keep}")));
+                }
+            },
+            Err(_) => media_versions::retention_count(),
+        };
+
+        let all_medias = medias::Entity::find().all(&app_context.db).await?;
+        let mut removed = 0;
+        for media in &all_medias {
+            removed += media_versions::prune(&app_context.db, media, keep).await?;
+        }
+
+        println!("✅ 历史版本清理完成（每个媒体保留 {keep} 个版本）");
+        println!("   检查媒体: {}", all_medias.len());
+        println!("   删除版本: {removed}");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+use crate::models::audit_logs::{self, AuditEntry};
+use crate::services::trash;
+use loco_rs::prelude::*;
+
+pub struct PurgeTrash;
+
+#[async_trait]
+impl Task for PurgeTrash {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "purge_trash".to_string(),
+            detail: "永久删除回收站中超过保留天数的书籍、章节和媒体".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, vars: &task::Vars) -> Result<()> {
+        let retention_days = match vars.cli_arg("days") {
+            Ok(days) => match days.trim().parse::<i64>() {
+                Ok(days) if days >= 0 => days,
+                _ => {
+                    println!("❌ 无效的保留天数: {days}");
+                    println!();
+                    println!("📖 使用方法:");
+                    println!("   cargo loco task purge_trash [days:<保留天数>]");
+                    println!();
+                    println!("💡 示例:");
+                    println!("   cargo loco task purge_trash days:7");
+                    return Err(Error::Message(format!("无效的保留天数: {days}")));
+                }
+            },
+            Err(_) => trash::retention_days(),
+        };
+
+        let summary = trash::purge_expired(app_context, retention_days).await?;
+        audit_logs::record(
+            &app_context.db,
+            AuditEntry::new("trash.purge_expired")
+                .after(serde_json::json!({
+                    "retention_days": retention_days,
+                    "books": summary.books,
+                    "chapters": summary.chapters,
+                    "medias": summary.medias,
+                }))
+                .source(audit_logs::SOURCE_TASK),
+        )
+        .await;
+
+        println!("✅ 回收站清理完成（保留 {retention_days} 天）");
+        println!("   书籍: {}", summary.books);
+        println!("   章节: {}", summary.chapters);
+        println!("   媒体: {}", summary.medias);
+
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+use crate::models::audit_logs::{self, AuditEntry};
 use crate::models::{_entities::medias, site_settings};
 use crate::services::qrcode::QRCODE_SERVICE;
 use loco_rs::prelude::*;
@@ -19,8 +20,7 @@ impl Task for RegenerateMediaUrls {
         println!();
 
         // 获取站点设置
-        const DEFAULT_SITE_URL: &str = "http://localhost:5150";
-        let settings = site_settings::Model::get_or_create(&app_context.db, DEFAULT_SITE_URL)
+        let settings = site_settings::Model::current(&app_context.db)
             .await
             .map_err(|e| Error::Message(format!("获取站点设置失败: {}", e)))?;
 
@@ -87,6 +87,19 @@ impl Task for RegenerateMediaUrls {
             }
         }
 
+        audit_logs::record(
+            &app_context.db,
+            AuditEntry::new("media.regenerate_urls")
+                .after(serde_json::json!({
+                    "site_url": settings.site_url,
+                    "total": total_count,
+                    "succeeded": success_count,
+                    "failed": failed_count,
+                }))
+                .source(audit_logs::SOURCE_TASK),
+        )
+        .await;
+
         println!();
         println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
         println!("📈 处理完成统计:");
@@ -0,0 +1,20 @@
+use crate::services::webhooks;
+use loco_rs::prelude::*;
+
+pub struct RetryWebhooks;
+
+#[async_trait]
+impl Task for RetryWebhooks {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "retry_webhooks".to_string(),
+            detail: "重新投递已到重试时间的 Webhook".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, _vars: &task::Vars) -> Result<()> {
+        let count = webhooks::retry_due(app_context).await?;
+        println!("✅ 已重新入队 {count} 条 Webhook 投递");
+        Ok(())
+    }
+}
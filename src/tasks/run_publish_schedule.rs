@@ -0,0 +1,30 @@
+use crate::services::publishing;
+use loco_rs::prelude::*;
+
+pub struct RunPublishSchedule;
+
+#[async_trait]
+impl Task for RunPublishSchedule {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "run_publish_schedule".to_string(),
+            detail: "按定时发布和下架时间切换书籍、媒体的公开状态并通知作者".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, _vars: &task::Vars) -> Result<()> {
+        let summary = publishing::run_due(app_context).await?;
+
+        println!("✅ 定时发布处理完成");
+        println!(
+            "   书籍: 发布 {} / 下架 {}",
+            summary.published_books, summary.unpublished_books
+        );
+        println!(
+            "   媒体: 发布 {} / 下架 {}",
+            summary.published_medias, summary.unpublished_medias
+        );
+
+        Ok(())
+    }
+}
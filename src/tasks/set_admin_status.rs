@@ -1,3 +1,4 @@
+use crate::models::audit_logs::{self, AuditEntry};
 use crate::models::users;
 use loco_rs::prelude::*;
 
@@ -78,6 +79,22 @@ impl Task for SetAdminStatus {
                 .await
                 .map_err(|e| Error::Message(format!("更新管理员状态失败: {}", e)))?;
 
+        audit_logs::record(
+            &app_context.db,
+            AuditEntry::new("user.update_role")
+                .target("user", user.id)
+                .before(serde_json::json!({
+                    "is_staff": user.is_staff,
+                    "is_superuser": user.is_superuser,
+                }))
+                .after(serde_json::json!({
+                    "is_staff": updated_user.is_staff,
+                    "is_superuser": updated_user.is_superuser,
+                }))
+                .source(audit_logs::SOURCE_TASK),
+        )
+        .await;
+
         let staff_status = if updated_user.is_staff { "是" } else { "否" };
         let super_status = if updated_user.is_superuser {
             "是"
@@ -0,0 +1,68 @@
+use crate::models::audit_logs::{self, AuditEntry};
+use crate::models::users;
+use crate::services::rate_limit::RATE_LIMITER;
+use loco_rs::prelude::*;
+
+pub struct UnlockAccount;
+
+#[async_trait]
+impl Task for UnlockAccount {
+    fn task(&self) -> TaskInfo {
+        TaskInfo {
+            name: "unlock_account".to_string(),
+            detail: "解除用户因多次登录失败造成的锁定".to_string(),
+        }
+    }
+
+    async fn run(&self, app_context: &AppContext, vars: &task::Vars) -> Result<()> {
+        let email = match vars.cli_arg("email") {
+            Ok(email) => email,
+            Err(_) => {
+                println!("❌ 缺少必需参数: email");
+                println!();
+                println!("📖 使用方法:");
+                println!("   cargo loco task unlock_account email:<邮箱>");
+                println!();
+                println!("💡 示例:");
+                println!("   cargo loco task unlock_account email:user@example.com");
+                return Err(Error::Message("缺少必需参数: email".to_string()));
+            }
+        };
+
+        let user = match users::Model::find_by_email(&app_context.db, email).await {
+            Ok(user) => user,
+            Err(ModelError::EntityNotFound) => {
+                return Err(Error::Message(format!("用户 {} 不存在", email)));
+            }
+            Err(e) => {
+                return Err(Error::Model(e));
+            }
+        };
+
+        // 内存存储只存在于服务进程中，命令行任务无法修改
+        if RATE_LIMITER.backend() == "memory" {
+            println!("⚠️  当前使用内存限流存储，锁定状态只保存在服务进程中");
+            println!(
+                "   请通过管理接口 POST /api/admin/users/{}/unlock 解锁，",
+                user.id
+            );
+            println!("   或配置 RATE_LIMIT_REDIS_URL（需启用 redis 特性）后再使用本任务");
+            return Ok(());
+        }
+
+        RATE_LIMITER.unlock(&user.pid.to_string()).await?;
+        audit_logs::record(
+            &app_context.db,
+            AuditEntry::new("user.unlock")
+                .target("user", user.id)
+                .source(audit_logs::SOURCE_TASK),
+        )
+        .await;
+
+        println!("✅ 已解除登录锁定!");
+        println!("   邮箱: {}", user.email);
+        println!("   用户ID: {}", user.id);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+use crate::models::audit_logs::Model;
+use serde::{Deserialize, Serialize};
+
+/// 审计日志记录（变更摘要解析为 JSON）
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditLogResponse {
+    pub id: i32,
+    pub actor_id: Option<i32>,
+    pub actor_email: Option<String>,
+    pub action: String,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub ip_address: Option<String>,
+    pub source: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<Model> for AuditLogResponse {
+    fn from(log: Model) -> Self {
+        Self {
+            before: log.before_value(),
+            after: log.after_value(),
+            id: log.id,
+            actor_id: log.actor_id,
+            actor_email: log.actor_email,
+            action: log.action,
+            target_type: log.target_type,
+            target_id: log.target_id,
+            ip_address: log.ip_address,
+            source: log.source,
+            created_at: log.created_at.into(),
+        }
+    }
+}
@@ -1,10 +1,13 @@
 use serde::{Deserialize, Serialize};
 
-use crate::models::_entities::users;
+use crate::models::_entities::{user_sessions, users};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub struct LoginResponse {
     pub token: String,
+    pub refresh_token: String,
+    /// 访问令牌有效期（秒）
+    pub expires_in: u64,
     pub pid: String,
     pub name: String,
     pub is_verified: bool,
@@ -14,9 +17,11 @@ pub struct LoginResponse {
 
 impl LoginResponse {
     #[must_use]
-    pub fn new(user: &users::Model, token: &String) -> Self {
+    pub fn new(user: &users::Model, token: &String, refresh_token: &str, expires_in: u64) -> Self {
         Self {
             token: token.to_string(),
+            refresh_token: refresh_token.to_string(),
+            expires_in,
             pid: user.pid.to_string(),
             name: user.name.clone(),
             is_verified: user.email_verified_at.is_some(),
@@ -47,3 +52,75 @@ impl CurrentResponse {
         }
     }
 }
+
+/// 登录会话（设备）信息
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SessionResponse {
+    pub id: i32,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    /// 是否为发起请求的当前会话
+    pub current: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl SessionResponse {
+    #[must_use]
+    pub fn new(session: user_sessions::Model, current_sid: Option<&str>) -> Self {
+        Self {
+            id: session.id,
+            current: current_sid == Some(session.pid.to_string().as_str()),
+            user_agent: session.user_agent,
+            ip_address: session.ip_address,
+            created_at: session.created_at.into(),
+            last_used_at: session.last_used_at.map(Into::into),
+            expires_at: session.expires_at.into(),
+        }
+    }
+}
+
+/// 已启用两步验证的账号登录时返回，需要再提交验证码完成登录
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MfaChallengeResponse {
+    pub mfa_required: bool,
+    pub mfa_token: String,
+    /// 验证码提交期限（秒）
+    pub expires_in: u64,
+}
+
+/// 单点登录授权地址
+///
+/// 前端跳转到 `authorization_url`，并保存 `state_token`，身份提供方回调后
+/// 连同授权码一起提交
+#[derive(Debug, Deserialize, Serialize)]
+pub struct OidcAuthorizeResponse {
+    pub authorization_url: String,
+    pub state_token: String,
+    /// `state_token` 有效期（秒）
+    pub expires_in: u64,
+}
+
+/// 两步验证状态
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TwoFactorStatusResponse {
+    pub enabled: bool,
+    /// 管理员账号必须启用两步验证
+    pub required: bool,
+    pub recovery_codes_remaining: usize,
+}
+
+/// 开始绑定两步验证时返回的密钥和扫码信息
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TwoFactorSetupResponse {
+    pub secret: String,
+    pub otpauth_url: String,
+    pub qrcode_svg: String,
+}
+
+/// 恢复码（只展示一次）
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
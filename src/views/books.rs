@@ -14,6 +14,8 @@ pub struct BookResponse {
     pub user_id: i32,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    pub publish_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub unpublish_at: Option<chrono::DateTime<chrono::Utc>>,
     pub media_count: Option<i64>,
     pub chapter_count: Option<i64>,
 }
@@ -31,6 +33,8 @@ impl From<Model> for BookResponse {
             user_id: book.user_id,
             created_at: book.created_at.into(),
             updated_at: book.updated_at.into(),
+            publish_at: book.publish_at.map(Into::into),
+            unpublish_at: book.unpublish_at.map(Into::into),
             media_count: None,
             chapter_count: None,
         }
@@ -38,17 +42,82 @@ impl From<Model> for BookResponse {
 }
 
 /// 书籍树形结构响应
+///
+/// `book.media_count` 和 `duration` 只统计书籍自身，`total_*` 包含所有子孙书籍
 #[derive(Debug, Serialize, Deserialize)]
 pub struct BookTreeResponse {
     pub book: BookResponse,
+    /// 媒体总时长（秒）
+    pub duration: i64,
+    pub total_media_count: i64,
+    pub total_duration: i64,
+    /// 书籍自身及所有上级书籍都已公开时为 true
+    pub effective_public: bool,
     pub children: Vec<BookTreeResponse>,
 }
 
 impl From<BookTree> for BookTreeResponse {
     fn from(tree: BookTree) -> Self {
+        let mut book = BookResponse::from(tree.book);
+        book.media_count = Some(tree.media.media_count);
         Self {
-            book: BookResponse::from(tree.book),
+            book,
+            duration: tree.media.duration,
+            total_media_count: tree.total.media_count,
+            total_duration: tree.total.duration,
+            effective_public: tree.effective_public,
             children: tree.children.into_iter().map(Self::from).collect(),
         }
     }
 }
+
+/// 书籍播客订阅源设置响应
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FeedSettingsResponse {
+    pub book_id: i32,
+    pub feed_url: String,
+    pub is_public: bool,
+    pub author: Option<String>,
+    pub owner_email: Option<String>,
+    pub language: Option<String>,
+    pub category: Option<String>,
+    pub explicit: bool,
+    pub copyright: Option<String>,
+}
+
+impl FeedSettingsResponse {
+    #[must_use]
+    pub fn new(book: &Model, feed_url: String) -> Self {
+        Self {
+            book_id: book.id,
+            feed_url,
+            is_public: book.is_public.unwrap_or(false),
+            author: book.feed_author.clone(),
+            owner_email: book.feed_owner_email.clone(),
+            language: book.feed_language.clone(),
+            category: book.feed_category.clone(),
+            explicit: book.feed_explicit.unwrap_or(false),
+            copyright: book.feed_copyright.clone(),
+        }
+    }
+}
+
+/// 书籍音频处理设置响应
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AudioProcessingSettingsResponse {
+    pub book_id: i32,
+    pub normalize_audio: bool,
+    /// 统一到的响度（LUFS）
+    pub target_lufs: f64,
+}
+
+impl AudioProcessingSettingsResponse {
+    #[must_use]
+    pub fn new(book: &Model, target_lufs: f64) -> Self {
+        Self {
+            book_id: book.id,
+            normalize_audio: book.normalize_audio,
+            target_lufs,
+        }
+    }
+}
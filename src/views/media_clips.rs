@@ -0,0 +1,43 @@
+use crate::models::_entities::media_clips::Model;
+use serde::{Deserialize, Serialize};
+
+/// 剪辑任务
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MediaClipResponse {
+    pub id: i32,
+    pub source_media_id: i32,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub title: String,
+    pub book_id: i32,
+    pub chapter_id: Option<i32>,
+    /// `pending`、`succeeded` 或 `failed`
+    pub status: String,
+    /// `copy`（按帧复制，无损）或 `reencode`（重新编码）
+    pub method: Option<String>,
+    /// 生成的新媒体
+    pub media_id: Option<i32>,
+    pub error: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<Model> for MediaClipResponse {
+    fn from(clip: Model) -> Self {
+        Self {
+            id: clip.id,
+            source_media_id: clip.source_media_id,
+            start_ms: clip.start_ms,
+            end_ms: clip.end_ms,
+            title: clip.title,
+            book_id: clip.book_id,
+            chapter_id: clip.chapter_id,
+            status: clip.status,
+            method: clip.method,
+            media_id: clip.media_id,
+            error: clip.error,
+            created_at: clip.created_at.into(),
+            updated_at: clip.updated_at.into(),
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use crate::models::_entities::media_placements::Model;
+use serde::{Deserialize, Serialize};
+
+/// 媒体的附加位置
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MediaPlacementResponse {
+    pub id: i32,
+    pub media_id: i32,
+    pub book_id: i32,
+    pub chapter_id: Option<i32>,
+    pub sort_order: Option<i32>,
+    /// 在该位置显示的标题，为空时使用媒体标题
+    pub title: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<Model> for MediaPlacementResponse {
+    fn from(placement: Model) -> Self {
+        Self {
+            id: placement.id,
+            media_id: placement.media_id,
+            book_id: placement.book_id,
+            chapter_id: placement.chapter_id,
+            sort_order: placement.sort_order,
+            title: placement.title,
+            created_at: placement.created_at.into(),
+            updated_at: placement.updated_at.into(),
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use crate::models::_entities::media_versions::Model;
+use serde::{Deserialize, Serialize};
+
+/// 媒体文件版本
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MediaVersionResponse {
+    pub id: i32,
+    pub media_id: i32,
+    pub version: i32,
+    pub file_type: String,
+    pub file_size: Option<i64>,
+    pub duration: Option<i32>,
+    pub mime_type: Option<String>,
+    pub original_filename: Option<String>,
+    pub uploaded_by: Option<i32>,
+    pub uploaded_at: chrono::DateTime<chrono::Utc>,
+    /// 是否为媒体当前使用的文件
+    pub is_current: bool,
+}
+
+impl MediaVersionResponse {
+    #[must_use]
+    pub fn new(version: Model, current_version: i32) -> Self {
+        Self {
+            id: version.id,
+            media_id: version.media_id,
+            version: version.version,
+            file_type: version.file_type,
+            file_size: version.file_size,
+            duration: version.duration,
+            mime_type: version.mime_type,
+            original_filename: version.original_filename,
+            uploaded_by: version.uploaded_by,
+            uploaded_at: version.created_at.into(),
+            is_current: version.version == current_version,
+        }
+    }
+}
@@ -1,4 +1,7 @@
+use crate::models::_entities::media_placements;
+use crate::models::_entities::media_subtitles;
 use crate::models::_entities::medias::Model;
+use crate::views::subtitles::SubtitleTrackResponse;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -23,6 +26,33 @@ pub struct MediaResponse {
     pub user_id: i32,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    pub publish_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub unpublish_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// 在所在章节（或书籍顶层）中的位置
+    pub sort_order: Option<i32>,
+    /// 通过附加位置出现在列表中时的位置 ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub placement_id: Option<i32>,
+    /// 存储检查发现文件丢失的时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_missing_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// 响度测量结果（书籍开启音频处理后生成）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub audio_analysis: Option<serde_json::Value>,
+}
+
+impl MediaResponse {
+    /// 附加位置中的媒体，位置设置了标题时使用位置的标题
+    #[must_use]
+    pub fn placed(media: Model, placement: media_placements::Model) -> Self {
+        let mut response = Self::from(media);
+        if let Some(title) = placement.title {
+            response.title = title;
+        }
+        response.sort_order = placement.sort_order;
+        response.placement_id = Some(placement.id);
+        response
+    }
 }
 
 impl From<Model> for MediaResponse {
@@ -48,6 +78,15 @@ impl From<Model> for MediaResponse {
             user_id: media.user_id,
             created_at: media.created_at.into(),
             updated_at: media.updated_at.into(),
+            publish_at: media.publish_at.map(Into::into),
+            unpublish_at: media.unpublish_at.map(Into::into),
+            sort_order: media.sort_order,
+            placement_id: None,
+            file_missing_at: media.file_missing_at.map(Into::into),
+            audio_analysis: media
+                .audio_analysis
+                .as_deref()
+                .and_then(|json| serde_json::from_str(json).ok()),
         }
     }
 }
@@ -64,6 +103,24 @@ pub struct PublicMediaResponse {
     pub original_filename: Option<String>,
     pub play_count: i32,
     pub created_at: chrono::DateTime<chrono::Utc>,
+    /// 可用的字幕和文稿
+    #[serde(default)]
+    pub tracks: Vec<SubtitleTrackResponse>,
+}
+
+impl PublicMediaResponse {
+    #[must_use]
+    pub fn with_tracks(
+        mut self,
+        access_token: &str,
+        subtitles: Vec<media_subtitles::Model>,
+    ) -> Self {
+        self.tracks = subtitles
+            .into_iter()
+            .map(|subtitle| SubtitleTrackResponse::new(subtitle, access_token))
+            .collect();
+        self
+    }
 }
 
 impl From<Model> for PublicMediaResponse {
@@ -78,6 +135,7 @@ impl From<Model> for PublicMediaResponse {
             original_filename: media.original_filename,
             play_count: media.play_count,
             created_at: media.created_at.into(),
+            tracks: Vec::new(),
         }
     }
 }
@@ -1,4 +1,11 @@
+pub mod audit_logs;
 pub mod auth;
 pub mod books;
 pub mod chapters;
+pub mod media_clips;
+pub mod media_placements;
+pub mod media_versions;
 pub mod medias;
+pub mod subtitles;
+pub mod tokens;
+pub mod webhooks;
@@ -0,0 +1,59 @@
+use crate::models::_entities::media_subtitles::Model;
+use serde::{Deserialize, Serialize};
+
+/// 字幕详情（作者管理用）
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubtitleResponse {
+    pub id: i32,
+    pub media_id: i32,
+    pub language: String,
+    pub label: Option<String>,
+    pub kind: String,
+    pub source_format: String,
+    pub cue_count: i32,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<Model> for SubtitleResponse {
+    fn from(subtitle: Model) -> Self {
+        Self {
+            id: subtitle.id,
+            media_id: subtitle.media_id,
+            language: subtitle.language,
+            label: subtitle.label,
+            kind: subtitle.kind,
+            source_format: subtitle.source_format,
+            cue_count: subtitle.cue_count,
+            created_at: subtitle.created_at.into(),
+            updated_at: subtitle.updated_at.into(),
+        }
+    }
+}
+
+/// 公开媒体可用的字幕轨道
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SubtitleTrackResponse {
+    pub id: i32,
+    pub language: String,
+    pub label: Option<String>,
+    pub kind: String,
+    /// WebVTT 地址，可直接用于 `<track src>`
+    pub url: String,
+}
+
+impl SubtitleTrackResponse {
+    #[must_use]
+    pub fn new(subtitle: Model, access_token: &str) -> Self {
+        Self {
+            url: format!(
+                "/api/public/media/{}/subtitles/{}",
+                access_token, subtitle.id
+            ),
+            id: subtitle.id,
+            language: subtitle.language,
+            label: subtitle.label,
+            kind: subtitle.kind,
+        }
+    }
+}
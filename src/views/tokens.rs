@@ -0,0 +1,43 @@
+use crate::models::_entities::personal_access_tokens::Model;
+use serde::{Deserialize, Serialize};
+
+/// 个人访问令牌信息（不含明文）
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenResponse {
+    pub id: i32,
+    pub name: String,
+    pub token_prefix: String,
+    pub scopes: Vec<String>,
+    pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub revoked_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<Model> for TokenResponse {
+    fn from(token: Model) -> Self {
+        Self {
+            id: token.id,
+            name: token.name,
+            token_prefix: token.token_prefix,
+            scopes: token
+                .scopes
+                .split(',')
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect(),
+            last_used_at: token.last_used_at.map(Into::into),
+            expires_at: token.expires_at.map(Into::into),
+            revoked_at: token.revoked_at.map(Into::into),
+            created_at: token.created_at.into(),
+        }
+    }
+}
+
+/// 新建令牌的响应，明文只在此时返回一次
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatedTokenResponse {
+    #[serde(flatten)]
+    pub info: TokenResponse,
+    pub token: String,
+}
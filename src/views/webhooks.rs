@@ -0,0 +1,74 @@
+use crate::models::_entities::{webhook_deliveries, webhook_endpoints};
+use serde::{Deserialize, Serialize};
+
+/// Webhook 地址信息（不含签名密钥）
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhookEndpointResponse {
+    pub id: i32,
+    pub url: String,
+    pub events: Vec<String>,
+    pub description: Option<String>,
+    pub is_active: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<webhook_endpoints::Model> for WebhookEndpointResponse {
+    fn from(endpoint: webhook_endpoints::Model) -> Self {
+        Self {
+            id: endpoint.id,
+            events: endpoint.event_list(),
+            url: endpoint.url,
+            description: endpoint.description,
+            is_active: endpoint.is_active,
+            created_at: endpoint.created_at.into(),
+            updated_at: endpoint.updated_at.into(),
+        }
+    }
+}
+
+/// 新建地址的响应，签名密钥只在此时返回一次
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatedWebhookEndpointResponse {
+    #[serde(flatten)]
+    pub info: WebhookEndpointResponse,
+    pub secret: String,
+}
+
+/// 投递记录
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhookDeliveryResponse {
+    pub id: i32,
+    pub endpoint_id: i32,
+    pub event: String,
+    pub payload: serde_json::Value,
+    pub status: String,
+    pub attempts: i32,
+    pub response_status: Option<i32>,
+    /// 从发出请求到收到响应头的耗时（毫秒）
+    pub latency_ms: Option<i32>,
+    pub error: Option<String>,
+    pub next_attempt_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub delivered_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl From<webhook_deliveries::Model> for WebhookDeliveryResponse {
+    fn from(delivery: webhook_deliveries::Model) -> Self {
+        Self {
+            id: delivery.id,
+            endpoint_id: delivery.endpoint_id,
+            event: delivery.event,
+            payload: serde_json::from_str(&delivery.payload)
+                .unwrap_or(serde_json::Value::String(delivery.payload)),
+            status: delivery.status,
+            attempts: delivery.attempts,
+            response_status: delivery.response_status,
+            latency_ms: delivery.latency_ms,
+            error: delivery.error,
+            next_attempt_at: delivery.next_attempt_at.map(Into::into),
+            delivered_at: delivery.delivered_at.map(Into::into),
+            created_at: delivery.created_at.into(),
+        }
+    }
+}
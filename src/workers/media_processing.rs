@@ -0,0 +1,41 @@
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::services::{audio_clipping, audio_processing};
+
+pub struct MediaProcessingWorker {
+    pub ctx: AppContext,
+}
+
+/// 媒体处理任务的类型
+#[derive(Deserialize, Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessingJob {
+    /// 测量响度并生成播放版本
+    Normalize,
+    /// 从媒体的时间范围剪辑出新媒体
+    Clip { clip_id: i32 },
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+pub struct MediaProcessingArgs {
+    pub media_id: i32,
+    pub job: ProcessingJob,
+}
+
+#[async_trait]
+impl BackgroundWorker<MediaProcessingArgs> for MediaProcessingWorker {
+    fn build(ctx: &AppContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+    async fn perform(&self, args: MediaProcessingArgs) -> Result<()> {
+        match args.job {
+            ProcessingJob::Normalize => {
+                audio_processing::normalize_media(&self.ctx.db, args.media_id).await
+            }
+            ProcessingJob::Clip { clip_id } => {
+                audio_clipping::process_clip(&self.ctx, clip_id).await
+            }
+        }
+    }
+}
@@ -1 +1,3 @@
 pub mod downloader;
+pub mod media_processing;
+pub mod webhook_delivery;
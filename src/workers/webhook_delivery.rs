@@ -0,0 +1,23 @@
+use loco_rs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::services::webhooks;
+
+pub struct WebhookDeliveryWorker {
+    pub ctx: AppContext,
+}
+
+#[derive(Deserialize, Debug, Serialize)]
+pub struct WebhookDeliveryArgs {
+    pub delivery_id: i32,
+}
+
+#[async_trait]
+impl BackgroundWorker<WebhookDeliveryArgs> for WebhookDeliveryWorker {
+    fn build(ctx: &AppContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+    async fn perform(&self, args: WebhookDeliveryArgs) -> Result<()> {
+        webhooks::deliver(&self.ctx, args.delivery_id).await
+    }
+}
@@ -2,8 +2,11 @@ use insta::{assert_debug_snapshot, with_settings};
 use loco_rs::testing::prelude::*;
 use qcast::app::App;
 use qcast::models::_entities::chapters::ActiveModel;
+use qcast::models::_entities::medias;
 use qcast::models::chapters;
-use sea_orm::{ActiveModelTrait, Set};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter, QueryOrder, Set,
+};
 use serial_test::serial;
 
 macro_rules! configure_insta {
@@ -101,6 +104,231 @@ async fn can_search_chapters_with_parents() {
     });
 }
 
+#[tokio::test]
+#[serial]
+async fn can_build_trees_in_memory() {
+    let boot = boot_test::<App>().await.unwrap();
+    seed::<App>(&boot.app_context).await.unwrap();
+    let db = &boot.app_context.db;
+
+    let book = qcast::models::_entities::books::ActiveModel {
+        title: Set("树形测试".to_string()),
+        user_id: Set(1),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    let chapter = |title: &str, parent_id: Option<i32>, sort_order: i32| ActiveModel {
+        title: Set(title.to_string()),
+        book_id: Set(book.id),
+        parent_id: Set(parent_id),
+        sort_order: Set(Some(sort_order)),
+        ..Default::default()
+    };
+    let root = chapter("根章节", None, 1).insert(db).await.unwrap();
+    let second = chapter("第二节", Some(root.id), 2)
+        .insert(db)
+        .await
+        .unwrap();
+    let first = chapter("第一节", Some(root.id), 1)
+        .insert(db)
+        .await
+        .unwrap();
+    let leaf = chapter("小节", Some(first.id), 1).insert(db).await.unwrap();
+    let mut trashed = chapter("已删除", Some(root.id), 3)
+        .insert(db)
+        .await
+        .unwrap()
+        .into_active_model();
+    trashed.deleted_at = Set(Some(chrono::Utc::now().into()));
+    trashed.update(db).await.unwrap();
+
+    for (chapter_id, deleted) in [
+        (first.id, false),
+        (first.id, false),
+        (leaf.id, false),
+        (leaf.id, true),
+    ] {
+        let mut media = medias::ActiveModel::create_new(
+            "媒体".to_string(),
+            None,
+            "audio".to_string(),
+            "/test/path.mp3".to_string(),
+            None,
+            None,
+            None,
+            None,
+            book.id,
+            Some(chapter_id),
+            1,
+        );
+        if deleted {
+            media.deleted_at = Set(Some(chrono::Utc::now().into()));
+        }
+        media.insert(db).await.unwrap();
+    }
+
+    // 同级按 sort_order 排序，已删除的章节和媒体不计入
+    let tree = chapters::Model::get_book_tree(db, book.id).await.unwrap();
+    assert_eq!(tree.len(), 1);
+    let children: Vec<i32> = tree[0].children.iter().map(|c| c.id).collect();
+    assert_eq!(children, vec![first.id, second.id]);
+    assert_eq!(tree[0].children[0].media_count, 2);
+    assert_eq!(tree[0].children[0].children[0].id, leaf.id);
+    assert_eq!(tree[0].children[0].children[0].media_count, 1);
+
+    let subtree = chapters::Model::get_tree(db, first.id).await.unwrap();
+    assert_eq!(subtree.id, first.id);
+    assert_eq!(subtree.children.len(), 1);
+    assert_eq!(subtree.children[0].id, leaf.id);
+
+    let counted = chapters::Model::find_by_book_with_media_count(db, book.id)
+        .await
+        .unwrap();
+    assert_eq!(counted.len(), 4);
+
+    assert!(chapters::Model::would_create_cycle(db, root.id, leaf.id)
+        .await
+        .unwrap());
+    assert!(chapters::Model::would_create_cycle(db, first.id, first.id)
+        .await
+        .unwrap());
+    assert!(!chapters::Model::would_create_cycle(db, leaf.id, second.id)
+        .await
+        .unwrap());
+}
+
+/// 5,000 个章节的书籍加载耗时，运行：
+/// `cargo test --test mod chapter_tree_benchmark -- --ignored --nocapture`
+#[tokio::test]
+#[serial]
+#[ignore = "benchmark"]
+async fn chapter_tree_benchmark() {
+    const ROOTS: i32 = 100;
+    const CHILDREN: i32 = 7;
+    const GRANDCHILDREN: i32 = 6;
+
+    let boot = boot_test::<App>().await.unwrap();
+    seed::<App>(&boot.app_context).await.unwrap();
+    let db = &boot.app_context.db;
+
+    let book = qcast::models::_entities::books::ActiveModel {
+        title: Set("大型书籍".to_string()),
+        user_id: Set(1),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    // 逐层批量插入，每层插入后读回 id 作为下一层的父章节
+    let mut parents: Vec<Option<i32>> = vec![None];
+    for (level, per_parent) in [ROOTS, CHILDREN, GRANDCHILDREN].into_iter().enumerate() {
+        let rows: Vec<ActiveModel> = parents
+            .iter()
+            .flat_map(|parent_id| {
+                (1..=per_parent).map(move |sort_order| ActiveModel {
+                    title: Set(format!("章节 {sort_order}")),
+                    book_id: Set(book.id),
+                    parent_id: Set(*parent_id),
+                    level: Set(Some(i32::try_from(level).unwrap())),
+                    sort_order: Set(Some(sort_order)),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        for chunk in rows.chunks(500) {
+            chapters::Entity::insert_many(chunk.to_vec())
+                .exec(db)
+                .await
+                .unwrap();
+        }
+        parents = chapters::Entity::find()
+            .filter(chapters::Column::BookId.eq(book.id))
+            .filter(chapters::Column::Level.eq(i32::try_from(level).unwrap()))
+            .order_by_asc(chapters::Column::Id)
+            .all(db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|c| Some(c.id))
+            .collect();
+    }
+
+    let media_rows: Vec<medias::ActiveModel> = parents
+        .iter()
+        .map(|chapter_id| {
+            medias::ActiveModel::create_new(
+                "媒体".to_string(),
+                None,
+                "audio".to_string(),
+                "/test/path.mp3".to_string(),
+                None,
+                None,
+                None,
+                None,
+                book.id,
+                *chapter_id,
+                1,
+            )
+        })
+        .collect();
+    for chunk in media_rows.chunks(200) {
+        medias::Entity::insert_many(chunk.to_vec())
+            .exec(db)
+            .await
+            .unwrap();
+    }
+
+    let started = std::time::Instant::now();
+    let tree = chapters::Model::get_book_tree(db, book.id).await.unwrap();
+    println!("get_book_tree: {:?}", started.elapsed());
+    assert_eq!(tree.len(), usize::try_from(ROOTS).unwrap());
+    let total: usize = tree
+        .iter()
+        .map(|root| {
+            1 + root
+                .children
+                .iter()
+                .map(|c| 1 + c.children.len())
+                .sum::<usize>()
+        })
+        .sum();
+    assert_eq!(total, 5000);
+    assert!(tree
+        .iter()
+        .all(|root| root.children[0].children[0].media_count == 1));
+
+    let started = std::time::Instant::now();
+    let flat = chapters::Model::get_flat_list_with_level(db, book.id)
+        .await
+        .unwrap();
+    println!("get_flat_list_with_level: {:?}", started.elapsed());
+    assert_eq!(flat.len(), 5000);
+
+    let started = std::time::Instant::now();
+    let counted = chapters::Model::find_by_book_with_media_count(db, book.id)
+        .await
+        .unwrap();
+    println!("find_by_book_with_media_count: {:?}", started.elapsed());
+    assert_eq!(counted.len(), 5000);
+
+    let started = std::time::Instant::now();
+    let subtree = chapters::Model::get_tree(db, tree[0].id).await.unwrap();
+    println!("get_tree: {:?}", started.elapsed());
+    assert_eq!(subtree.children.len(), usize::try_from(CHILDREN).unwrap());
+
+    let leaf_id = parents.last().copied().flatten().unwrap();
+    let started = std::time::Instant::now();
+    let cycle = chapters::Model::would_create_cycle(db, tree.last().unwrap().id, leaf_id)
+        .await
+        .unwrap();
+    println!("would_create_cycle: {:?}", started.elapsed());
+    assert!(cycle);
+}
+
 fn cleanup_chapter_model() -> Vec<(&'static str, &'static str)> {
     vec![
         (r"id: \d+,", "id: ID"),
@@ -0,0 +1,131 @@
+use loco_rs::testing::prelude::*;
+use qcast::app::App;
+use qcast::models::_entities::medias;
+use sea_orm::EntityTrait;
+use serde_json::json;
+use serial_test::serial;
+use std::time::{Duration, SystemTime};
+
+use super::prepare_data::{
+    auth_header, create_public_media, init_staff_login, init_superadmin_login, init_user_login,
+};
+
+/// 写入一个修改时间在两天前的文件，避免被当作正在进行的上传
+fn write_old_file(path: &str, content: &str) {
+    let path = std::path::Path::new(path);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+    let file = std::fs::File::options().write(true).open(path).unwrap();
+    file.set_modified(SystemTime::now() - Duration::from_secs(2 * 24 * 60 * 60))
+        .unwrap();
+}
+
+fn reported_paths(report: &serde_json::Value, key: &str) -> Vec<String> {
+    report[key]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|f| f["path"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+#[serial]
+async fn admin_can_check_and_repair_storage() {
+    request::<App, _, _>(|request, ctx| async move {
+        let admin = init_superadmin_login(&request, &ctx).await;
+        let auth = auth_header(&admin.token);
+
+        let response = request
+            .post("/api/books")
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({ "title": "存储检查" }))
+            .await;
+        let book_id = response.json::<serde_json::Value>()["id"].as_i64().unwrap();
+        // 测试媒体的文件路径不存在，也还没有二维码
+        let media =
+            create_public_media(&ctx, i32::try_from(book_id).unwrap(), None, admin.user.id).await;
+        let _ = std::fs::remove_file(format!("uploads/qrcodes/{}.svg", media.id));
+
+        let orphan = format!(
+            "uploads/users/999999/books/1/media/orphan-{}.mp3",
+            uuid::Uuid::new_v4()
+        );
+        write_old_file(&orphan, "orphan audio");
+        let orphan_qrcode = "uploads/qrcodes/999999999.svg";
+        write_old_file(orphan_qrcode, "<svg/>");
+
+        // 默认只报告，不修改
+        let response = request
+            .post("/api/admin/storage/check")
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({}))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let report = response.json::<serde_json::Value>();
+        assert_eq!(report["applied"], false);
+        let missing = report["missing_files"].as_array().unwrap();
+        assert!(missing.iter().any(|m| m["media_id"] == media.id));
+        assert!(reported_paths(&report, "orphan_files").contains(&orphan));
+        assert!(reported_paths(&report, "orphan_qrcodes").contains(&orphan_qrcode.to_string()));
+        assert!(report["missing_qrcodes"]
+            .as_array()
+            .unwrap()
+            .contains(&json!(media.id)));
+        assert!(std::path::Path::new(&orphan).exists());
+
+        // 执行修复
+        let response = request
+            .post("/api/admin/storage/check")
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({ "apply": true, "verify_checksums": true }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let report = response.json::<serde_json::Value>();
+        assert_eq!(report["applied"], true);
+        assert!(!std::path::Path::new(&orphan).exists());
+        assert!(!std::path::Path::new(orphan_qrcode).exists());
+        assert!(std::path::Path::new(&format!("uploads/qrcodes/{}.svg", media.id)).exists());
+
+        let media = medias::Entity::find_by_id(media.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(media.file_missing_at.is_some());
+        assert!(media.qr_code_path.is_some());
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn only_super_admin_can_repair_storage() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let auth = auth_header(&user.token);
+        let response = request
+            .post("/api/admin/storage/check")
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({}))
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        let staff = init_staff_login(&request, &ctx).await;
+        let auth = auth_header(&staff.token);
+        let response = request
+            .post("/api/admin/storage/check")
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({}))
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let response = request
+            .post("/api/admin/storage/check")
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({ "apply": true }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+    })
+    .await;
+}
@@ -0,0 +1,193 @@
+use axum::http::{HeaderName, HeaderValue};
+use loco_rs::testing::prelude::*;
+use qcast::app::App;
+use qcast::models::_entities::medias;
+use qcast::services::storage::STORAGE_SERVICE;
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, EntityTrait};
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data::{
+    auth_header, init_user_login, multipart_content_type, quiet_wav, upload_form,
+};
+
+/// 上传表单中的标题和书籍字段
+fn lesson_fields(book_id: i64) -> [(&'static str, String); 2] {
+    [
+        ("title", "响度测试".to_string()),
+        ("book_id", book_id.to_string()),
+    ]
+}
+
+#[tokio::test]
+#[serial]
+async fn normalizes_audio_when_enabled_for_book() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&user.token);
+
+        let response = request
+            .post("/api/books")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "title": "响度统一" }))
+            .await;
+        let book_id = response.json::<serde_json::Value>()["id"].as_i64().unwrap();
+
+        let response = request
+            .get(&format!("/api/books/{book_id}/audio-processing"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert_eq!(
+            response.json::<serde_json::Value>()["normalize_audio"],
+            false
+        );
+
+        let response = request
+            .put(&format!("/api/books/{book_id}/audio-processing"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "normalize_audio": true }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let settings = response.json::<serde_json::Value>();
+        assert_eq!(settings["normalize_audio"], true);
+        assert_eq!(settings["target_lufs"], -16.0);
+
+        let original = quiet_wav();
+        let response = request
+            .post("/api/media/upload")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .content_type(&multipart_content_type())
+            .bytes(
+                upload_form(
+                    &lesson_fields(book_id),
+                    "lesson.wav",
+                    "audio/wav",
+                    &original,
+                )
+                .into(),
+            )
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let media_id = response.json::<serde_json::Value>()["id"].as_i64().unwrap();
+
+        // 测试环境中处理任务同步执行
+        let response = request
+            .get(&format!("/api/media/{media_id}"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let analysis = response.json::<serde_json::Value>()["audio_analysis"].clone();
+        assert!(analysis["gain_db"].as_f64().unwrap() > 0.0);
+        assert!(analysis["trimmed_start_ms"].as_u64().unwrap() >= 350);
+        assert!(analysis["trimmed_end_ms"].as_u64().unwrap() >= 350);
+
+        let media = medias::Entity::find_by_id(i32::try_from(media_id).unwrap())
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        let rendition = STORAGE_SERVICE.resolve(media.normalized_file_path.as_deref().unwrap());
+        assert!(rendition.exists());
+        assert_ne!(
+            media.normalized_file_path.as_deref(),
+            Some(media.file_path.as_str())
+        );
+
+        // 播放处理后的版本：去掉了静音，比原文件短
+        let response = request.get(&format!("/api/media/{media_id}/stream")).await;
+        assert_eq!(response.status_code(), 200);
+        assert_eq!(response.headers().get("content-type").unwrap(), "audio/mp4");
+        assert!(response.as_bytes().len() < original.len());
+
+        // 订阅源中使用处理后版本的时长
+        request
+            .put(&format!("/api/books/{book_id}"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "is_public": true }))
+            .await;
+        let access_token = media.access_token.clone();
+        let mut active_media: medias::ActiveModel = media.into();
+        active_media.is_public = Set(true);
+        active_media.update(&ctx.db).await.unwrap();
+        let response = request
+            .get(&format!("/api/public/books/{book_id}/feed.xml"))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let xml = response.text();
+        assert!(xml.contains("<itunes:duration>00:00:01</itunes:duration>"));
+        assert!(xml.contains("type=\"audio/mp4\""));
+
+        // 落地页和嵌入播放器声明的也是播放版本的类型（Tera 会转义 `/`，只检查子类型）
+        let response = request
+            .get(&format!("/public/{access_token}"))
+            .add_header(
+                HeaderName::from_static("accept"),
+                HeaderValue::from_static("text/html"),
+            )
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert!(response.text().contains("mp4\""));
+        assert!(!response.text().contains("wav\""));
+
+        let response = request.get(&format!("/embed/media/{access_token}")).await;
+        assert_eq!(response.status_code(), 200);
+        assert!(response.text().contains("mp4\""));
+        assert!(!response.text().contains("wav\""));
+
+        // 关闭后播放原文件
+        request
+            .put(&format!("/api/books/{book_id}/audio-processing"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "normalize_audio": false }))
+            .await;
+        let response = request.get(&format!("/api/media/{media_id}/stream")).await;
+        assert_eq!(response.status_code(), 200);
+        assert_eq!(response.as_bytes().len(), original.len());
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn audio_is_not_processed_when_disabled() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&user.token);
+
+        let response = request
+            .post("/api/books")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "title": "原样播放" }))
+            .await;
+        let book_id = response.json::<serde_json::Value>()["id"].as_i64().unwrap();
+
+        let response = request
+            .post("/api/media/upload")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .content_type(&multipart_content_type())
+            .bytes(
+                upload_form(
+                    &lesson_fields(book_id),
+                    "lesson.wav",
+                    "audio/wav",
+                    &quiet_wav(),
+                )
+                .into(),
+            )
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let media = response.json::<serde_json::Value>();
+        assert!(media.get("audio_analysis").is_none());
+
+        let media =
+            medias::Entity::find_by_id(i32::try_from(media["id"].as_i64().unwrap()).unwrap())
+                .one(&ctx.db)
+                .await
+                .unwrap()
+                .unwrap();
+        assert!(media.normalized_file_path.is_none());
+        assert!(media.audio_analysis.is_none());
+    })
+    .await;
+}
@@ -0,0 +1,137 @@
+use loco_rs::testing::prelude::*;
+use qcast::app::App;
+use qcast::models::audit_logs::{self, AuditEntry};
+use sea_orm::{ActiveModelTrait, IntoActiveModel, Set};
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data::{auth_header, init_superadmin_login, init_user_login};
+
+#[tokio::test]
+#[serial]
+async fn content_changes_are_recorded() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&user.token);
+
+        // 未配置可信代理时忽略客户端传来的转发头
+        std::env::remove_var("TRUSTED_PROXIES");
+        let response = request
+            .post("/api/books")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .add_header("x-forwarded-for", "203.0.113.66")
+            .json(&json!({ "title": "审计测试" }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let book_id = response.json::<serde_json::Value>()["id"].as_i64().unwrap();
+
+        std::env::set_var("TRUSTED_PROXIES", "127.0.0.1");
+        let response = request
+            .delete(&format!("/api/books/{book_id}"))
+            .add_header(auth_key, auth_value)
+            .add_header("x-forwarded-for", "203.0.113.7")
+            .await;
+        std::env::remove_var("TRUSTED_PROXIES");
+        assert_eq!(response.status_code(), 200);
+
+        let admin = init_superadmin_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&admin.token);
+        let response = request
+            .get(&format!(
+                "/api/admin/audit-logs?target_type=book&target_id={book_id}"
+            ))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let body = response.json::<serde_json::Value>();
+        let logs = body["logs"].as_array().unwrap();
+        assert_eq!(logs.len(), 2);
+        // 按时间倒序，最新的在前
+        assert_eq!(logs[0]["action"], "book.delete");
+        assert_eq!(logs[0]["before"]["title"], "审计测试");
+        assert!(logs[0]["after"].is_null());
+        assert_eq!(logs[0]["actor_email"], "test@loco.com");
+        assert_eq!(logs[0]["ip_address"], "203.0.113.7");
+        assert_eq!(logs[0]["source"], "api");
+        assert_eq!(logs[1]["action"], "book.create");
+        assert_eq!(logs[1]["ip_address"], "127.0.0.1");
+        assert_eq!(body["pagination"]["total"], 2);
+
+        // 审计记录不允许修改
+        let entry = audit_logs::Model::create(&ctx.db, AuditEntry::new("test.append_only"))
+            .await
+            .unwrap();
+        let mut entry = entry.into_active_model();
+        entry.action = Set("test.tampered".to_string());
+        assert!(entry.update(&ctx.db).await.is_err());
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn admin_can_filter_and_paginate() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let admin = init_superadmin_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&admin.token);
+
+        for is_staff in [true, false] {
+            let response = request
+                .put(&format!("/api/admin/users/{}/role", user.user.id))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&json!({ "is_staff": is_staff, "is_superuser": false }))
+                .await;
+            assert_eq!(response.status_code(), 200);
+        }
+
+        let response = request
+            .get(&format!(
+                "/api/admin/audit-logs?action=user.&actor_id={}&per_page=1",
+                admin.user.id
+            ))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let body = response.json::<serde_json::Value>();
+        let logs = body["logs"].as_array().unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0]["action"], "user.update_role");
+        assert_eq!(logs[0]["before"]["is_staff"], true);
+        assert_eq!(logs[0]["after"]["is_staff"], false);
+        assert_eq!(body["pagination"]["total"], 2);
+        assert_eq!(body["pagination"]["total_pages"], 2);
+
+        let response = request
+            .get(&format!(
+                "/api/admin/audit-logs?action=user.update_role&actor_id={}&page=2&per_page=1",
+                admin.user.id
+            ))
+            .add_header(auth_key, auth_value)
+            .await;
+        let body = response.json::<serde_json::Value>();
+        let logs = body["logs"].as_array().unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0]["before"]["is_staff"], false);
+        assert_eq!(logs[0]["after"]["is_staff"], true);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn non_admin_cannot_query_audit_logs() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&user.token);
+
+        let response = request
+            .get("/api/admin/audit-logs")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 401);
+    })
+    .await;
+}
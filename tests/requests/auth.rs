@@ -1,7 +1,8 @@
 use insta::{assert_debug_snapshot, with_settings};
 use loco_rs::testing::prelude::*;
-use qcast::{app::App, models::users};
+use qcast::{app::App, models::users, views::auth::LoginResponse};
 use rstest::rstest;
+use sea_orm::IntoActiveModel;
 use serial_test::serial;
 
 use super::prepare_data;
@@ -17,6 +18,13 @@ macro_rules! configure_insta {
     };
 }
 
+/// 在用户模型过滤规则基础上隐藏刷新令牌
+fn cleanup_login_response() -> Vec<(&'static str, &'static str)> {
+    let mut filters = vec![(r"qcr_[0-9a-f]{64}", "REFRESH_TOKEN")];
+    filters.extend(cleanup_user_model());
+    filters
+}
+
 #[tokio::test]
 #[serial]
 async fn can_register() {
@@ -113,7 +121,7 @@ async fn can_login_with_verify(#[case] test_name: &str, #[case] password: &str)
         );
 
         with_settings!({
-            filters => cleanup_user_model()
+            filters => cleanup_login_response()
         }, {
             assert_debug_snapshot!(test_name, (response.status_code(), response.text()));
         });
@@ -183,7 +191,7 @@ async fn can_login_without_verify() {
         );
 
         with_settings!({
-            filters => cleanup_user_model()
+            filters => cleanup_login_response()
         }, {
             assert_debug_snapshot!(login_response.text());
         });
@@ -359,7 +367,7 @@ async fn can_auth_with_magic_link() {
         );
 
         with_settings!({
-            filters => cleanup_user_model()
+            filters => cleanup_login_response()
         }, {
             assert_debug_snapshot!(magic_link_response.text());
         });
@@ -536,3 +544,140 @@ async fn cannot_register_duplicate_email() {
     })
     .await;
 }
+
+/// 登录并返回完整的登录响应（含刷新令牌）
+async fn login_response(request: &loco_rs::TestServer) -> LoginResponse {
+    let response = request
+        .post("/api/auth/login")
+        .json(&serde_json::json!({
+            "email": "test@loco.com",
+            "password": "1234"
+        }))
+        .await;
+    assert_eq!(response.status_code(), 200, "Login request should succeed");
+    serde_json::from_str(&response.text()).unwrap()
+}
+
+async fn current_status(request: &loco_rs::TestServer, token: &str) -> u16 {
+    let (auth_key, auth_value) = prepare_data::auth_header(token);
+    request
+        .get("/api/auth/current")
+        .add_header(auth_key, auth_value)
+        .await
+        .status_code()
+        .as_u16()
+}
+
+#[tokio::test]
+#[serial]
+async fn can_refresh_and_detect_reused_refresh_token() {
+    configure_insta!();
+
+    request::<App, _, _>(|request, ctx| async move {
+        prepare_data::init_user_login(&request, &ctx).await;
+        let login = login_response(&request).await;
+        assert!(login.refresh_token.starts_with("qcr_"));
+
+        let response = request
+            .post("/api/auth/refresh")
+            .json(&serde_json::json!({ "refresh_token": login.refresh_token }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let refreshed: LoginResponse = serde_json::from_str(&response.text()).unwrap();
+        assert_ne!(refreshed.refresh_token, login.refresh_token);
+        assert_eq!(current_status(&request, &refreshed.token).await, 200);
+
+        // 旧刷新令牌再次使用视为盗用，整个会话被吊销
+        let response = request
+            .post("/api/auth/refresh")
+            .json(&serde_json::json!({ "refresh_token": login.refresh_token }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        let response = request
+            .post("/api/auth/refresh")
+            .json(&serde_json::json!({ "refresh_token": refreshed.refresh_token }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+        assert_eq!(current_status(&request, &refreshed.token).await, 401);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_logout_current_and_all_sessions() {
+    configure_insta!();
+
+    request::<App, _, _>(|request, ctx| async move {
+        let first = prepare_data::init_user_login(&request, &ctx).await;
+        let second = login_response(&request).await;
+        let third = login_response(&request).await;
+
+        let (auth_key, auth_value) = prepare_data::auth_header(&second.token);
+        let response = request
+            .get("/api/auth/sessions")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let sessions: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        let sessions = sessions.as_array().unwrap();
+        assert_eq!(sessions.len(), 3);
+        assert_eq!(sessions.iter().filter(|s| s["current"] == true).count(), 1);
+
+        // 退出当前设备只影响当前会话
+        let response = request
+            .post("/api/auth/logout")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert_eq!(current_status(&request, &second.token).await, 401);
+        assert_eq!(current_status(&request, &first.token).await, 200);
+
+        // 退出所有设备
+        let (auth_key, auth_value) = prepare_data::auth_header(&third.token);
+        let response = request
+            .post("/api/auth/logout-all")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert_eq!(current_status(&request, &first.token).await, 401);
+        assert_eq!(current_status(&request, &third.token).await, 401);
+
+        let response = request
+            .post("/api/auth/refresh")
+            .json(&serde_json::json!({ "refresh_token": third.refresh_token }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn sessions_are_revoked_on_password_and_role_change() {
+    configure_insta!();
+
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in = prepare_data::init_user_login(&request, &ctx).await;
+        assert_eq!(current_status(&request, &logged_in.token).await, 200);
+
+        users::Model::update_admin_status(&ctx.db, logged_in.user.id, true, false)
+            .await
+            .unwrap();
+        assert_eq!(current_status(&request, &logged_in.token).await, 401);
+
+        let login = login_response(&request).await;
+        assert_eq!(current_status(&request, &login.token).await, 200);
+
+        let user = users::Model::find_by_email(&ctx.db, "test@loco.com")
+            .await
+            .unwrap();
+        user.into_active_model()
+            .reset_password(&ctx.db, "new-password")
+            .await
+            .unwrap();
+        assert_eq!(current_status(&request, &login.token).await, 401);
+    })
+    .await;
+}
@@ -0,0 +1,236 @@
+use loco_rs::testing::prelude::*;
+use loco_rs::TestServer;
+use qcast::app::App;
+use sea_orm::EntityTrait;
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data::{auth_header, create_book, create_public_media, init_user_login};
+
+/// 创建“系列 / 第一部 / 上册”三层书籍，返回三本书的 ID
+async fn create_series(
+    request: &TestServer,
+    auth: &(axum::http::HeaderName, axum::http::HeaderValue),
+) -> (i32, i32, i32) {
+    let series = create_book(request, auth, json!({ "title": "系列" })).await;
+    let part = create_book(
+        request,
+        auth,
+        json!({ "title": "第一部", "parent_id": series }),
+    )
+    .await;
+    let volume = create_book(request, auth, json!({ "title": "上册", "parent_id": part })).await;
+    (series, part, volume)
+}
+
+#[tokio::test]
+#[serial]
+async fn book_parent_must_be_owned_and_acyclic() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let auth = auth_header(&user.token);
+        let (series, part, volume) = create_series(&request, &auth).await;
+
+        // 上级书籍不存在
+        let response = request
+            .post("/api/books")
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({ "title": "孤儿", "parent_id": 999_999 }))
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        // 不能移到自身或子孙书籍下
+        for parent in [series, volume] {
+            let response = request
+                .post(&format!("/api/books/{series}/move"))
+                .add_header(auth.0.clone(), auth.1.clone())
+                .json(&json!({ "new_parent_id": parent }))
+                .await;
+            assert_eq!(response.status_code(), 400);
+        }
+        let response = request
+            .put(&format!("/api/books/{part}"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({ "parent_id": volume }))
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        // 移到顶级
+        let response = request
+            .post(&format!("/api/books/{volume}/move"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({ "new_parent_id": null, "new_sort_order": 5 }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let moved = response.json::<serde_json::Value>();
+        assert_eq!(moved["book"]["parent_id"], serde_json::Value::Null);
+        assert_eq!(moved["book"]["sort_order"], 5);
+
+        let response = request
+            .get("/api/books/tree")
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        let roots = response.json::<Vec<serde_json::Value>>();
+        assert_eq!(roots.len(), 2);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn book_tree_aggregates_media_and_visibility() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let auth = auth_header(&user.token);
+        let (series, part, volume) = create_series(&request, &auth).await;
+        let mut medias = Vec::new();
+        for book_id in [series, volume, volume] {
+            medias.push(create_public_media(&ctx, book_id, None, user.user.id).await);
+        }
+        let media_info = format!("/api/public/media/{}/info", medias[2].access_token);
+        for book_id in [part, volume] {
+            request
+                .put(&format!("/api/books/{book_id}"))
+                .add_header(auth.0.clone(), auth.1.clone())
+                .json(&json!({ "is_public": true }))
+                .await;
+        }
+
+        let response = request
+            .get(&format!("/api/books/{series}/tree"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let tree = response.json::<serde_json::Value>();
+        assert_eq!(tree["book"]["media_count"], 1);
+        assert_eq!(tree["duration"], 120);
+        assert_eq!(tree["total_media_count"], 3);
+        assert_eq!(tree["total_duration"], 360);
+        assert_eq!(tree["children"][0]["total_media_count"], 2);
+        assert_eq!(tree["children"][0]["children"][0]["book"]["media_count"], 2);
+
+        // 上级书籍未公开时，子书籍虽然公开也不对外可见
+        assert_eq!(tree["effective_public"], false);
+        assert_eq!(tree["children"][0]["effective_public"], false);
+        let response = request
+            .get(&format!("/api/public/books/{volume}/feed.xml"))
+            .await;
+        assert_eq!(response.status_code(), 404);
+        // 媒体本身公开，但所在书籍或其上级书籍未公开
+        let response = request.get(&media_info).await;
+        assert_eq!(response.status_code(), 404);
+        let response = request
+            .get(&format!("/public/{}", medias[0].access_token))
+            .add_header(
+                axum::http::header::ACCEPT,
+                axum::http::HeaderValue::from_static("text/html"),
+            )
+            .await;
+        assert_eq!(response.status_code(), 404);
+
+        request
+            .put(&format!("/api/books/{series}"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({ "is_public": true }))
+            .await;
+        let response = request
+            .get(&format!("/api/books/{part}/tree"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        let tree = response.json::<serde_json::Value>();
+        assert_eq!(tree["effective_public"], true);
+        assert_eq!(tree["children"][0]["effective_public"], true);
+        let response = request
+            .get(&format!("/api/public/books/{volume}/feed.xml"))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let response = request.get(&media_info).await;
+        assert_eq!(response.status_code(), 200);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn deleting_book_reparents_or_cascades_children() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let auth = auth_header(&user.token);
+
+        // 默认：子书籍移到被删除书籍的上级下
+        let (series, part, volume) = create_series(&request, &auth).await;
+        let response = request
+            .delete(&format!("/api/books/{part}"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let response = request
+            .get(&format!("/api/books/{volume}"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        assert_eq!(response.json::<serde_json::Value>()["parent_id"], series);
+
+        // 级联：子书籍和其中的媒体一起移入回收站，并随上级一起恢复
+        let media = create_public_media(&ctx, volume, None, user.user.id).await;
+        let response = request
+            .delete(&format!("/api/books/{series}?children=cascade"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let response = request
+            .get(&format!("/api/books/{volume}"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        assert_eq!(response.status_code(), 404);
+
+        let response = request
+            .get("/api/trash")
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        let listing = response.json::<serde_json::Value>();
+        let trashed: Vec<i64> = listing["books"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|b| b["id"].as_i64().unwrap())
+            .collect();
+        assert!(trashed.contains(&i64::from(series)));
+        assert!(!trashed.contains(&i64::from(volume)));
+        assert!(listing["medias"].as_array().unwrap().is_empty());
+
+        let response = request
+            .post(&format!("/api/trash/books/{series}/restore"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let response = request
+            .get(&format!("/api/books/{series}/tree"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        let tree = response.json::<serde_json::Value>();
+        assert_eq!(tree["children"][0]["book"]["id"], volume);
+        assert_eq!(tree["total_media_count"], 1);
+
+        // 永久删除时回收站中的子书籍一起删除
+        request
+            .delete(&format!("/api/books/{series}?children=cascade"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        let response = request
+            .delete(&format!("/api/trash/books/{series}"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let response = request
+            .post(&format!("/api/trash/books/{volume}/restore"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        assert_eq!(response.status_code(), 404);
+        let purged = qcast::models::_entities::medias::Entity::find_by_id(media.id)
+            .one(&ctx.db)
+            .await
+            .unwrap();
+        assert!(purged.is_none());
+    })
+    .await;
+}
@@ -0,0 +1,129 @@
+use loco_rs::testing::prelude::*;
+use loco_rs::TestServer;
+use qcast::app::App;
+use qcast::models::storage_blobs;
+use qcast::services::storage::STORAGE_SERVICE;
+use serde_json::json;
+use serial_test::serial;
+use sha2::{Digest, Sha256};
+
+use super::prepare_data::{auth_header, init_user_login, media_form, multipart_content_type};
+
+async fn upload(
+    request: &TestServer,
+    auth: &(axum::http::HeaderName, axum::http::HeaderValue),
+    book_id: i64,
+    content: &str,
+) -> serde_json::Value {
+    let response = request
+        .post("/api/media/upload")
+        .add_header(auth.0.clone(), auth.1.clone())
+        .content_type(&multipart_content_type())
+        .bytes(
+            media_form(
+                &[
+                    ("title", "同一录音".to_string()),
+                    ("book_id", book_id.to_string()),
+                ],
+                "lesson.mp3",
+                content,
+            )
+            .into(),
+        )
+        .await;
+    assert_eq!(response.status_code(), 200);
+    response.json::<serde_json::Value>()
+}
+
+#[tokio::test]
+#[serial]
+async fn identical_uploads_share_one_file_until_last_reference_is_gone() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let auth = auth_header(&user.token);
+
+        let mut book_ids = Vec::new();
+        for title in ["教材一", "教材二"] {
+            let response = request
+                .post("/api/books")
+                .add_header(auth.0.clone(), auth.1.clone())
+                .json(&json!({ "title": title }))
+                .await;
+            book_ids.push(response.json::<serde_json::Value>()["id"].as_i64().unwrap());
+        }
+
+        let content = format!("shared audio {}", uuid::Uuid::new_v4());
+        let first = upload(&request, &auth, book_ids[0], &content).await;
+        let second = upload(&request, &auth, book_ids[1], &content).await;
+        assert_ne!(first["id"], second["id"]);
+        assert_ne!(first["access_token"], second["access_token"]);
+        assert_eq!(first["file_path"], second["file_path"]);
+
+        let file_path = first["file_path"].as_str().unwrap().to_string();
+        assert!(file_path.starts_with("blobs/"));
+        let hash = hex::encode(Sha256::digest(content.as_bytes()));
+        let blob = storage_blobs::Model::find_by_hash(&ctx.db, &hash)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(blob.file_path, file_path);
+        assert_eq!(blob.ref_count, 2);
+
+        // 替换为其他内容后旧文件仍作为历史版本被引用
+        let response = request
+            .put(&format!("/api/media/{}/replace-file", first["id"]))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .content_type(&multipart_content_type())
+            .bytes(media_form(&[], "lesson-v2.mp3", "another take").into())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert_ne!(response.json::<serde_json::Value>()["file_path"], file_path);
+
+        // 永久删除一个媒体，另一个媒体的文件保留
+        for media in [&first, &second] {
+            request
+                .delete(&format!("/api/media/{}", media["id"]))
+                .add_header(auth.0.clone(), auth.1.clone())
+                .await;
+        }
+        let response = request
+            .delete(&format!("/api/trash/medias/{}", first["id"]))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert!(tokio::fs::metadata(STORAGE_SERVICE.resolve(&file_path))
+            .await
+            .is_ok());
+        let blob = storage_blobs::Model::find_by_hash(&ctx.db, &hash)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(blob.ref_count, 1);
+
+        // 最后一个引用删除后文件和记录一起删除
+        let response = request
+            .delete(&format!("/api/trash/medias/{}", second["id"]))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert!(tokio::fs::metadata(STORAGE_SERVICE.resolve(&file_path))
+            .await
+            .is_err());
+        assert!(storage_blobs::Model::find_by_hash(&ctx.db, &hash)
+            .await
+            .unwrap()
+            .is_none());
+        // 记录删除后不能再增加引用
+        assert!(storage_blobs::Model::acquire(&ctx.db, &hash).await.is_err());
+
+        // 再次上传相同内容时重新登记
+        let third = upload(&request, &auth, book_ids[0], &content).await;
+        assert_eq!(third["file_path"], file_path);
+        let blob = storage_blobs::Model::find_by_hash(&ctx.db, &hash)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(blob.ref_count, 1);
+    })
+    .await;
+}
@@ -0,0 +1,208 @@
+use loco_rs::testing::prelude::*;
+use loco_rs::TestServer;
+use qcast::app::App;
+use qcast::models::_entities::medias;
+use qcast::services::storage::STORAGE_SERVICE;
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, IntoActiveModel, QueryFilter, Set};
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data::{
+    auth_header, create_book_with_chapters, create_chapter, create_public_media, init_user_login,
+};
+
+/// 创建书籍以及“第一章 / 第一节”两级章节，返回 (书籍, 第一章, 第一节) 的 ID
+async fn create_book_with_sections(
+    request: &TestServer,
+    auth: &(axum::http::HeaderName, axum::http::HeaderValue),
+    title: &str,
+) -> (i32, i32, i32) {
+    let (book_id, chapters) = create_book_with_chapters(request, auth, title, &["第一章"]).await;
+    let section_id = create_chapter(
+        request,
+        auth,
+        book_id,
+        json!({ "title": "第一节", "parent_id": chapters[0] }),
+    )
+    .await;
+    (book_id, chapters[0], section_id)
+}
+
+#[tokio::test]
+#[serial]
+async fn chapter_subtree_can_move_to_another_book() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let auth = auth_header(&user.token);
+
+        let (source_id, chapter_id, section_id) =
+            create_book_with_sections(&request, &auth, "原书").await;
+        let (target_id, target_chapter_id, _) =
+            create_book_with_sections(&request, &auth, "目标书").await;
+        let media = create_public_media(&ctx, source_id, Some(section_id), user.user.id).await;
+
+        // 同一本书内不能使用跨书移动
+        let url = format!("/api/books/{source_id}/chapters/{chapter_id}/move-to-book");
+        let response = request
+            .post(&url)
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({ "target_book_id": source_id }))
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        let response = request
+            .post(&url)
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({ "target_book_id": target_id, "new_parent_id": target_chapter_id }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let moved = response.json::<serde_json::Value>();
+        assert_eq!(moved["book_id"], target_id);
+        assert_eq!(moved["parent_id"], target_chapter_id);
+        assert_eq!(moved["level"], 1);
+        assert_eq!(moved["path"], format!("{target_chapter_id}/{chapter_id}"));
+
+        let response = request
+            .get(&format!("/api/books/{target_id}/chapters/tree"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        let tree = response.json::<serde_json::Value>();
+        let moved_node = tree[0]["children"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|c| c["id"] == chapter_id)
+            .unwrap();
+        assert_eq!(moved_node["children"][0]["id"], section_id);
+        assert_eq!(moved_node["children"][0]["level"], 2);
+        assert_eq!(moved_node["children"][0]["media_count"], 1);
+
+        let response = request
+            .get(&format!("/api/books/{source_id}/chapters/tree"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        assert!(response.json::<Vec<serde_json::Value>>().is_empty());
+
+        let media = medias::Entity::find_by_id(media.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(media.book_id, target_id);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn chapters_and_books_can_be_copied() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let auth = auth_header(&user.token);
+
+        let (book_id, chapter_id, section_id) =
+            create_book_with_sections(&request, &auth, "复制测试").await;
+        let media = create_public_media(&ctx, book_id, Some(section_id), user.user.id).await;
+        let source_file =
+            std::env::temp_dir().join(format!("qcast-copy-{}.mp3", uuid::Uuid::new_v4()));
+        tokio::fs::write(&source_file, b"ID3 test audio")
+            .await
+            .unwrap();
+        let mut item = media.into_active_model();
+        item.file_path = Set(source_file.to_string_lossy().to_string());
+        let media = item.update(&ctx.db).await.unwrap();
+
+        // 复制文件内容
+        let url = format!("/api/books/{book_id}/chapters/{chapter_id}/copy");
+        let response = request
+            .post(&url)
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({}))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let copy = response.json::<serde_json::Value>();
+        assert_ne!(copy["id"], chapter_id);
+        assert_eq!(copy["title"], "第一章");
+        assert_eq!(copy["level"], 0);
+        assert_eq!(copy["children"][0]["title"], "第一节");
+        assert_eq!(copy["children"][0]["media_count"], 1);
+        let copied_section = copy["children"][0]["id"].as_i64().unwrap();
+        let copied_media = medias::Entity::find()
+            .filter(medias::Column::ChapterId.eq(i32::try_from(copied_section).unwrap()))
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(copied_media.file_path, media.file_path);
+        assert_ne!(copied_media.access_token, media.access_token);
+        assert!(!copied_media.is_public);
+        // 复制出的文件以存储键记录
+        assert!(copied_media.file_path.starts_with("users/"));
+        assert!(
+            tokio::fs::metadata(STORAGE_SERVICE.resolve(&copied_media.file_path))
+                .await
+                .is_ok()
+        );
+
+        // 共用文件：永久删除副本不会删除原媒体的文件
+        let response = request
+            .post(&url)
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({ "share_files": true }))
+            .await;
+        let copy = response.json::<serde_json::Value>();
+        let shared_section = copy["children"][0]["id"].as_i64().unwrap();
+        let shared_media = medias::Entity::find()
+            .filter(medias::Column::ChapterId.eq(i32::try_from(shared_section).unwrap()))
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(shared_media.file_path, media.file_path);
+        request
+            .delete(&format!("/api/media/{}", shared_media.id))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        let response = request
+            .delete(&format!("/api/trash/medias/{}", shared_media.id))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert!(tokio::fs::metadata(&source_file).await.is_ok());
+
+        // 复制整本书：3 个顶级章节各有 1 个子章节，2 个未删除的媒体
+        let response = request
+            .post(&format!("/api/books/{book_id}/copy"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({}))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let book = response.json::<serde_json::Value>();
+        assert_eq!(book["title"], "复制测试（副本）");
+        assert_eq!(book["is_public"], false);
+        assert_eq!(book["chapter_count"], 6);
+        assert_eq!(book["media_count"], 2);
+
+        let response = request
+            .get(&format!("/api/books/{}/chapters/tree", book["id"]))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        let tree = response.json::<Vec<serde_json::Value>>();
+        assert_eq!(tree.len(), 3);
+        assert!(tree.iter().all(|root| root["children"][0]["level"] == 1));
+
+        let copied_files: Vec<String> = medias::Entity::find()
+            .filter(medias::Column::UserId.eq(user.user.id))
+            .all(&ctx.db)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter(|m| m.file_path != media.file_path)
+            .map(|m| m.file_path)
+            .collect();
+        for path in copied_files.iter().chain([&media.file_path]) {
+            let _ = tokio::fs::remove_file(STORAGE_SERVICE.resolve(path)).await;
+        }
+    })
+    .await;
+}
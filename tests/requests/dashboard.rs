@@ -0,0 +1,194 @@
+use loco_rs::testing::prelude::*;
+use qcast::app::App;
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data::{
+    auth_header, create_book, create_chapter, create_public_media, init_user_login,
+};
+
+#[tokio::test]
+#[serial]
+async fn can_export_analytics_as_csv() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in_user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&logged_in_user.token);
+
+        let response = request
+            .post("/api/books")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "title": "统计测试书籍", "is_public": true }))
+            .await;
+        let book: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        let book_id = book["id"].as_i64().unwrap() as i32;
+
+        let media = create_public_media(&ctx, book_id, None, logged_in_user.user.id).await;
+
+        // 两次播放，一次完播
+        for _ in 0..2 {
+            let response = request
+                .get(&format!("/api/public/media/{}/info", media.access_token))
+                .await;
+            assert_eq!(response.status_code(), 200);
+        }
+        // 同一来源重复上报完播只统计一次
+        for _ in 0..2 {
+            let response = request
+                .post(&format!(
+                    "/api/public/media/{}/complete",
+                    media.access_token
+                ))
+                .await;
+            assert_eq!(response.status_code(), 200);
+        }
+
+        let response = request
+            .get(&format!(
+                "/api/dashboard/export?format=csv&book_id={book_id}"
+            ))
+            .add_header(auth_key, auth_value)
+            .await;
+
+        assert_eq!(response.status_code(), 200);
+
+        let body = response.text();
+        let lines: Vec<&str> = body.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("media_id,title,file_type,book_id,book_title"));
+        assert!(lines[1].starts_with(&format!(
+            "{},Test Media,audio,{book_id},统计测试书籍",
+            media.id
+        )));
+        // total_plays,plays,completions,completion_rate,listened_seconds
+        assert!(lines[1].contains(",2,2,1,0.5000,120,"));
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_export_analytics_as_json_with_date_range() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in_user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&logged_in_user.token);
+
+        let response = request
+            .post("/api/books")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "title": "统计测试书籍", "is_public": true }))
+            .await;
+        let book: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        let book_id = book["id"].as_i64().unwrap() as i32;
+
+        let media = create_public_media(&ctx, book_id, None, logged_in_user.user.id).await;
+        request
+            .get(&format!("/api/public/media/{}/info", media.access_token))
+            .await;
+
+        // 过去的日期范围内没有播放事件
+        let response = request
+            .get("/api/dashboard/export?format=json&from=2020-01-01&to=2020-01-31")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+
+        assert_eq!(response.status_code(), 200);
+
+        let rows: Vec<serde_json::Value> = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["media_id"], media.id);
+        assert_eq!(rows[0]["total_plays"], 1);
+        assert_eq!(rows[0]["plays"], 0);
+        assert_eq!(rows[0]["completions"], 0);
+
+        // 回收站中的媒体不导出
+        request
+            .delete(&format!("/api/media/{}", media.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        let response = request
+            .get("/api/dashboard/export?format=json")
+            .add_header(auth_key, auth_value)
+            .await;
+        let rows: Vec<serde_json::Value> = serde_json::from_str(&response.text()).unwrap();
+        assert!(rows.is_empty());
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn exports_analytics_in_chapter_tree_order() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in_user = init_user_login(&request, &ctx).await;
+        let auth = auth_header(&logged_in_user.token);
+
+        // 后创建的章节排在前面，导出顺序按章节树而不是章节 ID
+        let book_id = create_book(&request, &auth, json!({ "title": "统计排序" })).await;
+        let second = create_chapter(
+            &request,
+            &auth,
+            book_id,
+            json!({ "title": "第二章", "sort_order": 2 }),
+        )
+        .await;
+        let first = create_chapter(
+            &request,
+            &auth,
+            book_id,
+            json!({ "title": "第一章", "sort_order": 1 }),
+        )
+        .await;
+        let lesson = create_chapter(
+            &request,
+            &auth,
+            book_id,
+            json!({ "title": "第一课", "parent_id": first }),
+        )
+        .await;
+        let user_id = logged_in_user.user.id;
+        let in_second = create_public_media(&ctx, book_id, Some(second), user_id).await;
+        let in_lesson = create_public_media(&ctx, book_id, Some(lesson), user_id).await;
+        let in_first = create_public_media(&ctx, book_id, Some(first), user_id).await;
+
+        let response = request
+            .get(&format!(
+                "/api/dashboard/export?format=json&book_id={book_id}"
+            ))
+            .add_header(auth.0, auth.1)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let rows: Vec<serde_json::Value> = serde_json::from_str(&response.text()).unwrap();
+        let ids: Vec<i64> = rows
+            .iter()
+            .map(|r| r["media_id"].as_i64().unwrap())
+            .collect();
+        assert_eq!(
+            ids,
+            [in_first.id, in_lesson.id, in_second.id].map(i64::from)
+        );
+        assert_eq!(rows[1]["chapter_path"], "第一章 / 第一课");
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn cannot_export_analytics_with_invalid_params() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in_user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&logged_in_user.token);
+
+        let response = request
+            .get("/api/dashboard/export?format=xlsx")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        let response = request
+            .get("/api/dashboard/export?from=2025-13-01")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 400);
+    })
+    .await;
+}
@@ -0,0 +1,112 @@
+use loco_rs::testing::prelude::*;
+use qcast::app::App;
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data::{auth_header, create_book, create_public_media, init_user_login};
+
+#[tokio::test]
+#[serial]
+async fn can_render_embed_player() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in_user = init_user_login(&request, &ctx).await;
+        let book_id = create_book(
+            &request,
+            &auth_header(&logged_in_user.token),
+            json!({ "title": "嵌入测试书籍", "is_public": true }),
+        )
+        .await;
+        let media = create_public_media(&ctx, book_id, None, logged_in_user.user.id).await;
+
+        let response = request
+            .get(&format!("/embed/media/{}", media.access_token))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert_eq!(
+            response.headers().get("content-security-policy").unwrap(),
+            "frame-ancestors 'self'"
+        );
+        let html = response.text();
+        assert!(html.contains("<audio"));
+        assert!(html.contains(&format!("data-token=\"{}\"", media.access_token)));
+
+        let response = request.get(&format!("/embed/books/{book_id}")).await;
+        assert_eq!(response.status_code(), 200);
+        assert!(response.text().contains("Test Media"));
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn can_get_oembed_for_access_url() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in_user = init_user_login(&request, &ctx).await;
+        let book_id = create_book(
+            &request,
+            &auth_header(&logged_in_user.token),
+            json!({ "title": "嵌入测试书籍", "is_public": true }),
+        )
+        .await;
+        let media = create_public_media(&ctx, book_id, None, logged_in_user.user.id).await;
+
+        let response = request
+            .get("/oembed")
+            .add_query_param("url", media.access_url.as_ref().unwrap())
+            .add_query_param("maxwidth", 240)
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let oembed: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(oembed["version"], "1.0");
+        assert_eq!(oembed["type"], "rich");
+        assert_eq!(oembed["title"], "Test Media");
+        assert_eq!(oembed["width"], 240);
+        assert_eq!(oembed["height"], 60);
+        assert!(oembed["html"]
+            .as_str()
+            .unwrap()
+            .contains(&format!("/embed/media/{}", media.access_token)));
+
+        let response = request
+            .get("/oembed")
+            .add_query_param(
+                "url",
+                format!("http://localhost:5150/embed/books/{book_id}"),
+            )
+            .await;
+        assert_eq!(response.status_code(), 200);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn cannot_embed_private_or_unknown_content() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in_user = init_user_login(&request, &ctx).await;
+        let book_id = create_book(
+            &request,
+            &auth_header(&logged_in_user.token),
+            json!({ "title": "嵌入测试书籍", "is_public": false }),
+        )
+        .await;
+
+        let response = request.get(&format!("/embed/books/{book_id}")).await;
+        assert_eq!(response.status_code(), 404);
+
+        let response = request
+            .get("/oembed")
+            .add_query_param("url", "http://other.example.com/public/abc")
+            .await;
+        assert_eq!(response.status_code(), 404);
+
+        let response = request
+            .get("/oembed")
+            .add_query_param("url", "http://localhost:5150/public/abc")
+            .add_query_param("format", "xml")
+            .await;
+        assert_eq!(response.status_code(), 501);
+    })
+    .await;
+}
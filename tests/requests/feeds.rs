@@ -0,0 +1,121 @@
+use axum::http::{HeaderName, HeaderValue};
+use loco_rs::testing::prelude::*;
+use qcast::app::App;
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data::{auth_header, create_public_media, init_user_login};
+
+#[tokio::test]
+#[serial]
+async fn can_get_podcast_feed_for_public_book() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in_user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&logged_in_user.token);
+
+        let response = request
+            .post("/api/books")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({
+                "title": "播客 & 故事",
+                "description": "每周更新",
+                "is_public": true
+            }))
+            .await;
+        let book: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        let book_id = book["id"].as_i64().unwrap() as i32;
+
+        let response = request
+            .put(&format!("/api/books/{book_id}/feed"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({
+                "author": "QCast 电台",
+                "language": "zh-CN",
+                "category": "Education",
+                "explicit": false
+            }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let settings: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(settings["language"], "zh-cn");
+        assert!(settings["feed_url"]
+            .as_str()
+            .unwrap()
+            .ends_with(&format!("/api/public/books/{book_id}/feed.xml")));
+
+        let media = create_public_media(&ctx, book_id, None, logged_in_user.user.id).await;
+
+        let response = request
+            .get(&format!("/api/public/books/{book_id}/feed.xml"))
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let xml = response.text();
+        assert!(xml.contains("<title>播客 &amp; 故事</title>"));
+        assert!(xml.contains("<itunes:author>QCast 电台</itunes:author>"));
+        assert!(xml.contains("<language>zh-cn</language>"));
+        assert!(xml.contains("<itunes:category text=\"Education\"/>"));
+        assert!(xml.contains(&format!(
+            "/api/public/media/{}\" length=\"1024000\" type=\"audio/mpeg\"/>",
+            media.access_token
+        )));
+        assert!(xml.contains("<itunes:duration>00:02:00</itunes:duration>"));
+
+        // 带 ETag 的条件请求返回 304
+        let etag = response.headers().get("etag").unwrap().clone();
+        let last_modified = response.headers().get("last-modified").unwrap().clone();
+
+        let response = request
+            .get(&format!("/api/public/books/{book_id}/feed.xml"))
+            .add_header(HeaderName::from_static("if-none-match"), etag)
+            .await;
+        assert_eq!(response.status_code(), 304);
+
+        let response = request
+            .get(&format!("/api/public/books/{book_id}/feed.xml"))
+            .add_header(
+                HeaderName::from_static("if-none-match"),
+                HeaderValue::from_static("\"stale\""),
+            )
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        // 删除媒体不会更新任何时间，只带 If-Modified-Since 的请求也要拿到新内容
+        let response = request
+            .delete(&format!("/api/media/{}", media.id))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let response = request
+            .get(&format!("/api/public/books/{book_id}/feed.xml"))
+            .add_header(HeaderName::from_static("if-modified-since"), last_modified)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert!(!response.text().contains(&media.access_token));
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn cannot_get_podcast_feed_for_private_book() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in_user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&logged_in_user.token);
+
+        let response = request
+            .post("/api/books")
+            .add_header(auth_key, auth_value)
+            .json(&json!({ "title": "私有书籍" }))
+            .await;
+        let book: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        let book_id = book["id"].as_i64().unwrap();
+
+        let response = request
+            .get(&format!("/api/public/books/{book_id}/feed.xml"))
+            .await;
+        assert_eq!(response.status_code(), 404);
+    })
+    .await;
+}
@@ -0,0 +1,111 @@
+use axum::http::{HeaderName, HeaderValue};
+use loco_rs::testing::prelude::*;
+use qcast::app::App;
+use qcast::models::chapters;
+use sea_orm::ActiveModelTrait;
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data::{auth_header, create_public_media, init_user_login};
+
+#[tokio::test]
+#[serial]
+async fn can_render_landing_page_for_browsers() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in_user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&logged_in_user.token);
+
+        let response = request
+            .post("/api/books")
+            .add_header(auth_key, auth_value)
+            .json(&json!({
+                "title": "落地页书籍",
+                "cover_image": "/static/covers/book.png",
+                "is_public": true
+            }))
+            .await;
+        let book: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        let book_id = book["id"].as_i64().unwrap() as i32;
+
+        let unit = chapters::ActiveModel::create_with_order(
+            &ctx.db,
+            book_id,
+            "第一单元".to_string(),
+            None,
+        )
+        .await
+        .unwrap()
+        .insert(&ctx.db)
+        .await
+        .unwrap();
+        chapters::Model::update_level_and_path(&ctx.db, unit.id, None)
+            .await
+            .unwrap();
+
+        let media = create_public_media(&ctx, book_id, Some(unit.id), logged_in_user.user.id).await;
+
+        let response = request
+            .get(&format!("/public/{}", media.access_token))
+            .add_header(
+                HeaderName::from_static("accept"),
+                HeaderValue::from_static("text/html,application/xhtml+xml;q=0.9,*/*;q=0.8"),
+            )
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert_eq!(response.headers().get("vary").unwrap(), "accept");
+
+        let html = response.text();
+        assert!(html.contains("<meta property=\"og:title\" content=\"Test Media\">"));
+        assert!(html.contains("<meta property=\"og:description\" content=\"Test Description\">"));
+        // Tera 会转义属性中的 `/`，这里只检查封面文件名
+        assert!(html.contains("<meta property=\"og:image\""));
+        assert!(html.contains("book.png"));
+        assert!(html.contains("<meta name=\"twitter:card\" content=\"player\">"));
+        assert!(html.contains("<span>落地页书籍</span><span>第一单元</span>"));
+        assert!(html.contains(&format!("data-token=\"{}\"", media.access_token)));
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn keeps_raw_streaming_for_non_html_clients() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in_user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&logged_in_user.token);
+
+        let response = request
+            .post("/api/books")
+            .add_header(auth_key, auth_value)
+            .json(&json!({ "title": "落地页书籍", "is_public": true }))
+            .await;
+        let book: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        let book_id = book["id"].as_i64().unwrap() as i32;
+        let media = create_public_media(&ctx, book_id, None, logged_in_user.user.id).await;
+
+        // 测试媒体没有实际文件，非 HTML 客户端走文件流逻辑返回 404
+        let response = request
+            .get(&format!("/public/{}", media.access_token))
+            .add_header(
+                HeaderName::from_static("accept"),
+                HeaderValue::from_static("audio/mpeg,*/*"),
+            )
+            .await;
+        assert_eq!(response.status_code(), 404);
+        assert!(!response.text().contains("<html"));
+
+        // 明确拒绝或更偏好其他类型的 HTML 也按文件请求处理
+        for accept in ["text/html;q=0,*/*", "audio/mpeg,text/html;q=0.5"] {
+            let response = request
+                .get(&format!("/public/{}", media.access_token))
+                .add_header(
+                    HeaderName::from_static("accept"),
+                    HeaderValue::from_static(accept),
+                )
+                .await;
+            assert_eq!(response.status_code(), 404);
+            assert!(!response.text().contains("<html"));
+        }
+    })
+    .await;
+}
@@ -0,0 +1,202 @@
+use loco_rs::app::AppContext;
+use loco_rs::testing::prelude::*;
+use qcast::app::App;
+use qcast::models::_entities::medias;
+use qcast::services::storage::STORAGE_SERVICE;
+use sea_orm::EntityTrait;
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data::{
+    auth_header, counting_wav, init_user_login, multipart_content_type, silent_mp3, upload_form,
+    MP3_FRAME_SIZE,
+};
+
+/// 上传表单中的标题和书籍字段
+fn lesson_fields(book_id: i64) -> [(&'static str, String); 2] {
+    [
+        ("title", "整节课录音".to_string()),
+        ("book_id", book_id.to_string()),
+    ]
+}
+
+async fn find_media(ctx: &AppContext, id: i64) -> medias::Model {
+    medias::Entity::find_by_id(i32::try_from(id).unwrap())
+        .one(&ctx.db)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn clips_mp3_by_copying_frames() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&user.token);
+
+        let response = request
+            .post("/api/books")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "title": "剪辑测试" }))
+            .await;
+        let book_id = response.json::<serde_json::Value>()["id"].as_i64().unwrap();
+
+        let response = request
+            .post("/api/media/upload")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .content_type(&multipart_content_type())
+            .bytes(
+                upload_form(
+                    &lesson_fields(book_id),
+                    "lesson.mp3",
+                    "audio/mpeg",
+                    &silent_mp3(100),
+                )
+                .into(),
+            )
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let source = response.json::<serde_json::Value>();
+        let source_id = source["id"].as_i64().unwrap();
+
+        let response = request
+            .post(&format!("/api/media/{source_id}/clip"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "start_ms": 1000, "end_ms": 2000 }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let clip = response.json::<serde_json::Value>();
+        assert_eq!(clip["status"], "succeeded", "{clip}");
+        assert_eq!(clip["method"], "copy");
+        assert_eq!(clip["title"], "整节课录音（0:01-0:02）");
+
+        // 新媒体有自己的访问令牌，文件由完整的帧组成
+        let media = find_media(&ctx, clip["media_id"].as_i64().unwrap()).await;
+        assert_ne!(media.access_token, source["access_token"].as_str().unwrap());
+        assert_eq!(media.book_id, i32::try_from(book_id).unwrap());
+        assert_eq!(media.mime_type.as_deref(), Some("audio/mpeg"));
+        assert_eq!(media.duration, Some(1));
+        // 新媒体排在原媒体之后
+        assert_eq!(media.sort_order, Some(2));
+        let content = std::fs::read(STORAGE_SERVICE.resolve(&media.file_path)).unwrap();
+        assert_eq!(content.len() % MP3_FRAME_SIZE, 0);
+        // 与 1000-2000 毫秒重叠的是第 38 到 76 帧
+        assert_eq!(content.len() / MP3_FRAME_SIZE, 39);
+        assert_eq!(&content[..2], &[0xFF, 0xFB]);
+
+        let response = request
+            .get(&format!("/api/media/clips/{}", clip["id"]))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert_eq!(
+            response.json::<serde_json::Value>()["media_id"],
+            clip["media_id"]
+        );
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn clips_wav_by_reencoding() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&user.token);
+
+        let response = request
+            .post("/api/books")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "title": "剪辑测试" }))
+            .await;
+        let book_id = response.json::<serde_json::Value>()["id"].as_i64().unwrap();
+
+        let original = counting_wav(3);
+        let response = request
+            .post("/api/media/upload")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .content_type(&multipart_content_type())
+            .bytes(
+                upload_form(
+                    &lesson_fields(book_id),
+                    "lesson.wav",
+                    "audio/wav",
+                    &original,
+                )
+                .into(),
+            )
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let source_id = response.json::<serde_json::Value>()["id"].as_i64().unwrap();
+
+        let response = request
+            .post(&format!("/api/media/{source_id}/clip"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "start_ms": 500, "end_ms": 2000, "title": "练习一" }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let clip = response.json::<serde_json::Value>();
+        assert_eq!(clip["status"], "succeeded", "{clip}");
+        assert_eq!(clip["method"], "reencode");
+
+        let media = find_media(&ctx, clip["media_id"].as_i64().unwrap()).await;
+        assert_eq!(media.title, "练习一");
+        assert_eq!(media.mime_type.as_deref(), Some("audio/wav"));
+        assert!(media.qr_code_path.is_some());
+
+        // 16 位 PCM 的采样原样保留：第 4000 到 15999 个采样
+        let content = std::fs::read(STORAGE_SERVICE.resolve(&media.file_path)).unwrap();
+        assert_eq!(content.len(), 44 + 12_000 * 2);
+        assert_eq!(&content[44..], &original[44 + 4000 * 2..44 + 16_000 * 2]);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn rejects_invalid_clip_ranges() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&user.token);
+
+        let response = request
+            .post("/api/books")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "title": "剪辑测试" }))
+            .await;
+        let book_id = response.json::<serde_json::Value>()["id"].as_i64().unwrap();
+
+        let response = request
+            .post("/api/media/upload")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .content_type(&multipart_content_type())
+            .bytes(
+                upload_form(
+                    &lesson_fields(book_id),
+                    "lesson.wav",
+                    "audio/wav",
+                    &counting_wav(2),
+                )
+                .into(),
+            )
+            .await;
+        let source_id = response.json::<serde_json::Value>()["id"].as_i64().unwrap();
+
+        for (start_ms, end_ms) in [
+            (-1, 1500),
+            (1000, 1500),
+            (2000, 1000),
+            (5000, 8000),
+            (1000, i64::MIN),
+        ] {
+            let response = request
+                .post(&format!("/api/media/{source_id}/clip"))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&json!({ "start_ms": start_ms, "end_ms": end_ms }))
+                .await;
+            assert_eq!(response.status_code(), 400, "{start_ms}-{end_ms}");
+        }
+    })
+    .await;
+}
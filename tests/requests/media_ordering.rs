@@ -0,0 +1,139 @@
+use loco_rs::testing::prelude::*;
+use qcast::app::App;
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data::{
+    auth_header, create_book_with_chapters, create_public_media, init_user_login,
+};
+
+fn listed_ids(listed: &[serde_json::Value]) -> Vec<i64> {
+    listed.iter().map(|m| m["id"].as_i64().unwrap()).collect()
+}
+
+#[tokio::test]
+#[serial]
+async fn medias_can_be_reordered_within_chapter() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let auth = auth_header(&user.token);
+        let (book_id, chapters) =
+            create_book_with_chapters(&request, &auth, "排序测试", &["第一章", "第二章"]).await;
+        let (chapter_id, other_chapter) = (chapters[0], chapters[1]);
+
+        let mut ids = Vec::new();
+        for _ in 0..3 {
+            let media = create_public_media(&ctx, book_id, Some(chapter_id), user.user.id).await;
+            assert_eq!(
+                media.sort_order,
+                Some(i32::try_from(ids.len() + 1).unwrap())
+            );
+            ids.push(i64::from(media.id));
+        }
+
+        let reversed: Vec<i64> = ids.iter().rev().copied().collect();
+        let response = request
+            .post("/api/media/batch-reorder")
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({ "book_id": book_id, "chapter_id": chapter_id, "media_ids": reversed }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let listed = response.json::<Vec<serde_json::Value>>();
+        assert_eq!(listed_ids(&listed), reversed);
+        assert_eq!(listed[0]["sort_order"], 1);
+
+        let response = request
+            .get(&format!("/api/media/by-chapter?chapter_id={chapter_id}"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        assert_eq!(
+            listed_ids(&response.json::<Vec<serde_json::Value>>()),
+            reversed
+        );
+
+        // 不在该章节中的媒体使整个重排失败
+        let response = request
+            .post("/api/media/batch-reorder")
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({ "book_id": book_id, "chapter_id": other_chapter, "media_ids": ids }))
+            .await;
+        assert_eq!(response.status_code(), 400);
+        let response = request
+            .get(&format!("/api/media/by-chapter?chapter_id={chapter_id}"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        assert_eq!(
+            listed_ids(&response.json::<Vec<serde_json::Value>>()),
+            reversed
+        );
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn media_can_move_between_chapters() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let auth = auth_header(&user.token);
+        let (book_id, chapters) =
+            create_book_with_chapters(&request, &auth, "排序测试", &["第一章", "第二章"]).await;
+        let (chapter_id, other_chapter) = (chapters[0], chapters[1]);
+
+        let first = create_public_media(&ctx, book_id, Some(chapter_id), user.user.id).await;
+        let second = create_public_media(&ctx, book_id, Some(other_chapter), user.user.id).await;
+
+        // 移到另一章节时排在最后
+        let response = request
+            .post(&format!("/api/media/{}/move", first.id))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({ "chapter_id": other_chapter }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let moved = response.json::<serde_json::Value>();
+        assert_eq!(moved["chapter_id"], other_chapter);
+        assert_eq!(moved["sort_order"], 2);
+
+        let response = request
+            .get(&format!("/api/media/by-chapter?chapter_id={other_chapter}"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        assert_eq!(
+            listed_ids(&response.json::<Vec<serde_json::Value>>()),
+            vec![i64::from(second.id), i64::from(first.id)]
+        );
+
+        // 指定位置移到书籍顶层
+        let response = request
+            .post(&format!("/api/media/{}/move", second.id))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({ "chapter_id": null, "sort_order": 7 }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let moved = response.json::<serde_json::Value>();
+        assert_eq!(moved["chapter_id"], serde_json::Value::Null);
+        assert_eq!(moved["sort_order"], 7);
+
+        // 目标章节必须属于目标书籍
+        let response = request
+            .post(&format!("/api/media/{}/move", first.id))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({ "chapter_id": 999_999 }))
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        // 已附加到目标章节的媒体不能再移过去
+        request
+            .post(&format!("/api/media/{}/placements", first.id))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({ "book_id": book_id, "chapter_id": chapter_id }))
+            .await;
+        let response = request
+            .post(&format!("/api/media/{}/move", first.id))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({ "chapter_id": chapter_id }))
+            .await;
+        assert_eq!(response.status_code(), 400);
+    })
+    .await;
+}
@@ -0,0 +1,229 @@
+use loco_rs::testing::prelude::*;
+use loco_rs::TestServer;
+use qcast::app::App;
+use qcast::models::media_placements;
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data::{
+    auth_header, create_book_with_chapters, create_public_media, init_user_login,
+};
+
+/// 创建书籍和“第一单元”章节，返回 (书籍, 章节) 的 ID
+async fn create_book_with_unit(
+    request: &TestServer,
+    auth: &(axum::http::HeaderName, axum::http::HeaderValue),
+    title: &str,
+) -> (i32, i32) {
+    let (book_id, chapters) = create_book_with_chapters(request, auth, title, &["第一单元"]).await;
+    (book_id, chapters[0])
+}
+
+#[tokio::test]
+#[serial]
+async fn media_can_be_placed_in_several_chapters() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let auth = auth_header(&user.token);
+
+        let (home_book, home_chapter) = create_book_with_unit(&request, &auth, "教材一").await;
+        let (other_book, other_chapter) = create_book_with_unit(&request, &auth, "教材二").await;
+        let media = create_public_media(&ctx, home_book, Some(home_chapter), user.user.id).await;
+        let url = format!("/api/media/{}/placements", media.id);
+
+        // 不能放在原始位置或重复放置
+        let response = request
+            .post(&url)
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({ "book_id": home_book, "chapter_id": home_chapter }))
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        let response = request
+            .post(&url)
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(
+                &json!({ "book_id": other_book, "chapter_id": other_chapter, "title": "听力练习" }),
+            )
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let placement = response.json::<serde_json::Value>();
+        assert_eq!(placement["sort_order"], 1);
+
+        let response = request
+            .post(&url)
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({ "book_id": other_book, "chapter_id": other_chapter }))
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        // 章节不属于目标书籍
+        let response = request
+            .post(&url)
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({ "book_id": other_book, "chapter_id": home_chapter }))
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        // 章节列表中使用位置的标题，访问令牌与原媒体相同
+        let response = request
+            .get(&format!("/api/media/by-chapter?chapter_id={other_chapter}"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        let listed = response.json::<Vec<serde_json::Value>>();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0]["id"], media.id);
+        assert_eq!(listed[0]["title"], "听力练习");
+        assert_eq!(listed[0]["access_token"], media.access_token.as_str());
+        assert_eq!(listed[0]["placement_id"], placement["id"]);
+
+        let response = request
+            .get(&format!("/api/books/{other_book}/chapters/tree"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        assert_eq!(response.json::<serde_json::Value>()[0]["media_count"], 1);
+
+        let response = request
+            .get(&format!("/api/books/{other_book}/tree"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        assert_eq!(response.json::<serde_json::Value>()["total_media_count"], 1);
+
+        let response = request
+            .get("/api/dashboard/stats")
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        let stats = response.json::<serde_json::Value>();
+        assert_eq!(stats["total_medias"], 1);
+        assert_eq!(stats["total_placements"], 1);
+
+        // 清空标题后使用媒体标题
+        let placement_url = format!("{url}/{}", placement["id"]);
+        let response = request
+            .put(&placement_url)
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({ "title": "", "sort_order": 3 }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let updated = response.json::<serde_json::Value>();
+        assert_eq!(updated["title"], serde_json::Value::Null);
+        assert_eq!(updated["sort_order"], 3);
+
+        // 删除媒体后不再出现在附加位置中
+        request
+            .delete(&format!("/api/media/{}", media.id))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        let response = request
+            .get(&format!("/api/media/by-chapter?chapter_id={other_chapter}"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        assert!(response.json::<Vec<serde_json::Value>>().is_empty());
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn removing_placement_keeps_media() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let auth = auth_header(&user.token);
+
+        let (home_book, _) = create_book_with_unit(&request, &auth, "教材一").await;
+        let (other_book, _) = create_book_with_unit(&request, &auth, "教材二").await;
+        let media = create_public_media(&ctx, home_book, None, user.user.id).await;
+        let url = format!("/api/media/{}/placements", media.id);
+
+        let response = request
+            .post(&url)
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({ "book_id": other_book }))
+            .await;
+        let placement_id = response.json::<serde_json::Value>()["id"].clone();
+
+        let response = request
+            .get(&url)
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        assert_eq!(response.json::<Vec<serde_json::Value>>().len(), 1);
+
+        let response = request
+            .delete(&format!("{url}/{placement_id}"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let response = request
+            .get(&url)
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        assert!(response.json::<Vec<serde_json::Value>>().is_empty());
+        let response = request
+            .get(&format!("/api/media/{}", media.id))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn placed_media_is_published_with_the_target_book() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let auth = auth_header(&user.token);
+
+        // 原始书籍未公开，附加到公开书籍后可以通过该书籍访问
+        let (home_book, _) = create_book_with_unit(&request, &auth, "教材一").await;
+        let (other_book, other_chapter) = create_book_with_unit(&request, &auth, "教材二").await;
+        request
+            .put(&format!("/api/books/{other_book}"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(&json!({ "is_public": true }))
+            .await;
+        let media = create_public_media(&ctx, home_book, None, user.user.id).await;
+        let info_url = format!("/api/public/media/{}/info", media.access_token);
+        let response = request.get(&info_url).await;
+        assert_eq!(response.status_code(), 404);
+
+        let response = request
+            .post(&format!("/api/media/{}/placements", media.id))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .json(
+                &json!({ "book_id": other_book, "chapter_id": other_chapter, "title": "听力练习" }),
+            )
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let response = request.get(&info_url).await;
+        assert_eq!(response.status_code(), 200);
+        let response = request
+            .get(&format!("/api/public/books/{other_book}/feed.xml"))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let xml = response.text();
+        assert!(xml.contains("<title>听力练习</title>"));
+        assert!(xml.contains(&media.access_token));
+        let response = request.get(&format!("/embed/books/{other_book}")).await;
+        assert_eq!(response.status_code(), 200);
+        assert!(response.text().contains("听力练习"));
+
+        // 章节移入回收站后不再统计其中的附加媒体
+        let stats = media_placements::Model::book_stats(&ctx.db, user.user.id)
+            .await
+            .unwrap();
+        assert_eq!(stats.get(&other_book), Some(&(1, 120)));
+        let response = request
+            .delete(&format!("/api/books/{other_book}/chapters/{other_chapter}"))
+            .add_header(auth.0.clone(), auth.1.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let stats = media_placements::Model::book_stats(&ctx.db, user.user.id)
+            .await
+            .unwrap();
+        assert!(stats.get(&other_book).is_none());
+    })
+    .await;
+}
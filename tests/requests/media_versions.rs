@@ -0,0 +1,113 @@
+use loco_rs::testing::prelude::*;
+use qcast::app::App;
+use qcast::models::_entities::medias;
+use qcast::models::media_versions;
+use qcast::services::media_versions as versions;
+use sea_orm::EntityTrait;
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data::{auth_header, init_user_login, media_form, multipart_content_type};
+
+#[tokio::test]
+#[serial]
+async fn replaced_files_are_kept_and_can_be_rolled_back() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&user.token);
+
+        let response = request
+            .post("/api/books")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "title": "版本测试" }))
+            .await;
+        let book_id = response.json::<serde_json::Value>()["id"].as_i64().unwrap();
+
+        let response = request
+            .post("/api/media/upload")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .content_type(&multipart_content_type())
+            .bytes(
+                media_form(
+                    &[
+                        ("title", "第一课".to_string()),
+                        ("book_id", book_id.to_string()),
+                    ],
+                    "lesson-v1.mp3",
+                    "version one",
+                )
+                .into(),
+            )
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let media = response.json::<serde_json::Value>();
+        let media_id = media["id"].as_i64().unwrap();
+        let access_token = media["access_token"].as_str().unwrap().to_string();
+
+        for name in ["lesson-v2.mp3", "lesson-v3.mp3"] {
+            let response = request
+                .put(&format!("/api/media/{media_id}/replace-file"))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .content_type(&multipart_content_type())
+                .bytes(media_form(&[], name, name).into())
+                .await;
+            assert_eq!(response.status_code(), 200);
+        }
+
+        let response = request
+            .get(&format!("/api/media/{media_id}/versions"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let list = response.json::<serde_json::Value>();
+        let list = list.as_array().unwrap();
+        assert_eq!(list.len(), 3);
+        assert_eq!(list[0]["version"], 3);
+        assert_eq!(list[0]["is_current"], true);
+        assert_eq!(list[2]["original_filename"], "lesson-v1.mp3");
+        assert_eq!(list[2]["uploaded_by"], user.user.id);
+
+        let response = request
+            .post(&format!("/api/media/{media_id}/versions/1/rollback"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let media = response.json::<serde_json::Value>();
+        assert_eq!(media["file_version"], 1);
+        assert_eq!(media["original_filename"], "lesson-v1.mp3");
+        assert_eq!(media["access_token"], access_token.as_str());
+
+        // 回滚到当前版本没有意义
+        let response = request
+            .post(&format!("/api/media/{media_id}/versions/1/rollback"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        // 回滚后再次替换，版本号继续递增
+        let response = request
+            .put(&format!("/api/media/{media_id}/replace-file"))
+            .add_header(auth_key, auth_value)
+            .content_type(&multipart_content_type())
+            .bytes(media_form(&[], "lesson-v4.mp3", "v4").into())
+            .await;
+        assert_eq!(response.json::<serde_json::Value>()["file_version"], 4);
+
+        // 只保留两个版本时，当前版本和最近的一个旧版本留下
+        let media = medias::Entity::find_by_id(i32::try_from(media_id).unwrap())
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        let removed = versions::prune(&ctx.db, &media, 2).await.unwrap();
+        assert_eq!(removed, 2);
+        let remaining: Vec<i32> = media_versions::Model::find_by_media(&ctx.db, media.id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|v| v.version)
+            .collect();
+        assert_eq!(remaining, vec![4, 3]);
+    })
+    .await;
+}
@@ -1,8 +1,30 @@
 mod admin_groups;
+mod admin_storage;
 mod admin_users;
+mod audio_processing;
+mod audit_logs;
 mod auth;
+mod book_hierarchy;
+mod content_store;
+mod content_transfer;
+mod dashboard;
+mod embed;
+mod feeds;
+mod landing;
+mod media_clips;
+mod media_ordering;
+mod media_placements;
+mod media_versions;
+mod oidc;
 mod prepare_data;
+mod publishing;
+mod rate_limit;
 mod site_settings;
+mod subtitles;
+mod tokens;
+mod trash;
+mod two_factor;
+mod webhooks;
 
 pub mod books;
 pub mod chapters;
@@ -1,6 +1,10 @@
 use axum::http::{HeaderName, HeaderValue};
 use loco_rs::{app::AppContext, TestServer};
-use qcast::{models::users, views::auth::LoginResponse};
+use qcast::{
+    models::{user_totp, users},
+    services::totp,
+    views::auth::{LoginResponse, MfaChallengeResponse},
+};
 use sea_orm::{ActiveModelTrait, ActiveValue::Set};
 
 const USER_EMAIL: &str = "test@loco.com";
@@ -51,6 +55,49 @@ pub async fn init_user_login(request: &TestServer, ctx: &AppContext) -> LoggedIn
     }
 }
 
+/// 为用户启用两步验证，并通过密码 + 验证码两步登录
+pub async fn login_with_two_factor(
+    request: &TestServer,
+    ctx: &AppContext,
+    user: &users::Model,
+    email: &str,
+    password: &str,
+) -> String {
+    let two_factor = user_totp::Model::begin_setup(&ctx.db, user.id)
+        .await
+        .unwrap();
+    let secret = two_factor.secret.clone();
+    let code = totp::code_at(&secret, unix_now()).unwrap();
+    two_factor.enable(&ctx.db, &code).await.unwrap().unwrap();
+
+    let response = request
+        .post("/api/auth/login")
+        .json(&serde_json::json!({
+            "email": email,
+            "password": password
+        }))
+        .await;
+    let challenge: MfaChallengeResponse = serde_json::from_str(&response.text()).unwrap();
+
+    // 绑定时已使用当前时间窗口的验证码，使用下一个窗口的验证码
+    let code = totp::code_at(&secret, unix_now() + totp::PERIOD).unwrap();
+    let response = request
+        .post("/api/auth/login/mfa")
+        .json(&serde_json::json!({
+            "mfa_token": challenge.mfa_token,
+            "code": code
+        }))
+        .await;
+    let login_response: LoginResponse = serde_json::from_str(&response.text()).unwrap();
+
+    login_response.token
+}
+
+/// 当前 Unix 时间（秒）
+pub fn unix_now() -> u64 {
+    u64::try_from(chrono::Utc::now().timestamp()).unwrap()
+}
+
 /// 创建超级管理员用户并登录
 pub async fn init_superadmin_login(request: &TestServer, ctx: &AppContext) -> LoggedInUser {
     let admin_email = "admin@loco.com";
@@ -79,21 +126,10 @@ pub async fn init_superadmin_login(request: &TestServer, ctx: &AppContext) -> Lo
     active_user.email_verified_at = Set(Some(chrono::Local::now().into()));
     user = active_user.update(&ctx.db).await.unwrap();
 
-    // 登录
-    let response = request
-        .post("/api/auth/login")
-        .json(&serde_json::json!({
-            "email": admin_email,
-            "password": admin_password
-        }))
-        .await;
-
-    let login_response: LoginResponse = serde_json::from_str(&response.text()).unwrap();
+    // 管理员必须启用两步验证
+    let token = login_with_two_factor(request, ctx, &user, admin_email, admin_password).await;
 
-    LoggedInUser {
-        user,
-        token: login_response.token,
-    }
+    LoggedInUser { user, token }
 }
 
 /// 创建普通员工（is_staff=true, is_superuser=false）
@@ -124,27 +160,169 @@ pub async fn init_staff_login(request: &TestServer, ctx: &AppContext) -> LoggedI
     active_user.email_verified_at = Set(Some(chrono::Local::now().into()));
     user = active_user.update(&ctx.db).await.unwrap();
 
-    // 登录
+    // 管理员必须启用两步验证
+    let token = login_with_two_factor(request, ctx, &user, staff_email, staff_password).await;
+
+    LoggedInUser { user, token }
+}
+
+pub fn auth_header(token: &str) -> (HeaderName, HeaderValue) {
+    let auth_header_value = HeaderValue::from_str(&format!("Bearer {}", &token)).unwrap();
+
+    (HeaderName::from_static("authorization"), auth_header_value)
+}
+
+/// 创建书籍，返回书籍 ID
+pub async fn create_book(
+    request: &TestServer,
+    auth: &(HeaderName, HeaderValue),
+    payload: serde_json::Value,
+) -> i32 {
     let response = request
-        .post("/api/auth/login")
-        .json(&serde_json::json!({
-            "email": staff_email,
-            "password": staff_password
-        }))
+        .post("/api/books")
+        .add_header(auth.0.clone(), auth.1.clone())
+        .json(&payload)
         .await;
+    assert_eq!(response.status_code(), 200);
+    i32::try_from(response.json::<serde_json::Value>()["id"].as_i64().unwrap()).unwrap()
+}
 
-    let login_response: LoginResponse = serde_json::from_str(&response.text()).unwrap();
+/// 在书籍下创建章节，返回章节 ID
+pub async fn create_chapter(
+    request: &TestServer,
+    auth: &(HeaderName, HeaderValue),
+    book_id: i32,
+    payload: serde_json::Value,
+) -> i32 {
+    let response = request
+        .post(&format!("/api/books/{book_id}/chapters"))
+        .add_header(auth.0.clone(), auth.1.clone())
+        .json(&payload)
+        .await;
+    assert_eq!(response.status_code(), 200);
+    i32::try_from(response.json::<serde_json::Value>()["id"].as_i64().unwrap()).unwrap()
+}
 
-    LoggedInUser {
-        user,
-        token: login_response.token,
+/// 创建书籍和若干顶层章节，返回书籍 ID 和按顺序排列的章节 ID
+pub async fn create_book_with_chapters(
+    request: &TestServer,
+    auth: &(HeaderName, HeaderValue),
+    title: &str,
+    chapters: &[&str],
+) -> (i32, Vec<i32>) {
+    let book_id = create_book(request, auth, serde_json::json!({ "title": title })).await;
+    let mut chapter_ids = Vec::new();
+    for chapter in chapters {
+        chapter_ids.push(
+            create_chapter(
+                request,
+                auth,
+                book_id,
+                serde_json::json!({ "title": chapter }),
+            )
+            .await,
+        );
     }
+    (book_id, chapter_ids)
 }
 
-pub fn auth_header(token: &str) -> (HeaderName, HeaderValue) {
-    let auth_header_value = HeaderValue::from_str(&format!("Bearer {}", &token)).unwrap();
+/// 测试上传请求使用的 multipart 分隔符
+pub const MULTIPART_BOUNDARY: &str = "qcast-test-boundary";
 
-    (HeaderName::from_static("authorization"), auth_header_value)
+/// multipart 上传请求的 Content-Type
+pub fn multipart_content_type() -> String {
+    format!("multipart/form-data; boundary={MULTIPART_BOUNDARY}")
+}
+
+/// 构造上传文件的 multipart 请求体：若干普通字段加一个 `file` 字段
+pub fn upload_form(
+    fields: &[(&str, String)],
+    filename: &str,
+    content_type: &str,
+    content: &[u8],
+) -> Vec<u8> {
+    let mut body = Vec::new();
+    for (name, value) in fields {
+        body.extend_from_slice(
+            format!(
+                "--{MULTIPART_BOUNDARY}\r\nContent-Disposition: form-data; name=\"{name}\"\r\n\r\n{value}\r\n"
+            )
+            .as_bytes(),
+        );
+    }
+    body.extend_from_slice(
+        format!(
+            "--{MULTIPART_BOUNDARY}\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"{filename}\"\r\n\
+             Content-Type: {content_type}\r\n\r\n"
+        )
+        .as_bytes(),
+    );
+    body.extend_from_slice(content);
+    body.extend_from_slice(format!("\r\n--{MULTIPART_BOUNDARY}--\r\n").as_bytes());
+    body
+}
+
+/// 构造上传 MP3 的 multipart 请求体
+pub fn media_form(fields: &[(&str, String)], filename: &str, content: &str) -> Vec<u8> {
+    upload_form(fields, filename, "audio/mpeg", content.as_bytes())
+}
+
+/// 8 kHz 单声道 16 位 PCM WAV
+pub fn pcm_wav(samples: &[i16]) -> Vec<u8> {
+    let data_len = u32::try_from(samples.len() * 2).unwrap();
+    let mut wav = Vec::new();
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&8000u32.to_le_bytes());
+    wav.extend_from_slice(&16_000u32.to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
+
+/// 0.5 秒静音、1 秒较轻的正弦波、0.5 秒静音
+pub fn quiet_wav() -> Vec<u8> {
+    let samples: Vec<i16> = (0..16_000u32)
+        .map(|i| {
+            if (4000..12_000).contains(&i) {
+                let t = f64::from(i) / 8000.0;
+                #[allow(clippy::cast_possible_truncation)]
+                let value = (1600.0 * (2.0 * std::f64::consts::PI * 440.0 * t).sin()) as i16;
+                value
+            } else {
+                0
+            }
+        })
+        .collect();
+    pcm_wav(&samples)
+}
+
+/// 采样值依次递增的 WAV，便于核对剪辑位置
+pub fn counting_wav(seconds: u32) -> Vec<u8> {
+    let samples: Vec<i16> = (0..seconds * 8000)
+        .map(|i| i16::try_from(i % 20_000).unwrap() - 10_000)
+        .collect();
+    pcm_wav(&samples)
+}
+
+/// MPEG-1 Layer III、128 kbps、44.1 kHz 单声道的静音帧长度
+pub const MP3_FRAME_SIZE: usize = 417;
+
+/// 由静音帧组成的 MP3，每帧 1152 个采样（约 26 毫秒）
+pub fn silent_mp3(frames: usize) -> Vec<u8> {
+    let mut frame = vec![0u8; MP3_FRAME_SIZE];
+    frame[..4].copy_from_slice(&[0xFF, 0xFB, 0x90, 0xC0]);
+    frame.repeat(frames)
 }
 
 /// 创建公开的测试媒体记录
@@ -0,0 +1,125 @@
+use chrono::{Duration, Utc};
+use loco_rs::testing::prelude::*;
+use qcast::app::App;
+use qcast::models::_entities::medias;
+use qcast::services::publishing;
+use sea_orm::EntityTrait;
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data::{auth_header, create_public_media, init_user_login};
+
+#[tokio::test]
+#[serial]
+async fn media_is_only_available_inside_its_window() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&user.token);
+
+        let response = request
+            .post("/api/books")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "title": "定时发布", "is_public": true }))
+            .await;
+        let book_id = response.json::<serde_json::Value>()["id"].as_i64().unwrap();
+        let media =
+            create_public_media(&ctx, i32::try_from(book_id).unwrap(), None, user.user.id).await;
+        let info_url = format!("/api/public/media/{}/info", media.access_token);
+
+        // 下架时间必须晚于发布时间
+        let response = request
+            .put(&format!("/api/media/{}/schedule", media.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({
+                "publish_at": Utc::now() + Duration::days(2),
+                "unpublish_at": Utc::now() + Duration::days(1),
+            }))
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        // 发布时间在未来：先取消公开，访问返回 403
+        let response = request
+            .put(&format!("/api/media/{}/schedule", media.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "publish_at": Utc::now() + Duration::days(1) }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert_eq!(response.json::<serde_json::Value>()["is_public"], false);
+        assert_eq!(request.get(&info_url).await.status_code(), 403);
+
+        // 发布时间已到，后台任务尚未运行时也可以访问
+        request
+            .put(&format!("/api/media/{}/schedule", media.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({
+                "publish_at": Utc::now() - Duration::minutes(1),
+                "unpublish_at": Utc::now() + Duration::days(30),
+            }))
+            .await;
+        assert_eq!(request.get(&info_url).await.status_code(), 200);
+
+        let summary = publishing::run_due(&ctx).await.unwrap();
+        assert_eq!(summary.published_medias, 1);
+        let updated = medias::Entity::find_by_id(media.id)
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(updated.is_public);
+        assert!(updated.publish_at.is_none());
+
+        // 已过下架时间返回 410，后台任务取消公开
+        request
+            .put(&format!("/api/media/{}/schedule", media.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "unpublish_at": Utc::now() - Duration::minutes(1) }))
+            .await;
+        assert_eq!(request.get(&info_url).await.status_code(), 410);
+        let summary = publishing::run_due(&ctx).await.unwrap();
+        assert_eq!(summary.unpublished_medias, 1);
+        assert_eq!(request.get(&info_url).await.status_code(), 410);
+
+        // 手动重新发布会清除已过期的下架时间
+        let response = request
+            .post(&format!("/api/media/{}/publish", media.id))
+            .add_header(auth_key, auth_value)
+            .await;
+        let body = response.json::<serde_json::Value>();
+        assert_eq!(body["is_public"], true);
+        assert!(body["unpublish_at"].is_null());
+        assert_eq!(request.get(&info_url).await.status_code(), 200);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn scheduled_book_feed_is_forbidden_before_publish_time() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&user.token);
+
+        let response = request
+            .post("/api/books")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "title": "下学期课程", "is_public": true }))
+            .await;
+        let book_id = response.json::<serde_json::Value>()["id"].as_i64().unwrap();
+
+        let response = request
+            .put(&format!("/api/books/{book_id}/schedule"))
+            .add_header(auth_key, auth_value)
+            .json(&json!({ "publish_at": Utc::now() + Duration::days(7) }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let body = response.json::<serde_json::Value>();
+        assert_eq!(body["is_public"], false);
+        assert!(body["publish_at"].is_string());
+
+        let response = request
+            .get(&format!("/api/public/books/{book_id}/feed.xml"))
+            .await;
+        assert_eq!(response.status_code(), 403);
+    })
+    .await;
+}
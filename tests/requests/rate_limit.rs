@@ -0,0 +1,151 @@
+use loco_rs::testing::prelude::*;
+use qcast::app::App;
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data::{auth_header, init_superadmin_login, init_user_login};
+
+#[tokio::test]
+#[serial]
+async fn account_is_locked_after_repeated_failures() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in_user = init_user_login(&request, &ctx).await;
+
+        for _ in 0..5 {
+            let response = request
+                .post("/api/auth/login")
+                .json(&json!({ "email": "test@loco.com", "password": "wrong" }))
+                .await;
+            assert_eq!(response.status_code(), 401);
+        }
+
+        // 锁定期间正确的密码也不能登录
+        let response = request
+            .post("/api/auth/login")
+            .json(&json!({ "email": "test@loco.com", "password": "1234" }))
+            .await;
+        assert_eq!(response.status_code(), 429);
+        let retry_after: u64 = response
+            .header("retry-after")
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(retry_after > 0 && retry_after <= 60);
+
+        // 普通用户不能解锁
+        let (auth_key, auth_value) = auth_header(&logged_in_user.token);
+        let unlock_url = format!("/api/admin/users/{}/unlock", logged_in_user.user.id);
+        let response = request
+            .post(&unlock_url)
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        let admin = init_superadmin_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&admin.token);
+        let response = request
+            .post(&unlock_url)
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let response = request
+            .post("/api/auth/login")
+            .json(&json!({ "email": "test@loco.com", "password": "1234" }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        // 未注册的邮箱与已注册账号的失败和锁定响应一致
+        for _ in 0..5 {
+            let response = request
+                .post("/api/auth/login")
+                .json(&json!({ "email": "nobody@loco.com", "password": "wrong" }))
+                .await;
+            assert_eq!(response.status_code(), 401);
+        }
+        let response = request
+            .post("/api/auth/login")
+            .json(&json!({ "email": "nobody@loco.com", "password": "1234" }))
+            .await;
+        assert_eq!(response.status_code(), 429);
+        assert!(response.maybe_header("retry-after").is_some());
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn requests_are_limited_per_ip() {
+    request::<App, _, _>(|request, _ctx| async move {
+        // 测试请求来自本机，把本机和内网代理配置为可信代理
+        std::env::set_var("TRUSTED_PROXIES", "127.0.0.1,10.0.0.0/8");
+        let payload = json!({ "email": "nobody@example.com" });
+
+        for _ in 0..30 {
+            let response = request
+                .post("/api/auth/forgot")
+                .add_header("x-forwarded-for", "198.51.100.23")
+                .json(&payload)
+                .await;
+            assert_eq!(response.status_code(), 200);
+        }
+
+        // 经过多层可信代理时取最右边的不可信地址
+        let response = request
+            .post("/api/auth/forgot")
+            .add_header("x-forwarded-for", "198.51.100.23, 10.0.0.1")
+            .json(&payload)
+            .await;
+        assert_eq!(response.status_code(), 429);
+        let body: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(body["error"], "too_many_requests");
+
+        // 客户端在最左边伪造的地址不起作用
+        let response = request
+            .post("/api/auth/forgot")
+            .add_header("x-forwarded-for", "203.0.113.50, 198.51.100.23")
+            .json(&payload)
+            .await;
+        assert_eq!(response.status_code(), 429);
+
+        // 其他来源不受影响
+        let response = request
+            .post("/api/auth/forgot")
+            .add_header("x-forwarded-for", "198.51.100.24")
+            .json(&payload)
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        // 未配置可信代理时忽略请求头，按连接地址计数
+        std::env::remove_var("TRUSTED_PROXIES");
+        let response = request
+            .post("/api/auth/forgot")
+            .add_header("x-forwarded-for", "198.51.100.23")
+            .json(&payload)
+            .await;
+        assert_eq!(response.status_code(), 200);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn emails_are_limited_per_account_without_revealing_it() {
+    request::<App, _, _>(|request, ctx| async move {
+        init_user_login(&request, &ctx).await;
+
+        for _ in 0..5 {
+            let response = request
+                .post("/api/auth/forgot")
+                .json(&json!({ "email": "test@loco.com" }))
+                .await;
+            assert_eq!(response.status_code(), 200);
+        }
+
+        // 欢迎邮件 + 3 封找回密码邮件
+        let deliveries = ctx.mailer.unwrap().deliveries();
+        assert_eq!(deliveries.count, 4);
+    })
+    .await;
+}
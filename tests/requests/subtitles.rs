@@ -0,0 +1,123 @@
+use loco_rs::testing::prelude::*;
+use qcast::app::App;
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data::{auth_header, create_public_media, init_user_login};
+
+const BOUNDARY: &str = "qcast-subtitle-boundary";
+
+/// 构造字幕上传的 multipart 请求体
+fn subtitle_form(filename: &str, content: &str, language: &str) -> Vec<u8> {
+    format!(
+        "--{BOUNDARY}\r\n\
+         Content-Disposition: form-data; name=\"language\"\r\n\r\n\
+         {language}\r\n\
+         --{BOUNDARY}\r\n\
+         Content-Disposition: form-data; name=\"label\"\r\n\r\n\
+         简体中文\r\n\
+         --{BOUNDARY}\r\n\
+         Content-Disposition: form-data; name=\"file\"; filename=\"{filename}\"\r\n\
+         Content-Type: application/octet-stream\r\n\r\n\
+         {content}\r\n\
+         --{BOUNDARY}--\r\n"
+    )
+    .into_bytes()
+}
+
+#[tokio::test]
+#[serial]
+async fn can_upload_srt_and_serve_as_vtt() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in_user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&logged_in_user.token);
+
+        let response = request
+            .post("/api/books")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "title": "字幕测试书籍", "is_public": true }))
+            .await;
+        let book: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        let book_id = book["id"].as_i64().unwrap() as i32;
+        let media = create_public_media(&ctx, book_id, None, logged_in_user.user.id).await;
+
+        let srt = "1\r\n00:00:01,000 --> 00:00:02,500\r\n大家好\r\n\r\n2\r\n00:00:03,000 --> 00:00:04,000\r\n今天学习第一课\r\n";
+        let response = request
+            .post(&format!("/api/media/{}/subtitles", media.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .content_type(&format!("multipart/form-data; boundary={BOUNDARY}"))
+            .bytes(subtitle_form("lesson.srt", srt, "zh-CN").into())
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let subtitle: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(subtitle["language"], "zh-CN");
+        assert_eq!(subtitle["kind"], "subtitles");
+        assert_eq!(subtitle["source_format"], "srt");
+        assert_eq!(subtitle["cue_count"], 2);
+
+        // 公开信息中包含字幕轨道
+        let response = request
+            .get(&format!("/api/public/media/{}/info", media.access_token))
+            .await;
+        let info: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        let tracks = info["tracks"].as_array().unwrap();
+        assert_eq!(tracks.len(), 1);
+        assert_eq!(tracks[0]["label"], "简体中文");
+
+        let response = request.get(tracks[0]["url"].as_str().unwrap()).await;
+        assert_eq!(response.status_code(), 200);
+        assert_eq!(
+            response.text(),
+            "WEBVTT\n\n00:00:01.000 --> 00:00:02.500\n大家好\n\n00:00:03.000 --> 00:00:04.000\n今天学习第一课\n\n"
+        );
+
+        let response = request
+            .get(&format!(
+                "/api/media/{}/subtitles/{}?format=txt",
+                media.id, subtitle["id"]
+            ))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert_eq!(response.text(), "大家好\n今天学习第一课");
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn cannot_upload_subtitle_with_invalid_timing() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in_user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&logged_in_user.token);
+
+        let response = request
+            .post("/api/books")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "title": "字幕测试书籍" }))
+            .await;
+        let book: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        let book_id = book["id"].as_i64().unwrap() as i32;
+        let media = create_public_media(&ctx, book_id, None, logged_in_user.user.id).await;
+
+        let vtt = "WEBVTT\n\n00:00:05.000 --> 00:00:03.000\n结束时间早于开始时间\n";
+        let response = request
+            .post(&format!("/api/media/{}/subtitles", media.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .content_type(&format!("multipart/form-data; boundary={BOUNDARY}"))
+            .bytes(subtitle_form("lesson.vtt", vtt, "zh-CN").into())
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        let vtt = "WEBVTT\n\n00:00:01.000 --> 00:00:03.000\n正常字幕\n";
+        let response = request
+            .post(&format!("/api/media/{}/subtitles", media.id))
+            .add_header(auth_key, auth_value)
+            .content_type(&format!("multipart/form-data; boundary={BOUNDARY}"))
+            .bytes(subtitle_form("lesson.vtt", vtt, "Chinese").into())
+            .await;
+        assert_eq!(response.status_code(), 400);
+    })
+    .await;
+}
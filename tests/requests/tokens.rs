@@ -0,0 +1,234 @@
+use axum::http::{HeaderName, HeaderValue};
+use loco_rs::testing::prelude::*;
+use loco_rs::TestServer;
+use qcast::app::App;
+use sea_orm::IntoActiveModel;
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data::{auth_header, init_staff_login, init_superadmin_login, init_user_login};
+
+#[tokio::test]
+#[serial]
+async fn can_use_scoped_personal_access_token() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in_user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&logged_in_user.token);
+
+        let response = request
+            .post("/api/tokens")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "name": "CI 只读", "scopes": ["books:read"] }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let created: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        let token = created["token"].as_str().unwrap().to_string();
+        assert!(token.starts_with("qcp_"));
+        assert_eq!(created["scopes"], json!(["books:read"]));
+        assert!(created["last_used_at"].is_null());
+
+        // 列表中不返回明文
+        let response = request
+            .get("/api/tokens")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let tokens: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(tokens.as_array().unwrap().len(), 1);
+        assert!(tokens[0].get("token").is_none());
+        assert!(token.starts_with(tokens[0]["token_prefix"].as_str().unwrap()));
+
+        // 只读令牌可以读取书籍
+        let (token_key, token_value) = auth_header(&token);
+        let response = request
+            .get("/api/books")
+            .add_header(token_key.clone(), token_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        // 也可以通过 X-API-Key 传递
+        let response = request
+            .get("/api/books")
+            .add_header(
+                HeaderName::from_static("x-api-key"),
+                HeaderValue::from_str(&token).unwrap(),
+            )
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        // 但不能创建书籍
+        let response = request
+            .post("/api/books")
+            .add_header(token_key.clone(), token_value.clone())
+            .json(&json!({ "title": "令牌创建的书籍" }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        // 令牌不能用于管理令牌
+        let response = request
+            .get("/api/tokens")
+            .add_header(token_key.clone(), token_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        // 使用后记录了最后使用时间
+        let response = request
+            .get("/api/tokens")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        let tokens: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        assert!(tokens[0]["last_used_at"].is_string());
+
+        // 吊销后不可再使用
+        let token_id = created["id"].as_i64().unwrap();
+        let response = request
+            .delete(&format!("/api/tokens/{token_id}"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let revoked: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        assert!(revoked["revoked_at"].is_string());
+
+        let response = request
+            .get("/api/books")
+            .add_header(token_key, token_value)
+            .await;
+        assert_eq!(response.status_code(), 401);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn cannot_create_token_with_invalid_scopes() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in_user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&logged_in_user.token);
+
+        let response = request
+            .post("/api/tokens")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "name": "错误", "scopes": ["books:delete"] }))
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        let response = request
+            .post("/api/tokens")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "name": "空权限", "scopes": [] }))
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        // 普通用户不能创建 admin 令牌
+        let response = request
+            .post("/api/tokens")
+            .add_header(auth_key, auth_value)
+            .json(&json!({ "name": "管理", "scopes": ["admin"] }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn rejects_unknown_token() {
+    request::<App, _, _>(|request, _ctx| async move {
+        let (token_key, token_value) = auth_header("qcp_0000000000000000");
+        let response = request
+            .get("/api/books")
+            .add_header(token_key, token_value)
+            .await;
+        assert_eq!(response.status_code(), 401);
+    })
+    .await;
+}
+
+/// 创建个人访问令牌，返回令牌明文
+async fn create_token(
+    request: &TestServer,
+    login_token: &str,
+    scopes: serde_json::Value,
+) -> String {
+    let (auth_key, auth_value) = auth_header(login_token);
+    let response = request
+        .post("/api/tokens")
+        .add_header(auth_key, auth_value)
+        .json(&json!({ "name": "自动化", "scopes": scopes }))
+        .await;
+    assert_eq!(response.status_code(), 200);
+    let created: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+    created["token"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+#[serial]
+async fn admin_scope_is_required_for_admin_endpoints() {
+    request::<App, _, _>(|request, ctx| async move {
+        let admin = init_superadmin_login(&request, &ctx).await;
+        let admin_token = create_token(&request, &admin.token, json!(["admin"])).await;
+        let read_token = create_token(&request, &admin.token, json!(["books:read"])).await;
+
+        let (token_key, token_value) = auth_header(&admin_token);
+        let response = request
+            .get("/api/admin/users/stats")
+            .add_header(token_key, token_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        // 管理员的其他令牌不能访问管理接口
+        let (token_key, token_value) = auth_header(&read_token);
+        let response = request
+            .get("/api/admin/users/stats")
+            .add_header(token_key, token_value)
+            .await;
+        assert_eq!(response.status_code(), 401);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn tokens_are_revoked_on_demotion_and_password_change() {
+    request::<App, _, _>(|request, ctx| async move {
+        let admin = init_superadmin_login(&request, &ctx).await;
+        let staff = init_staff_login(&request, &ctx).await;
+        let staff_token = create_token(&request, &staff.token, json!(["admin"])).await;
+        let (token_key, token_value) = auth_header(&staff_token);
+        let response = request
+            .get("/api/books")
+            .add_header(token_key.clone(), token_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        // 降级为普通用户后令牌失效
+        let (auth_key, auth_value) = auth_header(&admin.token);
+        let response = request
+            .put(&format!("/api/admin/users/{}/role", staff.user.id))
+            .add_header(auth_key, auth_value)
+            .json(&json!({ "is_staff": false, "is_superuser": false }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let response = request
+            .get("/api/books")
+            .add_header(token_key, token_value)
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        // 修改密码后令牌失效
+        let user = init_user_login(&request, &ctx).await;
+        let user_token = create_token(&request, &user.token, json!(["books:read"])).await;
+        user.user
+            .into_active_model()
+            .reset_password(&ctx.db, "new-password")
+            .await
+            .unwrap();
+        let (token_key, token_value) = auth_header(&user_token);
+        let response = request
+            .get("/api/books")
+            .add_header(token_key, token_value)
+            .await;
+        assert_eq!(response.status_code(), 401);
+    })
+    .await;
+}
@@ -0,0 +1,171 @@
+use loco_rs::testing::prelude::*;
+use qcast::app::App;
+use qcast::models::_entities::chapters;
+use sea_orm::EntityTrait;
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data::{auth_header, create_public_media, init_user_login};
+
+#[tokio::test]
+#[serial]
+async fn deleted_book_can_be_restored_with_its_content() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&user.token);
+
+        let response = request
+            .post("/api/books")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "title": "回收站书籍", "is_public": true }))
+            .await;
+        let book_id = response.json::<serde_json::Value>()["id"].as_i64().unwrap();
+        let response = request
+            .post(&format!("/api/books/{book_id}/chapters"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "title": "第一章" }))
+            .await;
+        let chapter_id = response.json::<serde_json::Value>()["id"].as_i64().unwrap();
+        let media = create_public_media(
+            &ctx,
+            i32::try_from(book_id).unwrap(),
+            Some(i32::try_from(chapter_id).unwrap()),
+            user.user.id,
+        )
+        .await;
+
+        let response = request
+            .delete(&format!("/api/books/{book_id}"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        // 公开链接返回 410
+        let response = request
+            .get(&format!("/api/public/media/{}/info", media.access_token))
+            .await;
+        assert_eq!(response.status_code(), 410);
+
+        // 只列出直接删除的书籍，章节和媒体随书籍一起恢复
+        let response = request
+            .get("/api/trash")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let body = response.json::<serde_json::Value>();
+        assert_eq!(body["books"].as_array().unwrap().len(), 1);
+        assert_eq!(body["books"][0]["id"], book_id);
+        assert!(body["chapters"].as_array().unwrap().is_empty());
+        assert!(body["medias"].as_array().unwrap().is_empty());
+
+        // 书籍在回收站中时不能单独恢复媒体
+        let response = request
+            .post(&format!("/api/trash/medias/{}/restore", media.id))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        let response = request
+            .post(&format!("/api/trash/books/{book_id}/restore"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let response = request
+            .get(&format!("/api/books/{book_id}/chapters/{chapter_id}"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let response = request
+            .get(&format!("/api/public/media/{}/info", media.access_token))
+            .await;
+        assert_eq!(response.status_code(), 200);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn restored_chapter_moves_to_root_when_parent_is_trashed() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&user.token);
+
+        let response = request
+            .post("/api/books")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "title": "章节恢复" }))
+            .await;
+        let book_id = response.json::<serde_json::Value>()["id"].as_i64().unwrap();
+        let response = request
+            .post(&format!("/api/books/{book_id}/chapters"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "title": "上级章节" }))
+            .await;
+        let parent_id = response.json::<serde_json::Value>()["id"].as_i64().unwrap();
+        let response = request
+            .post(&format!("/api/books/{book_id}/chapters"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "title": "子章节", "parent_id": parent_id }))
+            .await;
+        let child_id = response.json::<serde_json::Value>()["id"].as_i64().unwrap();
+
+        // 先单独删除子章节，再删除上级章节
+        for id in [child_id, parent_id] {
+            let response = request
+                .delete(&format!("/api/books/{book_id}/chapters/{id}"))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await;
+            assert_eq!(response.status_code(), 200);
+        }
+
+        let response = request
+            .get("/api/trash")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(
+            response.json::<serde_json::Value>()["chapters"]
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+
+        let response = request
+            .post(&format!("/api/trash/chapters/{child_id}/restore"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let child = chapters::Entity::find_by_id(i32::try_from(child_id).unwrap())
+            .one(&ctx.db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(child.deleted_at.is_none());
+        assert_eq!(child.parent_id, None);
+        assert_eq!(child.level, Some(0));
+
+        // 永久删除上级章节
+        let response = request
+            .delete(&format!("/api/trash/chapters/{parent_id}"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert!(
+            chapters::Entity::find_by_id(i32::try_from(parent_id).unwrap())
+                .one(&ctx.db)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        // 未在回收站中的章节不能直接永久删除
+        let response = request
+            .delete(&format!("/api/trash/chapters/{child_id}"))
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 404);
+    })
+    .await;
+}
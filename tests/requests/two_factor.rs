@@ -0,0 +1,232 @@
+use loco_rs::testing::prelude::*;
+use qcast::{
+    app::App,
+    models::users,
+    services::{rate_limit::RATE_LIMITER, totp},
+    views::auth::{
+        LoginResponse, MfaChallengeResponse, RecoveryCodesResponse, TwoFactorSetupResponse,
+    },
+};
+use sea_orm::{ActiveModelTrait, ActiveValue::Set};
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data::{auth_header, init_user_login, login_with_two_factor, unix_now};
+
+async fn login(request: &loco_rs::TestServer) -> MfaChallengeResponse {
+    let response = request
+        .post("/api/auth/login")
+        .json(&json!({ "email": "test@loco.com", "password": "1234" }))
+        .await;
+    assert_eq!(response.status_code(), 200);
+    serde_json::from_str(&response.text()).unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn can_enroll_and_login_with_two_factor() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in_user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&logged_in_user.token);
+
+        let response = request
+            .post("/api/auth/2fa/setup")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let setup: TwoFactorSetupResponse = serde_json::from_str(&response.text()).unwrap();
+        assert!(setup.otpauth_url.starts_with("otpauth://totp/"));
+        assert!(setup.qrcode_svg.contains("<svg"));
+
+        // 错误的验证码不能完成绑定
+        let response = request
+            .post("/api/auth/2fa/enable")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "code": "abcdef" }))
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        let code = totp::code_at(&setup.secret, unix_now()).unwrap();
+        let response = request
+            .post("/api/auth/2fa/enable")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "code": code }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let recovery: RecoveryCodesResponse = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(recovery.recovery_codes.len(), totp::RECOVERY_CODE_COUNT);
+
+        // 密码正确后要求第二步验证，不直接签发令牌
+        let challenge = login(&request).await;
+        assert!(challenge.mfa_required);
+
+        // 绑定时用过的验证码不能重放
+        let response = request
+            .post("/api/auth/login/mfa")
+            .json(&json!({ "mfa_token": challenge.mfa_token, "code": code }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        let next_code = totp::code_at(&setup.secret, unix_now() + totp::PERIOD).unwrap();
+        let response = request
+            .post("/api/auth/login/mfa")
+            .json(&json!({ "mfa_token": challenge.mfa_token, "code": next_code }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let login_response: LoginResponse = serde_json::from_str(&response.text()).unwrap();
+        assert!(!login_response.token.is_empty());
+
+        // 第二步令牌不能当作访问令牌使用
+        let (mfa_key, mfa_value) = auth_header(&challenge.mfa_token);
+        let response = request
+            .get("/api/auth/current")
+            .add_header(mfa_key, mfa_value)
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        // 恢复码只能使用一次
+        let recovery_code = recovery.recovery_codes[0].to_uppercase();
+        let challenge = login(&request).await;
+        let response = request
+            .post("/api/auth/login/mfa")
+            .json(&json!({ "mfa_token": challenge.mfa_token, "code": recovery_code }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let challenge = login(&request).await;
+        let response = request
+            .post("/api/auth/login/mfa")
+            .json(&json!({ "mfa_token": challenge.mfa_token, "code": recovery_code }))
+            .await;
+        assert_eq!(response.status_code(), 401);
+
+        let response = request
+            .get("/api/auth/2fa")
+            .add_header(auth_key, auth_value)
+            .await;
+        let status: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(status["enabled"], true);
+        assert_eq!(
+            status["recovery_codes_remaining"],
+            totp::RECOVERY_CODE_COUNT - 1
+        );
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn two_factor_login_issues_a_working_token() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in_user = init_user_login(&request, &ctx).await;
+        let token = login_with_two_factor(
+            &request,
+            &ctx,
+            &logged_in_user.user,
+            "test@loco.com",
+            "1234",
+        )
+        .await;
+
+        let (auth_key, auth_value) = auth_header(&token);
+        let response = request
+            .get("/api/auth/current")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let current: serde_json::Value = serde_json::from_str(&response.text()).unwrap();
+        assert_eq!(current["email"], "test@loco.com");
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn admin_routes_require_two_factor() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in_user = init_user_login(&request, &ctx).await;
+
+        let mut staff: users::ActiveModel = logged_in_user.user.clone().into();
+        staff.is_staff = Set(true);
+        staff.update(&ctx.db).await.unwrap();
+
+        let (auth_key, auth_value) = auth_header(&logged_in_user.token);
+
+        // 未启用两步验证的管理员不能访问后台
+        let response = request
+            .get("/api/admin/users")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 403);
+
+        let response = request
+            .post("/api/auth/2fa/setup")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        let setup: TwoFactorSetupResponse = serde_json::from_str(&response.text()).unwrap();
+        let response = request
+            .post("/api/auth/2fa/enable")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "code": totp::code_at(&setup.secret, unix_now()).unwrap() }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+
+        let response = request
+            .get("/api/admin/users")
+            .add_header(auth_key, auth_value)
+            .await;
+        assert_eq!(response.status_code(), 200);
+    })
+    .await;
+}
+
+#[tokio::test]
+#[serial]
+async fn wrong_codes_lock_the_account() {
+    request::<App, _, _>(|request, ctx| async move {
+        let logged_in_user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&logged_in_user.token);
+
+        let response = request
+            .post("/api/auth/2fa/setup")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        let setup: TwoFactorSetupResponse = serde_json::from_str(&response.text()).unwrap();
+        request
+            .post("/api/auth/2fa/enable")
+            .add_header(auth_key, auth_value)
+            .json(&json!({ "code": totp::code_at(&setup.secret, unix_now()).unwrap() }))
+            .await;
+
+        // 每次重新登录拿到新的挑战令牌，失败次数仍然累计
+        for _ in 0..5 {
+            let challenge = login(&request).await;
+            let response = request
+                .post("/api/auth/login/mfa")
+                .json(&json!({ "mfa_token": challenge.mfa_token, "code": "abcdef" }))
+                .await;
+            assert_eq!(response.status_code(), 401);
+        }
+
+        // 锁定期间密码正确也不能登录
+        let code = totp::code_at(&setup.secret, unix_now() + totp::PERIOD).unwrap();
+        let response = request
+            .post("/api/auth/login")
+            .json(&json!({ "email": "test@loco.com", "password": "1234" }))
+            .await;
+        assert_eq!(response.status_code(), 429);
+        assert!(response.maybe_header("retry-after").is_some());
+
+        RATE_LIMITER
+            .unlock(&logged_in_user.user.pid.to_string())
+            .await
+            .unwrap();
+        let challenge = login(&request).await;
+        let response = request
+            .post("/api/auth/login/mfa")
+            .json(&json!({ "mfa_token": challenge.mfa_token, "code": code }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+    })
+    .await;
+}
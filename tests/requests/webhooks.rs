@@ -0,0 +1,200 @@
+use loco_rs::testing::prelude::*;
+use qcast::app::App;
+use qcast::services::webhooks;
+use serde_json::json;
+use serial_test::serial;
+
+use super::prepare_data::{auth_header, create_public_media, init_staff_login, init_user_login};
+
+#[test]
+fn signature_covers_timestamp_and_body() {
+    let signature = webhooks::sign("whsec_test", 1_700_000_000, r#"{"event":"media.uploaded"}"#);
+    assert!(signature.starts_with("sha256="));
+    assert_eq!(signature.len(), "sha256=".len() + 64);
+    assert_eq!(
+        signature,
+        webhooks::sign("whsec_test", 1_700_000_000, r#"{"event":"media.uploaded"}"#)
+    );
+    assert_ne!(
+        signature,
+        webhooks::sign("whsec_test", 1_700_000_001, r#"{"event":"media.uploaded"}"#)
+    );
+    assert_ne!(
+        signature,
+        webhooks::sign(
+            "whsec_other",
+            1_700_000_000,
+            r#"{"event":"media.uploaded"}"#
+        )
+    );
+}
+
+#[test]
+fn private_addresses_are_forbidden() {
+    for ip in [
+        "127.0.0.1",
+        "10.1.2.3",
+        "172.16.0.1",
+        "192.168.1.1",
+        "169.254.169.254",
+        "100.64.0.1",
+        "0.0.0.0",
+        "::1",
+        "::",
+        "fd00::1",
+        "fe80::1",
+        "::ffff:127.0.0.1",
+    ] {
+        assert!(webhooks::is_forbidden_ip(ip.parse().unwrap()), "{ip}");
+    }
+    for ip in ["93.184.216.34", "2606:2800:220:1::1"] {
+        assert!(!webhooks::is_forbidden_ip(ip.parse().unwrap()), "{ip}");
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn failed_deliveries_are_logged_and_can_be_redelivered() {
+    request::<App, _, _>(|request, ctx| async move {
+        let user = init_user_login(&request, &ctx).await;
+        let (auth_key, auth_value) = auth_header(&user.token);
+
+        // 无效的地址和事件
+        let response = request
+            .post("/api/webhooks")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "url": "ftp://example.com/hook", "events": ["media.published"] }))
+            .await;
+        assert_eq!(response.status_code(), 400);
+        let response = request
+            .post("/api/webhooks")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "url": "https://example.com/hook", "events": ["media.played"] }))
+            .await;
+        assert_eq!(response.status_code(), 400);
+
+        // 本机和内网地址
+        for url in [
+            "http://127.0.0.1:9/hook",
+            "http://localhost/hook",
+            "http://[::1]/hook",
+            "http://169.254.169.254/latest/meta-data",
+        ] {
+            let response = request
+                .post("/api/webhooks")
+                .add_header(auth_key.clone(), auth_value.clone())
+                .json(&json!({ "url": url, "events": ["media.published"] }))
+                .await;
+            assert_eq!(response.status_code(), 400, "{url}");
+        }
+
+        // 指向无法解析的地址，投递必然失败
+        let response = request
+            .post("/api/webhooks")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({
+                "url": "http://qcast-webhook.invalid/hook",
+                "events": ["media.published", "media.deleted"],
+            }))
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let endpoint = response.json::<serde_json::Value>();
+        let endpoint_id = endpoint["id"].as_i64().unwrap();
+        assert!(endpoint["secret"].as_str().unwrap().starts_with("whsec_"));
+        assert_eq!(
+            endpoint["events"],
+            json!(["media.published", "media.deleted"])
+        );
+
+        // 列表中不返回密钥
+        let response = request
+            .get("/api/webhooks")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        let list = response.json::<Vec<serde_json::Value>>();
+        assert_eq!(list.len(), 1);
+        assert!(list[0].get("secret").is_none());
+
+        let response = request
+            .post("/api/books")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .json(&json!({ "title": "Webhook 测试" }))
+            .await;
+        let book_id = response.json::<serde_json::Value>()["id"].as_i64().unwrap();
+        let media =
+            create_public_media(&ctx, i32::try_from(book_id).unwrap(), None, user.user.id).await;
+
+        // 取消公开不触发事件，重新公开触发 media.published
+        for _ in 0..2 {
+            let response = request
+                .post(&format!("/api/media/{}/publish", media.id))
+                .add_header(auth_key.clone(), auth_value.clone())
+                .await;
+            assert_eq!(response.status_code(), 200);
+        }
+
+        let deliveries_url = format!("/api/webhooks/{endpoint_id}/deliveries");
+        let response = request
+            .get(&deliveries_url)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        let deliveries = response.json::<Vec<serde_json::Value>>();
+        assert_eq!(deliveries.len(), 1);
+        let delivery = &deliveries[0];
+        assert_eq!(delivery["event"], "media.published");
+        assert_eq!(delivery["payload"]["data"]["id"], media.id);
+        assert_eq!(delivery["status"], "pending");
+        assert_eq!(delivery["attempts"], 1);
+        assert!(delivery["error"].is_string());
+        assert!(delivery["next_attempt_at"].is_string());
+
+        let delivery_id = delivery["id"].as_i64().unwrap();
+        let response = request
+            .post(&format!("{deliveries_url}/{delivery_id}/redeliver"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        assert_eq!(response.json::<serde_json::Value>()["attempts"], 2);
+
+        // 删除书籍时其中的媒体一起移入回收站，同样触发 media.deleted
+        let response = request
+            .delete(&format!("/api/books/{book_id}"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let response = request
+            .get(&deliveries_url)
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        let deliveries = response.json::<Vec<serde_json::Value>>();
+        assert_eq!(deliveries.len(), 2);
+        let deleted = deliveries
+            .iter()
+            .find(|d| d["event"] == "media.deleted")
+            .unwrap();
+        assert_eq!(deleted["payload"]["data"]["id"], media.id);
+        assert_eq!(deleted["payload"]["data"]["permanent"], false);
+
+        // 其他用户看不到该地址
+        let other = init_staff_login(&request, &ctx).await;
+        let (other_key, other_value) = auth_header(&other.token);
+        let response = request
+            .get(&deliveries_url)
+            .add_header(other_key, other_value)
+            .await;
+        assert_eq!(response.status_code(), 404);
+
+        // 删除地址后不再投递
+        let response = request
+            .delete(&format!("/api/webhooks/{endpoint_id}"))
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert_eq!(response.status_code(), 200);
+        let response = request
+            .get("/api/webhooks")
+            .add_header(auth_key.clone(), auth_value.clone())
+            .await;
+        assert!(response.json::<Vec<serde_json::Value>>().is_empty());
+    })
+    .await;
+}